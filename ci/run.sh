@@ -33,7 +33,7 @@ if [ "${NO_STD}" = "1" ]; then
     FEATURES="rustc-internal-api"
     OP="build"
 else
-    FEATURES="rustc-internal-api,serde,rayon"
+    FEATURES="rustc-internal-api,serde,rayon,rkyv"
     OP="test"
 fi
 
@@ -52,6 +52,11 @@ fi
 "${CARGO}" -vv ${OP} --target="${TARGET}" --release
 "${CARGO}" -vv ${OP} --target="${TARGET}" --release --features "${FEATURES}"
 
+if [ "${TARGET}" = "x86_64-unknown-linux-gnu" ]; then
+    # Exercise the AVX2 lanes of IntSpec::hash_many
+    RUSTFLAGS="$RUSTFLAGS -C target-feature=+avx2" "${CARGO}" -vv test --target="${TARGET}" --lib
+fi
+
 if [ "${CHANNEL}" = "nightly" ] && [ "${NO_STD}" != 1 ]; then
     # Run benchmark on native targets, build them on non-native ones:
     NO_RUN=""
@@ -1,4 +1,5 @@
 use super::super::{BitMask, Tag};
+use super::GroupBackend;
 use core::{mem, ptr};
 
 // Use the native word size as the group size. Using a 64-bit group size on
@@ -27,6 +28,7 @@ pub(crate) const BITMASK_STRIDE: usize = 8;
 #[allow(clippy::cast_possible_truncation, clippy::unnecessary_cast)]
 pub(crate) const BITMASK_MASK: BitMaskWord = u64::from_ne_bytes([Tag::DELETED.0; 8]) as GroupWord;
 pub(crate) const BITMASK_ITER_MASK: BitMaskWord = !0;
+pub(crate) const BACKEND: GroupBackend = GroupBackend::Generic;
 
 /// Helper function to replicate a tag across a `GroupWord`.
 #[inline]
@@ -7,7 +7,25 @@ cfg_if! {
     // I attempted an implementation on ARM using NEON instructions, but it
     // turns out that most NEON instructions have multi-cycle latency, which in
     // the end outweighs any gains over the generic implementation.
+    //
+    // The AVX2 implementation is opt-in through the `avx2-group` feature and
+    // requires AVX2 to be enabled at compile time (e.g. with
+    // `-C target-feature=+avx2`): the group width determines the table layout,
+    // so it can't be selected at runtime. Wider groups halve the number of
+    // loads on long probe sequences and in iteration.
+    //
+    // On wasm32 the SIMD128 implementation is used when the module is built
+    // with `-C target-feature=+simd128`. WebAssembly has no runtime feature
+    // detection, so a module either requires SIMD support or it doesn't.
     if #[cfg(all(
+        feature = "avx2-group",
+        target_feature = "avx2",
+        target_arch = "x86_64",
+        not(miri),
+    ))] {
+        mod avx2;
+        use avx2 as imp;
+    } else if #[cfg(all(
         target_feature = "sse2",
         any(target_arch = "x86", target_arch = "x86_64"),
         not(miri),
@@ -24,6 +42,13 @@ cfg_if! {
     ))] {
         mod neon;
         use neon as imp;
+    } else if #[cfg(all(
+        target_arch = "wasm32",
+        target_feature = "simd128",
+        not(miri),
+    ))] {
+        mod wasm;
+        use wasm as imp;
     } else if #[cfg(all(
         feature = "nightly",
         target_arch = "loongarch64",
@@ -38,6 +63,48 @@ cfg_if! {
     }
 }
 pub(crate) use self::imp::Group;
+use self::imp::BACKEND;
 pub(super) use self::imp::{
     BitMaskWord, NonZeroBitMaskWord, BITMASK_ITER_MASK, BITMASK_MASK, BITMASK_STRIDE,
 };
+
+/// The implementation used to scan groups of control bytes.
+///
+/// The backend is selected at compile time from the target architecture and
+/// the target features enabled for the build; there is no runtime dispatch.
+/// For example, aarch64 targets use NEON unless they are big-endian or built
+/// without the `neon` target feature, in which case the portable word-sized
+/// fallback is used instead.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum GroupBackend {
+    /// 16-byte groups using SSE2 on x86 and x86_64.
+    Sse2,
+    /// 32-byte groups using AVX2 on x86_64, enabled by the `avx2-group`
+    /// feature.
+    Avx2,
+    /// 8-byte groups using NEON on little-endian aarch64.
+    Neon,
+    /// 16-byte groups using LSX on loongarch64, nightly only.
+    Lsx,
+    /// 16-byte groups using SIMD128 on wasm32 built with the `simd128`
+    /// target feature.
+    Simd128,
+    /// Word-sized groups using portable bit manipulation.
+    Generic,
+}
+
+impl GroupBackend {
+    /// Returns the backend selected for this build.
+    #[inline]
+    pub const fn current() -> Self {
+        BACKEND
+    }
+
+    /// Returns the number of control bytes scanned by a single group load in
+    /// this build.
+    #[inline]
+    pub const fn group_width() -> usize {
+        Group::WIDTH
+    }
+}
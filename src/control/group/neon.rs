@@ -1,4 +1,5 @@
 use super::super::{BitMask, Tag};
+use super::GroupBackend;
 use core::arch::aarch64 as neon;
 use core::mem;
 use core::num::NonZeroU64;
@@ -8,6 +9,7 @@ pub(crate) type NonZeroBitMaskWord = NonZeroU64;
 pub(crate) const BITMASK_STRIDE: usize = 8;
 pub(crate) const BITMASK_MASK: BitMaskWord = !0;
 pub(crate) const BITMASK_ITER_MASK: BitMaskWord = 0x8080_8080_8080_8080;
+pub(crate) const BACKEND: GroupBackend = GroupBackend::Neon;
 
 /// Abstraction over a group of control tags which can be scanned in
 /// parallel.
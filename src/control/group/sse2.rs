@@ -1,4 +1,5 @@
 use super::super::{BitMask, Tag};
+use super::GroupBackend;
 use core::mem;
 use core::num::NonZeroU16;
 
@@ -12,6 +13,7 @@ pub(crate) type NonZeroBitMaskWord = NonZeroU16;
 pub(crate) const BITMASK_STRIDE: usize = 1;
 pub(crate) const BITMASK_MASK: BitMaskWord = 0xffff;
 pub(crate) const BITMASK_ITER_MASK: BitMaskWord = !0;
+pub(crate) const BACKEND: GroupBackend = GroupBackend::Sse2;
 
 /// Abstraction over a group of control tags which can be scanned in
 /// parallel.
@@ -3,6 +3,7 @@ mod group;
 mod tag;
 
 use self::bitmask::BitMask;
+pub use self::group::GroupBackend;
 pub(crate) use self::{
     bitmask::BitMaskIter,
     group::Group,
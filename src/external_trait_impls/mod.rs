@@ -1,4 +1,6 @@
 #[cfg(feature = "rayon")]
 pub(crate) mod rayon;
+#[cfg(feature = "rkyv")]
+pub(crate) mod rkyv;
 #[cfg(feature = "serde")]
 mod serde;
@@ -1,5 +1,6 @@
 mod helpers;
 pub(crate) mod map;
 pub(crate) mod raw;
+pub(crate) mod raw_map;
 pub(crate) mod set;
 pub(crate) mod table;
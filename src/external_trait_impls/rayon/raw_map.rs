@@ -0,0 +1,306 @@
+use crate::control::Group;
+use crate::raw::map::{normalized_hash, BucketRange, EntrySpec, InsertError, RawTable2};
+#[cfg(feature = "raw-map-infallible")]
+use crate::raw::spec::HasherSpec;
+use crate::raw::Allocator;
+#[cfg(feature = "raw-map-infallible")]
+use crate::raw::Global;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "raw-map-infallible")]
+use core::hash::{BuildHasher, Hash};
+use rayon::iter::{
+    plumbing::{self, Folder, UnindexedConsumer, UnindexedProducer},
+    IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+};
+#[cfg(feature = "raw-map-infallible")]
+use rayon::iter::{FromParallelIterator, ParallelExtend};
+
+///
+/// `RawTable2::par_iter`返回的entry, 包含槽位下标以及Key与value的内存指针
+///
+#[derive(Copy, Clone, Debug)]
+pub struct RawEntryPtr {
+    index: usize,
+    key: *const u8,
+    value: *const u8,
+}
+
+// 只是指向table内存的指针, 访问时的同步由caller保证
+unsafe impl Send for RawEntryPtr {}
+unsafe impl Sync for RawEntryPtr {}
+
+impl RawEntryPtr {
+    /// 获取entry的槽位下标
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// 获取entry中Key的内存指针
+    pub fn key(&self) -> *const u8 {
+        self.key
+    }
+
+    /// 获取entry中value的内存指针
+    pub fn value(&self) -> *const u8 {
+        self.value
+    }
+}
+
+///
+/// `RawTable2::par_iter`返回的并行迭代器, 按`Group::WIDTH`对齐切分槽位数组并在rayon线程池中扫描
+///
+pub struct ParIter<'a, E: EntrySpec, A: Allocator> {
+    table: &'a RawTable2<E, A>,
+}
+
+// 并行扫描期间只读访问table, spec的hook可能被多个线程同时调用
+unsafe impl<E: EntrySpec + Sync, A: Allocator + Sync> Send for ParIter<'_, E, A> {}
+
+impl<E: EntrySpec + Sync, A: Allocator + Sync> ParallelIterator for ParIter<'_, E, A> {
+    type Item = RawEntryPtr;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let range = BucketRange::new(0, self.table.buckets());
+        let producer = ParIterProducer {
+            table: self.table,
+            range,
+        };
+        plumbing::bridge_unindexed(producer, consumer)
+    }
+}
+
+/// 扫描`range`中所有有效entry的producer
+struct ParIterProducer<'a, E: EntrySpec, A: Allocator> {
+    table: &'a RawTable2<E, A>,
+    range: BucketRange,
+}
+
+unsafe impl<E: EntrySpec + Sync, A: Allocator + Sync> Send for ParIterProducer<'_, E, A> {}
+
+impl<E: EntrySpec + Sync, A: Allocator + Sync> UnindexedProducer for ParIterProducer<'_, E, A> {
+    type Item = RawEntryPtr;
+
+    fn split(self) -> (Self, Option<Self>) {
+        let (start, end) = (self.range.start(), self.range.end());
+        // 从中点按组向下对齐切分, 不足两组时不再切分
+        let mid = start + (end - start) / 2 / Group::WIDTH * Group::WIDTH;
+        if mid == start {
+            return (self, None);
+        }
+        let table = self.table;
+        let left = ParIterProducer {
+            table,
+            range: BucketRange::new(start, mid),
+        };
+        let right = ParIterProducer {
+            table,
+            range: BucketRange::new(mid, end),
+        };
+        (left, Some(right))
+    }
+
+    fn fold_with<F>(self, mut folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        let mut index = self.range.start();
+        while let Some((i, key)) = self.table.next_entry_in(index, self.range.end()) {
+            index = i + 1;
+            let value = unsafe { self.table.value_ptr(key) };
+            folder = folder.consume(RawEntryPtr {
+                index: i,
+                key,
+                value,
+            });
+            if folder.full() {
+                break;
+            }
+        }
+        folder
+    }
+}
+
+impl<E: EntrySpec + Sync, A: Allocator + Sync> RawTable2<E, A> {
+    ///
+    /// 获取并行迭代所有entries的迭代器, 槽位数组按`Group::WIDTH`对齐切分后在rayon线程池中扫描
+    ///
+    pub fn par_iter(&self) -> ParIter<'_, E, A> {
+        ParIter { table: self }
+    }
+
+    ///
+    /// 在rayon线程池中对每个entry调用`f(key, value)`, `f`可能被多个线程同时调用
+    ///
+    pub fn par_for_each(&self, f: impl Fn(*const u8, *const u8) + Send + Sync) {
+        self.par_iter()
+            .for_each(|entry| f(entry.key(), entry.value()));
+    }
+
+    ///
+    /// `retain`的并行版本: `f(key, value)`在rayon线程池中并行求值,
+    /// 返回`false`的entries随后在当前线程中依次经过`on_evict`并被软删除
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub fn par_retain(&mut self, f: impl Fn(*const u8, *const u8) -> bool + Send + Sync) {
+        let evicted: Vec<usize> = self
+            .par_iter()
+            .filter(|entry| !f(entry.key(), entry.value()))
+            .map(|entry| entry.index())
+            .collect();
+        for index in evicted {
+            unsafe { self.evict(index) };
+        }
+        self.check_shrink();
+    }
+}
+
+/// 在rayon线程池中只读共享的指针数组, 指针所指内存的有效性由caller保证
+struct SharedPtrs<'a>(&'a [*const u8]);
+
+unsafe impl Sync for SharedPtrs<'_> {}
+
+impl SharedPtrs<'_> {
+    fn get(&self, i: usize) -> *const u8 {
+        self.0[i]
+    }
+}
+
+impl<E, A> RawTable2<E, A>
+where
+    E: EntrySpec + Clone + Send + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    ///
+    /// 在rayon线程池中并行构造包含所有`keys[i]: &K`与`values[i]: &V`的table, 重复的Key以靠后的value为准。
+    ///
+    /// 输入按hash前缀划分为`partitions`个分区(向上取整为2的幂, 为0时取rayon线程数),
+    /// 各分区的table在线程池中并行构造, 之后在当前线程中依次导入最终的table; 任一写入失败时返回错误
+    ///
+    /// # Panics
+    ///
+    /// `keys`与`values`长度不同时panic
+    ///
+    pub unsafe fn build_parallel(
+        keys: &[*const u8],
+        values: &[*const u8],
+        partitions: usize,
+        spec: E,
+        alloc: A,
+    ) -> Result<Self, InsertError> {
+        assert_eq!(
+            keys.len(),
+            values.len(),
+            "build_parallel requires one value per key"
+        );
+        let n = if partitions == 0 {
+            rayon::current_num_threads()
+        } else {
+            partitions
+        };
+        let mask = n.next_power_of_two() - 1;
+        let (keys, values) = (SharedPtrs(keys), SharedPtrs(values));
+
+        // 并行计算每个Key所属的分区, 分区下标取自hash的高32位
+        let mut parts = Vec::new();
+        (0..keys.0.len())
+            .into_par_iter()
+            .map(|i| (normalized_hash(&spec, keys.get(i)) >> 32) as usize & mask)
+            .collect_into_vec(&mut parts);
+
+        // 按分区稳定排序输入下标, 保证分区内重复的Key仍按输入顺序写入
+        let mut starts = vec![0; mask + 2];
+        for &part in &parts {
+            starts[part + 1] += 1;
+        }
+        for part in 1..starts.len() {
+            starts[part] += starts[part - 1];
+        }
+        let mut next = starts.clone();
+        let mut order = vec![0; parts.len()];
+        for (i, &part) in parts.iter().enumerate() {
+            order[next[part]] = i;
+            next[part] += 1;
+        }
+
+        let tables = (0..=mask)
+            .into_par_iter()
+            .map(|part| {
+                let indices = &order[starts[part]..starts[part + 1]];
+                let mut table = Self::new(indices.len(), spec.clone(), alloc.clone())?;
+                for &i in indices {
+                    table.try_insert(keys.get(i), values.get(i))?;
+                }
+                Ok(table)
+            })
+            .collect::<Result<Vec<_>, InsertError>>()?;
+
+        // 各分区的Key互不相同, 按分区依次导入即可
+        let len = tables.iter().map(|table| table.len()).sum();
+        let mut table = Self::new(len, spec, alloc)?;
+        for part in &tables {
+            table.extend_unchecked(part)?;
+        }
+        Ok(table)
+    }
+}
+
+///
+/// 在rayon线程池中将所有{key, value}写入各线程的局部table, 之后在当前线程中依次合并到当前table,
+/// 重复的Key以靠后的value为准。table不会调用value的析构函数, 因此要求`V: Copy`
+///
+#[cfg(feature = "raw-map-infallible")]
+impl<K, V, S, A> ParallelExtend<(K, V)> for RawTable2<HasherSpec<K, V, S>, A>
+where
+    K: Hash + Eq + Copy + Send,
+    V: Copy + Send,
+    S: BuildHasher + Clone + Send + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let (spec, alloc) = (self.spec().clone(), self.allocator().clone());
+        let tables: Vec<Self> = par_iter
+            .into_par_iter()
+            .fold(
+                || Self::new(0, spec.clone(), alloc.clone()).expect("map growth failure"),
+                |mut table, (key, value)| {
+                    // entry按`(K, V)`排布, 与`as_map::<K, V>`一致
+                    unsafe { table.as_map::<K, V>().insert(&key, value) };
+                    table
+                },
+            )
+            .collect();
+
+        // 按局部table的总entries数一次性预留, 避免合并期间多次扩容
+        let len = tables.iter().map(|table| table.len()).sum();
+        self.try_reserve(len).expect("map growth failure");
+        for part in &tables {
+            unsafe { self.extend(part) };
+        }
+    }
+}
+
+/// 以`S::default()`构造spec, 其余同`par_extend`
+#[cfg(feature = "raw-map-infallible")]
+impl<K, V, S> FromParallelIterator<(K, V)> for RawTable2<HasherSpec<K, V, S>>
+where
+    K: Hash + Eq + Copy + Send,
+    V: Copy + Send,
+    S: BuildHasher + Default + Clone + Send + Sync,
+{
+    fn from_par_iter<P>(par_iter: P) -> Self
+    where
+        P: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut table =
+            Self::new(0, HasherSpec::new(S::default()), Global).expect("map growth failure");
+        table.par_extend(par_iter);
+        table
+    }
+}
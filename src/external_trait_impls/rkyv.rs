@@ -0,0 +1,187 @@
+use crate::raw::map::{EntrySpec, RawTable2};
+use crate::raw::shared::{SharedError, SharedTable};
+use crate::raw::{Allocator, Global};
+use crate::TryReserveError;
+use core::fmt;
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::Writer;
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Deserialize, Place, Portable, Serialize};
+
+/// 写入归档时每次从table拷贝的字节数
+const ARCHIVE_CHUNK: usize = 4096;
+
+///
+/// `RawTable2`的归档形式: 与`RawTable2::export_shared`导出的区域完全一致的字节,
+/// 可以通过`attach`在归档内存上直接查找, 无需反序列化。
+///
+/// 区域在归档中按`RawTable2::shared_size`给出的对齐要求对齐, 因此归档缓冲区的起始地址同样需要满足该对齐要求
+/// (例如`rkyv::util::AlignedVec<32>`或mmap的页对齐内存), 否则`attach`返回`SharedError::Misaligned`
+///
+#[repr(transparent)]
+pub struct ArchivedRawTable2 {
+    bytes: ArchivedVec<u8>,
+}
+
+// 与`ArchivedVec<u8>`的内存结构相同
+unsafe impl Portable for ArchivedRawTable2 {}
+
+impl ArchivedRawTable2 {
+    /// 获取区域的全部字节
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    ///
+    /// attach到归档中的table, 语义同`SharedTable::attach`
+    ///
+    /// # Safety
+    ///
+    /// 归档须由相同架构上的`Serialize`写入, `spec`须与写入方的spec内存结构一致且hash在进程之间保持稳定
+    ///
+    pub unsafe fn attach<E: EntrySpec>(&self, spec: E) -> Result<SharedTable<'_, E>, SharedError> {
+        SharedTable::attach(self.as_bytes(), spec)
+    }
+
+    ///
+    /// 以`spec`与`alloc`构造新的table, 并将归档中的entries逐个拷贝进去
+    ///
+    /// # Safety
+    ///
+    /// 同`attach`
+    ///
+    pub unsafe fn to_table<E, A>(&self, spec: E, alloc: A) -> Result<RawTable2<E, A>, ArchiveError>
+    where
+        E: EntrySpec + Clone,
+        A: Allocator,
+    {
+        let shared = self.attach(spec.clone())?;
+        let mut table = RawTable2::new(shared.len(), spec, alloc)?;
+        for (entry, _) in shared.iter() {
+            // 导出的entries不引用其他内存, 且Key互不相同
+            table.insert_entry(entry)?;
+        }
+        Ok(table)
+    }
+}
+
+/// `RawTable2`的resolver, 记录区域在归档中的位置
+pub struct RawTable2Resolver {
+    bytes: VecResolver,
+    len: usize,
+}
+
+/// 归档或从归档恢复`RawTable2`时的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ArchiveError {
+    /// table无法导出, 或归档与spec不兼容
+    Shared(SharedError),
+    /// 恢复table时内存分配失败
+    Alloc(TryReserveError),
+}
+
+impl From<SharedError> for ArchiveError {
+    fn from(err: SharedError) -> Self {
+        Self::Shared(err)
+    }
+}
+
+impl From<TryReserveError> for ArchiveError {
+    fn from(err: TryReserveError) -> Self {
+        Self::Alloc(err)
+    }
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shared(err) => write!(f, "raw table archive error: {err:?}"),
+            Self::Alloc(err) => write!(f, "raw table allocation failed: {err:?}"),
+        }
+    }
+}
+
+impl core::error::Error for ArchiveError {}
+
+impl<E: EntrySpec, A: Allocator> Archive for RawTable2<E, A> {
+    type Archived = ArchivedRawTable2;
+    type Resolver = RawTable2Resolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        // `ArchivedRawTable2`只是`ArchivedVec<u8>`的透明包装
+        let out = unsafe { out.cast_unchecked::<ArchivedVec<u8>>() };
+        ArchivedVec::resolve_from_len(resolver.len, resolver.bytes, out);
+    }
+}
+
+///
+/// 按`RawTable2::shared_size`的对齐要求写入与`export_shared`一致的区域, entries保持本平台的字节序。
+///
+/// 区域直接从table内存分块写入, 不在内存中生成整个区域; table无法导出时返回`SharedError::Unsupported`
+///
+impl<E, A, S> Serialize<S> for RawTable2<E, A>
+where
+    E: EntrySpec,
+    A: Allocator,
+    S: Fallible + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let archive_err = |err: SharedError| S::Error::new(ArchiveError::from(err));
+        let (_, align) = self.shared_size().map_err(archive_err)?;
+        let mut chunks = self
+            .snapshot_chunks_in(ARCHIVE_CHUNK, false)
+            .map_err(archive_err)?;
+        // 以0填充到区域的对齐要求, 其可能超过`WriterExt::align`支持的范围
+        let mut buf = [0u8; ARCHIVE_CHUNK];
+        let mut padding = (align - serializer.pos() % align) % align;
+        while padding > 0 {
+            let n = padding.min(ARCHIVE_CHUNK);
+            serializer.write(&buf[..n])?;
+            padding -= n;
+        }
+        let pos = serializer.pos();
+        let len = chunks.total();
+        loop {
+            let n = chunks.fill(&mut buf);
+            if n == 0 {
+                break;
+            }
+            serializer.write(&buf[..n])?;
+        }
+        Ok(RawTable2Resolver {
+            bytes: VecResolver::from_pos(pos),
+            len,
+        })
+    }
+}
+
+///
+/// 以`E::default()`构造spec并通过`ArchivedRawTable2::to_table`恢复table。
+///
+/// 与`RawTable2::read_snapshot`相同, 只适用于hash在进程之间保持稳定、entries不引用其他内存的spec
+///
+impl<E, D> Deserialize<RawTable2<E, Global>, D> for ArchivedRawTable2
+where
+    E: EntrySpec + Default + Clone,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize(&self, _: &mut D) -> Result<RawTable2<E, Global>, D::Error> {
+        unsafe { self.to_table(E::default(), Global) }.map_err(D::Error::new)
+    }
+}
+
+///
+/// 只检查区域位于归档之内, 区域的内存结构只有在attach时才能按spec校验
+///
+unsafe impl<C> CheckBytes<C> for ArchivedRawTable2
+where
+    C: Fallible + ?Sized,
+    ArchivedVec<u8>: CheckBytes<C>,
+{
+    unsafe fn check_bytes(value: *const Self, context: &mut C) -> Result<(), C::Error> {
+        ArchivedVec::<u8>::check_bytes(value.cast(), context)
+    }
+}
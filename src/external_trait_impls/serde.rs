@@ -218,3 +218,186 @@ mod set {
         }
     }
 }
+
+mod raw_map {
+    use crate::raw::map::{EntrySpec, RawMap, RawTable2};
+    use crate::raw::Allocator;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, Visitor};
+    use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+    impl<K, V, E, A> Serialize for RawMap<'_, K, V, E, A>
+    where
+        K: Serialize,
+        V: Serialize,
+        E: EntrySpec,
+        A: Allocator,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_map(self.iter())
+        }
+    }
+
+    ///
+    /// 将map中的entries写入`RawMap`所借用的table, Key与value按`RawMap::try_insert`的语义写入
+    ///
+    impl<'de, K, V, E, A> DeserializeSeed<'de> for RawMap<'_, K, V, E, A>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+        E: EntrySpec,
+        A: Allocator,
+    {
+        type Value = ();
+
+        fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_map(self)
+        }
+    }
+
+    impl<'de, K, V, E, A> Visitor<'de> for RawMap<'_, K, V, E, A>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+        E: EntrySpec,
+        A: Allocator,
+    {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<M>(mut self, mut map: M) -> Result<(), M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            while let Some((key, value)) = map.next_entry::<K, V>()? {
+                unsafe { self.try_insert(&key, value) }
+                    .map_err(|_| de::Error::custom("raw map insertion failed"))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// 以`serialize_bytes`序列化的字节串
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    ///
+    /// 通过`EntrySpec::encode_key`/`encode_value`将每个entry序列化为字节串Key与字节串value组成的map,
+    /// spec不支持编码时返回错误
+    ///
+    impl<E: EntrySpec, A: Allocator> Serialize for RawTable2<E, A> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let spec = self.spec();
+            let mut map = serializer.serialize_map(Some(self.len()))?;
+            let (mut key, mut value) = (Vec::new(), Vec::new());
+            for (entry, v) in self.iter() {
+                key.clear();
+                value.clear();
+                if !spec.encode_key(entry, &mut key) || !spec.encode_value(v, &mut value) {
+                    return Err(ser::Error::custom("entry spec does not support encoding"));
+                }
+                map.serialize_entry(&Bytes(&key), &Bytes(&value))?;
+            }
+            map.end()
+        }
+    }
+
+    /// 接受字节串或`u8`序列的字节缓冲区
+    struct ByteBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ByteBufVisitor;
+
+            impl<'de> Visitor<'de> for ByteBufVisitor {
+                type Value = ByteBuf;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    formatter.write_str("a byte string")
+                }
+
+                fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                    Ok(ByteBuf(v.to_vec()))
+                }
+
+                fn visit_seq<S>(self, mut seq: S) -> Result<ByteBuf, S::Error>
+                where
+                    S: de::SeqAccess<'de>,
+                {
+                    let mut bytes = Vec::new();
+                    while let Some(byte) = seq.next_element()? {
+                        bytes.push(byte);
+                    }
+                    Ok(ByteBuf(bytes))
+                }
+            }
+
+            deserializer.deserialize_byte_buf(ByteBufVisitor)
+        }
+    }
+
+    ///
+    /// 将`RawTable2`的序列化结果写入借用的table: 每个entry通过`EntrySpec::decode_key`/`decode_value`解码后写入,
+    /// 已存在的Key覆盖其value; spec不支持解码或写入失败时返回错误
+    ///
+    impl<'de, E: EntrySpec, A: Allocator> DeserializeSeed<'de> for &mut RawTable2<E, A> {
+        type Value = ();
+
+        fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_map(RawTableVisitor(self))
+        }
+    }
+
+    struct RawTableVisitor<'a, E: EntrySpec, A: Allocator>(&'a mut RawTable2<E, A>);
+
+    impl<'de, E: EntrySpec, A: Allocator> Visitor<'de> for RawTableVisitor<'_, E, A> {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a map of encoded entries")
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<(), M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            while let Some((ByteBuf(key), ByteBuf(value))) = map.next_entry()? {
+                match unsafe { self.0.try_insert_encoded(&key, &value) } {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return Err(de::Error::custom("entry spec failed to decode entry"))
+                    }
+                    Err(_) => return Err(de::Error::custom("raw table insertion failed")),
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -54,6 +54,8 @@
 #[cfg(test)]
 #[macro_use]
 extern crate std;
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
 
 #[cfg_attr(test, macro_use)]
 #[cfg_attr(feature = "rustc-dep-of-std", allow(unused_extern_crates))]
@@ -131,6 +133,66 @@ pub mod hash_table {
     }
 }
 
+pub mod raw_map {
+    //! A hash table over raw memory, whose entry layout and key semantics are
+    //! supplied at runtime through an [`EntrySpec`].
+    //!
+    //! The APIs that panic on allocation failure are only available with the
+    //! `raw-map-infallible` feature (enabled by default); without it only the
+    //! fallible `try_*` paths remain, which suits `no_std` + `alloc` embedders.
+    pub use crate::control::GroupBackend;
+    pub use crate::raw::aggregate::*;
+    pub use crate::raw::alloc_stats::AllocStats;
+    #[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+    pub use crate::raw::arena::*;
+    pub use crate::raw::columnar::*;
+    pub use crate::raw::debug::*;
+    pub use crate::raw::entry_layout::*;
+    #[cfg(feature = "epoch")]
+    pub use crate::raw::epoch::*;
+    pub use crate::raw::incremental::*;
+    pub use crate::raw::inline::*;
+    pub use crate::raw::journal::*;
+    #[cfg(feature = "madvise")]
+    pub use crate::raw::madvise::MemoryHints;
+    pub use crate::raw::map::*;
+    #[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+    pub use crate::raw::pool::*;
+    pub use crate::raw::seqlock::*;
+    pub use crate::raw::shared::*;
+    pub use crate::raw::snapshot::*;
+    pub use crate::raw::spec::*;
+    pub use crate::raw::striped::*;
+
+    #[cfg(feature = "ffi")]
+    /// `extern "C"` entry points for driving a raw map from non-Rust runtimes.
+    ///
+    /// The functions are `#[no_mangle]`, so a `cdylib` crate that depends on
+    /// this one with the `ffi` feature exports them. Hosts that pass tables
+    /// across plugin boundaries should go through [`rawtable_vtable`](ffi::rawtable_vtable) and
+    /// [`RawTableObject`](ffi::RawTableObject), whose layout is versioned by
+    /// [`RAWTABLE_ABI_VERSION`](ffi::RAWTABLE_ABI_VERSION).
+    pub mod ffi {
+        pub use crate::raw::ffi::*;
+    }
+
+    #[cfg(feature = "rayon")]
+    /// [rayon]-based parallel iterator types for raw maps.
+    ///
+    /// [rayon]: https://docs.rs/rayon/1.0/rayon
+    pub mod rayon {
+        pub use crate::external_trait_impls::rayon::raw_map::*;
+    }
+
+    #[cfg(feature = "rkyv")]
+    /// [rkyv] archive support for raw map tables.
+    ///
+    /// [rkyv]: https://docs.rs/rkyv/0.8/rkyv
+    pub mod rkyv {
+        pub use crate::external_trait_impls::rkyv::*;
+    }
+}
+
 pub use crate::map::HashMap;
 pub use crate::set::HashSet;
 pub use crate::table::HashTable;
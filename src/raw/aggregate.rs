@@ -0,0 +1,80 @@
+use super::map::{EntrySpec, ExtendError, InsertError, RawTable2};
+use super::{Allocator, Global};
+use crate::TryReserveError;
+
+///
+/// 线程局部的聚合table: 每个线程持有自己的`LocalAggregator`并以`upsert`合并写入, 写入期间无需任何同步;
+/// 全部写入完成后通过`merge_into`以合并函数依次折叠到全局table中。
+///
+/// 适用于词频统计等value需要合并(而非覆盖)的并行聚合
+///
+pub struct LocalAggregator<E: EntrySpec, A: Allocator = Global> {
+    table: RawTable2<E, A>,
+}
+
+impl<E: EntrySpec, A: Allocator> LocalAggregator<E, A> {
+    /// 构造空的聚合table
+    pub fn new(spec: E, alloc: A) -> Result<Self, TryReserveError> {
+        Ok(Self::from_table(RawTable2::new(0, spec, alloc)?))
+    }
+
+    /// 以已有的table(例如预留了容量或设置了扩容策略的table)作为聚合table
+    pub fn from_table(table: RawTable2<E, A>) -> Self {
+        Self { table }
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`, Key已存在时以`combine(value, v)`将`v`合并到已有的value中
+    ///
+    pub unsafe fn upsert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+        combine: impl FnOnce(*const u8, *const u8),
+    ) -> Result<(), InsertError> {
+        self.table.try_upsert(key, value, combine)
+    }
+
+    ///
+    /// 将所有局部结果合并到`global`中, 已存在的Key以`combine(value, v)`合并value(`value`位于`global`中),
+    /// 成功后清空局部结果并保留其容量, 以便下一轮聚合复用。
+    ///
+    /// 错误语义同`RawTable2::try_merge`, 失败时局部结果保持不变
+    ///
+    pub unsafe fn merge_into(
+        &mut self,
+        global: &mut RawTable2<E, A>,
+        combine: impl FnMut(*const u8, *const u8),
+    ) -> Result<(), ExtendError> {
+        global.try_merge(&self.table, combine)?;
+        self.table.clear();
+        Ok(())
+    }
+
+    /// 获取局部结果
+    pub fn table(&self) -> &RawTable2<E, A> {
+        &self.table
+    }
+
+    /// 获取局部结果中的entries数量
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// 判断局部结果是否为空
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// 取出局部结果
+    pub fn into_table(self) -> RawTable2<E, A> {
+        self.table
+    }
+}
+
+impl<E: EntrySpec + Clone, A: Allocator + Clone> LocalAggregator<E, A> {
+    /// 以相同的spec与Allocator构造空的聚合table, 通常为每个工作线程各调用一次
+    pub fn fork(&self) -> Result<Self, TryReserveError> {
+        Self::new(self.table.spec().clone(), self.table.allocator().clone())
+    }
+}
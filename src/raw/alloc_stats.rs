@@ -0,0 +1,156 @@
+use super::{Allocator, Layout, NonNull};
+use core::cmp;
+#[cfg(feature = "madvise")]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+///
+/// table的内存分配统计, 涵盖bucket数组、大entry模式的value、SoA模式的value数组以及payload chunks
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct AllocStats {
+    allocations: usize,
+    bytes_requested: usize,
+    bytes_held: usize,
+    largest_allocation: usize,
+}
+
+impl AllocStats {
+    /// 获取成功分配(包括扩容时的重新分配)的次数
+    pub const fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// 获取累计成功分配的字节数
+    pub const fn bytes_requested(&self) -> usize {
+        self.bytes_requested
+    }
+
+    /// 获取当前持有(已分配且尚未释放)的字节数
+    pub const fn bytes_held(&self) -> usize {
+        self.bytes_held
+    }
+
+    /// 获取单次分配的最大字节数
+    pub const fn largest_allocation(&self) -> usize {
+        self.largest_allocation
+    }
+}
+
+///
+/// 记录分配统计的Allocator包装, `RawTable2`的所有分配均经由此包装转发给caller的Allocator
+///
+/// `allocate`/`deallocate`只拿到`&self`, 因此统计与`release`开关都是原子量, 包装与inner Allocator同样满足`Sync`。
+/// 与`LookupCache`一样只使用原子load/store(不依赖CAS), 每次更新对单个字段是原子的;
+/// table只在`&mut self`的操作中分配与释放, 正常情况下不存在并发更新, 即使出现也只会使统计偏差而不会产生数据竞争
+///
+pub(crate) struct CountingAlloc<A> {
+    inner: A,
+    allocations: AtomicUsize,
+    bytes_requested: AtomicUsize,
+    bytes_held: AtomicUsize,
+    largest_allocation: AtomicUsize,
+    /// 释放内存之前是否先建议`MADV_DONTNEED`, 由table在收缩与`reset`期间临时打开
+    #[cfg(feature = "madvise")]
+    release: AtomicBool,
+}
+
+impl<A> CountingAlloc<A> {
+    pub(crate) const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            allocations: AtomicUsize::new(0),
+            bytes_requested: AtomicUsize::new(0),
+            bytes_held: AtomicUsize::new(0),
+            largest_allocation: AtomicUsize::new(0),
+            #[cfg(feature = "madvise")]
+            release: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub(crate) fn stats(&self) -> AllocStats {
+        AllocStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            bytes_requested: self.bytes_requested.load(Ordering::Relaxed),
+            bytes_held: self.bytes_held.load(Ordering::Relaxed),
+            largest_allocation: self.largest_allocation.load(Ordering::Relaxed),
+        }
+    }
+
+    #[inline]
+    fn record_alloc(&self, layout: Layout) {
+        let size = layout.size();
+        let allocations = self.allocations.load(Ordering::Relaxed);
+        self.allocations.store(allocations + 1, Ordering::Relaxed);
+        let requested = self.bytes_requested.load(Ordering::Relaxed);
+        self.bytes_requested
+            .store(requested.saturating_add(size), Ordering::Relaxed);
+        let held = self.bytes_held.load(Ordering::Relaxed);
+        self.bytes_held.store(held + size, Ordering::Relaxed);
+        let largest = self.largest_allocation.load(Ordering::Relaxed);
+        self.largest_allocation
+            .store(cmp::max(largest, size), Ordering::Relaxed);
+    }
+
+    /// 设置释放内存之前是否先建议`MADV_DONTNEED`, 返回之前的设置
+    #[cfg(feature = "madvise")]
+    pub(crate) fn set_release(&self, release: bool) -> bool {
+        let previous = self.release.load(Ordering::Relaxed);
+        self.release.store(release, Ordering::Relaxed);
+        previous
+    }
+
+    /// 按设置在`ptr`处的内存被释放之前建议`MADV_DONTNEED`
+    #[inline]
+    unsafe fn before_dealloc(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        #[cfg(feature = "madvise")]
+        if self.release.load(Ordering::Relaxed) {
+            super::madvise::advise(_ptr, _layout.size(), super::madvise::Advice::DontNeed);
+        }
+    }
+
+    #[inline]
+    fn record_dealloc(&self, layout: Layout) {
+        let held = self.bytes_held.load(Ordering::Relaxed);
+        self.bytes_held
+            .store(held - layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+unsafe impl<A: Allocator> Allocator for CountingAlloc<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, super::AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.record_alloc(layout);
+        Ok(ptr)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.before_dealloc(ptr, layout);
+        self.inner.deallocate(ptr, layout);
+        self.record_dealloc(layout);
+    }
+}
+
+#[cfg(not(any(feature = "nightly", feature = "allocator-api2")))]
+unsafe impl<A: Allocator> Allocator for CountingAlloc<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, ()> {
+        let ptr = self.inner.allocate(layout)?;
+        self.record_alloc(layout);
+        Ok(ptr)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.before_dealloc(ptr, layout);
+        self.inner.deallocate(ptr, layout);
+        self.record_dealloc(layout);
+    }
+}
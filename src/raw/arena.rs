@@ -0,0 +1,147 @@
+use super::{AllocError, Allocator, Layout, NonNull};
+use crate::alloc::alloc::{alloc, dealloc};
+use crate::alloc::vec::Vec;
+use crate::util::{addr, invalid_mut};
+use core::cell::{Cell, RefCell};
+use core::{cmp, ptr};
+
+/// 单个chunk的最小字节数
+const ARENA_CHUNK_SIZE: usize = 64 * 1024;
+
+/// chunk自身的对齐要求, 更大的对齐要求在chunk内部补齐
+const ARENA_CHUNK_ALIGN: usize = 16;
+
+///
+/// 基于bump指针的arena分配器, `&ArenaAlloc`实现了`Allocator`, 可作为`RawTable2`、`HashMap`等容器的分配器。
+///
+/// 分配只移动当前chunk中的bump指针, 当前chunk不足时向全局分配器申请新的chunk(容量逐次翻倍);
+/// `deallocate`不做任何处理, 所有内存在`reset`或arena销毁时统一释放, 适用于按请求构造、用完整体丢弃的临时map。
+///
+/// `reset`需要`&mut self`, 因此借用此arena的容器必须先被销毁, 借用检查保证了重置时不存在悬垂的容器;
+/// 容器扩容时旧的bucket内存不会被回收, 预先指定容量可以减少浪费
+///
+pub struct ArenaAlloc {
+    /// 已分配的chunks, 最后一个为当前正在使用的chunk
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// 当前chunk中下一次分配的起始地址, 尚未申请chunk时为null
+    ptr: Cell<*mut u8>,
+    /// 当前chunk的结束地址
+    end: Cell<*mut u8>,
+}
+
+// chunks只归此arena所有, 可以随arena一同转移到其他线程
+unsafe impl Send for ArenaAlloc {}
+
+impl ArenaAlloc {
+    /// 构造空的arena, 首次分配时才申请chunk
+    pub const fn new() -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            ptr: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    ///
+    /// 释放除最后一个(即最大的)chunk以外的所有chunks, 并从头复用最后一个chunk。
+    ///
+    /// 之前分配的内存全部失效; `&mut self`保证了此时不存在借用此arena的容器
+    ///
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        let last = chunks.pop();
+        for (chunk, layout) in chunks.drain(..) {
+            unsafe { dealloc(chunk.as_ptr(), layout) };
+        }
+        match last {
+            Some((chunk, layout)) => {
+                chunks.push((chunk, layout));
+                self.ptr.set(chunk.as_ptr());
+                self.end.set(unsafe { chunk.as_ptr().add(layout.size()) });
+            }
+            None => {
+                self.ptr.set(ptr::null_mut());
+                self.end.set(ptr::null_mut());
+            }
+        }
+    }
+
+    /// 获取arena当前持有的chunks的总字节数
+    pub fn capacity(&self) -> usize {
+        self.chunks
+            .borrow()
+            .iter()
+            .map(|(_, layout)| layout.size())
+            .sum()
+    }
+
+    ///
+    /// 按`layout`分配内存, 当前chunk不足时申请新的chunk, 全局分配失败时返回`None`
+    ///
+    fn alloc_layout(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.size() == 0 {
+            return NonNull::new(invalid_mut(layout.align()));
+        }
+        if let Some(ptr) = self.bump(layout) {
+            return Some(ptr);
+        }
+
+        let last_size = self.chunks.borrow().last().map_or(0, |(_, l)| l.size());
+        let needed = layout.size().checked_add(layout.align())?;
+        let chunk_size = cmp::max(cmp::max(last_size * 2, ARENA_CHUNK_SIZE), needed);
+        let chunk_layout = Layout::from_size_align(chunk_size, ARENA_CHUNK_ALIGN).ok()?;
+        let chunk = NonNull::new(unsafe { alloc(chunk_layout) })?;
+        self.chunks.borrow_mut().push((chunk, chunk_layout));
+        self.ptr.set(chunk.as_ptr());
+        self.end.set(unsafe { chunk.as_ptr().add(chunk_size) });
+        self.bump(layout)
+    }
+
+    /// 尝试在当前chunk中分配
+    #[inline]
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let ptr = self.ptr.get();
+        if ptr.is_null() {
+            return None;
+        }
+        let offset = ptr.align_offset(layout.align());
+        let remaining = addr(self.end.get()) - addr(ptr);
+        if offset.checked_add(layout.size())? > remaining {
+            return None;
+        }
+        unsafe {
+            let start = ptr.add(offset);
+            self.ptr.set(start.add(layout.size()));
+            NonNull::new(start)
+        }
+    }
+}
+
+impl Default for ArenaAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ArenaAlloc {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.get_mut().drain(..) {
+            unsafe { dealloc(chunk.as_ptr(), layout) };
+        }
+    }
+}
+
+unsafe impl Allocator for &ArenaAlloc {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.alloc_layout(layout) {
+            Some(ptr) => Ok(unsafe {
+                NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(ptr.as_ptr(), layout.size()))
+            }),
+            None => Err(AllocError),
+        }
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
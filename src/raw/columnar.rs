@@ -0,0 +1,87 @@
+use super::entry_layout::Column;
+use super::map::{BucketRange, EntrySpec, RawTable2};
+use super::{ptr, Allocator};
+
+///
+/// 列缓冲区容纳不下待导出的行时返回的错误, 此时缓冲区不会被写入
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ColumnsTooSmall {
+    /// 待导出的行数
+    pub rows: usize,
+}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 按槽位下标顺序将所有entries的Key列与value列分别拷贝到两个稠密的列缓冲区, 返回导出的行数。
+    ///
+    /// `key`的偏移量相对entry起始地址, `value`的偏移量相对value起始地址(例如`EntrySpec::value_columns`中的一列),
+    /// 第i行分别写入`keys[i * key.size()..]`与`values[i * value.size()..]`;
+    /// 任一缓冲区容纳不下`len()`行时返回`ColumnsTooSmall`。适用于分析场景直接构造列式数据(例如Arrow的primitive数组),
+    /// 无需先收集到按行排布的中间`Vec`
+    ///
+    /// # Safety
+    ///
+    /// 两列须分别位于Key与value内部, 且不能包含未初始化的padding字节; 分离模式与大entry模式同样适用
+    ///
+    pub unsafe fn export_columns(
+        &self,
+        key: Column,
+        value: Column,
+        keys: &mut [u8],
+        values: &mut [u8],
+    ) -> Result<usize, ColumnsTooSmall> {
+        self.export_rows(
+            self.len(),
+            BucketRange::new(0, self.buckets()),
+            key,
+            value,
+            keys,
+            values,
+        )
+    }
+
+    ///
+    /// 与`export_columns`相同, 但只导出槽位范围`range`中的entries, 可配合`chunks`分批导出或由多个线程分别导出
+    ///
+    /// # Safety
+    ///
+    /// 同`export_columns`
+    ///
+    pub unsafe fn export_columns_in(
+        &self,
+        range: BucketRange,
+        key: Column,
+        value: Column,
+        keys: &mut [u8],
+        values: &mut [u8],
+    ) -> Result<usize, ColumnsTooSmall> {
+        let rows = self.iter_range(range).count();
+        self.export_rows(rows, range, key, value, keys, values)
+    }
+
+    /// 将`range`中的`rows`行entries写入列缓冲区
+    unsafe fn export_rows(
+        &self,
+        rows: usize,
+        range: BucketRange,
+        key: Column,
+        value: Column,
+        keys: &mut [u8],
+        values: &mut [u8],
+    ) -> Result<usize, ColumnsTooSmall> {
+        let fits =
+            |buf: &[u8], size: usize| rows.checked_mul(size).map_or(false, |n| n <= buf.len());
+        if !fits(keys, key.size()) || !fits(values, value.size()) {
+            return Err(ColumnsTooSmall { rows });
+        }
+        let (mut key_out, mut value_out) = (keys.as_mut_ptr(), values.as_mut_ptr());
+        for (entry, value_ptr) in self.iter_range(range) {
+            ptr::copy_nonoverlapping(entry.add(key.offset()), key_out, key.size());
+            ptr::copy_nonoverlapping(value_ptr.add(value.offset()), value_out, value.size());
+            key_out = key_out.add(key.size());
+            value_out = value_out.add(value.size());
+        }
+        Ok(rows)
+    }
+}
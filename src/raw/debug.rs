@@ -0,0 +1,125 @@
+use super::map::EntrySpec;
+use core::fmt;
+
+/// `TableDescriptor`的格式版本号, 已有字段的类型、顺序或语义发生变化时递增
+pub const TABLE_DESCRIPTOR_VERSION: u32 = 1;
+
+///
+/// `RawTable2::describe`返回的table内部结构描述。
+///
+/// 结构体为`#[repr(C)]`, 同一`version`内字段保持不变, 因此GDB/LLDB的python脚本可以直接按字段读取;
+/// 槽位`i`的bucket地址为`bucket0 + i * bucket_stride`, 其ctrl字节为`ctrl[i]`(最高位为0时有效)。
+/// table未分配buckets时`buckets`为0, 且`ctrl`与`bucket0`均为null
+///
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TableDescriptor {
+    /// 即`TABLE_DESCRIPTOR_VERSION`
+    pub version: u32,
+    /// 存储模式, 由`SEPARATE`等标志位组成
+    pub flags: u32,
+    /// bucket数量
+    pub buckets: usize,
+    /// entries数量
+    pub items: usize,
+    /// 扩容之前还能写入的entries数量
+    pub growth_left: usize,
+    /// tombstone数量
+    pub tombstones: usize,
+    /// entry的字节数(未按对齐补齐)
+    pub entry_size: usize,
+    /// entry的对齐要求
+    pub entry_align: usize,
+    /// ctrl字节数组的起始地址
+    pub ctrl: *const u8,
+    /// 槽位0的bucket地址
+    pub bucket0: *const u8,
+    /// 相邻槽位的bucket地址之差, bucket数组与ctrl字节位于同一块内存时为负数
+    pub bucket_stride: isize,
+}
+
+impl TableDescriptor {
+    /// ctrl字节与bucket数组分别分配(见`RawTable2::with_separate_buckets`)
+    pub const SEPARATE: u32 = 1 << 0;
+    /// 固定容量模式(见`RawTable2::with_fixed_capacity`)
+    pub const FIXED: u32 = 1 << 1;
+    /// 大entry模式, entry中只存放指向value内存的指针(见`EntrySpec::out_of_line_value`)
+    pub const OUT_OF_LINE: u32 = 1 << 2;
+    /// Key/Value分离存储(见`EntrySpec::split_value`)
+    pub const SPLIT: u32 = 1 << 3;
+    /// Value为零大小类型的set模式(见`EntrySpec::zero_sized_value`)
+    pub const SET: u32 = 1 << 4;
+}
+
+/// 以单行`key=value`的形式输出, 字段名与顺序同结构体定义
+impl fmt::Display for TableDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version={} flags={:#x} buckets={} items={} growth_left={} tombstones={} \
+             entry_size={} entry_align={} ctrl={:p} bucket0={:p} bucket_stride={}",
+            self.version,
+            self.flags,
+            self.buckets,
+            self.items,
+            self.growth_left,
+            self.tombstones,
+            self.entry_size,
+            self.entry_align,
+            self.ctrl,
+            self.bucket0,
+            self.bucket_stride
+        )
+    }
+}
+
+///
+/// `RawTable2::debug_visit`访问的entry, 通过`Debug`以spec的`fmt_key`/`fmt_value`输出`key => value`
+/// (set模式下只输出Key)
+///
+pub struct DebugEntry<'a> {
+    pub(crate) index: usize,
+    pub(crate) key: *const u8,
+    pub(crate) value: *const u8,
+    pub(crate) set: bool,
+    pub(crate) spec: &'a dyn EntrySpec,
+}
+
+impl DebugEntry<'_> {
+    /// 获取entry的槽位下标
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// 获取entry中Key的内存指针
+    pub fn key(&self) -> *const u8 {
+        self.key
+    }
+
+    /// 获取entry中value的内存指针, set模式下为悬垂指针
+    pub fn value(&self) -> *const u8 {
+        self.value
+    }
+}
+
+impl fmt::Debug for DebugEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.spec.fmt_key(self.key, f)?;
+        if !self.set {
+            f.write_str(" => ")?;
+            self.spec.fmt_value(self.value, f)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// `RawTable2::debug_visit`的访问者, 由调试转储或调试器中的pretty-printer实现
+///
+pub trait EntryVisitor {
+    /// 在访问entries之前以table的描述调用一次, 默认不做任何处理
+    fn begin(&mut self, _desc: &TableDescriptor) {}
+
+    /// 按槽位下标顺序访问每个entry, 返回`false`时停止访问
+    fn visit(&mut self, entry: &DebugEntry<'_>) -> bool;
+}
@@ -0,0 +1,290 @@
+use super::Layout;
+use core::mem;
+
+///
+/// entry的内存结构描述: entry总字节数、value在entry中的偏移量以及entry的对齐要求。
+///
+/// entry由Key与Value依次排布而成, `voff`处即为value的起始地址;
+/// 通过`with_metadata`可以在value之后预留每个bucket的元数据区域, 通过`with_cached_hash`可以在entry尾部缓存Key的hash
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(C)]
+pub struct EntryLayout {
+    size: usize,
+    voff: usize,
+    align: usize,
+    /// 元数据区域的偏移量, 没有元数据时等于value的结束位置
+    moff: usize,
+    /// 元数据区域的字节数
+    msize: usize,
+    /// hash缓存区域的偏移量, 没有缓存hash时为`NO_HASH`
+    hoff: usize,
+}
+
+/// `EntryLayout::hoff`的空值
+const NO_HASH: usize = usize::MAX;
+
+impl EntryLayout {
+    ///
+    /// 基于entry总字节数、value偏移量以及对齐要求构造entry内存结构。
+    ///
+    /// 此函数不做任何校验, caller需保证参数合法, 否则应使用`try_new`
+    ///
+    pub const fn new(size: usize, voff: usize, align: usize) -> Self {
+        Self {
+            size,
+            voff,
+            align,
+            moff: size,
+            msize: 0,
+            hoff: NO_HASH,
+        }
+    }
+
+    ///
+    /// `new`的校验版本, 参数不合法时返回错误:
+    ///
+    /// * `align`不是2的幂;
+    /// * `size`为0, 或不是`align`的整数倍, 或超出`Layout`所能表示的范围;
+    /// * `voff`大于`size`。
+    ///
+    /// value自身的对齐要求无法从这三个参数中推导, `voff`是否满足value的对齐要求仍需caller保证,
+    /// 基于Rust类型的entry应优先使用`of`
+    ///
+    pub const fn try_new(size: usize, voff: usize, align: usize) -> Result<Self, LayoutError> {
+        if !align.is_power_of_two() {
+            return Err(LayoutError::AlignNotPowerOfTwo);
+        }
+        if size == 0 {
+            return Err(LayoutError::ZeroSize);
+        }
+        if size % align != 0 || size > isize::MAX as usize - (align - 1) {
+            return Err(LayoutError::InvalidSize);
+        }
+        if voff > size {
+            return Err(LayoutError::OffsetOutOfBounds);
+        }
+        Ok(Self::new(size, voff, align))
+    }
+
+    ///
+    /// 基于Rust类型`(K, V)`计算entry内存结构, 包括K与V之间以及entry尾部的padding
+    ///
+    pub const fn of<K, V>() -> Self {
+        let k_align = mem::align_of::<K>();
+        let v_align = mem::align_of::<V>();
+        let align = if k_align > v_align { k_align } else { v_align };
+        let voff = round_up(mem::size_of::<K>(), v_align);
+        let size = round_up(voff + mem::size_of::<V>(), align);
+        Self::new(size, voff, align)
+    }
+
+    ///
+    /// 基于Rust类型`(K, V)`计算紧凑的entry内存结构: K与V之间以及entry尾部均没有padding, 对齐要求为1。
+    ///
+    /// 例如`(u64, u8)`的entry由16字节缩减为9字节, 但K与V均可能位于未对齐的地址上,
+    /// spec需通过`ptr::read_unaligned`等方式访问(见`PackedSpec`), 且不能借助`RawMap::get`获取value的引用
+    ///
+    pub const fn packed<K, V>() -> Self {
+        let voff = mem::size_of::<K>();
+        Self::new(voff + mem::size_of::<V>(), voff, 1)
+    }
+
+    ///
+    /// 在value之后追加按`align`对齐的`size`字节元数据区域(例如GC标记位、LRU时间戳或引用计数),
+    /// 返回新的entry内存结构, 已存在的元数据区域会被替换。
+    ///
+    /// 元数据随entry一同在扩容时迁移, 由`EntrySpec::metadata`交给table管理, `align`须为2的幂;
+    /// 已存在的hash缓存区域会移动到新元数据区域之后
+    ///
+    pub const fn with_metadata(self, size: usize, align: usize) -> Self {
+        let moff = round_up(self.moff, align);
+        let align = if self.align > align {
+            self.align
+        } else {
+            align
+        };
+        let layout = Self {
+            size: round_up(moff + size, align),
+            voff: self.voff,
+            align,
+            moff,
+            msize: size,
+            hoff: NO_HASH,
+        };
+        if self.hoff == NO_HASH {
+            layout
+        } else {
+            layout.with_cached_hash()
+        }
+    }
+
+    ///
+    /// 在entry尾部(元数据区域之后)追加8字节的hash缓存区域, 返回新的entry内存结构。
+    ///
+    /// 区域按`min(align, 8)`对齐, 不提高entry的对齐要求, 因此可能位于未对齐的地址上;
+    /// spec通过`EntrySpec::cached_hash`返回`cached_hash()`后, 扩容与原地rehash不再调用`EntrySpec::hash`,
+    /// 查找时也只对缓存hash相同的entry调用`EntrySpec::equals`
+    ///
+    pub const fn with_cached_hash(self) -> Self {
+        let end = self.moff + self.msize;
+        let hoff = round_up(end, if self.align < 8 { self.align } else { 8 });
+        Self {
+            size: round_up(hoff + 8, self.align),
+            voff: self.voff,
+            align: self.align,
+            moff: self.moff,
+            msize: self.msize,
+            hoff,
+        }
+    }
+
+    /// 获取entry总字节数(已包含尾部padding)
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 获取value在entry中的偏移量
+    pub const fn voff(&self) -> usize {
+        self.voff
+    }
+
+    /// 获取entry的对齐要求
+    pub const fn align(&self) -> usize {
+        self.align
+    }
+
+    /// 获取value的字节数, 即`voff`之后、元数据区域之前的全部字节
+    pub const fn value_size(&self) -> usize {
+        self.moff - self.voff
+    }
+
+    /// 获取元数据区域, 偏移量相对entry起始地址; 没有元数据时字节数为0
+    pub const fn metadata(&self) -> Column {
+        Column::new(self.moff, self.msize)
+    }
+
+    /// 获取hash缓存区域的偏移量, 没有缓存hash时返回`None`
+    pub const fn cached_hash(&self) -> Option<usize> {
+        if self.hoff == NO_HASH {
+            None
+        } else {
+            Some(self.hoff)
+        }
+    }
+
+    /// 转换为`EntrySpec::layout`所需的`Layout`
+    pub const fn layout(&self) -> Layout {
+        unsafe { Layout::from_size_align_unchecked(self.size, self.align) }
+    }
+}
+
+///
+/// entry中的一段连续内存(例如value区域中的一列或元数据区域), 由偏移量与字节数描述
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Column {
+    offset: usize,
+    size: usize,
+}
+
+impl Column {
+    /// 基于偏移量与字节数构造
+    pub const fn new(offset: usize, size: usize) -> Self {
+        Self { offset, size }
+    }
+
+    /// 获取偏移量
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// 获取字节数
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+}
+
+///
+/// value区域由`N`个定长列依次排布而成的entry内存结构, 例如聚合状态`(count, sum, min, max)`。
+///
+/// 每列按自身的对齐要求放置, value起始地址按所有列中最大的对齐要求取整,
+/// 因此只要entry按`entry().align()`对齐, 每列的地址都满足其对齐要求
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ColumnLayout<const N: usize> {
+    entry: EntryLayout,
+    columns: [Column; N],
+}
+
+impl<const N: usize> ColumnLayout<N> {
+    ///
+    /// 基于Key与各列的内存结构计算entry内存结构, 包括列之间以及entry尾部的padding
+    ///
+    pub const fn new(key: Layout, columns: [Layout; N]) -> Self {
+        let mut value_align = 1;
+        let mut i = 0;
+        while i < N {
+            if columns[i].align() > value_align {
+                value_align = columns[i].align();
+            }
+            i += 1;
+        }
+
+        let mut offsets = [Column::new(0, 0); N];
+        let mut value_size = 0;
+        let mut i = 0;
+        while i < N {
+            let offset = round_up(value_size, columns[i].align());
+            offsets[i] = Column::new(offset, columns[i].size());
+            value_size = offset + columns[i].size();
+            i += 1;
+        }
+
+        let align = if key.align() > value_align {
+            key.align()
+        } else {
+            value_align
+        };
+        let voff = round_up(key.size(), value_align);
+        let size = round_up(voff + value_size, align);
+        Self {
+            entry: EntryLayout::new(size, voff, align),
+            columns: offsets,
+        }
+    }
+
+    /// 获取完整的entry内存结构
+    pub const fn entry(&self) -> EntryLayout {
+        self.entry
+    }
+
+    /// 获取所有列, 偏移量均相对value起始地址
+    pub const fn columns(&self) -> &[Column; N] {
+        &self.columns
+    }
+
+    /// 获取第`col`列, `col`越界时panic
+    pub const fn column(&self, col: usize) -> Column {
+        self.columns[col]
+    }
+}
+
+/// `EntryLayout::try_new`校验失败时返回的错误
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LayoutError {
+    /// 对齐要求不是2的幂
+    AlignNotPowerOfTwo,
+    /// entry总字节数为0
+    ZeroSize,
+    /// entry总字节数不是对齐要求的整数倍, 或超出`Layout`所能表示的范围
+    InvalidSize,
+    /// value偏移量超出entry总字节数
+    OffsetOutOfBounds,
+}
+
+/// 将`n`向上取整为`align`的整数倍, `align`须为2的幂
+#[inline]
+const fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}
@@ -0,0 +1,105 @@
+use super::map::{EntrySpec, RawTable2};
+use super::{Allocator, Global};
+use crate::scopeguard::guard;
+use crate::TryReserveError;
+use core::hint;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+
+///
+/// 读多写少场景下的并发哈希表: 读取在当前发布的table快照上进行, 不加锁也不会被写入阻塞;
+/// 写入时复制当前table、在副本上修改并原子地发布新快照, 旧快照通过epoch回收在所有读者离开后释放。
+///
+/// 每次写入都会复制整个table, 因此只适用于写入远少于读取的场景(例如路由表); 多次修改应合并到一次`update`中。
+/// 写入之间通过自旋锁串行执行
+///
+pub struct EpochRawMap<E: EntrySpec, A: Allocator = Global> {
+    /// 当前发布的table快照
+    current: Atomic<RawTable2<E, A>>,
+    /// 写入方的互斥标记
+    writing: AtomicBool,
+}
+
+// spec的hook会被多个读者同时调用, 旧快照可能在任意线程中被释放
+unsafe impl<E: EntrySpec + Send + Sync, A: Allocator + Send + Sync> Send for EpochRawMap<E, A> {}
+unsafe impl<E: EntrySpec + Send + Sync, A: Allocator + Send + Sync> Sync for EpochRawMap<E, A> {}
+
+impl<E: EntrySpec + Clone, A: Allocator + Clone> EpochRawMap<E, A> {
+    /// 以`table`作为初始快照构造并发哈希表
+    pub fn new(table: RawTable2<E, A>) -> Self {
+        Self {
+            current: Atomic::new(table),
+            writing: AtomicBool::new(false),
+        }
+    }
+
+    ///
+    /// 在当前快照上调用`f`, 快照在`f`返回前不会被释放; 期间发布的新快照对`f`不可见
+    ///
+    pub fn read<R>(&self, f: impl FnOnce(&RawTable2<E, A>) -> R) -> R {
+        let guard = epoch::pin();
+        let table = self.current.load(Ordering::Acquire, &guard);
+        // 快照始终非空, 且在guard存活期间不会被回收
+        f(unsafe { table.deref() })
+    }
+
+    ///
+    /// 在当前快照中查找`key: &K`, 并以value指针(不存在时为`None`)调用`f`, 指针只在`f`中有效
+    ///
+    pub unsafe fn get<R>(&self, key: *const u8, f: impl FnOnce(Option<*const u8>) -> R) -> R {
+        self.read(|table| f(table.access(key)))
+    }
+
+    /// 获取当前快照中的entries数量
+    pub fn len(&self) -> usize {
+        self.read(|table| table.len())
+    }
+
+    /// 判断当前快照是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// 复制当前快照并以副本调用`f`, 之后将副本发布为新快照; 复制失败时返回错误且快照保持不变。
+    ///
+    /// 写入之间串行执行, `f`中不能再调用此map的`update`, 否则会死锁
+    ///
+    pub fn update<R>(
+        &self,
+        f: impl FnOnce(&mut RawTable2<E, A>) -> R,
+    ) -> Result<R, TryReserveError> {
+        while self
+            .writing
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let _unlock = guard(&self.writing, |writing| {
+            writing.store(false, Ordering::Release)
+        });
+
+        let guard = epoch::pin();
+        let old = self.current.load(Ordering::Acquire, &guard);
+        let mut table = unsafe { old.deref() }.try_clone()?;
+        let result = f(&mut table);
+        self.current.store(Owned::new(table), Ordering::Release);
+        // 旧快照在所有读者离开后释放
+        unsafe { guard.defer_destroy(old) };
+        Ok(result)
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> Drop for EpochRawMap<E, A> {
+    fn drop(&mut self) {
+        // `&mut self`保证不存在读者
+        unsafe {
+            drop(
+                self.current
+                    .load(Ordering::Relaxed, epoch::unprotected())
+                    .into_owned(),
+            )
+        };
+    }
+}
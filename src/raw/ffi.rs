@@ -0,0 +1,288 @@
+use super::entry_layout::EntryLayout;
+use super::map::{EntrySpec, RawTable2};
+use super::{Global, Layout};
+use crate::alloc::boxed::Box;
+use core::ffi::c_void;
+use core::{mem, ptr};
+
+///
+/// C可调用的spec: 宿主以函数指针提供Key的hash、相等性比较与赋值, 所有回调的首个参数均为`ctx`。
+///
+/// `hash`与`equals`接收的指针既可能是caller传入的Key, 也可能是table中的entry(Key位于entry起始处),
+/// 回调只能访问Key所占的字节; `assign_key`为空时按字节拷贝`voff`字节, `assign_value`为空时按字节拷贝`size - voff`字节
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RawSpecFns {
+    /// 传给每个回调的宿主上下文, table不会访问或释放它
+    pub ctx: *mut c_void,
+    /// 计算Key的hash
+    pub hash: unsafe extern "C" fn(ctx: *mut c_void, key: *const u8) -> u64,
+    /// 判断两个Key是否相等
+    pub equals: unsafe extern "C" fn(ctx: *mut c_void, key1: *const u8, key2: *const u8) -> bool,
+    /// 将`key`写入新entry, 为空时按字节拷贝
+    pub assign_key: Option<unsafe extern "C" fn(ctx: *mut c_void, entry: *mut u8, key: *const u8)>,
+    /// 将`v`写入entry中的value内存, 为空时按字节拷贝
+    pub assign_value: Option<unsafe extern "C" fn(ctx: *mut c_void, value: *mut u8, v: *const u8)>,
+}
+
+/// 由`EntryLayout`与`RawSpecFns`组成的spec
+struct FfiSpec {
+    layout: EntryLayout,
+    fns: RawSpecFns,
+}
+
+impl EntrySpec for FfiSpec {
+    #[inline]
+    fn layout(&self) -> Layout {
+        self.layout.layout()
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        unsafe { (self.fns.hash)(self.fns.ctx, entry) }
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { (self.fns.equals)(self.fns.ctx, entry1, entry2) }
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe {
+            match self.fns.assign_key {
+                Some(assign) => assign(self.fns.ctx, entry as *mut u8, k),
+                None => ptr::copy_nonoverlapping(k, entry as *mut u8, self.layout.voff()),
+            }
+        }
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(self.layout.voff()) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe {
+            match self.fns.assign_value {
+                Some(assign) => assign(self.fns.ctx, value as *mut u8, v),
+                None => ptr::copy_nonoverlapping(v, value as *mut u8, self.layout.value_size()),
+            }
+        }
+    }
+
+    #[inline]
+    fn zero_sized_value(&self) -> bool {
+        self.layout.value_size() == 0
+    }
+}
+
+///
+/// `rawtable_new`返回的不透明table句柄, 只能通过本模块的函数访问, 由`rawtable_free`释放
+///
+pub struct RawTableHandle {
+    table: RawTable2<FfiSpec, Global>,
+}
+
+///
+/// 基于entry总字节数、value偏移量以及对齐要求构造`rawtable_new`所需的entry内存结构, 供无法直接构造`EntryLayout`的宿主使用
+///
+#[no_mangle]
+pub extern "C" fn rawtable_entry_layout(size: usize, voff: usize, align: usize) -> EntryLayout {
+    EntryLayout::new(size, voff, align)
+}
+
+///
+/// 构造容量至少为`cap`的空table, 分配失败或`layout`不合法(见`EntryLayout::try_new`)时返回null。
+///
+/// 只使用`layout`的总字节数、value偏移量与对齐要求, 元数据与hash缓存区域被忽略
+///
+#[no_mangle]
+pub extern "C" fn rawtable_new(
+    layout: EntryLayout,
+    fns: RawSpecFns,
+    cap: usize,
+) -> *mut RawTableHandle {
+    let layout = match EntryLayout::try_new(layout.size(), layout.voff(), layout.align()) {
+        Ok(layout) => layout,
+        Err(_) => return ptr::null_mut(),
+    };
+    match RawTable2::new(cap, FfiSpec { layout, fns }, Global) {
+        Ok(table) => Box::into_raw(Box::new(RawTableHandle { table })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+///
+/// 释放`rawtable_new`返回的table, `table`为null时不做任何处理; 之后table中的所有指针均失效
+///
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_free(table: *mut RawTableHandle) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+///
+/// 查找`key`, 返回其value的内存指针, 不存在时返回null; 指针在下一次写入或删除之前有效
+///
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_get(table: *const RawTableHandle, key: *const u8) -> *const u8 {
+    (*table).table.access(key).unwrap_or(ptr::null())
+}
+
+///
+/// 写入`key`与`value`, Key已存在时覆盖其value; 分配失败时返回`false`且table保持不变
+///
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_set(
+    table: *mut RawTableHandle,
+    key: *const u8,
+    value: *const u8,
+) -> bool {
+    (*table).table.try_insert(key, value).is_ok()
+}
+
+///
+/// 删除`key`, 返回Key是否存在
+///
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_delete(table: *mut RawTableHandle, key: *const u8) -> bool {
+    let table = &mut (*table).table;
+    let len = table.len();
+    table.delete(key);
+    table.len() < len
+}
+
+/// 获取table中的entries数量
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_len(table: *const RawTableHandle) -> usize {
+    (*table).table.len()
+}
+
+///
+/// 从槽位`*cursor`开始查找下一个entry, 找到时写入其Key与value的内存指针, 将`*cursor`推进到该entry之后并返回`true`。
+///
+/// `*cursor`初始为0, 返回`false`时迭代结束; 迭代期间写入或删除entries后须从0重新开始
+///
+#[no_mangle]
+pub unsafe extern "C" fn rawtable_iter_next(
+    table: *const RawTableHandle,
+    cursor: *mut usize,
+    key: *mut *const u8,
+    value: *mut *const u8,
+) -> bool {
+    let table = &(*table).table;
+    match table.next_entry(*cursor) {
+        Some((index, entry)) => {
+            *cursor = index + 1;
+            *key = entry;
+            *value = table.value_ptr(entry);
+            true
+        }
+        None => {
+            *cursor = table.buckets();
+            false
+        }
+    }
+}
+
+/// `RawTableVTable`的ABI版本号, 已有字段的类型、顺序或语义发生不兼容的变化时递增
+pub const RAWTABLE_ABI_VERSION: u32 = 1;
+
+///
+/// 本模块各函数的函数表, 由`rawtable_vtable`返回, 供C++宿主跨插件边界持有table而不依赖Rust类型。
+///
+/// ABI约定:
+///
+/// * 结构体为`#[repr(C)]`, 已有字段的类型与顺序在同一`abi_version`内保持不变, 新函数只追加在末尾;
+/// * `size`为本结构体的字节数, caller访问追加的字段之前须确认`size`足以包含该字段;
+/// * `abi_version`与caller编译时的`RAWTABLE_ABI_VERSION`不一致时, caller不得使用此函数表。
+///
+/// 下方的编译期断言固定了本结构体、`RawTableObject`、`RawSpecFns`与`EntryLayout`的大小与对齐
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RawTableVTable {
+    /// 即`RAWTABLE_ABI_VERSION`
+    pub abi_version: u32,
+    /// 本结构体的字节数
+    pub size: u32,
+    /// 同`rawtable_new`
+    pub create: extern "C" fn(EntryLayout, RawSpecFns, usize) -> *mut RawTableHandle,
+    /// 同`rawtable_free`
+    pub destroy: unsafe extern "C" fn(*mut RawTableHandle),
+    /// 同`rawtable_get`
+    pub get: unsafe extern "C" fn(*const RawTableHandle, *const u8) -> *const u8,
+    /// 同`rawtable_set`
+    pub set: unsafe extern "C" fn(*mut RawTableHandle, *const u8, *const u8) -> bool,
+    /// 同`rawtable_delete`
+    pub delete: unsafe extern "C" fn(*mut RawTableHandle, *const u8) -> bool,
+    /// 同`rawtable_len`
+    pub len: unsafe extern "C" fn(*const RawTableHandle) -> usize,
+    /// 同`rawtable_iter_next`
+    pub iter_next: unsafe extern "C" fn(
+        *const RawTableHandle,
+        *mut usize,
+        *mut *const u8,
+        *mut *const u8,
+    ) -> bool,
+}
+
+static VTABLE: RawTableVTable = RawTableVTable {
+    abi_version: RAWTABLE_ABI_VERSION,
+    size: mem::size_of::<RawTableVTable>() as u32,
+    create: rawtable_new,
+    destroy: rawtable_free,
+    get: rawtable_get,
+    set: rawtable_set,
+    delete: rawtable_delete,
+    len: rawtable_len,
+    iter_next: rawtable_iter_next,
+};
+
+///
+/// table句柄与创建它的函数表: 宿主(或其他插件)只通过`vtable`中的函数访问`table`,
+/// 因此持有者无需与创建者链接同一份库, 也不会接触任何Rust类型
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RawTableObject {
+    /// 不透明的table句柄, 创建失败时为null
+    pub table: *mut RawTableHandle,
+    /// 创建`table`的函数表, 始终有效
+    pub vtable: *const RawTableVTable,
+}
+
+// ABI固定的结构体大小与对齐, 修改任何一项都必须递增`RAWTABLE_ABI_VERSION`
+const PTR: usize = mem::size_of::<usize>();
+const _: () = assert!(mem::size_of::<EntryLayout>() == 6 * PTR);
+const _: () = assert!(mem::align_of::<EntryLayout>() == PTR);
+const _: () = assert!(mem::size_of::<RawSpecFns>() == 5 * PTR);
+const _: () = assert!(mem::align_of::<RawSpecFns>() == PTR);
+const _: () = assert!(mem::size_of::<RawTableObject>() == 2 * PTR);
+const _: () = assert!(mem::size_of::<RawTableVTable>() == 8 + 7 * PTR);
+const _: () = assert!(mem::align_of::<RawTableVTable>() == PTR);
+
+/// 获取本库的函数表, 返回的指针在进程的整个生命周期内有效
+#[no_mangle]
+pub extern "C" fn rawtable_vtable() -> *const RawTableVTable {
+    &VTABLE
+}
+
+///
+/// 同`rawtable_new`, 返回table句柄与本库函数表的组合; 创建失败时`table`为null
+///
+#[no_mangle]
+pub extern "C" fn rawtable_new_object(
+    layout: EntryLayout,
+    fns: RawSpecFns,
+    cap: usize,
+) -> RawTableObject {
+    RawTableObject {
+        table: rawtable_new(layout, fns, cap),
+        vtable: &VTABLE,
+    }
+}
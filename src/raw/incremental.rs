@@ -0,0 +1,245 @@
+use super::map::{EntrySpec, GrowthPolicy, InsertError, Iter, RawTable2};
+use super::{Allocator, Global};
+use crate::TryReserveError;
+use core::mem;
+
+///
+/// 增量扩容的哈希表: 扩容时只分配新的table, 旧table中的entries在之后的每次写入或删除中分批迁移,
+/// 每次最多迁移`step`个, 从而将一次性rehash的停顿分摊到后续的操作中。
+///
+/// 迁移期间查找依次访问新旧两个table, 写入只发生在新table中; 新table的容量按扩容策略计算,
+/// 在旧table迁移完成之前足以容纳所有entries, 因此写入不会在迁移期间再次触发扩容。
+/// 写入速度远超迁移速度(例如`GrowthPolicy::Step`的步长过小)时, 剩余的entries会在下一次扩容前一次迁移完成
+///
+pub struct IncrementalRawMap<E: EntrySpec, A: Allocator = Global> {
+    /// 接收写入的table
+    table: RawTable2<E, A>,
+    /// 正在迁移的旧table, 不在扩容中时为`None`
+    old: Option<RawTable2<E, A>>,
+    /// 旧table中下一次迁移的起始槽位
+    cursor: usize,
+    /// 每次操作最多迁移的entries数量
+    step: usize,
+}
+
+impl<E: EntrySpec + Clone, A: Allocator + Clone> IncrementalRawMap<E, A> {
+    ///
+    /// 构造容量至少为`cap`的哈希表, 扩容后每次写入或删除最多迁移`step`个entries(至少为1)
+    ///
+    pub fn new(cap: usize, step: usize, spec: E, alloc: A) -> Result<Self, TryReserveError> {
+        Ok(Self {
+            table: RawTable2::new(cap, spec, alloc)?,
+            old: None,
+            cursor: 0,
+            step: step.max(1),
+        })
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`, 已存在的Key覆盖其value; 写入前先迁移至多`step`个entries。
+    ///
+    /// 错误语义同`RawTable2::try_insert`; 迁移失败(大entry模式的value或payload分配失败)时同样返回错误,
+    /// 此时尚未迁移的entries仍保留在旧table中
+    ///
+    pub unsafe fn try_insert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+    ) -> Result<(), InsertError> {
+        self.prepare(key)?;
+        self.table.try_insert(key, value)
+    }
+
+    ///
+    /// 获取`key: &K`的可赋值value地址, 不存在时先写入Key, 新写入Key的value未初始化。
+    ///
+    /// 错误语义同`try_insert`, 返回的地址在下一次修改此map之前有效
+    ///
+    pub unsafe fn try_assign(&mut self, key: *const u8) -> Result<*const u8, InsertError> {
+        self.prepare(key)?;
+        self.table.try_assign(key)
+    }
+
+    ///
+    /// 删除`key: &K`, 之后迁移至多`step`个entries; 删除不会失败, 迁移失败时留待之后的操作重试
+    ///
+    pub unsafe fn delete(&mut self, key: *const u8) {
+        self.table.delete(key);
+        if let Some(old) = &mut self.old {
+            old.delete(key);
+        }
+        let _ = self.migrate(self.step);
+    }
+
+    ///
+    /// 一次迁移完旧table中剩余的所有entries, 不在扩容中时不做任何处理
+    ///
+    pub fn finish_resize(&mut self) -> Result<(), TryReserveError> {
+        unsafe { self.migrate(usize::MAX) }
+    }
+
+    ///
+    /// 迁移完剩余的entries后拆出内部的table; 迁移失败时返回错误, 此时所有entries随当前map一同释放
+    ///
+    pub fn into_table(mut self) -> Result<RawTable2<E, A>, TryReserveError> {
+        self.finish_resize()?;
+        Ok(self.table)
+    }
+
+    ///
+    /// 写入`key`之前的准备: 迁移一批entries, 保证新table无需扩容即可写入新Key,
+    /// 并将`key`从旧table移动到新table, 以免同一个Key同时存在于两个table中
+    ///
+    unsafe fn prepare(&mut self, key: *const u8) -> Result<(), TryReserveError> {
+        self.migrate(self.step)?;
+        self.reserve_one()?;
+        if let Some(old) = &mut self.old {
+            if let Some(index) = old.find_index(key) {
+                self.table.move_from(old, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 保证新table在容纳旧table剩余entries之外还能写入一个新Key, 必要时开始新一轮扩容。
+    ///
+    /// 开始扩容时新table的容量大于所有entries的数量, 之后每迁移一个entry新table的剩余容量与旧table的entries数量同时减一,
+    /// 因此新table的剩余容量始终不少于旧table的entries数量
+    ///
+    fn reserve_one(&mut self) -> Result<(), TryReserveError> {
+        let pending = self.pending();
+        if self.table.growth_left() > pending {
+            return Ok(());
+        }
+        // 迁移跟不上写入, 先迁移完剩余的entries
+        self.finish_resize()?;
+        if self.table.growth_left() > 0 {
+            return Ok(());
+        }
+        let new_items = match self.table.len().checked_add(1) {
+            Some(new_items) => new_items,
+            None => return Err(TryReserveError::CapacityOverflow),
+        };
+        // tombstone较多时按原容量重建, 同样分批迁移
+        let capacity = self
+            .table
+            .growth_capacity(new_items)
+            .unwrap_or_else(|| self.table.capacity());
+        let table = self.table.empty_like(capacity)?;
+        self.old = Some(mem::replace(&mut self.table, table));
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// 从旧table迁移至多`n`个entries到新table, 旧table迁移完成后被释放
+    unsafe fn migrate(&mut self, n: usize) -> Result<(), TryReserveError> {
+        let old = match &mut self.old {
+            Some(old) => old,
+            None => return Ok(()),
+        };
+        for _ in 0..n {
+            let index = match old.next_entry(self.cursor) {
+                Some((index, _)) => index,
+                None => break,
+            };
+            self.table.move_from(old, index)?;
+            self.cursor = index + 1;
+        }
+        if old.is_empty() {
+            self.old = None;
+            self.cursor = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> IncrementalRawMap<E, A> {
+    ///
+    /// 获取`key: &K`的value指针, 迁移期间依次查找新旧两个table
+    ///
+    pub unsafe fn access(&self, key: *const u8) -> Option<*const u8> {
+        match self.table.access(key) {
+            Some(value) => Some(value),
+            None => self.old.as_ref()?.access(key),
+        }
+    }
+
+    /// 每次操作最多迁移的entries数量
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// 判断是否处于扩容迁移中
+    pub fn is_resizing(&self) -> bool {
+        self.old.is_some()
+    }
+
+    /// 获取旧table中尚未迁移的entries数量
+    pub fn pending(&self) -> usize {
+        self.old.as_ref().map_or(0, |old| old.len())
+    }
+
+    /// 获取entries数量, 包括尚未迁移的entries
+    pub fn len(&self) -> usize {
+        self.table.len() + self.pending()
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 获取接收写入的table在下一次扩容之前最多可容纳的entries数量
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    ///
+    /// 设置扩容时计算新容量的策略, 默认为`GrowthPolicy::Double`; 新策略从下一轮扩容开始生效
+    ///
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.table.set_growth_policy(policy);
+    }
+
+    /// 获取spec
+    pub fn spec(&self) -> &E {
+        self.table.spec()
+    }
+
+    ///
+    /// 迭代所有entries, 返回`(key, value)`的内存指针: 先给出新table中的entries, 再给出尚未迁移的entries
+    ///
+    pub fn iter(&self) -> IncrementalIter<'_, E, A> {
+        IncrementalIter {
+            current: self.table.iter(),
+            old: self.old.as_ref().map(|old| old.iter()),
+        }
+    }
+}
+
+///
+/// `IncrementalRawMap::iter`返回的迭代器
+///
+pub struct IncrementalIter<'a, E: EntrySpec, A: Allocator> {
+    current: Iter<'a, E, A>,
+    old: Option<Iter<'a, E, A>>,
+}
+
+impl<E: EntrySpec, A: Allocator> Iterator for IncrementalIter<'_, E, A> {
+    type Item = (*const u8, *const u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.current.next() {
+            Some(item) => Some(item),
+            None => self.old.as_mut()?.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.current.len() + self.old.as_ref().map_or(0, |old| old.len());
+        (len, Some(len))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> ExactSizeIterator for IncrementalIter<'_, E, A> {}
@@ -0,0 +1,279 @@
+use super::map::{AssignError, EntrySpec, InsertError, KeyScratch, RawTable2};
+use super::{Allocator, Global};
+use crate::TryReserveError;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+/// 内联区域的对齐要求, entry的对齐要求超过它时不使用内联存储
+const INLINE_ALIGN: usize = 16;
+
+/// 按`INLINE_ALIGN`对齐的`BYTES`字节内联区域
+#[repr(C, align(16))]
+struct InlineBuf<const BYTES: usize>([MaybeUninit<u8>; BYTES]);
+
+///
+/// 小规模时不分配堆内存的哈希表: 最多`BYTES / entry大小`个entries内联存放在结构体自身的`BYTES`字节区域中,
+/// 按插入顺序排列并通过`equals`线性查找, 超出后整体迁移(spill)到swiss table表示。
+///
+/// 内联期间不计算hash, 内部的`RawTable2`保持未分配bucket数组的空状态; 迁移之后即使entries减少也不再回到内联存储。
+/// 大entry模式、SoA模式、对齐要求超过16字节的spec, 以及需要payload的Key总是直接存放在swiss table中。
+/// 适用于数量巨大且大多只有几个entries的小map, 例如每个对象各自持有的属性表
+///
+pub struct InlineRawMap<E: EntrySpec, const BYTES: usize, A: Allocator = Global> {
+    /// 迁移之后存放所有entries的table, 内联期间只用于持有spec与Allocator
+    table: RawTable2<E, A>,
+    /// 内联的entries数量
+    len: usize,
+    /// 是否已迁移到`table`
+    spilled: bool,
+    /// 内联entries, 通过`&self`得到的entry地址同样可用于写入value(例如`try_assign`的返回值)
+    buf: UnsafeCell<InlineBuf<BYTES>>,
+}
+
+// 内联entries只在`&mut self`的操作中修改, 与`RawTable2`的线程安全约束一致
+unsafe impl<E: EntrySpec + Sync, const BYTES: usize, A: Allocator + Sync> Sync
+    for InlineRawMap<E, BYTES, A>
+{
+}
+
+impl<E: EntrySpec, const BYTES: usize, A: Allocator> InlineRawMap<E, BYTES, A> {
+    ///
+    /// 构造空的哈希表, 在entries超出内联容量之前不分配任何内存
+    ///
+    pub fn new(spec: E, alloc: A) -> Result<Self, TryReserveError> {
+        Ok(Self {
+            table: RawTable2::new(0, spec, alloc)?,
+            len: 0,
+            spilled: false,
+            buf: UnsafeCell::new(InlineBuf([MaybeUninit::uninit(); BYTES])),
+        })
+    }
+
+    ///
+    /// 获取内联存储最多容纳的entries数量, spec不支持内联存储时为0
+    ///
+    pub fn inline_capacity(&self) -> usize {
+        let layout = self.table.spec().layout();
+        if layout.align() > INLINE_ALIGN
+            || self.table.out_of_line().is_some()
+            || self.table.split().is_some()
+        {
+            return 0;
+        }
+        BYTES / layout.pad_to_align().size()
+    }
+
+    /// 判断entries是否仍内联存放
+    pub fn is_inline(&self) -> bool {
+        !self.spilled
+    }
+
+    /// 获取entries数量
+    pub fn len(&self) -> usize {
+        if self.spilled {
+            self.table.len()
+        } else {
+            self.len
+        }
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 获取spec
+    pub fn spec(&self) -> &E {
+        self.table.spec()
+    }
+
+    ///
+    /// 获取已迁移的swiss table, 内联期间返回`None`
+    ///
+    pub fn as_table(&self) -> Option<&RawTable2<E, A>> {
+        if self.spilled {
+            Some(&self.table)
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// 获取`key: &K`的value指针, 内联期间逐个比较Key
+    ///
+    pub unsafe fn access(&self, key: *const u8) -> Option<*const u8> {
+        if self.spilled {
+            return self.table.access(key);
+        }
+        let mut scratch = KeyScratch::new();
+        let key = self.normalize(key, &mut scratch);
+        let index = self.find_inline(key)?;
+        Some(self.table.value_ptr(self.entry(index)))
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`, 已存在的Key覆盖其value。
+    ///
+    /// 内联存储已满或Key需要payload时先迁移到swiss table; 错误语义同`RawTable2::try_insert`,
+    /// 迁移失败时entries仍内联存放
+    ///
+    pub unsafe fn try_insert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+    ) -> Result<(), InsertError> {
+        if !self.spilled {
+            let mut scratch = KeyScratch::new();
+            let k = self.normalize(key, &mut scratch);
+            let found = match self.find_inline(k) {
+                Some(index) => Some(Ok(index)),
+                None => self.insert_inline(k)?.map(Err),
+            };
+            match found {
+                _ if found.is_some() && self.table.spec().zero_sized_value() => return Ok(()),
+                Some(Ok(index)) => {
+                    let value_ptr = self.table.value_ptr(self.entry(index));
+                    self.table.spec().try_replace_value(value_ptr, value)?;
+                    return Ok(());
+                }
+                Some(Err(index)) => {
+                    let value_ptr = self.table.value_ptr(self.entry(index));
+                    if let Err(err) = self.table.spec().try_assign_value(value_ptr, value) {
+                        self.len -= 1;
+                        return Err(err.into());
+                    }
+                    return Ok(());
+                }
+                None => self.spill()?,
+            }
+        }
+        self.table.try_insert(key, value)
+    }
+
+    ///
+    /// 获取`key: &K`的可赋值value地址, 不存在时先写入Key, 新写入Key的value未初始化。
+    ///
+    /// 错误语义同`try_insert`, 返回的地址在下一次修改此map之前有效
+    ///
+    pub unsafe fn try_assign(&mut self, key: *const u8) -> Result<*const u8, InsertError> {
+        if !self.spilled {
+            let mut scratch = KeyScratch::new();
+            let k = self.normalize(key, &mut scratch);
+            let found = match self.find_inline(k) {
+                Some(index) => Some(index),
+                None => self.insert_inline(k)?,
+            };
+            match found {
+                Some(index) => return Ok(self.table.value_ptr(self.entry(index))),
+                None => self.spill()?,
+            }
+        }
+        self.table.try_assign(key)
+    }
+
+    ///
+    /// 删除`key: &K`, 内联期间由最后一个entry填补被删除的位置
+    ///
+    pub unsafe fn delete(&mut self, key: *const u8) {
+        if self.spilled {
+            return self.table.delete(key);
+        }
+        let mut scratch = KeyScratch::new();
+        let key = self.normalize(key, &mut scratch);
+        if let Some(index) = self.find_inline(key) {
+            self.len -= 1;
+            if index != self.len {
+                let size = self.stride();
+                ptr::copy_nonoverlapping(self.entry(self.len), self.entry(index), size);
+            }
+        }
+    }
+
+    ///
+    /// 依次以每个entry的Key与value指针调用`f`, 内联期间按插入顺序(删除会打乱顺序)
+    ///
+    pub fn for_each(&self, mut f: impl FnMut(*const u8, *const u8)) {
+        if self.spilled {
+            for (key, value) in self.table.iter() {
+                f(key, value);
+            }
+            return;
+        }
+        for index in 0..self.len {
+            let entry = self.entry(index);
+            f(entry, unsafe { self.table.value_ptr(entry) });
+        }
+    }
+
+    ///
+    /// 清空所有entries; 已迁移时保留swiss table的内存
+    ///
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.table.clear();
+    }
+
+    ///
+    /// 迁移到swiss table后拆出内部的table
+    ///
+    pub fn into_table(mut self) -> Result<RawTable2<E, A>, TryReserveError> {
+        if !self.spilled {
+            unsafe { self.spill()? };
+        }
+        Ok(self.table)
+    }
+
+    /// entry的步长, 即按对齐补齐后的entry字节数
+    fn stride(&self) -> usize {
+        self.table.spec().layout().pad_to_align().size()
+    }
+
+    /// 获取第`index`个内联entry的地址
+    fn entry(&self, index: usize) -> *mut u8 {
+        unsafe { (self.buf.get() as *mut u8).add(index * self.stride()) }
+    }
+
+    /// 通过`EntrySpec::normalize_key`规范化`key`, 返回的指针可能指向`scratch`
+    unsafe fn normalize(&self, key: *const u8, scratch: &mut KeyScratch) -> *const u8 {
+        self.table.spec().normalize_key(key, scratch.0.as_mut_ptr())
+    }
+
+    /// 在内联entries中查找已规范化的`key`
+    unsafe fn find_inline(&self, key: *const u8) -> Option<usize> {
+        let spec = self.table.spec();
+        (0..self.len).find(|&index| spec.equals(key, self.entry(index)))
+    }
+
+    ///
+    /// 将已规范化的`key`追加为新的内联entry并返回其下标, 内联存储已满或Key需要payload时返回`None`
+    ///
+    unsafe fn insert_inline(&mut self, key: *const u8) -> Result<Option<usize>, AssignError> {
+        let spec = self.table.spec();
+        if self.len >= self.inline_capacity() || spec.payload_size(key) > 0 {
+            return Ok(None);
+        }
+        let entry = self.entry(self.len);
+        if let Some(metadata) = spec.metadata() {
+            ptr::write_bytes(entry.add(metadata.offset()), 0, metadata.size());
+        }
+        spec.try_assign_key(entry, key)?;
+        self.len += 1;
+        Ok(Some(self.len - 1))
+    }
+
+    ///
+    /// 将所有内联entries迁移到swiss table, 预留的容量为内联容量的两倍
+    ///
+    unsafe fn spill(&mut self) -> Result<(), TryReserveError> {
+        self.table
+            .try_reserve((self.inline_capacity() * 2).max(1))?;
+        for index in 0..self.len {
+            // 容量已预留且内联的Key没有payload, 拷贝不会失败
+            self.table.insert_entry(self.entry(index))?;
+        }
+        self.len = 0;
+        self.spilled = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use super::spec::{INT_FMIX_1, INT_FMIX_2, INT_HASH_CROSS, INT_HASH_LANES, INT_MIX_PRIME};
+use core::arch::x86_64 as x86;
+
+/// 4个lane的64位字
+type Lanes = x86::__m256i;
+
+/// 每个AVX2寄存器容纳的lane数量
+const LANES_PER_VECTOR: usize = 4;
+
+///
+/// 逐lane计算`a * k`(mod 2^64)。
+///
+/// AVX2没有64x64位乘法, 拆分为三次32x32→64位乘法: `lo*lo + ((hi*lo + lo*hi) << 32)`, 结果与`wrapping_mul`相同
+///
+#[inline(always)]
+unsafe fn mul_const(a: Lanes, k: u64) -> Lanes {
+    let k_lo = x86::_mm256_set1_epi64x(i64::from(k as u32));
+    let k_hi = x86::_mm256_set1_epi64x((k >> 32) as i64);
+    let cross = x86::_mm256_add_epi64(
+        x86::_mm256_mul_epu32(x86::_mm256_srli_epi64(a, 32), k_lo),
+        x86::_mm256_mul_epu32(a, k_hi),
+    );
+    x86::_mm256_add_epi64(
+        x86::_mm256_mul_epu32(a, k_lo),
+        x86::_mm256_slli_epi64(cross, 32),
+    )
+}
+
+/// 逐lane计算`h ^ (h >> 33)`
+#[inline(always)]
+unsafe fn xor_shift(h: Lanes) -> Lanes {
+    x86::_mm256_xor_si256(h, x86::_mm256_srli_epi64(h, 33))
+}
+
+/// 逐lane计算`int_hash`, 输入为与种子异或之后的64位字
+#[inline(always)]
+unsafe fn mixed_lanes(x: Lanes) -> Lanes {
+    // 两个32位半字交叉相乘正好是`_mm256_mul_epu32`
+    let cross = x86::_mm256_xor_si256(
+        x86::_mm256_srli_epi64(x, 32),
+        x86::_mm256_set1_epi64x(INT_HASH_CROSS as i64),
+    );
+    let product = x86::_mm256_mul_epu32(x, cross);
+    let rotated =
+        x86::_mm256_or_si256(x86::_mm256_slli_epi64(x, 23), x86::_mm256_srli_epi64(x, 41));
+    let mut h = xor_shift(x86::_mm256_xor_si256(product, rotated));
+    h = xor_shift(mul_const(h, INT_FMIX_1));
+    xor_shift(mul_const(h, INT_FMIX_2))
+}
+
+/// 将`words`与种子异或之后逐个寄存器调用`f`, 结果写入`out`
+#[inline(always)]
+unsafe fn hash_with(
+    words: &[u64; INT_HASH_LANES],
+    seed: u64,
+    out: &mut [u64; INT_HASH_LANES],
+    f: impl Fn(Lanes) -> Lanes,
+) {
+    let seed = x86::_mm256_set1_epi64x(seed as i64);
+    for i in (0..INT_HASH_LANES).step_by(LANES_PER_VECTOR) {
+        let x = x86::_mm256_loadu_si256(words.as_ptr().add(i).cast());
+        let h = f(x86::_mm256_xor_si256(x, seed));
+        x86::_mm256_storeu_si256(out.as_mut_ptr().add(i).cast(), h);
+    }
+}
+
+/// `IntHash::Mixed`的批量版本, 与逐个计算的结果逐位一致
+#[inline]
+pub(super) unsafe fn mixed(
+    words: &[u64; INT_HASH_LANES],
+    seed: u64,
+    out: &mut [u64; INT_HASH_LANES],
+) {
+    hash_with(words, seed, out, |x| unsafe { mixed_lanes(x) });
+}
+
+/// `IntHash::Fibonacci`的批量版本, 与逐个计算的结果逐位一致
+#[inline]
+pub(super) unsafe fn fibonacci(
+    words: &[u64; INT_HASH_LANES],
+    seed: u64,
+    out: &mut [u64; INT_HASH_LANES],
+) {
+    hash_with(words, seed, out, |x| unsafe { mul_const(x, INT_MIX_PRIME) });
+}
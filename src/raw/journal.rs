@@ -0,0 +1,296 @@
+use super::map::{EntrySpec, InsertError, RawTable2};
+use super::{Allocator, Global};
+use crate::alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+/// 日志记录对应的写入操作
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum JournalOp {
+    /// 写入了新的Key
+    Insert,
+    /// 覆盖了已存在Key的value
+    Overwrite,
+    /// 删除了已存在的Key
+    Delete,
+}
+
+impl JournalOp {
+    fn tag(self) -> u8 {
+        match self {
+            Self::Insert => 0,
+            Self::Overwrite => 1,
+            Self::Delete => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::Insert),
+            1 => Some(Self::Overwrite),
+            2 => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+///
+/// 一条日志记录, Key与value为`EntrySpec::encode_key`/`encode_value`的编码结果, `Delete`记录的value为空。
+///
+/// 记录只在`JournalSink::append`期间有效, sink须自行拷贝或写出
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct JournalRecord<'r> {
+    /// 写入操作
+    pub op: JournalOp,
+    /// 编码后的Key
+    pub key: &'r [u8],
+    /// 编码后的value
+    pub value: &'r [u8],
+}
+
+impl<'r> JournalRecord<'r> {
+    ///
+    /// 将记录以紧凑的二进制格式追加到`out`: 操作类型(1字节)、LEB128编码的Key长度与Key、LEB128编码的value长度与value
+    ///
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.op.tag());
+        for bytes in [self.key, self.value] {
+            let mut len = bytes.len();
+            while len >= 0x80 {
+                out.push(len as u8 | 0x80);
+                len >>= 7;
+            }
+            out.push(len as u8);
+            out.extend_from_slice(bytes);
+        }
+    }
+
+    ///
+    /// 从`bytes`起始处解码`encode`写入的一条记录, 返回记录及其字节数; 数据不完整或无效时返回`None`
+    ///
+    pub fn decode(bytes: &'r [u8]) -> Option<(Self, usize)> {
+        let op = JournalOp::from_tag(*bytes.first()?)?;
+        let mut pos = 1;
+        let mut field = || {
+            let mut len = 0usize;
+            let mut shift = 0;
+            loop {
+                let byte = *bytes.get(pos)?;
+                pos += 1;
+                len |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            let field = bytes.get(pos..pos.checked_add(len)?)?;
+            pos += len;
+            Some(field)
+        };
+        let key = field()?;
+        let value = field()?;
+        Some((Self { op, key, value }, pos))
+    }
+}
+
+///
+/// 依次解码`encode`写入的连续记录的迭代器, 遇到不完整或无效的记录(例如写入中途崩溃留下的尾部)时结束
+///
+pub struct JournalRecords<'r> {
+    bytes: &'r [u8],
+    /// 已解码的字节数
+    consumed: usize,
+}
+
+impl<'r> JournalRecords<'r> {
+    /// 构造解码`bytes`中记录的迭代器
+    pub fn new(bytes: &'r [u8]) -> Self {
+        Self { bytes, consumed: 0 }
+    }
+
+    ///
+    /// 获取已解码的完整记录的字节数, 迭代结束后即为有效前缀的长度, 可据此截断日志尾部
+    ///
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+}
+
+impl<'r> Iterator for JournalRecords<'r> {
+    type Item = JournalRecord<'r>;
+
+    fn next(&mut self) -> Option<JournalRecord<'r>> {
+        let (record, len) = JournalRecord::decode(&self.bytes[self.consumed..])?;
+        self.consumed += len;
+        Some(record)
+    }
+}
+
+impl FusedIterator for JournalRecords<'_> {}
+
+/// 接收日志记录的sink, 例如追加写入日志文件
+pub trait JournalSink {
+    /// 接收一条已生效的写入操作的记录
+    fn append(&mut self, record: JournalRecord<'_>);
+}
+
+impl<F: FnMut(JournalRecord<'_>)> JournalSink for F {
+    fn append(&mut self, record: JournalRecord<'_>) {
+        self(record)
+    }
+}
+
+/// `JournaledTable`写入或replay时的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum JournalError {
+    /// spec不支持编码或无法解码记录中的Key、value
+    Codec,
+    /// table写入失败
+    Insert(InsertError),
+}
+
+impl From<InsertError> for JournalError {
+    fn from(value: InsertError) -> Self {
+        Self::Insert(value)
+    }
+}
+
+///
+/// 记录每次写入的`RawTable2`包装: 每次insert、覆盖与删除生效后, 通过`EntrySpec::encode_key`/`encode_value`
+/// 将其编码为一条记录交给sink; 之后可在快照(或空table)上通过`replay`重放记录恢复table。
+///
+/// 记录在写入生效后才交给sink, 只有sink持久化记录之后写入才应被视为已提交
+///
+pub struct JournaledTable<E: EntrySpec, S: JournalSink, A: Allocator = Global> {
+    table: RawTable2<E, A>,
+    sink: S,
+    /// 编码Key与value的缓冲区, 在多次写入之间复用
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+impl<E: EntrySpec, S: JournalSink, A: Allocator> JournaledTable<E, S, A> {
+    ///
+    /// 以`table`及其已有的entries为起点记录之后的写入, 已有的entries不会被记录
+    ///
+    pub fn new(table: RawTable2<E, A>, sink: S) -> Self {
+        Self {
+            table,
+            sink,
+            key: Vec::new(),
+            value: Vec::new(),
+        }
+    }
+
+    ///
+    /// 在`table`(空table或通过`read_snapshot`恢复的快照)上依次重放`records`, 重放的记录不会再交给`sink`。
+    ///
+    /// 记录无法解码时返回`JournalError::Codec`, 此前的记录已生效
+    ///
+    pub unsafe fn replay<'r>(
+        mut table: RawTable2<E, A>,
+        records: impl IntoIterator<Item = JournalRecord<'r>>,
+        sink: S,
+    ) -> Result<Self, JournalError> {
+        for record in records {
+            let decoded = match record.op {
+                JournalOp::Insert | JournalOp::Overwrite => {
+                    table.try_insert_encoded(record.key, record.value)?
+                }
+                JournalOp::Delete => table.delete_encoded(record.key),
+            };
+            if !decoded {
+                return Err(JournalError::Codec);
+            }
+        }
+        Ok(Self::new(table, sink))
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`并记录`Insert`或`Overwrite`。
+    ///
+    /// spec不支持编码时返回`JournalError::Codec`, 此时写入已生效但未被记录
+    ///
+    pub unsafe fn try_insert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+    ) -> Result<(), JournalError> {
+        let op = if self.table.access(key).is_some() {
+            JournalOp::Overwrite
+        } else {
+            JournalOp::Insert
+        };
+        self.table.try_insert(key, value)?;
+        let entry = self.table.find_entry(key).unwrap();
+        self.key.clear();
+        self.value.clear();
+        let spec = self.table.spec();
+        if !spec.encode_key(entry, &mut self.key)
+            || !spec.encode_value(self.table.value_ptr(entry), &mut self.value)
+        {
+            return Err(JournalError::Codec);
+        }
+        self.sink.append(JournalRecord {
+            op,
+            key: &self.key,
+            value: &self.value,
+        });
+        Ok(())
+    }
+
+    ///
+    /// 删除`key: &K`并记录`Delete`, Key不存在时返回`Ok(false)`且不产生记录。
+    ///
+    /// spec不支持编码时返回`JournalError::Codec`, 此时Key不会被删除
+    ///
+    pub unsafe fn delete(&mut self, key: *const u8) -> Result<bool, JournalError> {
+        let entry = match self.table.find_entry(key) {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        self.key.clear();
+        if !self.table.spec().encode_key(entry, &mut self.key) {
+            return Err(JournalError::Codec);
+        }
+        self.table.delete(key);
+        self.sink.append(JournalRecord {
+            op: JournalOp::Delete,
+            key: &self.key,
+            value: &[],
+        });
+        Ok(true)
+    }
+
+    ///
+    /// 获取底层table, 用于查找或写入快照
+    ///
+    pub fn table(&self) -> &RawTable2<E, A> {
+        &self.table
+    }
+
+    /// 获取sink
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// 获取sink的可变引用, 例如在写入快照后截断日志
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// 获取entries数量
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// 拆分为底层table与sink
+    pub fn into_parts(self) -> (RawTable2<E, A>, S) {
+        (self.table, self.sink)
+    }
+}
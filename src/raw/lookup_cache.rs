@@ -0,0 +1,83 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 查找缓存的槽数, 按hash直接映射到其中一个槽
+const LOOKUP_CACHE_SLOTS: usize = 4;
+
+///
+/// 记录最近查找的hash与命中槽位下标的缓存, 供`RawTable2::set_lookup_cache`开启后的查找跳过探测。
+///
+/// 每个槽记录写入时的代数, table移动entries(扩容、收缩、原地整理、`reset`等)时递增代数使所有槽失效。
+/// 缓存只是提示: 命中后caller仍需确认槽位有效且Key相等, 因此并发查找交错写入同一个槽也不会得到错误结果
+///
+pub(crate) struct LookupCache {
+    generation: AtomicUsize,
+    slots: [CacheSlot; LOOKUP_CACHE_SLOTS],
+}
+
+struct CacheSlot {
+    /// hash的低位(32位平台上截断)
+    hash: AtomicUsize,
+    index: AtomicUsize,
+    generation: AtomicUsize,
+}
+
+impl CacheSlot {
+    const fn new() -> Self {
+        Self {
+            hash: AtomicUsize::new(0),
+            index: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl LookupCache {
+    /// 构造空缓存, 槽的初始代数为0, 因此在首次写入之前都不会命中
+    pub(crate) const fn new() -> Self {
+        Self {
+            generation: AtomicUsize::new(1),
+            slots: [
+                CacheSlot::new(),
+                CacheSlot::new(),
+                CacheSlot::new(),
+                CacheSlot::new(),
+            ],
+        }
+    }
+
+    #[inline(always)]
+    fn slot(&self, hash: u64) -> &CacheSlot {
+        // 低位用于起始槽位、最高7位用于H2, 取中间的位选择缓存槽
+        &self.slots[(hash >> 32) as usize % LOOKUP_CACHE_SLOTS]
+    }
+
+    /// 获取当前代数下`hash`最近一次命中的槽位下标
+    #[inline]
+    pub(crate) fn get(&self, hash: u64) -> Option<usize> {
+        let slot = self.slot(hash);
+        if slot.generation.load(Ordering::Relaxed) != self.generation.load(Ordering::Relaxed)
+            || slot.hash.load(Ordering::Relaxed) != hash as usize
+        {
+            return None;
+        }
+        Some(slot.index.load(Ordering::Relaxed))
+    }
+
+    /// 记录`hash`命中槽位`index`
+    #[inline]
+    pub(crate) fn put(&self, hash: u64, index: usize) {
+        let slot = self.slot(hash);
+        slot.hash.store(hash as usize, Ordering::Relaxed);
+        slot.index.store(index, Ordering::Relaxed);
+        slot.generation
+            .store(self.generation.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    /// 递增代数, 使所有槽失效
+    #[inline]
+    pub(crate) fn invalidate(&mut self) {
+        let generation = self.generation.get_mut();
+        // 跳过0, 未写入过的槽始终无效
+        *generation = generation.wrapping_add(1).max(1);
+    }
+}
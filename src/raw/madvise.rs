@@ -0,0 +1,115 @@
+use core::ptr::NonNull;
+
+///
+/// `RawTable2`向内核提供的内存使用建议(`madvise`), 只影响性能而不影响table的行为。
+///
+/// 建议只作用于完全落在table分配内的整页, 小于一页的数组不会被建议; 内核不支持或调用失败时静默忽略。
+/// `MADV_HUGEPAGE`仅在Linux/Android上生效, 其他建议在所有unix平台上生效, 非unix平台上全部为空操作
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MemoryHints {
+    huge_pages: bool,
+    release_on_shrink: bool,
+    sequential_scans: bool,
+}
+
+impl MemoryHints {
+    /// 构造不提供任何建议的配置
+    pub const fn new() -> Self {
+        Self {
+            huge_pages: false,
+            release_on_shrink: false,
+            sequential_scans: false,
+        }
+    }
+
+    /// 设置是否对bucket数组建议`MADV_HUGEPAGE`, 在设置时以及之后每次重新分配时生效
+    pub const fn with_huge_pages(mut self, enabled: bool) -> Self {
+        self.huge_pages = enabled;
+        self
+    }
+
+    ///
+    /// 设置是否在收缩与`reset`释放内存之前建议`MADV_DONTNEED`,
+    /// 使内存立即归还给内核, 而不是由allocator缓存
+    ///
+    pub const fn with_release_on_shrink(mut self, enabled: bool) -> Self {
+        self.release_on_shrink = enabled;
+        self
+    }
+
+    ///
+    /// 设置是否在全表扫描(`for_each_key`、`retain`、`clear_and_evict`与`scan`)期间建议`MADV_SEQUENTIAL`,
+    /// 扫描结束后恢复为`MADV_NORMAL`
+    ///
+    pub const fn with_sequential_scans(mut self, enabled: bool) -> Self {
+        self.sequential_scans = enabled;
+        self
+    }
+
+    /// 判断是否建议`MADV_HUGEPAGE`
+    pub const fn huge_pages(&self) -> bool {
+        self.huge_pages
+    }
+
+    /// 判断是否在释放内存之前建议`MADV_DONTNEED`
+    pub const fn release_on_shrink(&self) -> bool {
+        self.release_on_shrink
+    }
+
+    /// 判断是否在全表扫描期间建议`MADV_SEQUENTIAL`
+    pub const fn sequential_scans(&self) -> bool {
+        self.sequential_scans
+    }
+}
+
+/// 传给`madvise`的建议
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Advice {
+    HugePage,
+    DontNeed,
+    Sequential,
+    Normal,
+}
+
+///
+/// 获取`[ptr, ptr + len)`中完全覆盖的整页范围`(起始地址, 长度)`, 不足一页时返回`None`
+///
+pub(crate) fn page_range(ptr: usize, len: usize, page: usize) -> Option<(usize, usize)> {
+    let start = ptr.checked_add(page - 1)? & !(page - 1);
+    let end = ptr.checked_add(len)? & !(page - 1);
+    if start < end {
+        Some((start, end - start))
+    } else {
+        None
+    }
+}
+
+///
+/// 对`[ptr, ptr + len)`中完全覆盖的整页给出建议, 调用失败时静默忽略。
+///
+/// caller需保证该范围属于table持有的同一块分配; `Advice::DontNeed`会丢弃页中的内容,
+/// 只能在这块分配被释放之前调用
+///
+#[cfg(unix)]
+pub(crate) unsafe fn advise(ptr: NonNull<u8>, len: usize, advice: Advice) {
+    let advice = match advice {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Advice::HugePage => libc::MADV_HUGEPAGE,
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Advice::HugePage => return,
+        Advice::DontNeed => libc::MADV_DONTNEED,
+        Advice::Sequential => libc::MADV_SEQUENTIAL,
+        Advice::Normal => libc::MADV_NORMAL,
+    };
+    let page = match libc::sysconf(libc::_SC_PAGESIZE) {
+        page if page > 0 && (page as usize).is_power_of_two() => page as usize,
+        _ => return,
+    };
+    if let Some((start, len)) = page_range(ptr.as_ptr() as usize, len, page) {
+        libc::madvise(start as *mut libc::c_void, len, advice);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) unsafe fn advise(_ptr: NonNull<u8>, _len: usize, _advice: Advice) {}
@@ -1,10 +1,46 @@
+// 指针与整数之间的转换须经由`addr`或指针差, 以便在`-Zmiri-strict-provenance`下运行
+#![cfg_attr(feature = "nightly", warn(lossy_provenance_casts))]
+
+use super::alloc_stats::{AllocStats, CountingAlloc};
+use super::columnar::ColumnsTooSmall;
+use super::debug::{DebugEntry, EntryVisitor, TableDescriptor, TABLE_DESCRIPTOR_VERSION};
+use super::entry_layout::Column;
+use super::lookup_cache::LookupCache;
+#[cfg(feature = "madvise")]
+use super::madvise::{advise, Advice, MemoryHints};
+use super::payload::PayloadArena;
+#[cfg(feature = "raw-map-infallible")]
+use super::RawIterHashInner;
+#[cfg(feature = "std")]
+use super::TagSliceExt;
 use super::{
-    ptr, unlikely, Allocator, Fallibility, Global, Group, Layout, PhantomData, RawTableInner,
-    TableLayout, TryReserveError,
+    bucket_mask_to_capacity, capacity_to_buckets, do_alloc, h1, invalid_mut, likely, mem, ptr,
+    unlikely, Allocator, Fallibility, FullBucketsIndices, Global, Group, Layout, NonNull,
+    PhantomData, ProbeSeq, RawTableInner, TableLayout, Tag, TryReserveError,
 };
+use crate::alloc::boxed::Box;
+use crate::alloc::vec::Vec;
+use crate::scopeguard::guard;
+use crate::util::{addr, prefetch_read};
+use core::cmp::{self, Ordering};
+use core::fmt;
+use core::iter::FusedIterator;
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+#[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+use core::sync::atomic::Ordering as AtomicOrdering;
 
+///
+/// 基于entry内存结构计算table内存结构, bucket步长为按对齐补齐后的entry大小。
+///
+/// 对齐要求超过`Group::WIDTH`时(例如SIMD向量或按缓存行对齐的entry), 整块内存按entry对齐分配,
+/// ctrl字节的偏移量同样按entry对齐取整, 因此bucket数组与ctrl字节之间会留有padding
+///
 impl From<Layout> for TableLayout {
     fn from(value: Layout) -> Self {
+        let value = value.pad_to_align();
         Self {
             size: value.size(),
             ctrl_align: if value.align() > Group::WIDTH {
@@ -16,9 +52,22 @@ impl From<Layout> for TableLayout {
     }
 }
 
+impl TableLayout {
+    /// 将整块内存的对齐要求提高到`align`(须为2的幂), 小于现有对齐要求时不做改变
+    fn with_align(self, align: usize) -> Self {
+        Self {
+            size: self.size,
+            ctrl_align: cmp::max(self.ctrl_align, align),
+        }
+    }
+}
+
+/// 2 MiB大页的对齐要求, 可用于`RawTable2::try_with_bucket_align`
+pub const HUGE_PAGE_ALIGN: usize = 2 * 1024 * 1024;
+
 /// 哈希表内部的Entry规范, 用于caller注入自定义的实现细节
 pub trait EntrySpec {
-    /// 获取此entry的内存结构
+    /// 获取此entry的内存结构, bucket步长为按对齐补齐后的`size`, 对齐要求可以超过`Group::WIDTH`
     fn layout(&self) -> Layout;
     /// 计算此entry中Key的hash值
     fn hash(&self, entry: *const u8) -> u64;
@@ -28,232 +77,4035 @@ pub trait EntrySpec {
     fn assign_key(&self, entry: *const u8, k: *const u8);
     /// 获取此entry中Value的内存指针
     fn access_value(&self, entry: *const u8) -> *const u8;
-}
+    ///
+    /// 将`v`指向的Value写入entry中的Value内存`value`(即`access_value`的返回值)。
+    ///
+    /// 默认按`value_size`字节拷贝; 未提供`value_size`的spec须覆盖此方法, 否则写入value时panic
+    ///
+    // 与其他hook相同, 指针有效性由table保证
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        let size = self
+            .value_size()
+            .expect("EntrySpec must implement assign_value or value_size");
+        unsafe { ptr::copy_nonoverlapping(v, value as *mut u8, size) }
+    }
+    ///
+    /// 获取Value的字节数(不含entry末尾的填充), 供`assign_value`的默认实现按字节拷贝, 默认为`None`
+    ///
+    fn value_size(&self) -> Option<usize> {
+        None
+    }
+    /// 使用`v`覆盖已存在的Value, spec可借此释放(或减少引用计数)被替换的旧Value, 默认直接调用`assign_value`
+    fn replace_value(&self, value: *const u8, v: *const u8) {
+        self.assign_value(value, v)
+    }
 
-///
-/// 面向原生内存的<K, V>哈希表
-///
-pub struct RawTable2<E: EntrySpec, A: Allocator = Global> {
-    entry: E,
-    /// caller可以指定自己的Allocator
-    alloc: A,
-    /// 内部swiss table
-    inner: RawTableInner,
-}
+    /// `assign_key`的fallible版本, 供`try_insert`等fallible写入路径使用, 默认直接调用`assign_key`
+    fn try_assign_key(&self, entry: *const u8, k: *const u8) -> Result<(), AssignError> {
+        self.assign_key(entry, k);
+        Ok(())
+    }
+    /// `assign_value`的fallible版本, 供`try_insert`等fallible写入路径使用, 默认直接调用`assign_value`
+    fn try_assign_value(&self, value: *const u8, v: *const u8) -> Result<(), AssignError> {
+        self.assign_value(value, v);
+        Ok(())
+    }
+    /// `replace_value`的fallible版本, 供`try_insert`等fallible写入路径使用, 默认直接调用`replace_value`
+    fn try_replace_value(&self, value: *const u8, v: *const u8) -> Result<(), AssignError> {
+        self.replace_value(value, v);
+        Ok(())
+    }
 
-impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
     ///
-    /// 构造新的哈希表, 支持指定默认cap, 若为0则视为构造空的哈希表
+    /// 获取Key`k`需要存放在table内部arena中的变长数据字节数, 默认为0即定长Key。
     ///
-    pub fn new(cap: usize, entry: E, alloc: A) -> Result<Self, TryReserveError> {
-        let table_layout = TableLayout::from(entry.layout());
-        let inner = RawTableInner::fallible_with_capacity(
-            &alloc,
-            table_layout,
-            cap,
-            Fallibility::Fallible,
-        )?;
-        Ok(Self {
-            entry,
-            alloc,
-            inner,
-        })
+    /// 返回值大于0时, table会在`assign_key`之后为该Key分配等长的arena内存并调用`assign_payload`,
+    /// 由spec将变长数据拷贝进arena并在entry中记录其引用(例如地址与长度)。
+    ///
+    fn payload_size(&self, _k: *const u8) -> usize {
+        0
     }
+    ///
+    /// 将Key`k`的变长数据拷贝至table分配的`payload`内存(长度为`payload_size(k)`),
+    /// 并在entry中记录该payload的引用。payload地址在table执行`clear`或被销毁之前保持不变
+    ///
+    fn assign_payload(&self, _entry: *const u8, _k: *const u8, _payload: *mut u8) {}
 
     ///
-    /// 获取`key: &K`在此map中的`value: &V`, 出入参均为K/V的有效内存指针。
-    /// 它的使用场景为`map.get(key)`, 计算过程中直接使用`self.hash_fn`和`self.eq_fn`
+    /// 批量计算`keys`中各Key的hash值并依次写入`out`, 二者长度相同。
     ///
-    pub unsafe fn access(&self, key: *const u8) -> Option<*const u8> {
-        self.find(key)
-            .map(|o| self.entry.access_value(self.bucket(o)))
+    /// 批量操作(例如`extend`)会通过此方法计算hash, 默认逐个调用`hash`;
+    /// Key为定长类型的spec可以借此一次性计算多个hash(例如SIMD)
+    ///
+    fn hash_many(&self, keys: &[*const u8], out: &mut [u64]) {
+        for (key, hash) in keys.iter().zip(out.iter_mut()) {
+            *hash = self.hash(*key);
+        }
     }
 
     ///
-    /// 获取`key: &K`在当前map中的“可赋值地址”, 出入参均为K/V的有效内存地址。
+    /// 返回`true`时表示`hash`与`hash_many`只产生32位有效hash(高32位被忽略), 默认为`false`。
     ///
-    /// 它的使用场景为`map.set(key, value)`, 只是过程分为两步走:
-    /// 先计算key槽位物理地址, 然后向地址内写入value；此函数只负责第一步, 即按需扩容+返回value地址, 由caller写入数据
+    /// table由这32位同时得到起始槽位(H1)与ctrl字节(H2), 因此spec可以在32位目标(嵌入式、wasm32)上全程使用32位运算;
+    /// 64位目标上同样有效, 但32位hash在大table中的冲突概率更高
     ///
-    pub unsafe fn assign(&mut self, key: *const u8) -> *const u8 {
-        self.check_growth(1).expect("map growth failure");
-
-        let index = self.find_or_insert(key);
-        let bucket = self.bucket(index);
-        // 返回value内存地址
-        self.entry.access_value(bucket)
+    fn hash32(&self) -> bool {
+        false
     }
 
     ///
-    /// 将other中的所有buckets导入当前map
+    /// entry被table驱逐前调用, `value`为`access_value(entry)`, 默认不做任何处理。
     ///
-    pub unsafe fn extend(&mut self, other: &Self) {
-        self.check_growth(other.len()).expect("map growth failure");
+    /// 驱逐由`retain`、`clear_and_evict`等操作触发, 不包括caller显式调用的`delete`与`clear`;
+    /// 宿主runtime可以借此统一释放GC root或记录驱逐日志
+    ///
+    fn on_evict(&self, _entry: *const u8, _value: *const u8) {}
 
-        let size = self.entry.layout().size();
-        for other_idx in other.inner.full_buckets_indices() {
-            let entry = other.bucket(other_idx) as *const u8;
-            let index = self.find_or_insert(entry);
-            let bucket = self.bucket(index);
-            ptr::copy_nonoverlapping(entry, bucket, size);
-        }
+    ///
+    /// 在hash与相等性比较之前对caller传入的Key`k`进行规范化, 返回规范化后的Key指针, 默认原样返回。
+    ///
+    /// 规范化后的Key可以写入`scratch`(长度为`KEY_SCRATCH_SIZE`字节, 按16字节对齐)并返回`scratch`,
+    /// 也可以返回spec自身持有的常量Key。写入table的Key是规范化之后的Key,
+    /// 因此可以实现大小写不敏感的字符串、统一NaN表示的浮点数等Key语义
+    ///
+    fn normalize_key(&self, k: *const u8, _scratch: *mut u8) -> *const u8 {
+        k
     }
 
     ///
-    /// 从当前map中删除指定key, 即将该key对应的Bucket软删除
+    /// 返回`Some(layout)`时启用大entry模式: value存放在table按`layout`单独分配的内存中,
+    /// entry中`access_value`所指的位置只存放指向该内存的指针(须按指针大小与对齐预留), 默认为`None`。
     ///
-    pub unsafe fn delete(&mut self, key: *const u8) {
-        self.find(key).map(|i| self.inner.erase(i));
+    /// 该内存在新Key写入时分配, 在entry被删除、清空或table销毁时释放;
+    /// 所有接收value指针的hook(`assign_value`、`replace_value`、`on_evict`等)得到的均为该内存的地址。
+    /// 适用于KB级别的value, 避免其拖慢探测与扩容
+    ///
+    fn out_of_line_value(&self) -> Option<Layout> {
+        None
     }
 
     ///
-    /// 清空当前map中的所有entries, 不需要卸载内存, 软删所有Bucket即可
+    /// 返回`true`时表示Value为零大小类型(即entry的`voff == size`), table作为raw hash set使用, 默认为`false`。
     ///
-    pub fn clear(&mut self) {
-        self.inner.clear_no_drop();
+    /// 此时entry只包含Key, `assign_value`与`replace_value`(及其fallible版本)不会被调用,
+    /// 所有返回或传入value指针的位置得到的均为按entry对齐的悬垂指针, `out_of_line_value`被忽略
+    ///
+    fn zero_sized_value(&self) -> bool {
+        false
     }
 
     ///
-    /// 获取当前map中的entries数量
+    /// 返回`Some(layout)`时启用Key/Value分离(SoA)存储: `layout`描述的entry只包含Key,
+    /// value按`layout`(本hook的返回值)依次存放在独立的value数组中, 下标与entry所在的bucket相同, 默认为`None`。
     ///
-    pub fn len(&self) -> usize {
-        self.inner.items
+    /// 此时`access_value`不会被调用, value地址由bucket下标计算得到; 探测只访问Key数组, 适用于value较大且查找密集的场景。
+    /// 可与`out_of_line_value`组合使用, 此时value数组中存放的是指向value内存的指针
+    ///
+    fn split_value(&self) -> Option<Layout> {
+        None
     }
 
     ///
-    /// 针对当前table派生出`map<K, V>`的便捷封装, caller需要保证内存安全性
+    /// 返回spec的身份标识, `extend`等跨table操作据此判断两个spec是否兼容, 默认为`None`即不参与比较。
     ///
-    pub unsafe fn as_map<K, V>(&'_ mut self) -> RawMap<'_, K, V, E, A> {
-        RawMap {
-            table: self,
-            phantom: Default::default(),
-        }
+    /// 内存结构相同但Key语义不同的spec(例如不同的hash种子或编码)应返回不同的标识
+    ///
+    fn spec_id(&self) -> Option<u64> {
+        None
     }
 
     ///
-    /// Swiss-Table可以视为一个稀疏的一维数组, 即部分slot为有效的Bucket,
-    /// 它的迭代策略为[0...len]遍历所有槽位中的“FULL”状态并返回该槽位内存地址。
+    /// 获取value区域的列结构(偏移量相对value起始地址), 供`RawTable2::access_column`按列访问value, 默认为空即value不分列。
     ///
-    /// 此方法是map迭代器的核心实现, 用于获取map底层一维数组中`[index, size)`的首个有效entry。
+    /// 通常由`ColumnLayout::columns`提供
     ///
-    pub fn next_entry(&self, index: usize) -> Option<(usize, *const u8)> {
-        let mut curr_idx = index;
-        loop {
-            // 加载[curr_idx, curr_idx+16]的一组控制字节, 它可能是未对齐的
-            let group = unsafe {
-                let group_ptr = self.inner.ctrl(curr_idx);
-                Group::load(group_ptr)
-            };
-            // 匹配FULL状态的位掩码
-            let mask = group.match_full();
-            if mask.any_bit_set() {
-                let group_offset = mask.trailing_zeros();
-                let matched_index = curr_idx + group_offset; // 此函数只关心next, 而非next_all
-                let bucket = unsafe { self.bucket(matched_index) };
-                return Some((matched_index, bucket));
-            }
-            curr_idx += Group::WIDTH;
-            // 检查是否遍历到了末尾
-            if curr_idx >= self.inner.buckets() {
-                return None;
-            }
-        }
+    fn value_columns(&self) -> &[Column] {
+        &[]
     }
 
-    #[inline(always)]
-    unsafe fn bucket(&self, index: usize) -> *mut u8 {
-        self.inner.bucket_ptr(index, self.entry.layout().size())
+    ///
+    /// 获取每个bucket的元数据区域(偏移量相对entry起始地址), 默认为`None`即没有元数据, 通常由`EntryLayout::metadata`提供。
+    ///
+    /// 新Key写入时元数据被清零, 之后table只在扩容与`extend`时随entry整体拷贝, 不会被value的写入覆盖;
+    /// 通过`RawTable2::metadata_at`访问
+    ///
+    fn metadata(&self) -> Option<Column> {
+        None
     }
 
-    #[inline(always)]
-    unsafe fn find(&self, key: *const u8) -> Option<usize> {
-        let hash = self.entry.hash(key);
-        let mut equals = |index| self.entry.equals(key, self.bucket(index));
-        self.inner.find_inner(hash, &mut equals)
+    ///
+    /// 返回`Some(offset)`时table在entry中偏移量为`offset`处的8字节缓存Key的64位hash, 默认为`None`, 通常由`EntryLayout::cached_hash`提供。
+    ///
+    /// hash在新Key写入时由table写入, 扩容与原地rehash直接读取缓存而不再调用`hash`;
+    /// 查找时先比较缓存的hash, 只有hash相同才调用`equals`, 因此同样可以作为Key的摘要加速较长Key(例如指向长字节串的指针)的比较,
+    /// 尤其是查找不存在的Key。该区域可能未按8字节对齐, 由table按未对齐方式读写
+    ///
+    fn cached_hash(&self) -> Option<usize> {
+        None
     }
 
-    #[inline(always)]
-    unsafe fn find_or_insert(&mut self, key: *const u8) -> usize {
-        let hash = self.entry.hash(key);
-        let mut equals = |index| self.entry.equals(key, self.bucket(index));
-        match self.inner.find_or_find_insert_slot_inner(hash, &mut equals) {
-            Ok(index) => index,
-            Err(slot) => {
-                let old_ctrl = *self.inner.ctrl(slot.index);
-                self.inner.record_item_insert_at(slot.index, old_ctrl, hash);
-                let bucket = self.bucket(slot.index);
-                self.entry.assign_key(bucket, key); // write key into slot
-                slot.index
-            }
-        }
+    ///
+    /// 返回`Some(width)`时启用原子value模式: value为按自身大小对齐的`u32`或`u64`, 默认为`None`。
+    ///
+    /// 此时可以通过`&RawTable2`上的`fetch_add`、`compare_exchange_value`等方法并发修改已存在Key的value,
+    /// `&self`保证期间不会发生扩容或rehash; 与这些方法同时进行的非原子value读写是数据竞争。
+    /// 各宽度只在目标平台支持对应的原子操作(`target_has_atomic`)时可用, 否则这些方法panic
+    ///
+    fn atomic_value(&self) -> Option<AtomicWidth> {
+        None
+    }
+
+    ///
+    /// 将entry中的Key编码后追加到`out`, 供serde等序列化路径使用; 默认不支持编码, 返回`false`。
+    ///
+    /// 编码结果须能被`decode_key`还原, 且不应依赖进程内的地址
+    ///
+    fn encode_key(&self, _entry: *const u8, _out: &mut Vec<u8>) -> bool {
+        false
+    }
+    /// 将`value`(即`access_value`的返回值)处的Value编码后追加到`out`, 默认不支持编码, 返回`false`
+    fn encode_value(&self, _value: *const u8, _out: &mut Vec<u8>) -> bool {
+        false
+    }
+    ///
+    /// 从`bytes`解码出Key并写入`k`(长度为`KEY_SCRATCH_SIZE`字节, 按16字节对齐), 解码失败或不支持时返回`false`。
+    ///
+    /// 写入的Key随后以`k: &K`的形式写入table
+    ///
+    fn decode_key(&self, _bytes: &[u8], _k: *mut u8) -> bool {
+        false
+    }
+    ///
+    /// 从`bytes`解码出Value并写入`value`处尚未初始化的Value内存(与`assign_value`相同), 解码失败或不支持时返回`false`
+    ///
+    fn decode_value(&self, _bytes: &[u8], _value: *const u8) -> bool {
+        false
+    }
+    ///
+    /// 原地反转`entry`中Key各个标量字段的字节序(大小端互换), 供快照在字节序不同的平台之间传输; 默认不支持, 返回`false`。
+    ///
+    /// 返回值只能取决于spec而不能取决于entry的内容, 不支持时不得修改entry; 元数据区域按字节原样传输,
+    /// 缓存的hash由table处理。转换后的Key在对方平台上的hash须与转换前相同
+    ///
+    fn swap_key_bytes(&self, _entry: *mut u8) -> bool {
+        false
+    }
+    ///
+    /// 原地反转`value`处Value各个标量字段的字节序, 要求同`swap_key_bytes`; 默认不支持, 返回`false`, set模式下不会被调用
+    ///
+    fn swap_value_bytes(&self, _value: *mut u8) -> bool {
+        false
+    }
+    ///
+    /// 格式化`entry`中的Key, 供`RawTable2::debug_visit`的访问者与调试转储渲染entries, 默认输出entry的地址
+    ///
+    fn fmt_key(&self, entry: *const u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{entry:p}")
     }
+    ///
+    /// 格式化`value`处的Value(即`RawTable2::value_ptr`的返回值), 默认输出其地址; set模式下不会被调用
+    ///
+    fn fmt_value(&self, value: *const u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{value:p}")
+    }
+}
 
+/// 原子value模式下value的宽度
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AtomicWidth {
+    /// value为`u32`
+    U32,
+    /// value为`u64`
+    U64,
+}
+
+/// 原子value模式下按宽度转换后的value, 每种宽度只在目标平台支持对应的原子操作时存在
+#[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+enum AtomicSlot<'a> {
+    #[cfg(target_has_atomic = "32")]
+    U32(&'a AtomicU32),
+    #[cfg(target_has_atomic = "64")]
+    U64(&'a AtomicU64),
+}
+
+/// `EntrySpec::normalize_key`可用的临时缓冲区字节数
+pub const KEY_SCRATCH_SIZE: usize = 64;
+
+/// `EntrySpec::normalize_key`使用的临时缓冲区
+#[repr(C, align(16))]
+pub(crate) struct KeyScratch(pub(crate) [u8; KEY_SCRATCH_SIZE]);
+
+impl KeyScratch {
     #[inline(always)]
-    unsafe fn check_growth(&mut self, additional: usize) -> Result<(), TryReserveError> {
-        if unlikely(additional > self.inner.growth_left) {
-            self.do_growth(additional, Fallibility::Fallible)
-        } else {
-            Ok(())
-        }
+    pub(crate) fn new() -> Self {
+        Self([0; KEY_SCRATCH_SIZE])
     }
+}
 
-    #[cold]
-    #[inline(never)]
-    unsafe fn do_growth(
-        &mut self,
-        additional: usize,
-        fallibility: Fallibility,
-    ) -> Result<(), TryReserveError> {
-        let layout = self.entry.layout();
-        self.inner.reserve_rehash_inner(
-            &self.alloc,
-            additional,
-            &|table, index| self.entry.hash(table.bucket_ptr(index, layout.size())),
-            fallibility,
-            TableLayout::from(layout),
-            None,
-        )
+/// 计算`key: &K`经`spec.normalize_key`规范化之后的hash, 与table内部查找使用的hash一致
+pub(crate) unsafe fn normalized_hash<E: EntrySpec>(spec: &E, key: *const u8) -> u64 {
+    with_normalized_key(spec, key, |key| spec_hash(spec, key))
+}
+
+///
+/// 将spec给出的hash转换为table内部使用的hash: `EntrySpec::hash32`时把低32位复制到高32位,
+/// 使H1(低位)与H2(最高7位)均来自这32位, 在32位目标上两者本就只取低32位, 结果不变
+///
+#[inline(always)]
+pub(crate) fn table_hash<E: EntrySpec>(spec: &E, hash: u64) -> u64 {
+    if spec.hash32() {
+        let low = u64::from(hash as u32);
+        low | (low << 32)
+    } else {
+        hash
     }
 }
 
-pub struct RawMap<'a, K, V, E: EntrySpec, A: Allocator> {
-    table: &'a mut RawTable2<E, A>,
-    phantom: PhantomData<(K, V)>,
+///
+/// 将table返回的entry或value地址转换为`NonNull`: bucket地址位于已分配的数组中,
+/// set模式与零大小的大entry value为按对齐构造的悬垂指针, 因此均非空
+///
+#[inline(always)]
+unsafe fn non_null(ptr: *const u8) -> NonNull<u8> {
+    debug_assert!(!ptr.is_null());
+    NonNull::new_unchecked(ptr as *mut u8)
 }
 
-impl<'a, K, V, E: EntrySpec, A: Allocator> RawMap<'a, K, V, E, A> {
-    /// 获取此map中指定key的value引用
-    pub unsafe fn get(&self, key: &K) -> Option<&V> {
-        let key_ptr = key as *const K as *const u8;
-        self.table.access(key_ptr).map(|ptr| &*(ptr as *const V))
+/// 计算`key`在table内部使用的hash
+#[inline(always)]
+pub(crate) fn spec_hash<E: EntrySpec>(spec: &E, key: *const u8) -> u64 {
+    table_hash(spec, spec.hash(key))
+}
+
+/// 批量计算`keys`在table内部使用的hash
+#[inline(always)]
+fn spec_hash_many<E: EntrySpec>(spec: &E, keys: &[*const u8], out: &mut [u64]) {
+    spec.hash_many(keys, out);
+    if spec.hash32() {
+        for hash in out {
+            *hash = table_hash(spec, *hash);
+        }
     }
+}
 
-    /// 将{key, value}写入此map
-    pub unsafe fn insert(&mut self, key: &K, value: V) {
-        let key_ptr = key as *const K as *const u8;
-        let val_addr = self.table.assign(key_ptr);
-        let val_mut_ref = &mut *(val_addr as *mut V);
-        *val_mut_ref = value;
+/// 以`key: &K`经`spec.normalize_key`规范化之后的Key指针调用`f`
+pub(crate) unsafe fn with_normalized_key<E: EntrySpec, R>(
+    spec: &E,
+    key: *const u8,
+    f: impl FnOnce(*const u8) -> R,
+) -> R {
+    let mut scratch = KeyScratch::new();
+    f(spec.normalize_key(key, scratch.0.as_mut_ptr()))
+}
+
+/// 获取table中`entry`的hash, spec缓存了hash时直接读取缓存而不调用`EntrySpec::hash`
+#[inline(always)]
+unsafe fn entry_hash<E: EntrySpec>(spec: &E, entry: *const u8) -> u64 {
+    match spec.cached_hash() {
+        Some(offset) => ptr::read_unaligned(entry.add(offset) as *const u64),
+        None => spec_hash(spec, entry),
     }
+}
 
-    /// 删除此map中指定key的entry
-    pub unsafe fn delete(&mut self, key: &K) {
-        let key_ptr = key as *const K as *const u8;
-        self.table.delete(key_ptr);
+/// spec缓存hash时将`hash`写入`entry`的hash缓存区域
+#[inline(always)]
+unsafe fn store_hash<E: EntrySpec>(spec: &E, entry: *mut u8, hash: u64) {
+    if let Some(offset) = spec.cached_hash() {
+        ptr::write_unaligned(entry.add(offset) as *mut u64, hash);
     }
+}
 
-    /// Merge all entries of other into this map.
-    pub unsafe fn extend(&mut self, other: &Self) {
-        self.table.extend(&other.table);
+/// 将`EntrySpec`的所有方法转发至`**self`, 以保证指针类型不会丢失被覆盖的默认实现
+macro_rules! forward_entry_spec {
+    () => {
+        forward_entry_spec!(@hash);
+        forward_entry_spec!(@rest);
+    };
+    // 决定hash结果与spec身份的方法
+    (@hash) => {
+        #[inline]
+        fn hash(&self, entry: *const u8) -> u64 {
+            (**self).hash(entry)
+        }
+        #[inline]
+        fn hash_many(&self, keys: &[*const u8], out: &mut [u64]) {
+            (**self).hash_many(keys, out)
+        }
+        #[inline]
+        fn spec_id(&self) -> Option<u64> {
+            (**self).spec_id()
+        }
+    };
+    (@rest) => {
+        #[inline]
+        fn layout(&self) -> Layout {
+            (**self).layout()
+        }
+        #[inline]
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            (**self).equals(entry1, entry2)
+        }
+        #[inline]
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            (**self).assign_key(entry, k)
+        }
+        #[inline]
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            (**self).access_value(entry)
+        }
+        #[inline]
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            (**self).assign_value(value, v)
+        }
+        #[inline]
+        fn value_size(&self) -> Option<usize> {
+            (**self).value_size()
+        }
+        #[inline]
+        fn replace_value(&self, value: *const u8, v: *const u8) {
+            (**self).replace_value(value, v)
+        }
+        #[inline]
+        fn try_assign_key(&self, entry: *const u8, k: *const u8) -> Result<(), AssignError> {
+            (**self).try_assign_key(entry, k)
+        }
+        #[inline]
+        fn try_assign_value(&self, value: *const u8, v: *const u8) -> Result<(), AssignError> {
+            (**self).try_assign_value(value, v)
+        }
+        #[inline]
+        fn try_replace_value(&self, value: *const u8, v: *const u8) -> Result<(), AssignError> {
+            (**self).try_replace_value(value, v)
+        }
+        #[inline]
+        fn payload_size(&self, k: *const u8) -> usize {
+            (**self).payload_size(k)
+        }
+        #[inline]
+        fn assign_payload(&self, entry: *const u8, k: *const u8, payload: *mut u8) {
+            (**self).assign_payload(entry, k, payload)
+        }
+        #[inline]
+        fn hash32(&self) -> bool {
+            (**self).hash32()
+        }
+        #[inline]
+        fn on_evict(&self, entry: *const u8, value: *const u8) {
+            (**self).on_evict(entry, value)
+        }
+        #[inline]
+        fn normalize_key(&self, k: *const u8, scratch: *mut u8) -> *const u8 {
+            (**self).normalize_key(k, scratch)
+        }
+        #[inline]
+        fn out_of_line_value(&self) -> Option<Layout> {
+            (**self).out_of_line_value()
+        }
+        #[inline]
+        fn zero_sized_value(&self) -> bool {
+            (**self).zero_sized_value()
+        }
+        #[inline]
+        fn split_value(&self) -> Option<Layout> {
+            (**self).split_value()
+        }
+        #[inline]
+        fn value_columns(&self) -> &[Column] {
+            (**self).value_columns()
+        }
+        #[inline]
+        fn metadata(&self) -> Option<Column> {
+            (**self).metadata()
+        }
+        #[inline]
+        fn cached_hash(&self) -> Option<usize> {
+            (**self).cached_hash()
+        }
+        #[inline]
+        fn atomic_value(&self) -> Option<AtomicWidth> {
+            (**self).atomic_value()
+        }
+        #[inline]
+        fn encode_key(&self, entry: *const u8, out: &mut Vec<u8>) -> bool {
+            (**self).encode_key(entry, out)
+        }
+        #[inline]
+        fn encode_value(&self, value: *const u8, out: &mut Vec<u8>) -> bool {
+            (**self).encode_value(value, out)
+        }
+        #[inline]
+        fn decode_key(&self, bytes: &[u8], k: *mut u8) -> bool {
+            (**self).decode_key(bytes, k)
+        }
+        #[inline]
+        fn decode_value(&self, bytes: &[u8], value: *const u8) -> bool {
+            (**self).decode_value(bytes, value)
+        }
+        #[inline]
+        fn swap_key_bytes(&self, entry: *mut u8) -> bool {
+            (**self).swap_key_bytes(entry)
+        }
+        #[inline]
+        fn swap_value_bytes(&self, value: *mut u8) -> bool {
+            (**self).swap_value_bytes(value)
+        }
+        #[inline]
+        fn fmt_key(&self, entry: *const u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt_key(entry, f)
+        }
+        #[inline]
+        fn fmt_value(&self, value: *const u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt_value(value, f)
+        }
+    };
+}
+
+impl<E: EntrySpec + ?Sized> EntrySpec for &E {
+    forward_entry_spec!();
+}
+
+impl<E: EntrySpec + ?Sized> EntrySpec for Box<E> {
+    forward_entry_spec!();
+}
+
+///
+/// 声明spec可以跨线程使用的包装, 用于持有宿主裸指针(因而不满足`Send`/`Sync`)的spec。
+///
+/// 包装后的spec转发`EntrySpec`的所有方法, 并无条件地实现`Send`与`Sync`,
+/// 从而使以其构造的`RawTable2`可以在线程之间转移或共享
+///
+#[derive(Clone, Copy, Debug)]
+pub struct AssertThreadSafe<E>(E);
+
+impl<E> AssertThreadSafe<E> {
+    ///
+    /// 包装`spec`
+    ///
+    /// # Safety
+    ///
+    /// caller须保证`spec`自身以及其hook访问的宿主内存可以在任意线程中使用, 且hook可以被多个线程同时调用
+    ///
+    pub const unsafe fn new(spec: E) -> Self {
+        Self(spec)
     }
 
-    /// Clear all entries in this map.
-    pub unsafe fn clear(&mut self) {
-        self.table.clear()
+    /// 取出被包装的spec
+    pub fn into_inner(self) -> E {
+        self.0
+    }
+}
+
+impl<E> core::ops::Deref for AssertThreadSafe<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.0
+    }
+}
+
+// 由`AssertThreadSafe::new`的caller保证
+unsafe impl<E> Send for AssertThreadSafe<E> {}
+unsafe impl<E> Sync for AssertThreadSafe<E> {}
+
+impl<E: EntrySpec> EntrySpec for AssertThreadSafe<E> {
+    forward_entry_spec!();
+}
+
+impl<E: OrderedEntrySpec> OrderedEntrySpec for AssertThreadSafe<E> {
+    #[inline]
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering {
+        self.0.compare(entry1, entry2)
+    }
+}
+
+///
+/// 将种子混入spec所给hash的包装, 使table的起始槽位与ctrl字节依赖于种子, 用于Key可能由攻击者控制的table(HashDoS)。
+///
+/// 混合是由种子决定的双射, 只能阻止针对部分hash位(起始槽位、H2)构造的冲突; spec的hash本身可以被构造出完全相同的结果时
+/// (例如不带种子的FNV), 仍需spec使用带密钥的hash。种子参与`spec_id`, 因此不同种子的table之间不能直接导入entries,
+/// 共享区域的`attach`也会校验种子
+///
+#[derive(Clone, Copy, Debug)]
+pub struct SeededSpec<E> {
+    spec: E,
+    seed: u64,
+}
+
+impl<E> SeededSpec<E> {
+    ///
+    /// 以显式种子包装`spec`, 相同的种子总是得到相同的hash, 用于复现问题或跨进程共享table
+    ///
+    pub const fn with_seed(spec: E, seed: u64) -> Self {
+        Self { spec, seed }
+    }
+
+    ///
+    /// 以随机种子包装`spec`, 每次调用得到不同的种子
+    ///
+    #[cfg(feature = "default-hasher")]
+    pub fn new(spec: E) -> Self {
+        use core::hash::{BuildHasher, Hasher};
+        let mut hasher = crate::DefaultHashBuilder::default().build_hasher();
+        hasher.write_u64(0);
+        Self::with_seed(spec, hasher.finish())
+    }
+
+    /// 获取种子
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 取出被包装的spec
+    pub fn into_inner(self) -> E {
+        self.spec
+    }
+}
+
+impl<E: EntrySpec> SeededSpec<E> {
+    /// 将种子混入spec给出的hash, `hash32`时只在低32位内混合
+    #[inline(always)]
+    fn mix(&self, hash: u64) -> u64 {
+        if self.spec.hash32() {
+            let mut h = (hash as u32) ^ (self.seed as u32) ^ ((self.seed >> 32) as u32);
+            h ^= h >> 16;
+            h = h.wrapping_mul(0x85eb_ca6b);
+            h ^= h >> 13;
+            h = h.wrapping_mul(0xc2b2_ae35);
+            u64::from(h ^ (h >> 16))
+        } else {
+            let mut h = hash ^ self.seed;
+            h ^= h >> 33;
+            h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+            h ^= h >> 33;
+            h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+            h ^ (h >> 33)
+        }
+    }
+}
+
+impl<E> core::ops::Deref for SeededSpec<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.spec
+    }
+}
+
+impl<E: EntrySpec> EntrySpec for SeededSpec<E> {
+    forward_entry_spec!(@rest);
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        self.mix(self.spec.hash(entry))
+    }
+
+    fn hash_many(&self, keys: &[*const u8], out: &mut [u64]) {
+        self.spec.hash_many(keys, out);
+        for hash in out {
+            *hash = self.mix(*hash);
+        }
+    }
+
+    #[inline]
+    fn spec_id(&self) -> Option<u64> {
+        let id = self.spec.spec_id().unwrap_or(0);
+        Some(id ^ self.mix(0x5eed_5eed_5eed_5eed))
+    }
+}
+
+impl<E: OrderedEntrySpec> OrderedEntrySpec for SeededSpec<E> {
+    #[inline]
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering {
+        self.spec.compare(entry1, entry2)
+    }
+}
+
+///
+/// 基于`dyn EntrySpec`的哈希表, spec在运行时动态分发。
+///
+/// 不同spec构造的table具有相同的类型, 因此可以存放在同一个集合中(例如插件注册表)
+///
+pub type RawTable2Dyn<A = Global> = RawTable2<Box<dyn EntrySpec>, A>;
+
+/// 支持按Key排序的Entry规范, 用于`RawTable2::sorted_indices`等有序扫描
+pub trait OrderedEntrySpec: EntrySpec {
+    /// 比较两个entry的Key的大小, 须与`equals`保持一致(相等的Key返回`Ordering::Equal`)
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering;
+}
+
+/// 批量操作中每次通过`EntrySpec::hash_many`计算hash的Key数量
+const HASH_BATCH: usize = 8;
+
+///
+/// 槽位数量不超过此值(即至多两个Group)且entries不超过`SMALL_SCAN_ITEMS`时, 查找跳过hash直接线性比较所有Key,
+/// 适用于脚本语言对象属性表等大量的极小map
+///
+const SMALL_SCAN_BUCKETS: usize = Group::WIDTH * 2;
+
+/// 小table线性查找时至多比较的entries数量
+const SMALL_SCAN_ITEMS: usize = 8;
+
+/// `reserve_populated`预先写入内存时假定的页大小
+const PAGE_SIZE: usize = 4096;
+
+/// `EntrySpec`的fallible hook(`try_assign_key`等)写入失败时返回的错误
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AssignError;
+
+/// `RawTable2`的fallible写入操作返回的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum InsertError {
+    /// table扩容或payload内存分配失败
+    Reserve(TryReserveError),
+    /// spec写入Key或Value失败, 新占用的槽位已被回滚
+    Assign(AssignError),
+}
+
+impl From<TryReserveError> for InsertError {
+    fn from(value: TryReserveError) -> Self {
+        Self::Reserve(value)
+    }
+}
+
+impl From<AssignError> for InsertError {
+    fn from(value: AssignError) -> Self {
+        Self::Assign(value)
+    }
+}
+
+/// `RawTable2::try_extend`返回的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExtendError {
+    /// 两个table的entry内存结构或spec标识不一致, 当前map未被修改
+    Incompatible,
+    /// table扩容失败, 当前map未被修改
+    Reserve(TryReserveError),
+}
+
+impl From<TryReserveError> for ExtendError {
+    fn from(value: TryReserveError) -> Self {
+        Self::Reserve(value)
+    }
+}
+
+/// `RawTable2::try_new_in`返回的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NewError {
+    /// caller给出的entry内存结构与`EntrySpec::layout`不一致
+    LayoutMismatch,
+    /// `try_with_bucket_align`指定的对齐要求不是2的幂或过大
+    InvalidAlign,
+    /// bucket数组或SoA模式的value数组分配失败
+    Reserve(TryReserveError),
+}
+
+impl From<TryReserveError> for NewError {
+    fn from(value: TryReserveError) -> Self {
+        Self::Reserve(value)
+    }
+}
+
+///
+/// `RawTable2`扩容时计算新容量的策略, 输入为当前容量与所需的最小容量(现有entries加上待写入的数量)。
+///
+/// bucket数量始终为2的幂, 策略给出的容量会被向上取整到能容纳它的bucket数量,
+/// 因此小于翻倍的策略在容量越过当前bucket数量时仍会使bucket数量翻倍;
+/// 策略的作用在于一次扩容到更大的容量(例如已知最终规模时), 减少多次扩容带来的rehash
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub enum GrowthPolicy {
+    /// 至少扩容到下一档, 即bucket数量翻倍
+    #[default]
+    Double,
+    /// 扩容到当前容量的`分子 / 分母`倍
+    Ratio(usize, usize),
+    /// 扩容到当前容量加上固定的步长
+    Step(usize),
+    /// 由caller根据`(当前容量, 所需的最小容量)`计算新容量
+    Custom(fn(usize, usize) -> usize),
+}
+
+impl GrowthPolicy {
+    /// 根据当前容量与所需的最小容量计算新容量, 结果不小于`required`且大于`capacity`
+    fn capacity(&self, capacity: usize, required: usize) -> usize {
+        let target = match *self {
+            Self::Double => capacity + 1,
+            Self::Ratio(num, den) => capacity.saturating_mul(num) / den.max(1),
+            Self::Step(step) => capacity.saturating_add(step),
+            Self::Custom(f) => f(capacity, required),
+        };
+        target.max(required).max(capacity + 1)
+    }
+}
+
+///
+/// `RawTable2::memory_usage`返回的堆内存占用, 单位均为字节
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MemoryUsage {
+    ctrl_bytes: usize,
+    bucket_bytes: usize,
+    value_bytes: usize,
+    payload_bytes: usize,
+    total: usize,
+    per_entry_overhead: usize,
+}
+
+impl MemoryUsage {
+    /// 获取控制字节数组(包括镜像首个group的尾部)的字节数
+    pub const fn ctrl_bytes(&self) -> usize {
+        self.ctrl_bytes
+    }
+
+    /// 获取bucket数组(包括其后为对齐控制字节而填充的部分)的字节数
+    pub const fn bucket_bytes(&self) -> usize {
+        self.bucket_bytes
+    }
+
+    /// 获取大entry模式下单独分配的value或SoA模式下value数组的字节数
+    pub const fn value_bytes(&self) -> usize {
+        self.value_bytes
+    }
+
+    /// 获取变长payload内存池持有的字节数
+    pub const fn payload_bytes(&self) -> usize {
+        self.payload_bytes
+    }
+
+    /// 获取table持有的堆内存总字节数
+    pub const fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 获取平均每个entry在entry自身(包括单独存放的value)大小之外占用的字节数(向下取整), table为空时为0
+    pub const fn per_entry_overhead(&self) -> usize {
+        self.per_entry_overhead
+    }
+}
+
+///
+/// `RawTable2`在`delete`/`retain`之后自动收缩的策略:
+/// 容量大于`min_capacity`且entries数量少于容量的`1 / divisor`时, 按spec的hash重建为更小的table。
+///
+/// 收缩后的容量为entries数量的两倍(不小于`min_capacity`), 避免删除与插入交替时反复扩缩容
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ShrinkPolicy {
+    divisor: usize,
+    min_capacity: usize,
+}
+
+impl ShrinkPolicy {
+    /// 构造收缩策略, entries数量少于容量的`1 / divisor`且容量大于`min_capacity`时收缩
+    pub const fn new(divisor: usize, min_capacity: usize) -> Self {
+        Self {
+            divisor,
+            min_capacity,
+        }
+    }
+
+    /// 获取触发收缩的容量与entries数量之比
+    pub const fn divisor(&self) -> usize {
+        self.divisor
+    }
+
+    /// 获取不再收缩的容量下限
+    pub const fn min_capacity(&self) -> usize {
+        self.min_capacity
+    }
+
+    fn should_shrink(&self, items: usize, capacity: usize) -> bool {
+        capacity > self.min_capacity && items < capacity / cmp::max(self.divisor, 1)
+    }
+
+    fn target(&self, items: usize) -> usize {
+        cmp::max(cmp::max(items.saturating_mul(2), self.min_capacity), 1)
+    }
+}
+
+impl Default for ShrinkPolicy {
+    /// entries数量少于容量的1/4且容量大于1024时收缩
+    fn default() -> Self {
+        Self::new(4, 1024)
+    }
+}
+
+///
+/// `RawTable2`在`delete`/`retain`之后自动回收tombstone的策略:
+/// tombstone数量不少于`min_tombstones`且超过容量的`1 / divisor`时, 在原有内存中重新整理所有entries(同`compact`)。
+///
+/// 删除频繁的负载下tombstone会拉长探测序列, 直到下次扩容才被回收; 原地整理不分配内存,
+/// 但会移动entries, 之前获取的entry地址全部失效
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TombstonePolicy {
+    divisor: usize,
+    min_tombstones: usize,
+}
+
+impl TombstonePolicy {
+    /// 构造回收策略, tombstone数量不少于`min_tombstones`且超过容量的`1 / divisor`时回收
+    pub const fn new(divisor: usize, min_tombstones: usize) -> Self {
+        Self {
+            divisor,
+            min_tombstones,
+        }
+    }
+
+    /// 获取触发回收的容量与tombstone数量之比
+    pub const fn divisor(&self) -> usize {
+        self.divisor
+    }
+
+    /// 获取触发回收的tombstone数量下限
+    pub const fn min_tombstones(&self) -> usize {
+        self.min_tombstones
+    }
+
+    fn should_purge(&self, tombstones: usize, capacity: usize) -> bool {
+        tombstones >= cmp::max(self.min_tombstones, 1)
+            && tombstones > capacity / cmp::max(self.divisor, 1)
+    }
+}
+
+impl Default for TombstonePolicy {
+    /// tombstone超过容量的1/4且不少于16个时回收
+    fn default() -> Self {
+        Self::new(4, 16)
+    }
+}
+
+///
+/// 面向原生内存的<K, V>哈希表
+///
+/// `A`为caller指定的分配器: 开启`allocator-api2` feature(默认开启)时为`allocator_api2::alloc::Allocator`,
+/// 因此在stable Rust上即可使用自定义分配器(例如arena); 开启`nightly`时为标准库的`Allocator`
+///
+pub struct RawTable2<E: EntrySpec, A: Allocator = Global> {
+    entry: E,
+    /// caller可以指定自己的Allocator, 由`CountingAlloc`包装以记录分配统计
+    alloc: CountingAlloc<A>,
+    /// 内部swiss table
+    inner: RawTableInner,
+    /// 存放Key变长数据的arena
+    payload: PayloadArena,
+    /// SoA模式下的value数组, 与`inner`的buckets一一对应; 其他模式或未分配buckets时为null
+    values: *mut u8,
+    /// 扩容时计算新容量的策略
+    growth_policy: GrowthPolicy,
+    /// 删除entries之后自动收缩的策略, `None`表示从不自动收缩
+    shrink_policy: Option<ShrinkPolicy>,
+    /// 删除entries之后自动回收tombstone的策略, `None`表示只在扩容或`compact`时回收
+    tombstone_policy: Option<TombstonePolicy>,
+    /// bucket数组(连同ctrl字节)分配时的对齐要求, 不小于entry自身的对齐与`Group::WIDTH`
+    bucket_align: usize,
+    /// 是否为ctrl字节与bucket数组分别分配的分离模式
+    separate: bool,
+    /// 是否为从不重新分配bucket数组的固定容量模式
+    fixed: bool,
+    /// 分离模式下独立分配的bucket数组, 其他模式或未分配buckets时为null
+    entries: *mut u8,
+    /// 向内核提供的bucket数组内存使用建议
+    #[cfg(feature = "madvise")]
+    memory_hints: MemoryHints,
+    /// 最近查找命中的槽位缓存, `None`表示未开启
+    lookup_cache: Option<LookupCache>,
+    /// 调试模式下bucket数组被扩容或rehash的次数, 用于检测迭代期间的失效
+    #[cfg(debug_assertions)]
+    generation: usize,
+}
+
+// SAFETY: table独占其bucket数组、value与payload内存, 裸指针只指向这些内存, 跨线程移动或共享table等同于移动或共享这些内存。
+// 经由`&self`可变的状态只有`CountingAlloc`的分配统计与`LookupCache`, 二者都由原子量实现, 因此`A: Sync`时共享`&RawTable2`不会产生数据竞争。
+// 持有宿主裸指针的spec不满足`Send`/`Sync`, 须通过`AssertThreadSafe`显式声明
+unsafe impl<E: EntrySpec + Send, A: Allocator + Send> Send for RawTable2<E, A> {}
+unsafe impl<E: EntrySpec + Sync, A: Allocator + Sync> Sync for RawTable2<E, A> {}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 构造新的哈希表, 支持指定默认cap, 若为0则视为构造空的哈希表
+    ///
+    pub fn new(cap: usize, entry: E, alloc: A) -> Result<Self, TryReserveError> {
+        Self::new_in(cap, 0, false, entry, alloc)
+    }
+
+    ///
+    /// 全程fallible的构造函数, `layout`为caller预期的entry内存结构, 须与`spec.layout()`一致。
+    ///
+    /// 内存结构不一致或任何分配失败时均返回错误而非panic/abort, 适用于以`panic = abort`编译的嵌入方
+    ///
+    pub fn try_new_in(cap: usize, layout: Layout, spec: E, alloc: A) -> Result<Self, NewError> {
+        if layout.pad_to_align() != spec.layout().pad_to_align() {
+            return Err(NewError::LayoutMismatch);
+        }
+        Ok(Self::new_in(cap, 0, false, spec, alloc)?)
+    }
+
+    ///
+    /// 构造bucket数组按`bucket_align`字节对齐分配的哈希表, 例如按`HUGE_PAGE_ALIGN`对齐以便大页承载超大table。
+    ///
+    /// 对齐要求在table的整个生命周期内保持不变(扩容、收缩时同样生效); bucket数组之后会按对齐要求填充,
+    /// 因此最多浪费`bucket_align`字节, 只适用于bucket数组远大于对齐要求的table。
+    /// `bucket_align`不是2的幂时返回`NewError::InvalidAlign`
+    ///
+    pub fn try_with_bucket_align(
+        cap: usize,
+        bucket_align: usize,
+        spec: E,
+        alloc: A,
+    ) -> Result<Self, NewError> {
+        if !bucket_align.is_power_of_two() || bucket_align > isize::MAX as usize / 2 {
+            return Err(NewError::InvalidAlign);
+        }
+        Ok(Self::new_in(cap, bucket_align, false, spec, alloc)?)
+    }
+
+    ///
+    /// 构造ctrl字节与bucket数组分别分配的哈希表(分离模式)。
+    ///
+    /// 分离模式下ctrl字节可以单独放置(例如放在更快的内存层级中), bucket数组按槽位下标升序排列;
+    /// 扩容与收缩按新容量重建两块内存; `compact`、`rehash_with`、`rebuild_in_place`以及tombstone过多时的整理
+    /// 均在原有的两块内存中原地rehash, bucket数组与ctrl字节同步移动, 不分配内存
+    ///
+    pub fn with_separate_buckets(cap: usize, entry: E, alloc: A) -> Result<Self, TryReserveError> {
+        Self::new_in(cap, 0, true, entry, alloc)
+    }
+
+    ///
+    /// 构造固定容量的哈希表: bucket数组只在构造时分配一次, 之后从不扩容、收缩或rehash,
+    /// 因此entry的地址在table的整个生命周期内保持不变(显式调用`compact`、`rebuild_in_place`或`reset`除外)。
+    ///
+    /// 容量耗尽时`try_insert`等fallible写入返回`TryReserveError::CapacityOverflow`, `insert`等则panic;
+    /// 删除entry可能留下tombstone, tombstone同样占用容量, 直到调用`compact`在原地重新整理
+    ///
+    pub fn with_fixed_capacity(cap: usize, entry: E, alloc: A) -> Result<Self, TryReserveError> {
+        let mut table = Self::new_in(cap, 0, false, entry, alloc)?;
+        table.fixed = true;
+        Ok(table)
+    }
+
+    fn new_in(
+        cap: usize,
+        bucket_align: usize,
+        separate: bool,
+        entry: E,
+        alloc: A,
+    ) -> Result<Self, TryReserveError> {
+        let alloc = CountingAlloc::new(alloc);
+        let table_layout = if separate {
+            TableLayout::new::<()>()
+        } else {
+            TableLayout::from(entry.layout())
+        }
+        .with_align(bucket_align);
+        let inner = RawTableInner::fallible_with_capacity(
+            &alloc,
+            table_layout,
+            cap,
+            Fallibility::Fallible,
+        )?;
+        let mut table = Self {
+            entry,
+            alloc,
+            inner,
+            payload: PayloadArena::new(),
+            values: ptr::null_mut(),
+            growth_policy: GrowthPolicy::Double,
+            shrink_policy: None,
+            tombstone_policy: None,
+            bucket_align: table_layout.ctrl_align,
+            separate,
+            fixed: false,
+            entries: ptr::null_mut(),
+            #[cfg(feature = "madvise")]
+            memory_hints: MemoryHints::new(),
+            lookup_cache: None,
+            #[cfg(debug_assertions)]
+            generation: 0,
+        };
+        table.entries = unsafe { table.alloc_entry_array(&table.inner, Fallibility::Fallible)? };
+        table.values = unsafe { table.alloc_value_array(&table.inner, Fallibility::Fallible)? };
+        Ok(table)
+    }
+
+    ///
+    /// 获取`key: &K`在此map中的`value: &V`, 出入参均为K/V的有效内存指针。
+    /// 它的使用场景为`map.get(key)`, 计算过程中直接使用`self.hash_fn`和`self.eq_fn`
+    ///
+    pub unsafe fn access(&self, key: *const u8) -> Option<*const u8> {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.find(key).map(|o| self.value_ptr(self.bucket(o)))
+    }
+
+    /// `access`的`NonNull`版本, 以类型保证入参与返回的value地址均非空
+    pub unsafe fn access_nonnull(&self, key: NonNull<u8>) -> Option<NonNull<u8>> {
+        self.access(key.as_ptr()).map(|value| non_null(value))
+    }
+
+    ///
+    /// 按`hash`探测并对每个H2匹配的entry调用`eq(entry)`, 返回首个匹配entry的value指针。
+    ///
+    /// 探测循环随`eq`单态化展开, 供特化的spec(例如`IntSpec`)绕过`find`直接比较Key
+    ///
+    #[inline(always)]
+    pub(crate) unsafe fn find_value_by(
+        &self,
+        hash: u64,
+        mut eq: impl FnMut(*const u8) -> bool,
+    ) -> Option<*const u8> {
+        let tag = Tag::full(hash);
+        let mut probe = self.inner.probe_seq(hash);
+        loop {
+            let group = Group::load(self.inner.ctrl(probe.pos));
+            for bit in group.match_tag(tag) {
+                let bucket = self.bucket((probe.pos + bit) & self.inner.bucket_mask);
+                if likely(eq(bucket)) {
+                    return Some(self.value_ptr(bucket));
+                }
+            }
+            if likely(group.match_empty().any_bit_set()) {
+                return None;
+            }
+            probe.move_next(self.inner.bucket_mask);
+        }
+    }
+
+    ///
+    /// 预取`key: &K`首个探测Group的ctrl字节与起始bucket, 只计算hash, 不做探测与Key比较。
+    ///
+    /// 调用方提前得知即将访问的Key时(例如解释器提前一条字节码得知下一次属性访问)调用,
+    /// 使之后`access`等查找的cache miss与其间的其他工作重叠; 小table只预取ctrl字节的起始位置
+    ///
+    pub unsafe fn prefetch(&self, key: *const u8) {
+        if self.inner.is_empty_singleton() {
+            return;
+        }
+        if self.inner.bucket_mask < SMALL_SCAN_BUCKETS && self.inner.items <= SMALL_SCAN_ITEMS {
+            prefetch_read(self.inner.ctrl(0));
+            return;
+        }
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.prefetch_probe(spec_hash(&self.entry, key));
+    }
+
+    ///
+    /// 批量查找`N`个Key, 依次返回各自的value指针, 结果与逐个调用`access`一致。
+    ///
+    /// 先批量计算全部hash并预取各自首个探测Group, 再轮流推进每个Key的探测, 使多个Key的cache miss相互重叠;
+    /// 适用于hash join、inline cache批量未命中等一次到达多个Key的场景, `N`通常取8~16
+    ///
+    pub unsafe fn lookup_pipeline<const N: usize>(
+        &self,
+        keys: [*const u8; N],
+    ) -> [Option<*const u8>; N] {
+        let mut results = [None; N];
+        if self.inner.items == 0 {
+            return results;
+        }
+        let mut scratch: [KeyScratch; N] = core::array::from_fn(|_| KeyScratch::new());
+        let mut normalized = [ptr::null(); N];
+        for ((key, scratch), normalized) in keys.iter().zip(&mut scratch).zip(&mut normalized) {
+            *normalized = self.entry.normalize_key(*key, scratch.0.as_mut_ptr());
+        }
+        let mut hashes = [0; N];
+        spec_hash_many(&self.entry, &normalized, &mut hashes);
+        let mut probes: [ProbeSeq; N] = core::array::from_fn(|i| {
+            self.prefetch_probe(hashes[i]);
+            self.inner.probe_seq(hashes[i])
+        });
+
+        // 每轮为每个未完成的Key检查一个Group, 未命中时推进到下一个Group并预取, 下一轮再检查
+        let mut pending = [true; N];
+        let mut remaining = N;
+        while remaining > 0 {
+            for i in 0..N {
+                if !pending[i] {
+                    continue;
+                }
+                let probe = &mut probes[i];
+                let group = Group::load(self.inner.ctrl(probe.pos));
+                let mut found = None;
+                for bit in group.match_tag(Tag::full(hashes[i])) {
+                    let index = (probe.pos + bit) & self.inner.bucket_mask;
+                    if likely(self.key_equals(normalized[i], hashes[i], index)) {
+                        found = Some(index);
+                        break;
+                    }
+                }
+                if found.is_none() && !group.match_empty().any_bit_set() {
+                    probe.move_next(self.inner.bucket_mask);
+                    prefetch_read(self.inner.ctrl(probe.pos));
+                    continue;
+                }
+                if let Some(index) = found {
+                    #[cfg(feature = "spec-validation")]
+                    self.validate_equal_keys(hashes[i], self.bucket(index));
+                    results[i] = Some(self.value_ptr(self.bucket(index)));
+                }
+                pending[i] = false;
+                remaining -= 1;
+            }
+        }
+        results
+    }
+
+    ///
+    /// 获取槽位`index`处entry的元数据区域指针, 元数据区域由`EntrySpec::metadata`给出。
+    ///
+    /// `index`越界、槽位为空或spec没有元数据时返回`None`; 返回的指针在entry被删除或table扩容前有效
+    ///
+    pub fn metadata_at(&self, index: usize) -> Option<*mut u8> {
+        let metadata = self.entry.metadata()?;
+        if index >= self.inner.buckets() || !unsafe { self.inner.is_bucket_full(index) } {
+            return None;
+        }
+        Some(unsafe { self.bucket(index).add(metadata.offset()) })
+    }
+
+    ///
+    /// 获取`key: &K`在此map中value的第`col`列的内存指针, 列结构由`EntrySpec::value_columns`给出。
+    ///
+    /// # Panics
+    ///
+    /// `col`超出列数时panic
+    ///
+    pub unsafe fn access_column(&self, key: *const u8, col: usize) -> Option<*const u8> {
+        let offset = self.entry.value_columns()[col].offset();
+        self.access(key).map(|value| value.add(offset))
+    }
+
+    ///
+    /// 获取`key: &K`的原子value, Key不存在时返回`None`
+    ///
+    /// # Panics
+    ///
+    /// spec未启用原子value模式, 或目标平台不支持该宽度的原子操作时panic
+    ///
+    #[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+    unsafe fn atomic_slot(&self, key: *const u8) -> Option<AtomicSlot<'_>> {
+        let width = self
+            .entry
+            .atomic_value()
+            .expect("spec does not use atomic values");
+        let value = self.access(key)?;
+        Some(match width {
+            #[cfg(target_has_atomic = "32")]
+            AtomicWidth::U32 => {
+                debug_assert!(addr(value) % mem::align_of::<AtomicU32>() == 0);
+                AtomicSlot::U32(&*(value as *const AtomicU32))
+            }
+            #[cfg(target_has_atomic = "64")]
+            AtomicWidth::U64 => {
+                debug_assert!(addr(value) % mem::align_of::<AtomicU64>() == 0);
+                AtomicSlot::U64(&*(value as *const AtomicU64))
+            }
+            #[allow(unreachable_patterns)]
+            _ => panic!("{width:?} atomic values are not supported on this target"),
+        })
+    }
+
+    ///
+    /// 以原子操作读取`key: &K`的value, Key不存在时返回`None`
+    ///
+    /// # Panics
+    ///
+    /// spec未启用原子value模式(见`EntrySpec::atomic_value`)时panic
+    ///
+    #[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+    pub unsafe fn load_value(&self, key: *const u8, order: AtomicOrdering) -> Option<u64> {
+        Some(match self.atomic_slot(key)? {
+            #[cfg(target_has_atomic = "32")]
+            AtomicSlot::U32(value) => u64::from(value.load(order)),
+            #[cfg(target_has_atomic = "64")]
+            AtomicSlot::U64(value) => value.load(order),
+        })
+    }
+
+    ///
+    /// 以原子操作将`delta`(回绕地)加到`key: &K`的value上并返回旧value, Key不存在时返回`None`。
+    ///
+    /// 只需要`&self`, 多个线程可以同时对同一个table计数; value为`u32`时只使用`delta`的低32位
+    ///
+    /// # Panics
+    ///
+    /// spec未启用原子value模式(见`EntrySpec::atomic_value`)时panic
+    ///
+    #[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+    pub unsafe fn fetch_add(
+        &self,
+        key: *const u8,
+        delta: u64,
+        order: AtomicOrdering,
+    ) -> Option<u64> {
+        Some(match self.atomic_slot(key)? {
+            #[cfg(target_has_atomic = "32")]
+            AtomicSlot::U32(value) => u64::from(value.fetch_add(delta as u32, order)),
+            #[cfg(target_has_atomic = "64")]
+            AtomicSlot::U64(value) => value.fetch_add(delta, order),
+        })
+    }
+
+    ///
+    /// 当`key: &K`的value等于`current`时以原子操作将其替换为`new`, 语义同`AtomicU64::compare_exchange`;
+    /// Key不存在时返回`None`, value为`u32`时只使用`current`与`new`的低32位
+    ///
+    /// # Panics
+    ///
+    /// spec未启用原子value模式(见`EntrySpec::atomic_value`)时panic
+    ///
+    #[cfg(any(target_has_atomic = "32", target_has_atomic = "64"))]
+    pub unsafe fn compare_exchange_value(
+        &self,
+        key: *const u8,
+        current: u64,
+        new: u64,
+        success: AtomicOrdering,
+        failure: AtomicOrdering,
+    ) -> Option<Result<u64, u64>> {
+        Some(match self.atomic_slot(key)? {
+            #[cfg(target_has_atomic = "32")]
+            AtomicSlot::U32(value) => value
+                .compare_exchange(current as u32, new as u32, success, failure)
+                .map(u64::from)
+                .map_err(u64::from),
+            #[cfg(target_has_atomic = "64")]
+            AtomicSlot::U64(value) => value.compare_exchange(current, new, success, failure),
+        })
+    }
+
+    ///
+    /// 获取`key: &K`在当前map中的“可赋值地址”, 出入参均为K/V的有效内存地址。
+    ///
+    /// 它的使用场景为`map.set(key, value)`, 只是过程分为两步走:
+    /// 先计算key槽位物理地址, 然后向地址内写入value；此函数只负责第一步, 即按需扩容+返回value地址, 由caller写入数据
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn assign(&mut self, key: *const u8) -> *const u8 {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.reserve_for(key).expect("map growth failure");
+        let index = self.find_or_insert(key).unwrap_or_else(|index| index);
+        let bucket = self.bucket(index);
+        // 返回value内存地址
+        self.value_ptr(bucket)
+    }
+
+    /// `assign`的`NonNull`版本
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn assign_nonnull(&mut self, key: NonNull<u8>) -> NonNull<u8> {
+        non_null(self.assign(key.as_ptr()))
+    }
+
+    ///
+    /// `assign`的fallible版本, 扩容、payload分配或spec的`try_assign_key`失败时返回错误而非panic,
+    /// 此时新占用的槽位已被回滚
+    ///
+    pub unsafe fn try_assign(&mut self, key: *const u8) -> Result<*const u8, InsertError> {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.reserve_for(key)?;
+        let assign_key = |spec: &E, bucket| spec.try_assign_key(bucket, key);
+        let index = self
+            .try_find_or_insert(key, assign_key)?
+            .unwrap_or_else(|index| index);
+        Ok(self.value_ptr(self.bucket(index)))
+    }
+
+    /// `try_assign`的`NonNull`版本
+    pub unsafe fn try_assign_nonnull(
+        &mut self,
+        key: NonNull<u8>,
+    ) -> Result<NonNull<u8>, InsertError> {
+        self.try_assign(key.as_ptr()).map(|value| non_null(value))
+    }
+
+    ///
+    /// `try_assign`的无容量检查版本: 不检查是否需要扩容, 适用于已通过`try_reserve`或构造时的容量预留足够空间的批量写入循环。
+    ///
+    /// payload分配或spec的`try_assign_key`失败时返回错误, 此时新占用的槽位已被回滚
+    ///
+    /// # Safety
+    ///
+    /// 除`try_assign`的要求外, caller须保证调用时当前table至少还能容纳一个新entry(即上一次预留之后写入的新Key数量小于预留的数量),
+    /// debug构建下不满足时panic
+    ///
+    pub unsafe fn assign_unchecked_capacity(
+        &mut self,
+        key: *const u8,
+    ) -> Result<*const u8, InsertError> {
+        debug_assert!(
+            self.inner.growth_left > 0,
+            "assign_unchecked_capacity called without reserved capacity"
+        );
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        let assign_key = |spec: &E, bucket| spec.try_assign_key(bucket, key);
+        let index = self
+            .try_find_or_insert(key, assign_key)?
+            .unwrap_or_else(|index| index);
+        Ok(self.value_ptr(self.bucket(index)))
+    }
+
+    ///
+    /// 将`key: &K`与`value: &V`写入当前map, 出入参均为K/V的有效内存指针。
+    ///
+    /// 新的Key通过`assign_value`写入value; 已存在的Key通过`replace_value`覆盖旧value
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn insert(&mut self, key: *const u8, value: *const u8) {
+        self.write(
+            key,
+            value,
+            |spec, value, v| spec.replace_value(value, v),
+            |spec, value, v| spec.assign_value(value, v),
+        )
+    }
+
+    ///
+    /// `insert`的实现, 已存在的Key以`on_existing(spec, value, v)`覆盖其value,
+    /// 新Key以`on_new(spec, value, v)`写入value
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    unsafe fn write(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+        on_existing: impl FnOnce(&E, *const u8, *const u8),
+        on_new: impl FnOnce(&E, *const u8, *const u8),
+    ) {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.reserve_for(key).expect("map growth failure");
+        match self.find_or_insert(key) {
+            _ if self.entry.zero_sized_value() => {}
+            Ok(index) => {
+                let bucket = self.bucket(index);
+                on_existing(&self.entry, self.value_ptr(bucket), value);
+            }
+            Err(index) => {
+                let bucket = self.bucket(index);
+                on_new(&self.entry, self.value_ptr(bucket), value);
+            }
+        }
+    }
+
+    ///
+    /// `insert`的fallible版本, 扩容失败或spec的`try_assign_*` hook失败时返回错误而非panic。
+    ///
+    /// 新Key的Key或Value写入失败时, 新占用的槽位会被回滚, map内容保持不变
+    ///
+    pub unsafe fn try_insert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+    ) -> Result<(), InsertError> {
+        self.try_write(
+            key,
+            value,
+            |spec, value, v| spec.try_replace_value(value, v),
+            |spec, value, v| spec.try_assign_value(value, v),
+        )
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`, Key已存在时以`combine(value, v)`将`v`合并到已有的value中(例如累加计数),
+    /// 其中`value`为map中的value地址。错误语义同`try_insert`
+    ///
+    pub unsafe fn try_upsert(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+        combine: impl FnOnce(*const u8, *const u8),
+    ) -> Result<(), InsertError> {
+        self.try_write(
+            key,
+            value,
+            |_, value, v| {
+                combine(value, v);
+                Ok(())
+            },
+            |spec, value, v| spec.try_assign_value(value, v),
+        )
+    }
+
+    ///
+    /// 写入通过`EntrySpec::decode_key`/`decode_value`从`key`与`value`解码出的entry, 已存在的Key的value被直接覆盖。
+    ///
+    /// value先解码到临时内存, 解码成功之后才写入table; spec解码失败时返回`Ok(false)`,
+    /// 分配失败时返回错误, 两种情况下table均保持不变(已存在的value不受影响)
+    ///
+    pub(crate) unsafe fn try_insert_encoded(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<bool, InsertError> {
+        let mut scratch = KeyScratch::new();
+        let k = scratch.0.as_mut_ptr();
+        if !self.entry.decode_key(key, k) {
+            return Ok(false);
+        }
+        if self.entry.zero_sized_value() {
+            self.try_assign(k)?;
+            return Ok(true);
+        }
+
+        // 大entry模式与SoA模式按value的内存结构分配临时内存, 否则分配整个entry并解码到其value位置
+        let separate = self.out_of_line().or_else(|| self.split());
+        let layout = separate.unwrap_or_else(|| self.entry.layout());
+        let temp = if layout.size() == 0 {
+            invalid_mut(layout.align())
+        } else {
+            match do_alloc(&self.alloc, layout) {
+                Ok(temp) => temp.as_ptr(),
+                Err(_) => return Err(Fallibility::Fallible.alloc_err(layout).into()),
+            }
+        };
+        let (decoded, size) = match separate {
+            Some(_) => (temp as *const u8, layout.size()),
+            None => {
+                let decoded = self.entry.access_value(temp);
+                (decoded, layout.size() - decoded.offset_from(temp) as usize)
+            }
+        };
+        let result = if self.entry.decode_value(value, decoded) {
+            self.try_assign(k).map(|slot| {
+                ptr::copy_nonoverlapping(decoded, slot as *mut u8, size);
+                true
+            })
+        } else {
+            Ok(false)
+        };
+        if layout.size() != 0 {
+            self.alloc.deallocate(NonNull::new_unchecked(temp), layout);
+        }
+        result
+    }
+
+    ///
+    /// 删除通过`EntrySpec::decode_key`从`key`解码出的Key, spec解码失败时返回`false`
+    ///
+    pub(crate) unsafe fn delete_encoded(&mut self, key: &[u8]) -> bool {
+        let mut scratch = KeyScratch::new();
+        let k = scratch.0.as_mut_ptr();
+        if !self.entry.decode_key(key, k) {
+            return false;
+        }
+        self.delete(k);
+        true
+    }
+
+    /// 获取`key: &K`所在entry的槽位下标
+    pub(crate) unsafe fn find_index(&self, key: *const u8) -> Option<usize> {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.find(key)
+    }
+
+    ///
+    /// 将`other`中槽位`index`处的entry(连同单独存放的value与payload)移动到当前table, 之后该槽位在`other`中被软删除。
+    ///
+    /// caller需保证两个table兼容、槽位有效、Key不在当前table中且已预留足够的容量;
+    /// value或payload分配失败时返回错误, 此时entry仍保留在`other`中
+    ///
+    pub(crate) unsafe fn move_from(
+        &mut self,
+        other: &mut Self,
+        index: usize,
+    ) -> Result<(), TryReserveError> {
+        let bucket = other.bucket(index);
+        let hash = entry_hash(&self.entry, bucket);
+        let mut combine = |spec: &E, value, v| spec.replace_value(value, v);
+        self.extend_one(bucket, other.value_ptr(bucket), hash, &mut combine)?;
+        other.free_value(bucket);
+        other.inner.erase(index);
+        Ok(())
+    }
+
+    ///
+    /// 将table之外的`entry`(value位于entry内部)整体拷贝到当前table, 需要时扩容。
+    ///
+    /// caller需保证Key不在当前table中, 且spec未启用大entry模式与SoA模式
+    ///
+    pub(crate) unsafe fn insert_entry(&mut self, entry: *const u8) -> Result<(), TryReserveError> {
+        self.check_growth(1)?;
+        let hash = spec_hash(&self.entry, entry);
+        let mut combine = |spec: &E, value, v| spec.replace_value(value, v);
+        self.extend_one(entry, self.value_ptr(entry), hash, &mut combine)
+    }
+
+    /// 获取`key: &K`所在entry的内存指针
+    pub(crate) unsafe fn find_entry(&self, key: *const u8) -> Option<*const u8> {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.find(key).map(|index| self.bucket(index) as *const u8)
+    }
+
+    ///
+    /// `try_insert`与`try_upsert`的实现, Key已存在时以`on_existing(spec, value, v)`写入value,
+    /// 新Key以`on_new(spec, value, v)`写入value
+    ///
+    #[inline(always)]
+    unsafe fn try_write(
+        &mut self,
+        key: *const u8,
+        value: *const u8,
+        on_existing: impl FnOnce(&E, *const u8, *const u8) -> Result<(), AssignError>,
+        on_new: impl FnOnce(&E, *const u8, *const u8) -> Result<(), AssignError>,
+    ) -> Result<(), InsertError> {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        self.reserve_for(key)?;
+        let assign_key = |spec: &E, bucket| spec.try_assign_key(bucket, key);
+        match self.try_find_or_insert(key, assign_key)? {
+            _ if self.entry.zero_sized_value() => {}
+            Ok(index) => {
+                let bucket = self.bucket(index);
+                on_existing(&self.entry, self.value_ptr(bucket), value)?;
+            }
+            Err(index) => {
+                let bucket = self.bucket(index);
+                let assigned = on_new(&self.entry, self.value_ptr(bucket), value);
+                if let Err(err) = assigned {
+                    self.free_value(bucket);
+                    self.inner.erase(index);
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 将other中的所有buckets导入当前map, 已存在的Key通过`replace_value`覆盖其value。
+    ///
+    /// Key的hash按`HASH_BATCH`个一组通过`EntrySpec::hash_many`批量计算
+    ///
+    /// # Panics
+    ///
+    /// 两个table不兼容(见`is_compatible`)时panic
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn extend(&mut self, other: &Self) {
+        assert!(
+            self.is_compatible(other),
+            "extend requires tables with compatible entry specs"
+        );
+        self.check_growth(other.len()).expect("map growth failure");
+        self.extend_unchecked(other)
+            .expect("map value allocation failure");
+    }
+
+    ///
+    /// `extend`的fallible版本, 两个table不兼容或扩容失败时返回错误, 此时当前map保持不变。
+    ///
+    /// 大entry模式的value或payload分配失败时同样返回错误, 此时已导入的entries保留在当前map中
+    ///
+    pub unsafe fn try_extend(&mut self, other: &Self) -> Result<(), ExtendError> {
+        if !self.is_compatible(other) {
+            return Err(ExtendError::Incompatible);
+        }
+        self.check_growth(other.len())?;
+        self.extend_unchecked(other)?;
+        Ok(())
+    }
+
+    ///
+    /// 将other中的所有entries合并到当前map, 已存在的Key以`combine(value, v)`合并value,
+    /// 其中`value`为当前map中的value地址, `v`为other中的value地址; 用于合并各线程的局部聚合结果等场景。
+    ///
+    /// 错误语义同`try_extend`
+    ///
+    pub unsafe fn try_merge(
+        &mut self,
+        other: &Self,
+        mut combine: impl FnMut(*const u8, *const u8),
+    ) -> Result<(), ExtendError> {
+        if !self.is_compatible(other) {
+            return Err(ExtendError::Incompatible);
+        }
+        self.check_growth(other.len())?;
+        self.merge_unchecked(other, |_, value, v| combine(value, v))?;
+        Ok(())
+    }
+
+    ///
+    /// 判断other中的entries能否直接导入当前map: 两个spec的entry内存结构
+    /// (`layout`、value偏移量、value存储模式)以及`spec_id`必须一致。
+    ///
+    /// value偏移量通过other中的首个entry比较, other为空时无需比较
+    ///
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        let (this, that) = (&self.entry, &other.entry);
+        if this.layout().pad_to_align() != that.layout().pad_to_align()
+            || this.zero_sized_value() != that.zero_sized_value()
+            || self.out_of_line() != other.out_of_line()
+            || self.split() != other.split()
+            || this.spec_id() != that.spec_id()
+        {
+            return false;
+        }
+        if this.zero_sized_value() || self.split().is_some() {
+            return true;
+        }
+        match unsafe { other.inner.full_buckets_indices() }.next() {
+            Some(index) => unsafe {
+                let bucket = other.bucket(index);
+                this.access_value(bucket) == that.access_value(bucket)
+            },
+            None => true,
+        }
+    }
+
+    ///
+    /// 将other中的所有buckets导入当前map, caller需保证两个table兼容且已预留足够的容量
+    ///
+    pub(crate) unsafe fn extend_unchecked(&mut self, other: &Self) -> Result<(), TryReserveError> {
+        self.merge_unchecked(other, |spec, value, v| spec.replace_value(value, v))
+    }
+
+    ///
+    /// 将other中的所有buckets导入当前map, 已存在的Key通过`combine(spec, value, v)`合并value,
+    /// caller需保证两个table兼容且已预留足够的容量
+    ///
+    unsafe fn merge_unchecked(
+        &mut self,
+        other: &Self,
+        mut combine: impl FnMut(&E, *const u8, *const u8),
+    ) -> Result<(), TryReserveError> {
+        let mut indices = other.inner.full_buckets_indices();
+        let mut entries = [ptr::null(); HASH_BATCH];
+        let mut hashes = [0u64; HASH_BATCH];
+        loop {
+            let mut n = 0;
+            for other_idx in indices.by_ref().take(HASH_BATCH) {
+                entries[n] = other.bucket(other_idx) as *const u8;
+                n += 1;
+            }
+            if n == 0 {
+                return Ok(());
+            }
+            spec_hash_many(&self.entry, &entries[..n], &mut hashes[..n]);
+            for i in 0..n {
+                let value = other.value_ptr(entries[i]);
+                self.extend_one(entries[i], value, hashes[i], &mut combine)?;
+            }
+        }
+    }
+
+    ///
+    /// 将另一个map中的`entry`(其value地址为`value`)导入当前map, Key已存在时以`combine`合并value,
+    /// caller需保证已预留足够的容量。
+    ///
+    /// value或payload分配失败时回滚新占用的槽位
+    ///
+    #[inline(always)]
+    unsafe fn extend_one(
+        &mut self,
+        entry: *const u8,
+        value: *const u8,
+        hash: u64,
+        combine: &mut impl FnMut(&E, *const u8, *const u8),
+    ) -> Result<(), TryReserveError> {
+        match self.find_or_claim(entry, hash) {
+            Ok(_) if self.entry.zero_sized_value() => {}
+            Ok(index) => {
+                // 保留当前Key(及其payload)不变, 仅合并value
+                let bucket = self.bucket(index);
+                combine(&self.entry, self.value_ptr(bucket), value);
+            }
+            Err(index) => {
+                let bucket = self.bucket(index);
+                ptr::copy_nonoverlapping(entry, bucket, self.entry.layout().size());
+                store_hash(&self.entry, bucket, hash);
+                // 大entry模式下value仍指向other的内存, 需要拷贝到新分配的内存中;
+                // SoA模式下value位于other的value数组中, 同样需要拷贝
+                if let Some(layout) = self.out_of_line() {
+                    if let Err(err) = self.alloc_value(bucket, layout) {
+                        self.inner.erase(index);
+                        return Err(err);
+                    }
+                    ptr::copy_nonoverlapping(
+                        value,
+                        self.value_ptr(bucket) as *mut u8,
+                        layout.size(),
+                    );
+                } else if let Some(layout) = self.split() {
+                    ptr::copy_nonoverlapping(
+                        value,
+                        self.value_ptr(bucket) as *mut u8,
+                        layout.size(),
+                    );
+                }
+                // 变长数据仍指向other的arena, 需要拷贝到当前map的arena中
+                let payload_size = self.entry.payload_size(entry);
+                if payload_size > 0 {
+                    let payload = match self.payload.alloc(&self.alloc, payload_size) {
+                        Ok(payload) => payload,
+                        Err(err) => {
+                            self.free_value(bucket);
+                            self.inner.erase(index);
+                            return Err(err);
+                        }
+                    };
+                    self.entry.assign_payload(bucket, entry, payload.as_ptr());
+                }
+                #[cfg(feature = "spec-validation")]
+                self.validate_assigned_key(entry, hash, bucket);
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 从当前map中删除指定key, 即将该key对应的Bucket软删除
+    ///
+    pub unsafe fn delete(&mut self, key: *const u8) {
+        let mut scratch = KeyScratch::new();
+        let key = self.entry.normalize_key(key, scratch.0.as_mut_ptr());
+        if let Some(index) = self.find(key) {
+            self.free_value(self.bucket(index));
+            self.inner.erase(index);
+            self.check_shrink();
+        }
+    }
+
+    ///
+    /// 清空当前map中的所有entries, 不需要卸载内存, 软删所有Bucket即可
+    ///
+    pub fn clear(&mut self) {
+        unsafe { self.free_values() };
+        self.inner.clear_no_drop();
+        unsafe { self.payload.free(&self.alloc) };
+    }
+
+    ///
+    /// 清空当前map中的所有entries并释放全部内存, 回到容量为0的状态。
+    ///
+    /// 与销毁后重新构造不同, spec、扩缩容策略、对齐要求与分离模式等配置均被保留;
+    /// 固定容量模式的table被重置后容量为0, 之后的写入都会失败
+    ///
+    pub fn reset(&mut self) {
+        self.releasing(|table| unsafe { table.free_all() });
+        self.inner = RawTableInner::NEW;
+        self.values = ptr::null_mut();
+        self.entries = ptr::null_mut();
+        self.entries_moved();
+    }
+
+    ///
+    /// 释放table持有的全部内存, 之后`inner`、`values`与`entries`均不可再使用
+    ///
+    unsafe fn free_all(&mut self) {
+        self.free_values();
+        self.payload.free(&self.alloc);
+        self.free_value_array(&self.inner, self.values);
+        self.free_entry_array(&self.inner, self.entries);
+        if !self.inner.is_empty_singleton() {
+            self.inner.free_buckets(&self.alloc, self.table_layout());
+        }
+    }
+
+    ///
+    /// 仅保留`f(key, value)`返回`true`的entries, 其余entries在`on_evict`之后被软删除
+    ///
+    pub fn retain(&mut self, mut f: impl FnMut(*const u8, *const u8) -> bool) {
+        self.advise_scan(true);
+        unsafe {
+            for index in self.inner.full_buckets_indices() {
+                let bucket = self.bucket(index);
+                if !f(bucket, self.value_ptr(bucket)) {
+                    self.evict(index);
+                }
+            }
+        }
+        self.advise_scan(false);
+        self.check_shrink();
+    }
+
+    ///
+    /// 在`on_evict`之后软删除槽位`index`处的entry, caller需保证该槽位有效
+    ///
+    pub(crate) unsafe fn evict(&mut self, index: usize) {
+        let bucket = self.bucket(index);
+        self.entry.on_evict(bucket, self.value_ptr(bucket));
+        self.free_value(bucket);
+        self.inner.erase(index);
+    }
+
+    ///
+    /// 清空当前map中的所有entries, 与`clear`不同的是每个entry都会先经过`on_evict`
+    ///
+    pub fn clear_and_evict(&mut self) {
+        self.advise_scan(true);
+        unsafe {
+            for index in self.inner.full_buckets_indices() {
+                let bucket = self.bucket(index);
+                self.entry.on_evict(bucket, self.value_ptr(bucket));
+            }
+        }
+        self.advise_scan(false);
+        self.clear();
+    }
+
+    ///
+    /// 获取当前map中的entries数量
+    ///
+    pub fn len(&self) -> usize {
+        self.inner.items
+    }
+
+    ///
+    /// 预留至少可再写入`additional`个entries的容量, 固定容量模式下容量不足时返回`CapacityOverflow`
+    ///
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        unsafe { self.check_growth(additional) }
+    }
+
+    ///
+    /// 预留至少可再写入`additional`个entries的容量, 并逐页写入bucket数组(以及SoA模式的value数组)的空槽位,
+    /// 使操作系统提前完成缺页处理, 避免扩容后的首批写入在关键路径上集中触发缺页。
+    ///
+    /// 只写入空槽位, 已有entries不受影响; 大entry模式下单独分配的value与payload内存池不会被预先写入
+    ///
+    pub fn reserve_populated(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        unsafe { self.check_growth(additional)? };
+        if self.inner.is_empty_singleton() {
+            return Ok(());
+        }
+        let stride = self.entry.layout().pad_to_align().size();
+        let vsize = self
+            .split()
+            .map_or(0, |layout| layout.pad_to_align().size());
+        let (mut last_bucket_page, mut last_value_page) = (usize::MAX, usize::MAX);
+        for index in 0..self.inner.buckets() {
+            if unsafe { self.inner.is_bucket_full(index) } {
+                continue;
+            }
+            unsafe {
+                let bucket = self.bucket(index);
+                if stride != 0 && addr(bucket) / PAGE_SIZE != last_bucket_page {
+                    last_bucket_page = addr(bucket) / PAGE_SIZE;
+                    ptr::write_volatile(bucket, 0);
+                }
+                let value = self.values.wrapping_add(index * vsize);
+                if vsize != 0 && addr(value) / PAGE_SIZE != last_value_page {
+                    last_value_page = addr(value) / PAGE_SIZE;
+                    ptr::write_volatile(value, 0);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 获取当前table的堆内存占用, 包括控制字节、bucket数组、value内存以及payload内存池
+    ///
+    pub fn memory_usage(&self) -> MemoryUsage {
+        // entry自身的大小, 单独存放的value也计入其中
+        let entry_size = self.entry.layout().pad_to_align().size()
+            + self
+                .out_of_line()
+                .or(self.split())
+                .map_or(0, |layout| layout.size());
+        let (ctrl_bytes, bucket_bytes) = if self.inner.is_empty_singleton() {
+            (0, 0)
+        } else {
+            let buckets = self.inner.buckets();
+            let table_layout = self.table_layout();
+            // 已分配的table的内存结构一定可以计算
+            let (layout, ctrl_offset) = table_layout.calculate_layout_for(buckets).unwrap();
+            let bucket_bytes = if self.separate {
+                self.entry_array_layout().0 * buckets
+            } else {
+                ctrl_offset
+            };
+            (layout.size() - ctrl_offset, bucket_bytes)
+        };
+        let value_bytes = if let Some(layout) = self.out_of_line() {
+            layout.size() * self.inner.items
+        } else if let Some(layout) = self.split() {
+            if self.inner.is_empty_singleton() {
+                0
+            } else {
+                layout.pad_to_align().size() * self.inner.buckets()
+            }
+        } else {
+            0
+        };
+        let payload_bytes = self.payload.capacity();
+        let total = ctrl_bytes + bucket_bytes + value_bytes + payload_bytes;
+        let per_entry_overhead = match self.inner.items {
+            0 => 0,
+            items => total.saturating_sub(entry_size * items) / items,
+        };
+        MemoryUsage {
+            ctrl_bytes,
+            bucket_bytes,
+            value_bytes,
+            payload_bytes,
+            total,
+            per_entry_overhead,
+        }
+    }
+
+    ///
+    /// 复制当前table的所有entries(包括大entry模式的value与payload)到新分配的table中,
+    /// 新table保留扩缩容策略、对齐要求、分离模式与固定容量模式等配置
+    ///
+    pub fn try_clone(&self) -> Result<Self, TryReserveError>
+    where
+        E: Clone,
+        A: Clone,
+    {
+        let cap = if self.fixed {
+            self.capacity()
+        } else {
+            self.len()
+        };
+        let mut table = self.empty_like(cap)?;
+        // spec相同, 无需检查兼容性
+        unsafe {
+            table.check_growth(self.len())?;
+            table.extend_unchecked(self)?;
+        }
+        Ok(table)
+    }
+
+    ///
+    /// 构造与当前table配置相同(spec、扩缩容策略、对齐要求、分离模式与固定容量模式)、容量至少为`cap`的空table
+    ///
+    pub(crate) fn empty_like(&self, cap: usize) -> Result<Self, TryReserveError>
+    where
+        E: Clone,
+        A: Clone,
+    {
+        let alloc = self.allocator().clone();
+        let mut table = Self::new_in(
+            cap,
+            self.bucket_align,
+            self.separate,
+            self.entry.clone(),
+            alloc,
+        )?;
+        table.growth_policy = self.growth_policy;
+        table.shrink_policy = self.shrink_policy;
+        table.tombstone_policy = self.tombstone_policy;
+        table.fixed = self.fixed;
+        #[cfg(feature = "madvise")]
+        table.set_memory_hints(self.memory_hints);
+        table.set_lookup_cache(self.has_lookup_cache());
+        Ok(table)
+    }
+
+    ///
+    /// 获取当前table在不重新分配bucket数组的情况下最多可容纳的entries数量
+    ///
+    pub fn capacity(&self) -> usize {
+        bucket_mask_to_capacity(self.inner.bucket_mask)
+    }
+
+    ///
+    /// 在原有内存中重新整理所有entries, 回收删除entries留下的tombstone, 不分配任何内存。
+    ///
+    /// entries会在bucket数组内移动, 之前获取的entry地址全部失效;
+    /// 固定容量模式的table可以在允许移动entries的时机调用此方法恢复可用容量
+    ///
+    pub fn compact(&mut self) {
+        if !self.inner.is_empty_singleton() {
+            unsafe { self.rehash_entries_in_place(false) };
+        }
+        self.entries_moved();
+    }
+
+    ///
+    /// 判断当前map是否为空
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// 使用新的spec(例如新的hash种子或hash算法)重建当前table, 复用已有的bucket内存,
+    /// 仅基于新spec的hash在原地重新计算所有控制字节与entry位置。
+    ///
+    /// 新spec必须能够识别当前table中已有entries的内存结构(包括payload引用)。
+    ///
+    /// # Panics
+    ///
+    /// 新spec的`layout`与当前spec不一致时panic
+    ///
+    pub fn rehash_with<E2: EntrySpec>(self, spec: E2) -> RawTable2<E2, A> {
+        let layout = self.entry.layout().pad_to_align();
+        assert_eq!(
+            layout,
+            spec.layout().pad_to_align(),
+            "rehash_with requires an identical entry layout"
+        );
+        assert_eq!(
+            self.entry.out_of_line_value(),
+            spec.out_of_line_value(),
+            "rehash_with requires an identical out-of-line value layout"
+        );
+        assert_eq!(
+            self.split(),
+            spec.split_value().filter(|_| !spec.zero_sized_value()),
+            "rehash_with requires an identical split value layout"
+        );
+
+        let this = mem::ManuallyDrop::new(self);
+        let mut table = unsafe {
+            drop(ptr::read(&this.entry));
+            RawTable2 {
+                entry: spec,
+                alloc: ptr::read(&this.alloc),
+                inner: ptr::read(&this.inner),
+                payload: ptr::read(&this.payload),
+                values: this.values,
+                growth_policy: this.growth_policy,
+                shrink_policy: this.shrink_policy,
+                tombstone_policy: this.tombstone_policy,
+                bucket_align: this.bucket_align,
+                separate: this.separate,
+                fixed: this.fixed,
+                entries: this.entries,
+                #[cfg(feature = "madvise")]
+                memory_hints: this.memory_hints,
+                lookup_cache: this.lookup_cache.as_ref().map(|_| LookupCache::new()),
+                #[cfg(debug_assertions)]
+                generation: this.generation + 1,
+            }
+        };
+        if !table.inner.is_empty_singleton() {
+            unsafe { table.rehash_entries_in_place(true) };
+        }
+        table
+    }
+
+    ///
+    /// `rehash_with`的原地版本: 替换为同类型的新spec(例如轮换hash种子)并在原有内存中重新计算所有控制字节与entry位置,
+    /// 不分配任何内存, SoA模式的value与分离模式的bucket数组同样原地移动。
+    ///
+    /// 新spec的entry内存结构(`layout`、大entry模式与SoA模式的value内存结构)与当前spec不一致时,
+    /// 以`Err`返回新spec且当前table保持不变
+    ///
+    pub fn rebuild_in_place(&mut self, spec: E) -> Result<(), E> {
+        if self.entry.layout().pad_to_align() != spec.layout().pad_to_align()
+            || self.entry.zero_sized_value() != spec.zero_sized_value()
+            || self.entry.out_of_line_value() != spec.out_of_line_value()
+            || self.split() != spec.split_value().filter(|_| !spec.zero_sized_value())
+        {
+            return Err(spec);
+        }
+        self.entry = spec;
+        if !self.inner.is_empty_singleton() {
+            unsafe { self.rehash_entries_in_place(true) };
+        }
+        self.entries_moved();
+        Ok(())
+    }
+
+    ///
+    /// 基于当前spec的hash原地重新计算所有控制字节与entry位置, 不分配内存。
+    ///
+    /// 与`RawTableInner::rehash_in_place`的算法相同, 但SoA模式下value随Key一同移动,
+    /// 分离模式下entries在独立的bucket数组中移动; caller需保证table已分配buckets。
+    /// `refresh`为`true`时(更换spec之后)重新计算并写入缓存的hash, 否则直接使用缓存
+    ///
+    unsafe fn rehash_entries_in_place(&mut self, refresh: bool) {
+        let stride = self.entry.layout().pad_to_align().size();
+        let vsize = self
+            .split()
+            .map_or(0, |layout| layout.pad_to_align().size());
+        let (entries, values, separate) = (self.entries, self.values, self.separate);
+        let spec = &self.entry;
+        let bucket = |inner: &RawTableInner, index: usize| {
+            if separate {
+                entries.add(index * stride)
+            } else {
+                inner.bucket_ptr(index, stride)
+            }
+        };
+
+        self.inner.prepare_rehash_in_place();
+        // hash panic时尚未处理的entries(DELETED)无法恢复, 只需保证growth_left与items一致
+        let mut guard = guard(&mut self.inner, |inner| {
+            inner.growth_left = bucket_mask_to_capacity(inner.bucket_mask) - inner.items;
+        });
+        'outer: for i in 0..guard.buckets() {
+            if *guard.ctrl(i) != Tag::DELETED {
+                continue;
+            }
+            'inner: loop {
+                let hash = if refresh {
+                    let hash = spec_hash(spec, bucket(&guard, i));
+                    store_hash(spec, bucket(&guard, i), hash);
+                    hash
+                } else {
+                    entry_hash(spec, bucket(&guard, i))
+                };
+                let new_i = guard.find_insert_slot(hash).index;
+                if likely(guard.is_in_same_group(i, new_i, hash)) {
+                    guard.set_ctrl_hash(i, hash);
+                    continue 'outer;
+                }
+
+                let (i_p, new_i_p) = (bucket(&guard, i), bucket(&guard, new_i));
+                let (i_v, new_i_v) = (
+                    values.wrapping_add(i * vsize),
+                    values.wrapping_add(new_i * vsize),
+                );
+                let prev_ctrl = guard.replace_ctrl_hash(new_i, hash);
+                if prev_ctrl == Tag::EMPTY {
+                    guard.set_ctrl(i, Tag::EMPTY);
+                    ptr::copy_nonoverlapping(i_p, new_i_p, stride);
+                    if vsize != 0 {
+                        ptr::copy_nonoverlapping(i_v, new_i_v, vsize);
+                    }
+                    continue 'outer;
+                } else {
+                    debug_assert_eq!(prev_ctrl, Tag::DELETED);
+                    ptr::swap_nonoverlapping(i_p, new_i_p, stride);
+                    if vsize != 0 {
+                        ptr::swap_nonoverlapping(i_v, new_i_v, vsize);
+                    }
+                    continue 'inner;
+                }
+            }
+        }
+    }
+
+    ///
+    /// 针对当前table派生出`map<K, V>`的便捷封装, caller需要保证内存安全性
+    ///
+    pub unsafe fn as_map<K, V>(&'_ mut self) -> RawMap<'_, K, V, E, A> {
+        RawMap {
+            table: self,
+            phantom: Default::default(),
+        }
+    }
+
+    ///
+    /// Swiss-Table可以视为一个稀疏的一维数组, 即部分slot为有效的Bucket,
+    /// 它的迭代策略为[0...len]遍历所有槽位中的“FULL”状态并返回该槽位内存地址。
+    ///
+    /// 此方法是map迭代器的核心实现, 用于获取map底层一维数组中`[index, size)`的首个有效entry。
+    ///
+    pub fn next_entry(&self, index: usize) -> Option<(usize, *const u8)> {
+        self.next_entry_in(index, self.inner.buckets())
+    }
+
+    /// `next_entry`的`NonNull`版本
+    pub fn next_entry_nonnull(&self, index: usize) -> Option<(usize, NonNull<u8>)> {
+        self.next_entry(index)
+            .map(|(index, entry)| (index, unsafe { non_null(entry) }))
+    }
+
+    ///
+    /// `next_entry`的有界版本, 获取槽位`[start, end)`中的首个有效entry, `end`超出`buckets`时按`buckets`处理。
+    ///
+    /// 适用于增量处理: 每次只扫描固定数量的槽位, 不会越过`end`继续扫描
+    ///
+    pub fn next_entry_in(&self, start: usize, end: usize) -> Option<(usize, *const u8)> {
+        let end = end.min(self.inner.buckets());
+        let mut curr_idx = start;
+        // 检查是否遍历到了末尾
+        while curr_idx < end {
+            // 加载[curr_idx, curr_idx+16]的一组控制字节, 它可能是未对齐的
+            let group = unsafe {
+                let group_ptr = self.inner.ctrl(curr_idx);
+                Group::load(group_ptr)
+            };
+            // 匹配FULL状态的位掩码
+            let mask = group.match_full();
+            if mask.any_bit_set() {
+                // 此函数只关心next, 而非next_all
+                let matched_index = curr_idx + mask.trailing_zeros();
+                // 越过`end`的下标说明范围内已没有有效entry(末尾的一组控制字节还会读到首组的镜像)
+                if matched_index >= end {
+                    return None;
+                }
+                let bucket = unsafe { self.bucket(matched_index) };
+                return Some((matched_index, bucket));
+            }
+            curr_idx += Group::WIDTH;
+        }
+        None
+    }
+
+    ///
+    /// `next_entry`的反向版本, 获取槽位`[0, index)`中的最后一个有效entry, `index`超出`buckets`时按`buckets`处理。
+    ///
+    /// 适用于从尾部开始扫描的驱逐或整理算法
+    ///
+    pub fn prev_entry(&self, index: usize) -> Option<(usize, *const u8)> {
+        let mut end = index.min(self.inner.buckets());
+        while end > 0 {
+            let matched_index = if end >= Group::WIDTH {
+                // 加载[end-16, end)的一组控制字节, 组内全部槽位均在范围内
+                let curr_idx = end - Group::WIDTH;
+                let group = unsafe { Group::load(self.inner.ctrl(curr_idx)) };
+                let mask = group.match_full();
+                end = curr_idx;
+                if !mask.any_bit_set() {
+                    continue;
+                }
+                curr_idx + Group::WIDTH - 1 - mask.leading_zeros()
+            } else {
+                // 首组不足16个槽位, `end`之后的控制字节(包括首组的镜像)需要排除
+                let group = unsafe { Group::load(self.inner.ctrl(0)) };
+                let limit = end;
+                end = 0;
+                match group.match_full().into_iter().filter(|&i| i < limit).last() {
+                    Some(matched_index) => matched_index,
+                    None => continue,
+                }
+            };
+            let bucket = unsafe { self.bucket(matched_index) };
+            return Some((matched_index, bucket));
+        }
+        None
+    }
+
+    ///
+    /// 按槽位下标对每个entry调用`f(key)`, 只扫描控制字节并访问entry中的Key, 不计算value指针。
+    ///
+    /// 适用于在大table上构建布隆过滤器或Key摘要, 避免value被带入缓存
+    ///
+    pub fn for_each_key(&self, mut f: impl FnMut(*const u8)) {
+        self.advise_scan(true);
+        unsafe {
+            for index in self.inner.full_buckets_indices() {
+                f(self.bucket(index));
+            }
+        }
+        self.advise_scan(false);
+    }
+
+    ///
+    /// 按Key的完整64位hash升序迭代当前map中的所有entries, 返回`(key, value)`的内存指针。
+    ///
+    /// hash在调用时即时计算, 因此内容相同的table无论写入顺序如何都得到相同的迭代顺序,
+    /// 可用于生成可复现的快照或可归并的有序段; hash完全相同的不同Key之间仍按槽位下标排列
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub fn iter_by_hash(&self) -> impl Iterator<Item = (*const u8, *const u8)> + '_ {
+        let mut hashed: Vec<(u64, usize)> = self
+            .full_buckets_indices()
+            .map(|index| {
+                (
+                    unsafe { entry_hash(&self.entry, self.bucket(index)) },
+                    index,
+                )
+            })
+            .collect();
+        hashed.sort_unstable();
+        hashed.into_iter().map(move |(_, index)| unsafe {
+            let bucket = self.bucket(index);
+            (bucket as *const u8, self.value_ptr(bucket))
+        })
+    }
+
+    ///
+    /// 从`token`处继续按hash升序扫描至多`limit`个hash(`limit`为0时按1处理)对应的entries, 对每个entry调用`f(key, value)`,
+    /// 返回下一次扫描所需的token, 全部扫描完成时返回的token满足`ScanToken::is_done`。
+    ///
+    /// 首次扫描计算所有entries的hash并排序后记录在token中(每个entry占用8字节), 之后每批只按hash重新定位对应的entries,
+    /// 不再计算其他entries的hash。两次扫描之间可以任意写入、删除、扩容或rehash, 语义为:
+    ///
+    /// * 从首次扫描到扫描结束期间一直存在的entry恰好被访问一次, 扩容或rehash移动entry不影响按hash定位;
+    /// * 扫描期间删除的entry至多被访问一次, 写入的entry只有hash与尚未扫描的某个entry相同时才会被访问。
+    ///
+    /// hash相同的entries总是在同一批中返回, 因此一批可能超过`limit`个entries
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub fn scan(
+        &self,
+        mut token: ScanToken,
+        limit: usize,
+        mut f: impl FnMut(*const u8, *const u8),
+    ) -> ScanToken {
+        if token.is_done() {
+            return token;
+        }
+        if !token.started {
+            self.advise_scan(true);
+            let mut pending: Vec<u64> = self
+                .full_buckets_indices()
+                .map(|index| unsafe { entry_hash(&self.entry, self.bucket(index)) })
+                .collect();
+            self.advise_scan(false);
+            // 降序排列, 每批从末尾取出
+            pending.sort_unstable_by(|a, b| b.cmp(a));
+            pending.dedup();
+            token.pending = pending;
+            token.started = true;
+        }
+        let batch = token.pending.len().saturating_sub(limit.max(1));
+        for hash in token.pending.drain(batch..).rev() {
+            unsafe {
+                for index in RawIterHashInner::new(&self.inner, hash) {
+                    let bucket = self.bucket(index);
+                    if entry_hash(&self.entry, bucket) == hash {
+                        f(bucket, self.value_ptr(bucket));
+                    }
+                }
+            }
+        }
+        token.done = token.pending.is_empty();
+        token
+    }
+
+    ///
+    /// 按槽位下标迭代当前map中所有有效entry的下标, 支持双向迭代且长度精确。
+    ///
+    /// 适用于只需要槽位下标的场景(例如FFI游标或并行切分), 下标可以通过`metadata_at`等按下标访问的方法使用
+    ///
+    pub fn full_buckets_indices(&self) -> BucketIndices<'_, E, A> {
+        BucketIndices {
+            table: self,
+            inner: unsafe { self.inner.full_buckets_indices() },
+            end: self.inner.buckets(),
+            items: self.inner.items,
+            generation: self.generation(),
+        }
+    }
+
+    ///
+    /// 获取指向首个entry之前的游标, 可以在迭代过程中通过`Cursor::remove_current`删除entry
+    ///
+    pub fn cursor(&mut self) -> Cursor<'_, E, A> {
+        Cursor {
+            table: self,
+            next: 0,
+            current: None,
+        }
+    }
+
+    ///
+    /// 将槽位数组按`Group::WIDTH`对齐切分为至多`n`个互不相交的连续范围, 每个范围可以通过`iter_range`独立迭代。
+    ///
+    /// 适用于多线程并行扫描大table, 各线程只需持有自己的范围而无需共享同一个游标; `n`为0时按1处理
+    ///
+    pub fn chunks(&self, n: usize) -> impl Iterator<Item = BucketRange> {
+        let buckets = self.inner.buckets();
+        let groups = (buckets + Group::WIDTH - 1) / Group::WIDTH;
+        let n = n.clamp(1, groups);
+        let step = (groups + n - 1) / n * Group::WIDTH;
+        (0..buckets)
+            .step_by(step)
+            .map(move |start| BucketRange::new(start, (start + step).min(buckets)))
+    }
+
+    ///
+    /// 迭代槽位范围`range`中的所有entries, 返回`(key, value)`的内存指针, 顺序与槽位下标一致
+    ///
+    pub fn iter_range(&self, range: BucketRange) -> RangeIter<'_, E, A> {
+        RangeIter {
+            table: self,
+            index: range.start(),
+            end: range.end(),
+            generation: self.generation(),
+        }
+    }
+
+    ///
+    /// 获取调试模式下bucket数组被扩容或rehash的次数, release模式下恒为0。
+    ///
+    /// 基于`next_entry`手动迭代的caller可以在迭代前后比较此值, 以发现迭代期间的扩容;
+    /// `iter`等迭代器会在调试模式下自动检查并panic
+    ///
+    pub fn generation(&self) -> usize {
+        #[cfg(debug_assertions)]
+        return self.generation;
+        #[cfg(not(debug_assertions))]
+        return 0;
+    }
+
+    #[inline(always)]
+    fn check_generation(&self, generation: usize) {
+        debug_assert_eq!(
+            self.generation(),
+            generation,
+            "RawTable2 was grown or rehashed during iteration"
+        );
+    }
+
+    /// 标记entries已被移动或重新分配: 使查找缓存失效, 调试模式下递增`generation`
+    #[inline]
+    fn entries_moved(&mut self) {
+        if let Some(cache) = &mut self.lookup_cache {
+            cache.invalidate();
+        }
+        #[cfg(debug_assertions)]
+        {
+            self.generation += 1;
+        }
+    }
+
+    ///
+    /// 设置此table扩容时计算新容量的策略, 默认为`GrowthPolicy::Double`
+    ///
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.growth_policy = policy;
+    }
+
+    /// 获取此table扩容时计算新容量的策略
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth_policy
+    }
+
+    ///
+    /// 设置此table在`delete`/`retain`之后自动收缩的策略, `None`(默认)表示从不自动收缩
+    ///
+    pub fn set_shrink_policy(&mut self, policy: Option<ShrinkPolicy>) {
+        self.shrink_policy = policy;
+    }
+
+    /// 获取此table自动收缩的策略
+    pub fn shrink_policy(&self) -> Option<ShrinkPolicy> {
+        self.shrink_policy
+    }
+
+    ///
+    /// 设置此table在`delete`/`retain`之后自动回收tombstone的策略, `None`(默认)表示只在扩容或`compact`时回收;
+    /// 固定容量模式的table不会自动回收
+    ///
+    pub fn set_tombstone_policy(&mut self, policy: Option<TombstonePolicy>) {
+        self.tombstone_policy = policy;
+    }
+
+    /// 获取此table自动回收tombstone的策略
+    pub fn tombstone_policy(&self) -> Option<TombstonePolicy> {
+        self.tombstone_policy
+    }
+
+    ///
+    /// 设置此table向内核提供的内存使用建议, 默认不提供任何建议;
+    /// 开启`MADV_HUGEPAGE`时立即对当前的bucket数组生效
+    ///
+    #[cfg(feature = "madvise")]
+    pub fn set_memory_hints(&mut self, hints: MemoryHints) {
+        self.memory_hints = hints;
+        self.advise_huge_pages();
+    }
+
+    /// 获取此table向内核提供的内存使用建议
+    #[cfg(feature = "madvise")]
+    pub fn memory_hints(&self) -> MemoryHints {
+        self.memory_hints
+    }
+
+    ///
+    /// 设置是否开启查找缓存, 默认不开启。
+    ///
+    /// 开启后`access`等按Key查找时先检查同一hash最近命中的槽位, 同一个Key被反复查找时(例如解释器的inline cache)
+    /// 跳过探测; 命中时仍需计算hash并比较一次Key, 小table的线性扫描不经过缓存
+    ///
+    pub fn set_lookup_cache(&mut self, enabled: bool) {
+        if enabled != self.lookup_cache.is_some() {
+            self.lookup_cache = if enabled {
+                Some(LookupCache::new())
+            } else {
+                None
+            };
+        }
+    }
+
+    /// 判断此table是否开启了查找缓存
+    pub fn has_lookup_cache(&self) -> bool {
+        self.lookup_cache.is_some()
+    }
+
+    ///
+    /// 获取table内部结构的紧凑描述, 供调试器脚本与调试转储使用(见`TableDescriptor`)
+    ///
+    pub fn describe(&self) -> TableDescriptor {
+        let empty = self.inner.is_empty_singleton();
+        let stride = self.entry.layout().pad_to_align().size();
+        let (bucket0, bucket_stride) = if empty {
+            (ptr::null(), 0)
+        } else if self.separate {
+            (self.entries as *const u8, stride as isize)
+        } else {
+            // bucket数组位于ctrl字节之前, 按槽位下标降序排列
+            (unsafe { self.bucket(0) as *const u8 }, -(stride as isize))
+        };
+        let mut flags = 0;
+        for (flag, enabled) in [
+            (TableDescriptor::SEPARATE, self.separate),
+            (TableDescriptor::FIXED, self.fixed),
+            (TableDescriptor::OUT_OF_LINE, self.out_of_line().is_some()),
+            (TableDescriptor::SPLIT, self.split().is_some()),
+            (TableDescriptor::SET, self.entry.zero_sized_value()),
+        ] {
+            if enabled {
+                flags |= flag;
+            }
+        }
+        TableDescriptor {
+            version: TABLE_DESCRIPTOR_VERSION,
+            flags,
+            buckets: if empty { 0 } else { self.inner.buckets() },
+            items: self.inner.items,
+            growth_left: self.inner.growth_left,
+            tombstones: self.tombstones(),
+            entry_size: self.entry.layout().size(),
+            entry_align: self.entry.layout().align(),
+            ctrl: if empty {
+                ptr::null()
+            } else {
+                self.inner.ctrl.as_ptr() as *const u8
+            },
+            bucket0,
+            bucket_stride,
+        }
+    }
+
+    ///
+    /// 以`describe`的结果调用`v.begin`, 之后按槽位下标顺序以每个entry调用`v.visit`, 直到其返回`false`。
+    ///
+    /// entry经由spec的`fmt_key`/`fmt_value`格式化, 用于调试转储与调试器中的pretty-printer
+    ///
+    pub fn debug_visit(&self, v: &mut dyn EntryVisitor) {
+        v.begin(&self.describe());
+        let set = self.entry.zero_sized_value();
+        let mut index = 0;
+        while let Some((i, key)) = self.next_entry(index) {
+            index = i + 1;
+            let entry = DebugEntry {
+                index: i,
+                key,
+                value: unsafe { self.value_ptr(key) },
+                set,
+                spec: &self.entry,
+            };
+            if !v.visit(&entry) {
+                break;
+            }
+        }
+    }
+
+    /// 获取删除entries留下的tombstone数量, tombstone在扩容、收缩或原地整理之前一直占用容量
+    pub fn tombstones(&self) -> usize {
+        bucket_mask_to_capacity(self.inner.bucket_mask) - self.inner.items - self.inner.growth_left
+    }
+
+    /// 获取bucket数组分配时的对齐要求
+    pub fn bucket_align(&self) -> usize {
+        self.bucket_align
+    }
+
+    /// 判断此table是否为ctrl字节与bucket数组分别分配的分离模式
+    pub fn is_separate(&self) -> bool {
+        self.separate
+    }
+
+    /// 判断此table是否为从不重新分配bucket数组的固定容量模式
+    pub fn is_fixed(&self) -> bool {
+        self.fixed
+    }
+
+    /// 获取此table使用的spec
+    pub fn spec(&self) -> &E {
+        &self.entry
+    }
+
+    ///
+    /// 获取此table使用的Allocator, 可以借此从同一个arena中为相关的数据结构分配内存
+    ///
+    pub fn allocator(&self) -> &A {
+        self.alloc.inner()
+    }
+
+    ///
+    /// 获取此table的内存分配统计, 包括bucket数组、value与payload的全部分配
+    ///
+    pub fn alloc_stats(&self) -> AllocStats {
+        self.alloc.stats()
+    }
+
+    ///
+    /// 获取当前table的槽位数量, 即`next_entry_in`等按槽位下标扫描的上界
+    ///
+    pub fn buckets(&self) -> usize {
+        self.inner.buckets()
+    }
+
+    ///
+    /// 迭代当前map中的所有entries, 返回`(key, value)`的内存指针, 顺序与槽位下标一致
+    ///
+    pub fn iter(&self) -> Iter<'_, E, A> {
+        Iter {
+            table: self,
+            index: 0,
+            end: self.inner.buckets(),
+            items: self.inner.items,
+            generation: self.generation(),
+        }
+    }
+
+    ///
+    /// 获取大entry模式下value的内存结构, set模式下恒为`None`
+    ///
+    #[inline(always)]
+    pub(crate) fn out_of_line(&self) -> Option<Layout> {
+        if self.entry.zero_sized_value() {
+            None
+        } else {
+            self.entry.out_of_line_value()
+        }
+    }
+
+    ///
+    /// 获取SoA模式下value的内存结构, set模式下恒为`None`
+    ///
+    #[inline(always)]
+    pub(crate) fn split(&self) -> Option<Layout> {
+        if self.entry.zero_sized_value() {
+            None
+        } else {
+            self.entry.split_value()
+        }
+    }
+
+    ///
+    /// 获取entry中存放value的位置: SoA模式下位于value数组中, 否则为`access_value`的返回值
+    ///
+    #[inline(always)]
+    unsafe fn value_slot(&self, entry: *const u8) -> *const u8 {
+        match self.split() {
+            Some(layout) => {
+                let stride = self.entry.layout().pad_to_align().size();
+                // 以同一块分配内的指针差计算下标, 不经过整数转换, 保持指针的provenance
+                let index = if self.separate {
+                    entry.offset_from(self.entries) as usize / stride
+                } else {
+                    (self.inner.ctrl(0) as *const u8).offset_from(entry) as usize / stride - 1
+                };
+                self.values.add(index * layout.pad_to_align().size())
+            }
+            None => self.entry.access_value(entry),
+        }
+    }
+
+    ///
+    /// 为`inner`的所有buckets分配SoA模式下的value数组, 其他模式或`inner`未分配buckets时返回null
+    ///
+    unsafe fn alloc_value_array(
+        &self,
+        inner: &RawTableInner,
+        fallibility: Fallibility,
+    ) -> Result<*mut u8, TryReserveError> {
+        match self.split() {
+            Some(layout) => {
+                let layout = layout.pad_to_align();
+                self.alloc_array(inner, layout.size(), layout.align(), fallibility)
+            }
+            None => Ok(ptr::null_mut()),
+        }
+    }
+
+    ///
+    /// 为`inner`的所有buckets分配分离模式下的bucket数组, 其他模式或`inner`未分配buckets时返回null
+    ///
+    unsafe fn alloc_entry_array(
+        &self,
+        inner: &RawTableInner,
+        fallibility: Fallibility,
+    ) -> Result<*mut u8, TryReserveError> {
+        if !self.separate {
+            return Ok(ptr::null_mut());
+        }
+        let (stride, align) = self.entry_array_layout();
+        self.alloc_array(inner, stride, align, fallibility)
+    }
+
+    /// 分离模式下bucket数组的步长与对齐要求
+    #[inline]
+    fn entry_array_layout(&self) -> (usize, usize) {
+        let layout = self.entry.layout().pad_to_align();
+        (layout.size(), cmp::max(layout.align(), self.bucket_align))
+    }
+
+    ///
+    /// 为`inner`的每个bucket分配`stride`字节、整体按`align`对齐的数组, `inner`未分配buckets时返回null
+    ///
+    unsafe fn alloc_array(
+        &self,
+        inner: &RawTableInner,
+        stride: usize,
+        align: usize,
+        fallibility: Fallibility,
+    ) -> Result<*mut u8, TryReserveError> {
+        if inner.is_empty_singleton() {
+            return Ok(ptr::null_mut());
+        }
+        let size = match stride.checked_mul(inner.buckets()) {
+            Some(size) if size <= isize::MAX as usize - (align - 1) => size,
+            _ => return Err(fallibility.capacity_overflow()),
+        };
+        let layout = Layout::from_size_align_unchecked(size, align);
+        if size == 0 {
+            return Ok(invalid_mut(layout.align()));
+        }
+        match do_alloc(&self.alloc, layout) {
+            Ok(values) => Ok(values.as_ptr()),
+            Err(_) => Err(fallibility.alloc_err(layout)),
+        }
+    }
+
+    ///
+    /// 释放`alloc_value_array`为`inner`分配的value数组
+    ///
+    unsafe fn free_value_array(&self, inner: &RawTableInner, values: *mut u8) {
+        if let Some(layout) = self.split() {
+            let layout = layout.pad_to_align();
+            self.free_array(inner, layout.size(), layout.align(), values);
+        }
+    }
+
+    ///
+    /// 释放`alloc_entry_array`为`inner`分配的bucket数组
+    ///
+    unsafe fn free_entry_array(&self, inner: &RawTableInner, entries: *mut u8) {
+        if self.separate {
+            let (stride, align) = self.entry_array_layout();
+            self.free_array(inner, stride, align, entries);
+        }
+    }
+
+    ///
+    /// 释放`alloc_array`为`inner`分配的数组
+    ///
+    unsafe fn free_array(
+        &self,
+        inner: &RawTableInner,
+        stride: usize,
+        align: usize,
+        array: *mut u8,
+    ) {
+        let size = stride * inner.buckets();
+        if !array.is_null() && size != 0 {
+            let layout = Layout::from_size_align_unchecked(size, align);
+            self.alloc.deallocate(NonNull::new_unchecked(array), layout);
+        }
+    }
+
+    ///
+    /// 获取entry中value的实际内存地址, 大entry模式下为value位置处存放的指针
+    ///
+    #[inline(always)]
+    pub(crate) unsafe fn value_ptr(&self, entry: *const u8) -> *const u8 {
+        if self.entry.zero_sized_value() {
+            return invalid_mut(self.entry.layout().align());
+        }
+        let value = self.value_slot(entry);
+        if self.out_of_line().is_some() {
+            *(value as *const *const u8)
+        } else {
+            value
+        }
+    }
+
+    ///
+    /// 大entry模式下为entry分配value内存, 并将其地址写入`access_value`处
+    ///
+    unsafe fn alloc_value(&self, entry: *const u8, layout: Layout) -> Result<(), TryReserveError> {
+        let value = if layout.size() == 0 {
+            invalid_mut(layout.align())
+        } else {
+            match do_alloc(&self.alloc, layout) {
+                Ok(value) => value.as_ptr(),
+                Err(_) => return Err(Fallibility::Fallible.alloc_err(layout)),
+            }
+        };
+        *(self.value_slot(entry) as *mut *mut u8) = value;
+        Ok(())
+    }
+
+    ///
+    /// 大entry模式下释放entry的value内存, 非大entry模式下不做任何处理
+    ///
+    unsafe fn free_value(&self, entry: *const u8) {
+        if let Some(layout) = self.out_of_line() {
+            if layout.size() != 0 {
+                let value = self.value_ptr(entry) as *mut u8;
+                self.alloc.deallocate(NonNull::new_unchecked(value), layout);
+            }
+        }
+    }
+
+    ///
+    /// 大entry模式下释放所有entries的value内存, 之后table中的entries只能被清空
+    ///
+    unsafe fn free_values(&mut self) {
+        if self.out_of_line().is_some() {
+            for index in self.inner.full_buckets_indices() {
+                self.free_value(self.bucket(index));
+            }
+        }
+    }
+
+    ///
+    /// 对当前的bucket数组(连同ctrl字节)以及分离模式、SoA模式下单独分配的数组给出建议
+    ///
+    #[cfg(feature = "madvise")]
+    fn advise_arrays(&self, advice: Advice) {
+        if self.inner.is_empty_singleton() {
+            return;
+        }
+        let buckets = self.inner.buckets();
+        unsafe {
+            let (ptr, layout) = self.inner.allocation_info(self.table_layout());
+            advise(ptr, layout.size(), advice);
+            if let Some(entries) = NonNull::new(self.entries) {
+                advise(entries, self.entry_array_layout().0 * buckets, advice);
+            }
+            if let (Some(values), Some(layout)) = (NonNull::new(self.values), self.split()) {
+                advise(values, layout.pad_to_align().size() * buckets, advice);
+            }
+        }
+    }
+
+    /// 按`memory_hints`对新分配的数组建议`MADV_HUGEPAGE`
+    #[inline]
+    fn advise_huge_pages(&self) {
+        #[cfg(feature = "madvise")]
+        if self.memory_hints.huge_pages() {
+            self.advise_arrays(Advice::HugePage);
+        }
+    }
+
+    /// 按`memory_hints`在全表扫描开始(`scanning`为true)时建议`MADV_SEQUENTIAL`, 结束时恢复为`MADV_NORMAL`
+    #[inline]
+    fn advise_scan(&self, _scanning: bool) {
+        #[cfg(feature = "madvise")]
+        if self.memory_hints.sequential_scans() {
+            self.advise_arrays(if _scanning {
+                Advice::Sequential
+            } else {
+                Advice::Normal
+            });
+        }
+    }
+
+    ///
+    /// 按`memory_hints`在`f`释放内存之前建议`MADV_DONTNEED`, 用于收缩与`reset`
+    ///
+    #[inline]
+    fn releasing<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        #[cfg(feature = "madvise")]
+        if self.memory_hints.release_on_shrink() {
+            let prev = self.alloc.set_release(true);
+            let mut table = guard(self, |table| {
+                table.alloc.set_release(prev);
+            });
+            return f(&mut table);
+        }
+        f(self)
+    }
+
+    /// bucket数组的内存结构, 对齐要求按`bucket_align`提高
+    #[inline]
+    fn table_layout(&self) -> TableLayout {
+        if self.separate {
+            // 分离模式下`inner`只分配ctrl字节
+            TableLayout::new::<()>().with_align(self.bucket_align)
+        } else {
+            TableLayout::from(self.entry.layout()).with_align(self.bucket_align)
+        }
+    }
+
+    ///
+    /// 获取`inner`及entry的自然内存结构(不含`bucket_align`), 只在bucket数组与ctrl字节位于同一块内存、
+    /// 且entries不引用该块之外的内存(分离模式、SoA value、大entry模式与payload均会引用)时返回
+    ///
+    pub(super) fn contiguous_block(&self) -> Option<(&RawTableInner, TableLayout)> {
+        if self.separate
+            || self.split().is_some()
+            || self.out_of_line().is_some()
+            || self.payload.capacity() > 0
+        {
+            return None;
+        }
+        Some((&self.inner, TableLayout::from(self.entry.layout())))
+    }
+
+    ///
+    /// 取出`contiguous_block`中的`inner`, 之后当前map为空; bucket数组按`bucket_align`对齐分配时返回`None`。
+    ///
+    /// caller负责以`TableLayout::from(spec.layout())`、通过当前map的allocator释放该内存块
+    ///
+    pub(super) fn take_contiguous_block(&mut self) -> Option<RawTableInner> {
+        let (_, table_layout) = self.contiguous_block()?;
+        if self.table_layout().ctrl_align != table_layout.ctrl_align {
+            return None;
+        }
+        let inner = mem::replace(&mut self.inner, RawTableInner::NEW);
+        self.entries_moved();
+        Some(inner)
+    }
+
+    ///
+    /// 构造bucket数量恰好为`buckets`(2的幂)的table, ctrl字节均为EMPTY, bucket数组未初始化;
+    /// caller写入ctrl字节与bucket数组后须通过`inner_mut`设置`items`与`growth_left`
+    ///
+    #[cfg(feature = "std")]
+    pub(super) fn with_exact_buckets(
+        buckets: usize,
+        entry: E,
+        alloc: A,
+    ) -> Result<Self, TryReserveError> {
+        let mut table = Self::new_in(0, 0, false, entry, alloc)?;
+        unsafe {
+            let mut inner = RawTableInner::new_uninitialized(
+                &table.alloc,
+                table.table_layout(),
+                buckets,
+                Fallibility::Fallible,
+            )?;
+            inner.ctrl_slice().fill_empty();
+            table.inner = inner;
+        }
+        Ok(table)
+    }
+
+    /// 获取`inner`的可变引用, 修改后caller须保证其与ctrl字节、bucket数组一致
+    #[cfg(feature = "std")]
+    pub(super) unsafe fn inner_mut(&mut self) -> &mut RawTableInner {
+        &mut self.inner
+    }
+
+    #[inline(always)]
+    unsafe fn bucket(&self, index: usize) -> *mut u8 {
+        let stride = self.entry.layout().pad_to_align().size();
+        if self.separate {
+            self.entries.add(index * stride)
+        } else {
+            self.inner.bucket_ptr(index, stride)
+        }
+    }
+
+    ///
+    /// 预取`hash`首个探测Group的ctrl字节与起始槽位的bucket。
+    ///
+    /// 紧接着探测时预取没有收益, 只用于在真正探测之前已经得知hash的场景(`prefetch`与`lookup_pipeline`)
+    ///
+    #[inline(always)]
+    unsafe fn prefetch_probe(&self, hash: u64) {
+        if self.inner.is_empty_singleton() {
+            return;
+        }
+        let pos = h1(hash) & self.inner.bucket_mask;
+        prefetch_read(self.inner.ctrl(pos));
+        prefetch_read(self.bucket(pos));
+    }
+
+    #[inline(always)]
+    unsafe fn find(&self, key: *const u8) -> Option<usize> {
+        // 缓存hash的spec通常equals代价较高, 小table同样先计算hash再比较缓存
+        if self.inner.bucket_mask < SMALL_SCAN_BUCKETS
+            && self.inner.items <= SMALL_SCAN_ITEMS
+            && self.entry.cached_hash().is_none()
+        {
+            return self.find_small(key);
+        }
+        let hash = spec_hash(&self.entry, key);
+        let found = match self.find_cached(key, hash) {
+            Some(index) => Some(index),
+            None => {
+                let mut equals = |index| self.key_equals(key, hash, index);
+                let found = self.inner.find_inner(hash, &mut equals);
+                if let (Some(cache), Some(index)) = (&self.lookup_cache, found) {
+                    cache.put(hash, index);
+                }
+                found
+            }
+        };
+        #[cfg(feature = "spec-validation")]
+        if let Some(index) = found {
+            self.validate_equal_keys(hash, self.bucket(index));
+        }
+        found
+    }
+
+    ///
+    /// 开启查找缓存时检查`hash`最近命中的槽位, 槽位仍有效且Key相等时返回其下标
+    ///
+    #[inline]
+    unsafe fn find_cached(&self, key: *const u8, hash: u64) -> Option<usize> {
+        let index = self.lookup_cache.as_ref()?.get(hash)?;
+        if index <= self.inner.bucket_mask
+            && self.inner.is_bucket_full(index)
+            && self.key_equals(key, hash, index)
+        {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// 小table的查找: 不计算hash, 直接按ctrl字节扫描所有entries并逐个比较Key, 省去hash与H2匹配的固定开销
+    ///
+    #[inline]
+    unsafe fn find_small(&self, key: *const u8) -> Option<usize> {
+        let found = self
+            .inner
+            .full_buckets_indices()
+            .find(|&index| self.entry.equals(key, self.bucket(index)));
+        #[cfg(feature = "spec-validation")]
+        if let Some(index) = found {
+            self.validate_equal_keys(spec_hash(&self.entry, key), self.bucket(index));
+        }
+        found
+    }
+
+    ///
+    /// 判断hash为`hash`的`key`是否与槽位`index`中的Key相等; spec缓存hash时先比较缓存的hash,
+    /// 不同的Key在hash不同时无需调用`equals`
+    ///
+    #[inline(always)]
+    unsafe fn key_equals(&self, key: *const u8, hash: u64, index: usize) -> bool {
+        let bucket = self.bucket(index);
+        if let Some(offset) = self.entry.cached_hash() {
+            if ptr::read_unaligned(bucket.add(offset) as *const u64) != hash {
+                return false;
+            }
+        }
+        self.entry.equals(key, bucket)
+    }
+
+    ///
+    /// 查找或插入Key, 以`Ok`返回已存在Key的槽位下标, 以`Err`返回新写入Key的槽位下标
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    #[inline(always)]
+    unsafe fn find_or_insert(&mut self, key: *const u8) -> Result<usize, usize> {
+        let assign_key = |spec: &E, bucket| {
+            spec.assign_key(bucket, key);
+            Ok(())
+        };
+        self.try_find_or_insert(key, assign_key)
+            .expect("map payload allocation failure")
+    }
+
+    ///
+    /// `find_or_insert`的fallible版本, 通过`assign_key`写入新Key, 写入失败时回滚新占用的槽位
+    ///
+    #[inline(always)]
+    unsafe fn try_find_or_insert(
+        &mut self,
+        key: *const u8,
+        assign_key: impl FnOnce(&E, *const u8) -> Result<(), AssignError>,
+    ) -> Result<Result<usize, usize>, InsertError> {
+        let hash = spec_hash(&self.entry, key);
+        match self.find_or_claim(key, hash) {
+            Ok(index) => Ok(Ok(index)),
+            Err(index) => {
+                // 确认Key不存在之后才分配payload, 之后任一步失败时一并归还payload并回滚新占用的槽位
+                let payload_size = self.entry.payload_size(key);
+                let payload = if payload_size > 0 {
+                    match self.payload.alloc(&self.alloc, payload_size) {
+                        Ok(payload) => Some(payload),
+                        Err(err) => {
+                            self.inner.erase(index);
+                            return Err(err.into());
+                        }
+                    }
+                } else {
+                    None
+                };
+                let rollback = |this: &mut Self| {
+                    if let Some(payload) = payload {
+                        this.payload.release_last(payload, payload_size);
+                    }
+                    this.inner.erase(index);
+                };
+                let bucket = self.bucket(index);
+                // write key into slot
+                if let Err(err) = assign_key(&self.entry, bucket) {
+                    rollback(self);
+                    return Err(err.into());
+                }
+                if let Some(payload) = payload {
+                    self.entry.assign_payload(bucket, key, payload.as_ptr());
+                }
+                if let Some(metadata) = self.entry.metadata() {
+                    ptr::write_bytes(bucket.add(metadata.offset()), 0, metadata.size());
+                }
+                store_hash(&self.entry, bucket, hash);
+                if let Some(layout) = self.out_of_line() {
+                    if let Err(err) = self.alloc_value(bucket, layout) {
+                        rollback(self);
+                        return Err(err.into());
+                    }
+                }
+                #[cfg(feature = "spec-validation")]
+                self.validate_assigned_key(key, hash, bucket);
+                Ok(Err(index))
+            }
+        }
+    }
+
+    ///
+    /// 查找Key所在的槽位, 若不存在则占用一个新槽位(仅写入控制字节), 以`Err`返回新槽位下标
+    ///
+    #[inline(always)]
+    unsafe fn find_or_claim(&mut self, key: *const u8, hash: u64) -> Result<usize, usize> {
+        let mut equals = |index| self.key_equals(key, hash, index);
+        match self.inner.find_or_find_insert_slot_inner(hash, &mut equals) {
+            Ok(index) => {
+                #[cfg(feature = "spec-validation")]
+                self.validate_equal_keys(hash, self.bucket(index));
+                Ok(index)
+            }
+            Err(slot) => {
+                let old_ctrl = *self.inner.ctrl(slot.index);
+                self.inner.record_item_insert_at(slot.index, old_ctrl, hash);
+                Err(slot.index)
+            }
+        }
+    }
+
+    ///
+    /// 校验`assign_key`写入的Key: 必须与自身相等、与原Key相等, 且hash在拷贝前后保持不变
+    ///
+    #[cfg(feature = "spec-validation")]
+    unsafe fn validate_assigned_key(&self, key: *const u8, hash: u64, bucket: *const u8) {
+        assert!(
+            self.entry.equals(bucket, bucket),
+            "EntrySpec violation: assigned key is not equal to itself"
+        );
+        assert!(
+            self.entry.equals(key, bucket),
+            "EntrySpec violation: assigned key is not equal to the source key"
+        );
+        assert_eq!(
+            spec_hash(&self.entry, bucket),
+            hash,
+            "EntrySpec violation: key hash changed after assign_key"
+        );
+    }
+
+    ///
+    /// 校验`equals`判定相等的两个Key具有相同的hash
+    ///
+    #[cfg(feature = "spec-validation")]
+    unsafe fn validate_equal_keys(&self, hash: u64, bucket: *const u8) {
+        assert_eq!(
+            spec_hash(&self.entry, bucket),
+            hash,
+            "EntrySpec violation: equal keys have different hashes"
+        );
+    }
+
+    ///
+    /// 为写入`key`预留一个entry的容量, 固定容量模式下已存在的Key只覆盖value, 无需额外容量
+    ///
+    #[inline(always)]
+    unsafe fn reserve_for(&mut self, key: *const u8) -> Result<(), TryReserveError> {
+        match self.check_growth(1) {
+            Err(_) if self.fixed && self.find(key).is_some() => Ok(()),
+            result => result,
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn check_growth(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        if unlikely(additional > self.inner.growth_left) {
+            if self.fixed {
+                return Err(Fallibility::Fallible.capacity_overflow());
+            }
+            self.do_growth(additional, Fallibility::Fallible)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    unsafe fn do_growth(
+        &mut self,
+        additional: usize,
+        fallibility: Fallibility,
+    ) -> Result<(), TryReserveError> {
+        let new_items = match self.inner.items.checked_add(additional) {
+            Some(new_items) => new_items,
+            None => return Err(fallibility.capacity_overflow()),
+        };
+        match self.growth_capacity(new_items) {
+            Some(capacity) => self.resize(capacity, fallibility),
+            None => {
+                self.rehash_entries_in_place(false);
+                self.entries_moved();
+                Ok(())
+            }
+        }
+    }
+
+    ///
+    /// 计算容纳`new_items`个entries时扩容的新容量。
+    ///
+    /// 与`reserve_rehash_inner`一致: tombstone较多(`new_items`不超过容量的一半)时返回`None`,
+    /// 表示按原容量重建即可, 否则按扩容策略计算新容量
+    ///
+    pub(crate) fn growth_capacity(&self, new_items: usize) -> Option<usize> {
+        let full_capacity = bucket_mask_to_capacity(self.inner.bucket_mask);
+        if new_items <= full_capacity / 2 {
+            None
+        } else {
+            Some(self.growth_policy.capacity(full_capacity, new_items))
+        }
+    }
+
+    /// 获取不触发扩容或原地重建时还能写入的新Key数量
+    pub(crate) fn growth_left(&self) -> usize {
+        self.inner.growth_left
+    }
+
+    ///
+    /// 删除entries之后按收缩策略检查是否需要收缩table, 收缩失败时保持原table不变;
+    /// 未收缩时再按tombstone回收策略检查是否需要原地整理
+    ///
+    pub(crate) fn check_shrink(&mut self) {
+        if self.fixed {
+            return;
+        }
+        if !self.shrink() {
+            self.purge_tombstones();
+        }
+    }
+
+    /// 按收缩策略收缩table, 返回是否已重建
+    fn shrink(&mut self) -> bool {
+        let policy = match self.shrink_policy {
+            Some(policy) => policy,
+            None => return false,
+        };
+        let capacity = bucket_mask_to_capacity(self.inner.bucket_mask);
+        if !policy.should_shrink(self.inner.items, capacity) {
+            return false;
+        }
+        let target = policy.target(self.inner.items);
+        let table_layout = self.table_layout();
+        // bucket数量不会减少时无需重建
+        if capacity_to_buckets(target, table_layout).map_or(true, |b| b >= self.inner.buckets()) {
+            return false;
+        }
+        // 收缩只是尽力而为, 分配失败时继续使用原table
+        self.releasing(|table| unsafe { table.resize(target, Fallibility::Fallible).is_ok() })
+    }
+
+    /// 按tombstone回收策略在原有内存中重新整理entries
+    fn purge_tombstones(&mut self) {
+        let policy = match self.tombstone_policy {
+            Some(policy) => policy,
+            None => return,
+        };
+        let capacity = bucket_mask_to_capacity(self.inner.bucket_mask);
+        if policy.should_purge(self.tombstones(), capacity) {
+            self.compact();
+        }
+    }
+
+    ///
+    /// 以容纳当前entries的最小bucket数组重建table并回收所有tombstone,
+    /// `reorder`为true时按探测起点顺序写入entries; 分配失败时保持原table不变
+    ///
+    pub(crate) fn rebuild_fitted(&mut self, reorder: bool) -> Result<(), TryReserveError> {
+        let items = self.inner.items;
+        unsafe {
+            // 空table没有需要排列的entries
+            if reorder && items > 0 {
+                self.resize_split(items, Fallibility::Fallible, true)?;
+                self.entries_moved();
+                Ok(())
+            } else {
+                self.resize(items, Fallibility::Fallible)
+            }
+        }
+    }
+
+    ///
+    /// 按`capacity`重新分配table并通过spec的hash迁移所有entries, 可用于扩容或收缩
+    ///
+    unsafe fn resize(
+        &mut self,
+        capacity: usize,
+        fallibility: Fallibility,
+    ) -> Result<(), TryReserveError> {
+        if self.split().is_some() || self.separate {
+            self.resize_split(capacity, fallibility, false)?;
+        } else {
+            let layout = self.entry.layout().pad_to_align();
+            let hasher = |table: &mut RawTableInner, index| {
+                entry_hash(&self.entry, table.bucket_ptr(index, layout.size()))
+            };
+            self.inner.resize_inner(
+                &self.alloc,
+                capacity,
+                &hasher,
+                fallibility,
+                self.table_layout(),
+            )?;
+            self.advise_huge_pages();
+        }
+        self.entries_moved();
+        Ok(())
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// SoA模式或分离模式下按`capacity`重建table, Key与value一同迁移到新分配的内存中。
+    ///
+    /// `reorder`为true时按entries在新table中的起始探测位置升序写入(适用于所有模式),
+    /// 使每个entry尽量落在其探测起点附近
+    ///
+    unsafe fn resize_split(
+        &mut self,
+        capacity: usize,
+        fallibility: Fallibility,
+        reorder: bool,
+    ) -> Result<(), TryReserveError> {
+        let layout = self.entry.layout().pad_to_align();
+        let vsize = self
+            .split()
+            .map_or(0, |layout| layout.pad_to_align().size());
+        let table_layout = self.table_layout();
+        let mut order: Vec<(usize, usize)> = Vec::new();
+        if reorder {
+            let mask = match capacity_to_buckets(capacity, table_layout) {
+                Some(buckets) => buckets - 1,
+                None => return Err(fallibility.capacity_overflow()),
+            };
+            if order.try_reserve_exact(self.inner.items).is_err() {
+                return Err(match Layout::array::<(usize, usize)>(self.inner.items) {
+                    Ok(layout) => fallibility.alloc_err(layout),
+                    Err(_) => fallibility.capacity_overflow(),
+                });
+            }
+            for index in self.inner.full_buckets_indices() {
+                let hash = entry_hash(&self.entry, self.bucket(index));
+                order.push((h1(hash) & mask, index));
+            }
+            order.sort_unstable();
+        }
+        let mut new_inner = RawTableInner::fallible_with_capacity(
+            &self.alloc,
+            table_layout,
+            capacity,
+            fallibility,
+        )?;
+        let new_entries = match self.alloc_entry_array(&new_inner, fallibility) {
+            Ok(entries) => entries,
+            Err(err) => {
+                if !new_inner.is_empty_singleton() {
+                    new_inner.free_buckets(&self.alloc, table_layout);
+                }
+                return Err(err);
+            }
+        };
+        let new_values = match self.alloc_value_array(&new_inner, fallibility) {
+            Ok(values) => values,
+            Err(err) => {
+                self.free_entry_array(&new_inner, new_entries);
+                if !new_inner.is_empty_singleton() {
+                    new_inner.free_buckets(&self.alloc, table_layout);
+                }
+                return Err(err);
+            }
+        };
+
+        let mut move_entry = |index: usize| {
+            let bucket = self.bucket(index);
+            let (new_index, _) = new_inner.prepare_insert_slot(entry_hash(&self.entry, bucket));
+            let new_bucket = if self.separate {
+                new_entries.add(new_index * layout.size())
+            } else {
+                new_inner.bucket_ptr(new_index, layout.size())
+            };
+            ptr::copy_nonoverlapping(bucket, new_bucket, layout.size());
+            if self.split().is_some() {
+                ptr::copy_nonoverlapping(
+                    self.values.add(index * vsize),
+                    new_values.add(new_index * vsize),
+                    vsize,
+                );
+            }
+        };
+        if reorder {
+            order.iter().for_each(|&(_, index)| move_entry(index));
+        } else {
+            self.inner.full_buckets_indices().for_each(move_entry);
+        }
+        new_inner.growth_left -= self.inner.items;
+        new_inner.items = self.inner.items;
+
+        mem::swap(&mut self.inner, &mut new_inner);
+        let old_entries = mem::replace(&mut self.entries, new_entries);
+        self.free_entry_array(&new_inner, old_entries);
+        let old_values = mem::replace(&mut self.values, new_values);
+        self.free_value_array(&new_inner, old_values);
+        if !new_inner.is_empty_singleton() {
+            new_inner.free_buckets(&self.alloc, table_layout);
+        }
+        self.advise_huge_pages();
+        Ok(())
+    }
+}
+
+impl<E: OrderedEntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 获取当前map中所有entries的槽位下标, 按Key升序排列
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub fn sorted_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = unsafe { self.inner.full_buckets_indices() }.collect();
+        indices.sort_unstable_by(|&a, &b| unsafe {
+            self.entry.compare(self.bucket(a), self.bucket(b))
+        });
+        indices
+    }
+
+    ///
+    /// 按Key升序迭代当前map中的所有entries, 返回`(key, value)`的内存指针
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (*const u8, *const u8)> + '_ {
+        self.sorted_indices().into_iter().map(move |index| unsafe {
+            let bucket = self.bucket(index);
+            (bucket as *const u8, self.value_ptr(bucket))
+        })
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> Drop for RawTable2<E, A> {
+    fn drop(&mut self) {
+        unsafe { self.free_all() };
+    }
+}
+
+///
+/// `RawTable2::cursor`返回的游标, 按槽位下标顺序访问entries, 并支持删除当前entry后继续迭代。
+///
+/// 删除只软删除当前槽位, 不会移动其他entries, 因此之后的扫描从下一个槽位重新加载控制字节即可
+///
+pub struct Cursor<'a, E: EntrySpec, A: Allocator> {
+    table: &'a mut RawTable2<E, A>,
+    /// 下一次扫描的起始槽位下标
+    next: usize,
+    /// 当前entry的槽位下标, 尚未开始、已删除或已到末尾时为`None`
+    current: Option<usize>,
+}
+
+impl<E: EntrySpec, A: Allocator> Cursor<'_, E, A> {
+    ///
+    /// 移动到下一个entry, 已到末尾时返回`false`
+    ///
+    pub fn advance(&mut self) -> bool {
+        match self.table.next_entry(self.next) {
+            Some((index, _)) => {
+                self.next = index + 1;
+                self.current = Some(index);
+                true
+            }
+            None => {
+                self.next = self.table.buckets();
+                self.current = None;
+                false
+            }
+        }
+    }
+
+    ///
+    /// 获取当前entry的`(key, value)`内存指针, 尚未开始、已删除或已到末尾时返回`None`
+    ///
+    pub fn current(&self) -> Option<(*const u8, *const u8)> {
+        let index = self.current?;
+        unsafe {
+            let bucket = self.table.bucket(index);
+            Some((bucket, self.table.value_ptr(bucket)))
+        }
+    }
+
+    ///
+    /// 删除当前entry, 与`RawTable2::delete`相同不会调用`on_evict`; 没有当前entry时返回`false`。
+    ///
+    /// 删除后`current`返回`None`, 调用`advance`即可继续迭代
+    ///
+    pub fn remove_current(&mut self) -> bool {
+        let Some(index) = self.current.take() else {
+            return false;
+        };
+        unsafe {
+            self.table.free_value(self.table.bucket(index));
+            self.table.inner.erase(index);
+        }
+        true
+    }
+}
+
+///
+/// `RawTable2::full_buckets_indices`返回的迭代器, 正向基于`RawTableInner::full_buckets_indices`逐组扫描,
+/// 反向基于`prev_entry`扫描
+///
+pub struct BucketIndices<'a, E: EntrySpec, A: Allocator> {
+    table: &'a RawTable2<E, A>,
+    inner: FullBucketsIndices,
+    /// 下一次反向扫描的结束槽位下标(不含)
+    end: usize,
+    /// 尚未返回的下标数量, 正反两个方向共享, 因此两端不会重叠
+    items: usize,
+    /// 迭代器创建时table的`generation`
+    generation: usize,
+}
+
+impl<E: EntrySpec, A: Allocator> Iterator for BucketIndices<'_, E, A> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.items == 0 {
+            return None;
+        }
+        self.table.check_generation(self.generation);
+        let index = self.inner.next()?;
+        self.items -= 1;
+        Some(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> DoubleEndedIterator for BucketIndices<'_, E, A> {
+    fn next_back(&mut self) -> Option<usize> {
+        if self.items == 0 {
+            return None;
+        }
+        self.table.check_generation(self.generation);
+        let (index, _) = self.table.prev_entry(self.end)?;
+        self.end = index;
+        self.items -= 1;
+        Some(index)
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> ExactSizeIterator for BucketIndices<'_, E, A> {}
+
+impl<E: EntrySpec, A: Allocator> FusedIterator for BucketIndices<'_, E, A> {}
+
+///
+/// `RawTable2::scan`的扫描位置, 记录尚未扫描的entries的hash, 按hash定位entries, 因此在扫描之间写入、删除或扩容后仍然有效
+///
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ScanToken {
+    /// 尚未扫描的hash(已去重), 按降序排列
+    pending: Vec<u64>,
+    /// 是否已记录扫描开始时的entries
+    started: bool,
+    /// 是否已扫描完成
+    done: bool,
+}
+
+impl ScanToken {
+    /// 从头开始扫描的token
+    pub const START: Self = Self {
+        pending: Vec::new(),
+        started: false,
+        done: false,
+    };
+
+    /// 判断扫描是否已完成
+    pub const fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// 槽位数组中的一段连续范围`[start, end)`, 由`RawTable2::chunks`切分得到
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BucketRange {
+    start: usize,
+    end: usize,
+}
+
+impl BucketRange {
+    /// 基于起始与结束(不含)槽位下标构造范围
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// 获取起始槽位下标
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// 获取结束槽位下标(不含)
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+}
+
+/// `RawTable2::iter_range`返回的迭代器, 基于`next_entry_in`扫描范围内的有效entry
+pub struct RangeIter<'a, E: EntrySpec, A: Allocator> {
+    table: &'a RawTable2<E, A>,
+    /// 下一次扫描的起始槽位下标
+    index: usize,
+    /// 范围的结束槽位下标(不含)
+    end: usize,
+    /// 迭代器创建时table的`generation`
+    generation: usize,
+}
+
+impl<E: EntrySpec, A: Allocator> Iterator for RangeIter<'_, E, A> {
+    type Item = (*const u8, *const u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.table.check_generation(self.generation);
+        let Some((index, bucket)) = self.table.next_entry_in(self.index, self.end) else {
+            self.index = self.end;
+            return None;
+        };
+        self.index = index + 1;
+        Some((bucket, unsafe { self.table.value_ptr(bucket) }))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> FusedIterator for RangeIter<'_, E, A> {}
+
+/// `RawTable2::iter`返回的迭代器, 基于`next_entry`(反向为`prev_entry`)逐个扫描有效entry
+pub struct Iter<'a, E: EntrySpec, A: Allocator> {
+    table: &'a RawTable2<E, A>,
+    /// 下一次扫描的起始槽位下标
+    index: usize,
+    /// 下一次反向扫描的结束槽位下标(不含)
+    end: usize,
+    /// 尚未返回的entry数量
+    items: usize,
+    /// 迭代器创建时table的`generation`
+    generation: usize,
+}
+
+impl<E: EntrySpec, A: Allocator> Iterator for Iter<'_, E, A> {
+    type Item = (*const u8, *const u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.items == 0 {
+            return None;
+        }
+        self.table.check_generation(self.generation);
+        let (index, bucket) = self.table.next_entry(self.index)?;
+        self.index = index + 1;
+        self.items -= 1;
+        Some((bucket, unsafe { self.table.value_ptr(bucket) }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> DoubleEndedIterator for Iter<'_, E, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.items == 0 {
+            return None;
+        }
+        self.table.check_generation(self.generation);
+        let (index, bucket) = self.table.prev_entry(self.end)?;
+        self.end = index;
+        self.items -= 1;
+        Some((bucket, unsafe { self.table.value_ptr(bucket) }))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> ExactSizeIterator for Iter<'_, E, A> {}
+
+impl<E: EntrySpec, A: Allocator> FusedIterator for Iter<'_, E, A> {}
+
+/// 基于`RawTable2`派生的`map<K, V>`视图, 由caller保证K/V与entry内存结构一致
+pub struct RawMap<'a, K, V, E: EntrySpec, A: Allocator> {
+    table: &'a mut RawTable2<E, A>,
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V, E: EntrySpec, A: Allocator> RawMap<'a, K, V, E, A> {
+    /// 获取此map中指定key的value引用
+    pub unsafe fn get(&self, key: &K) -> Option<&V> {
+        let key_ptr = key as *const K as *const u8;
+        self.table.access(key_ptr).map(|ptr| &*(ptr as *const V))
+    }
+
+    ///
+    /// 按未对齐方式读取此map中指定key的value副本, value可能未对齐时(例如`EntryLayout::packed`)
+    /// 应使用此方法代替`get`
+    ///
+    pub unsafe fn read(&self, key: &K) -> Option<V>
+    where
+        V: Copy,
+    {
+        let key_ptr = key as *const K as *const u8;
+        self.table
+            .access(key_ptr)
+            .map(|ptr| ptr::read_unaligned(ptr as *const V))
+    }
+
+    ///
+    /// 将{key, value}写入此map, value的所有权转移至map。
+    ///
+    /// 新Key的value直接写入而不经过spec的`assign_value`; Key已存在时旧value先被移出,
+    /// 再通过`replace_value`写入新value, 之后drop旧value
+    ///
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn insert(&mut self, key: &K, value: V) {
+        let key_ptr = key as *const K as *const u8;
+        let value = mem::ManuallyDrop::new(value);
+        self.table.write(
+            key_ptr,
+            &*value as *const V as *const u8,
+            |spec, old, v| {
+                let prev = mem::ManuallyDrop::new(ptr::read_unaligned(old as *const V));
+                spec.replace_value(old, v);
+                drop(mem::ManuallyDrop::into_inner(prev));
+            },
+            |_, slot, v| ptr::write_unaligned(slot as *mut V, ptr::read(v as *const V)),
+        );
+    }
+
+    ///
+    /// `insert`的fallible版本, 写入失败时value会被直接drop, 此时已存在的旧value保留在map中
+    ///
+    pub unsafe fn try_insert(&mut self, key: &K, value: V) -> Result<(), InsertError> {
+        let key_ptr = key as *const K as *const u8;
+        let mut value = mem::ManuallyDrop::new(value);
+        let result = self.table.try_write(
+            key_ptr,
+            &*value as *const V as *const u8,
+            |spec, old, v| {
+                let prev = mem::ManuallyDrop::new(ptr::read_unaligned(old as *const V));
+                spec.try_replace_value(old, v)?;
+                drop(mem::ManuallyDrop::into_inner(prev));
+                Ok(())
+            },
+            |spec, slot, v| spec.try_assign_value(slot, v),
+        );
+        if result.is_err() {
+            mem::ManuallyDrop::drop(&mut value);
+        }
+        result
+    }
+
+    /// 删除此map中指定key的entry
+    pub unsafe fn delete(&mut self, key: &K) {
+        let key_ptr = key as *const K as *const u8;
+        self.table.delete(key_ptr);
+    }
+
+    /// Merge all entries of other into this map.
+    #[cfg(feature = "raw-map-infallible")]
+    pub unsafe fn extend(&mut self, other: &Self) {
+        self.table.extend(other.table);
+    }
+
+    /// `extend`的fallible版本, 扩容或内存分配失败时返回错误而非panic
+    pub unsafe fn try_extend(&mut self, other: &Self) -> Result<(), ExtendError> {
+        self.table.try_extend(other.table)
+    }
+
+    ///
+    /// 按槽位下标顺序将所有Key与value分别拷贝到`keys`与`values`, 返回导出的行数, 得到两个稠密的列。
+    ///
+    /// 按未对齐方式读取, 因此同样适用于`EntryLayout::packed`; 任一切片短于`size()`时返回`ColumnsTooSmall`且不写入
+    ///
+    pub unsafe fn export_columns(
+        &self,
+        keys: &mut [K],
+        values: &mut [V],
+    ) -> Result<usize, ColumnsTooSmall>
+    where
+        K: Copy,
+        V: Copy,
+    {
+        let rows = self.table.len();
+        if keys.len() < rows || values.len() < rows {
+            return Err(ColumnsTooSmall { rows });
+        }
+        for (row, (key, value)) in self.table.iter().enumerate() {
+            keys[row] = ptr::read_unaligned(key as *const K);
+            values[row] = ptr::read_unaligned(value as *const V);
+        }
+        Ok(rows)
+    }
+
+    /// Clear all entries in this map.
+    pub unsafe fn clear(&mut self) {
+        self.table.clear()
     }
 
     /// Obtains the count of entries in this map.
     pub fn size(&self) -> usize {
         self.table.len()
     }
+
+    ///
+    /// 迭代此map中的所有{key, value}, 引用的生命周期与此map的借用绑定。
+    ///
+    /// K/V与entry内存结构的一致性已在`as_map`时由caller保证, 对齐则在创建引用之前逐个检查,
+    /// 因此此方法是safe的; K或V可能未对齐时(例如`EntryLayout::packed`)应使用`export_columns`或`read`
+    ///
+    /// # Panics
+    ///
+    /// 迭代到Key或value未按`K`/`V`的对齐要求对齐的entry时panic
+    ///
+    pub fn iter(&self) -> MapIter<'_, K, V, E, A> {
+        MapIter {
+            inner: self.table.iter(),
+            phantom: PhantomData,
+        }
+    }
+
+    ///
+    /// 迭代此map中的所有{key, value}并返回value的可变引用, 可用于批量更新value而无需重新查找Key。
+    ///
+    /// 与`iter`相同, 对齐在创建引用之前逐个检查
+    ///
+    /// # Panics
+    ///
+    /// 迭代到Key或value未按`K`/`V`的对齐要求对齐的entry时panic
+    ///
+    pub fn iter_mut(&mut self) -> MapIterMut<'_, K, V, E, A> {
+        MapIterMut {
+            inner: self.table.iter(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+///
+/// 消费此视图, 将所有{key, value}按字节移出table, 迭代结束(或迭代器被drop)时table被清空但保留bucket内存。
+///
+/// 迭代器被提前drop时剩余的{key, value}同样被移出并drop; Key引用的payload在table被清空后失效
+///
+impl<'a, K, V, E: EntrySpec, A: Allocator> IntoIterator for RawMap<'a, K, V, E, A> {
+    type Item = (K, V);
+    type IntoIter = MapIntoIter<'a, K, V, E, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MapIntoIter {
+            table: self.table,
+            index: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// `RawMap::iter`返回的迭代器, 基于`RawTable2::iter`将entry指针转换为`(&K, &V)`
+pub struct MapIter<'a, K, V, E: EntrySpec, A: Allocator> {
+    inner: Iter<'a, E, A>,
+    phantom: PhantomData<(&'a K, &'a V)>,
+}
+
+///
+/// 将entry与value指针转换为`K`与`V`的指针, 两者未按各自的对齐要求对齐时panic, 以免`iter`与`iter_mut`创建未对齐的引用
+///
+#[inline]
+fn aligned_entry<K, V>(key: *const u8, value: *const u8) -> (*const K, *const V) {
+    assert!(
+        addr(key) % mem::align_of::<K>() == 0 && addr(value) % mem::align_of::<V>() == 0,
+        "RawMap iterators require aligned K and V, use `export_columns` for packed entries"
+    );
+    (key.cast(), value.cast())
+}
+
+impl<'a, K, V, E: EntrySpec, A: Allocator> Iterator for MapIter<'a, K, V, E, A> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, value)| {
+            let (key, value) = aligned_entry::<K, V>(key, value);
+            unsafe { (&*key, &*value) }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V, E: EntrySpec, A: Allocator> ExactSizeIterator for MapIter<'_, K, V, E, A> {}
+
+impl<K, V, E: EntrySpec, A: Allocator> FusedIterator for MapIter<'_, K, V, E, A> {}
+
+/// `RawMap::iter_mut`返回的迭代器, 将entry指针转换为`(&K, &mut V)`
+pub struct MapIterMut<'a, K, V, E: EntrySpec, A: Allocator> {
+    inner: Iter<'a, E, A>,
+    phantom: PhantomData<(&'a K, &'a mut V)>,
+}
+
+impl<'a, K, V, E: EntrySpec, A: Allocator> Iterator for MapIterMut<'a, K, V, E, A> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, value)| {
+            let (key, value) = aligned_entry::<K, V>(key, value);
+            unsafe { (&*key, &mut *(value as *mut V)) }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V, E: EntrySpec, A: Allocator> ExactSizeIterator for MapIterMut<'_, K, V, E, A> {}
+
+impl<K, V, E: EntrySpec, A: Allocator> FusedIterator for MapIterMut<'_, K, V, E, A> {}
+
+/// `RawMap::into_iter`返回的迭代器, 每返回一个{key, value}即从table中软删除对应的entry
+pub struct MapIntoIter<'a, K, V, E: EntrySpec, A: Allocator> {
+    table: &'a mut RawTable2<E, A>,
+    /// 下一次扫描的起始槽位下标
+    index: usize,
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<K, V, E: EntrySpec, A: Allocator> Iterator for MapIntoIter<'_, K, V, E, A> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.table.is_empty() {
+            return None;
+        }
+        let (index, bucket) = self.table.next_entry(self.index)?;
+        self.index = index + 1;
+        unsafe {
+            // K与V可能未对齐(例如`EntryLayout::packed`)
+            let key = ptr::read_unaligned(bucket as *const K);
+            let value = ptr::read_unaligned(self.table.value_ptr(bucket) as *const V);
+            // 大entry模式下value已被移出, 仅释放其内存
+            self.table.free_value(bucket);
+            self.table.inner.erase(index);
+            Some((key, value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.len(), Some(self.table.len()))
+    }
+}
+
+impl<K, V, E: EntrySpec, A: Allocator> ExactSizeIterator for MapIntoIter<'_, K, V, E, A> {}
+
+impl<K, V, E: EntrySpec, A: Allocator> FusedIterator for MapIntoIter<'_, K, V, E, A> {}
+
+impl<K, V, E: EntrySpec, A: Allocator> Drop for MapIntoIter<'_, K, V, E, A> {
+    fn drop(&mut self) {
+        // 与`RawIntoIter`一样drop剩余的{key, value}; 每个entry在移出时即被删除, drop panic时不会重复drop
+        self.for_each(drop);
+        self.table.clear();
+    }
 }
@@ -1,6 +1,16 @@
+use super::alloc_stats::AllocStats;
+use super::columnar::ColumnsTooSmall;
+use super::debug::*;
+use super::entry_layout::*;
 use super::map::*;
-use super::Global;
+use super::spec::*;
+use super::{Global, Group};
+use fnv::FnvBuildHasher;
 use std::alloc::Layout;
+use std::boxed::Box;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::vec::Vec;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Float64Key(Layout);
@@ -12,6 +22,7 @@ impl EntrySpec for Float64Key {
     }
 
     #[inline]
+    #[allow(unreachable_patterns)]
     fn hash(&self, ptr: *const u8) -> u64 {
         let p = unsafe { &*(ptr as *const f64) };
         match *p {
@@ -75,7 +86,4790 @@ fn test_map() {
         map.clear();
         assert_eq!(map.size(), 0);
         assert_eq!(map2.size(), 10000);
-        
+
         // 针对map2迭代
     }
 }
+
+#[test]
+fn test_map_iter() {
+    let entry = Float64Key(EntryLayout::of::<f64, f64>().layout());
+    let mut table = RawTable2::new(0, entry, Global).unwrap();
+    let mut table2 = RawTable2::new(0, entry, Global).unwrap();
+
+    unsafe {
+        let map = table.as_map::<f64, f64>();
+        let mut map2 = table2.as_map::<f64, f64>();
+        for i in 0..10000 {
+            map2.insert(&(i as f64), i as f64);
+        }
+
+        let mut sum = 0.0;
+        for (k, v) in map2.iter() {
+            assert_eq!(k, v);
+            sum += *v;
+        }
+        assert_eq!(sum, (0..10000).sum::<i32>() as f64);
+        assert_eq!(map2.iter().len(), 10000);
+        assert_eq!(map.iter().next(), None);
+
+        // 批量更新value
+        for (k, v) in map2.iter_mut() {
+            *v = *k * 2.0;
+        }
+        assert_eq!(map2.get(&4321.0), Some(&8642.0));
+        assert_eq!(map2.iter_mut().len(), 10000);
+    }
+}
+
+#[test]
+fn test_hasher_spec() {
+    let spec = HasherSpec::<u64, u32, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u32>();
+        for i in 0..1000u64 {
+            map.insert(&i, i as u32 * 2);
+        }
+        assert_eq!(map.size(), 1000);
+        assert_eq!(map.get(&500), Some(&1000));
+        assert!(map.get(&1000).is_none());
+    }
+
+    let words: [&[u8]; 3] = [b"foo", b"bar", b"baz"];
+    let spec = BytesSpec::<u8, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<&[u8], u8>();
+        for (i, word) in words.iter().enumerate() {
+            map.insert(word, i as u8);
+        }
+        let key: &[u8] = &b"xbar"[1..];
+        assert_eq!(map.get(&key), Some(&1));
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+    let spec = PodSpec::<Point, f64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<Point, f64>();
+        map.insert(&Point { x: 1, y: 2 }, 0.5);
+        map.insert(&Point { x: 2, y: 1 }, 1.5);
+        assert_eq!(map.get(&Point { x: 1, y: 2 }), Some(&0.5));
+        assert_eq!(map.get(&Point { x: 2, y: 1 }), Some(&1.5));
+        assert!(map.get(&Point { x: 1, y: 1 }).is_none());
+    }
+}
+
+#[test]
+fn test_packed_spec() {
+    let layout = EntryLayout::packed::<u64, u8>();
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (9, 8, 1));
+    assert_eq!(layout.value_size(), 1);
+
+    let spec = PackedSpec::<u64, u8, _>::new(FnvBuildHasher::default());
+    assert_eq!(spec.layout().size(), 9);
+    let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    let mut other = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u8>();
+        for i in 0..1000u64 {
+            map.insert(&i, i as u8);
+        }
+        map.insert(&7, 70);
+        map.delete(&8);
+        assert_eq!(map.size(), 999);
+        assert_eq!(map.read(&7), Some(70));
+        assert_eq!(map.read(&999), Some(999u64 as u8));
+        assert_eq!(map.read(&8), None);
+
+        let mut map = other.as_map::<u64, u8>();
+        map.insert(&7, 0);
+        map.insert(&5000, 50);
+        other.extend(&table);
+        let map = other.as_map::<u64, u8>();
+        assert_eq!(map.size(), 1000);
+        assert_eq!(map.read(&7), Some(70));
+        assert_eq!(map.read(&5000), Some(50));
+    }
+    let keys: Vec<u64> = other
+        .iter_sorted()
+        .map(|(entry, _)| unsafe { std::ptr::read_unaligned(entry as *const u64) })
+        .take(3)
+        .collect();
+    assert_eq!(keys, [0, 1, 2]);
+}
+
+#[test]
+fn test_map_into_iter_packed() {
+    let spec = PackedSpec::<u8, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u8, u64>();
+        for i in 0..100 {
+            map.insert(&i, u64::from(i) << 32 | 1);
+        }
+        let mut drained: Vec<(u8, u64)> = map.into_iter().collect();
+        drained.sort_unstable();
+        assert_eq!(
+            drained,
+            (0..100)
+                .map(|i| (i, u64::from(i) << 32 | 1))
+                .collect::<Vec<_>>()
+        );
+    }
+    assert!(table.is_empty());
+}
+
+#[test]
+#[should_panic = "RawMap iterators require aligned K and V"]
+fn test_map_iter_packed() {
+    let spec = PackedSpec::<u8, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u8, u64>();
+        // 步长为9字节, 连续的entries中必有value未按8字节对齐
+        for i in 0..8 {
+            map.insert(&i, 10);
+        }
+        let _ = map.iter().count();
+    }
+}
+
+#[test]
+#[should_panic = "RawMap iterators require aligned K and V"]
+fn test_map_iter_mut_packed() {
+    let spec = PackedSpec::<u8, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u8, u64>();
+        for i in 0..8 {
+            map.insert(&i, 10);
+        }
+        for (_, value) in map.iter_mut() {
+            *value += 1;
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "spec-validation")]
+#[should_panic = "EntrySpec violation: key hash changed after assign_key"]
+fn test_spec_validation() {
+    // 所有Key均相等, 但assign_key时改写了Key导致hash变化
+    struct BrokenSpec;
+    impl EntrySpec for BrokenSpec {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, _: *const u8, _: *const u8) -> bool {
+            true
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) + 1 }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+    }
+
+    let mut table = RawTable2::new(0, BrokenSpec, Global).unwrap();
+    unsafe {
+        table.as_map::<u64, u64>().insert(&1, 1);
+    }
+}
+
+/// entry为`(&[u8], u64)`, Key的字节内容存放在table的arena中
+struct StrKey;
+
+impl EntrySpec for StrKey {
+    fn layout(&self) -> Layout {
+        Layout::new::<(&[u8], u64)>()
+    }
+    fn hash(&self, entry: *const u8) -> u64 {
+        let key = unsafe { *(entry as *const &[u8]) };
+        key.iter().fold(0xcbf29ce484222325, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { *(entry1 as *const &[u8]) == *(entry2 as *const &[u8]) }
+    }
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { *(entry as *mut &[u8]) = *(k as *const &[u8]) }
+    }
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(std::mem::size_of::<&[u8]>()) }
+    }
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { *(value as *mut u64) = *(v as *const u64) }
+    }
+    fn payload_size(&self, k: *const u8) -> usize {
+        unsafe { *(k as *const &[u8]) }.len()
+    }
+    fn assign_payload(&self, entry: *const u8, k: *const u8, payload: *mut u8) {
+        unsafe {
+            let key = *(k as *const &[u8]);
+            std::ptr::copy_nonoverlapping(key.as_ptr(), payload, key.len());
+            *(entry as *mut &[u8]) = std::slice::from_raw_parts(payload, key.len());
+        }
+    }
+}
+
+#[test]
+fn test_payload() {
+    fn key_ptr(key: &&[u8]) -> *const u8 {
+        key as *const &[u8] as *const u8
+    }
+
+    let mut table = RawTable2::new(0, StrKey, Global).unwrap();
+    let mut table2 = RawTable2::new(0, StrKey, Global).unwrap();
+    unsafe {
+        for i in 0..2000u64 {
+            // Key的内存在写入后即被释放
+            let key = std::format!("key-{}", i);
+            *(table.assign(key_ptr(&key.as_bytes())) as *mut u64) = i;
+        }
+        assert_eq!(table.len(), 2000);
+        for i in 0..2000u64 {
+            let key = std::format!("key-{}", i);
+            let value = table.access(key_ptr(&key.as_bytes())).unwrap();
+            assert_eq!(*(value as *const u64), i);
+        }
+
+        *(table2.assign(key_ptr(&&b"key-7"[..])) as *mut u64) = 0;
+        table2.extend(&table);
+        table.clear();
+        assert_eq!(table2.len(), 2000);
+        let value = table2.access(key_ptr(&&b"key-7"[..])).unwrap();
+        assert_eq!(*(value as *const u64), 7);
+        let value = table2.access(key_ptr(&&b"key-1999"[..])).unwrap();
+        assert_eq!(*(value as *const u64), 1999);
+    }
+}
+
+#[test]
+fn test_payload_released_on_assign_failure() {
+    // 拒绝以`bad`开头的Key
+    struct Rejecting;
+    impl EntrySpec for Rejecting {
+        fn layout(&self) -> Layout {
+            StrKey.layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            StrKey.hash(entry)
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            StrKey.equals(entry1, entry2)
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            StrKey.assign_key(entry, k)
+        }
+        fn try_assign_key(&self, entry: *const u8, k: *const u8) -> Result<(), AssignError> {
+            if unsafe { *(k as *const &[u8]) }.starts_with(b"bad") {
+                return Err(AssignError);
+            }
+            self.assign_key(entry, k);
+            Ok(())
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            StrKey.access_value(entry)
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            StrKey.assign_value(value, v)
+        }
+        fn payload_size(&self, k: *const u8) -> usize {
+            StrKey.payload_size(k)
+        }
+        fn assign_payload(&self, entry: *const u8, k: *const u8, payload: *mut u8) {
+            StrKey.assign_payload(entry, k, payload)
+        }
+    }
+
+    let mut table = RawTable2::new(0, Rejecting, Global).unwrap();
+    let bad = [b'b', b'a', b'd'].repeat(1000);
+    let good = [b'k'; 3000];
+    unsafe {
+        for i in 0..100u64 {
+            let key: &[u8] = &bad;
+            assert_eq!(
+                table.try_insert(
+                    &key as *const &[u8] as *const u8,
+                    &i as *const u64 as *const u8
+                ),
+                Err(InsertError::Assign(AssignError))
+            );
+        }
+        assert!(table.is_empty());
+        // 失败的写入归还了payload, 之后的Key复用同一个chunk
+        let key: &[u8] = &good;
+        table
+            .try_insert(
+                &key as *const &[u8] as *const u8,
+                &1u64 as *const u64 as *const u8,
+            )
+            .unwrap();
+    }
+    assert_eq!(table.memory_usage().payload_bytes(), 4096);
+}
+
+#[test]
+fn test_replace_value() {
+    // 记录被替换掉的旧value
+    struct Releasing(Rc<RefCell<Vec<u64>>>);
+    impl EntrySpec for Releasing {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn replace_value(&self, value: *const u8, v: *const u8) {
+            self.0.borrow_mut().push(unsafe { *(value as *const u64) });
+            self.assign_value(value, v);
+        }
+    }
+
+    let released = Rc::new(RefCell::new(Vec::new()));
+    let mut table = RawTable2::new(0, Releasing(released.clone()), Global).unwrap();
+    let mut table2 = RawTable2::new(0, Releasing(Default::default()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        map.insert(&1, 10);
+        map.insert(&2, 20);
+        map.insert(&1, 11);
+        assert_eq!(map.get(&1), Some(&11));
+
+        let mut map2 = table2.as_map::<u64, u64>();
+        map2.insert(&2, 200);
+        map2.insert(&3, 300);
+        map.extend(&map2);
+        assert_eq!(map.get(&2), Some(&200));
+        assert_eq!(map.get(&3), Some(&300));
+    }
+    assert_eq!(*released.borrow(), [10, 20]);
+}
+
+#[test]
+fn test_insert_drops_replaced_value() {
+    let spec = HasherSpec::<u64, Rc<()>, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let counted = Rc::new(());
+    unsafe {
+        let mut map = table.as_map::<u64, Rc<()>>();
+        map.insert(&1, counted.clone());
+        map.insert(&2, counted.clone());
+        assert_eq!(Rc::strong_count(&counted), 3);
+        // 覆盖已存在的Key时旧value被drop
+        map.insert(&1, counted.clone());
+        assert_eq!(Rc::strong_count(&counted), 3);
+        map.try_insert(&2, counted.clone()).unwrap();
+        assert_eq!(Rc::strong_count(&counted), 3);
+        assert_eq!(map.size(), 2);
+        for (_, value) in map.into_iter() {
+            drop(value);
+        }
+    }
+    assert_eq!(Rc::strong_count(&counted), 1);
+}
+
+#[test]
+fn test_default_assign_value() {
+    // 只提供value_size, 由默认的assign_value按字节拷贝
+    struct SizedValue;
+    impl EntrySpec for SizedValue {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u32)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn value_size(&self) -> Option<usize> {
+            Some(4)
+        }
+    }
+
+    let mut table = RawTable2::new(0, SizedValue, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u32>();
+        map.insert(&1, 10);
+        map.insert(&1, 11);
+        map.insert(&2, 20);
+        assert_eq!(map.get(&1), Some(&11));
+        assert_eq!(map.get(&2), Some(&20));
+    }
+}
+
+#[test]
+fn test_hash_many() {
+    // 记录每次批量计算hash的Key数量
+    struct Batching(Rc<RefCell<Vec<usize>>>);
+    impl EntrySpec for Batching {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn hash_many(&self, keys: &[*const u8], out: &mut [u64]) {
+            self.0.borrow_mut().push(keys.len());
+            for (key, hash) in keys.iter().zip(out.iter_mut()) {
+                *hash = self.hash(*key);
+            }
+        }
+    }
+
+    let batches = Rc::new(RefCell::new(Vec::new()));
+    let mut table = RawTable2::new(0, Batching(batches.clone()), Global).unwrap();
+    let mut table2 = RawTable2::new(0, Batching(batches.clone()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..20 {
+            map.insert(&i, i * 2);
+        }
+        let mut map2 = table2.as_map::<u64, u64>();
+        map2.extend(&map);
+        assert_eq!(map2.size(), 20);
+        assert_eq!(map2.get(&19), Some(&38));
+    }
+    assert_eq!(*batches.borrow(), [8, 8, 4]);
+}
+
+#[test]
+fn test_try_insert() {
+    // value的写入受限于配额, 配额耗尽后写入失败
+    struct Quota(Cell<usize>);
+    impl EntrySpec for Quota {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn try_assign_value(&self, value: *const u8, v: *const u8) -> Result<(), AssignError> {
+            if self.0.get() == 0 {
+                return Err(AssignError);
+            }
+            self.0.set(self.0.get() - 1);
+            self.assign_value(value, v);
+            Ok(())
+        }
+    }
+
+    let mut table = RawTable2::new(0, Quota(Cell::new(3)), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..3 {
+            assert_eq!(map.try_insert(&i, i), Ok(()));
+        }
+        assert_eq!(map.try_insert(&3, 3), Err(InsertError::Assign(AssignError)));
+        assert_eq!(map.size(), 3);
+        assert!(map.get(&3).is_none());
+        // 已存在的Key走replace_value, 不受配额限制
+        assert_eq!(map.try_insert(&0, 10), Ok(()));
+        assert_eq!(map.get(&0), Some(&10));
+    }
+}
+
+#[test]
+fn test_iter_sorted() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in (0..100).rev() {
+            map.insert(&(i * 7 % 100), i);
+        }
+    }
+    let keys: Vec<u64> = table
+        .iter_sorted()
+        .map(|(k, _)| unsafe { *(k as *const u64) })
+        .collect();
+    assert_eq!(keys, (0..100).collect::<Vec<_>>());
+    assert_eq!(table.sorted_indices().len(), 100);
+}
+
+#[test]
+fn test_on_evict() {
+    // 记录被驱逐的Key
+    struct Evicting(Rc<RefCell<Vec<u64>>>);
+    impl EntrySpec for Evicting {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn on_evict(&self, entry: *const u8, _value: *const u8) {
+            self.0.borrow_mut().push(unsafe { *(entry as *const u64) });
+        }
+    }
+
+    let evicted = Rc::new(RefCell::new(Vec::new()));
+    let mut table = RawTable2::new(0, Evicting(evicted.clone()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..10 {
+            map.insert(&i, i);
+        }
+        map.delete(&9);
+    }
+    assert!(evicted.borrow().is_empty());
+
+    table.retain(|_, v| unsafe { *(v as *const u64) } % 2 == 0);
+    assert_eq!(table.len(), 5);
+    evicted.borrow_mut().sort_unstable();
+    assert_eq!(*evicted.borrow(), [1, 3, 5, 7]);
+
+    evicted.borrow_mut().clear();
+    table.clear_and_evict();
+    assert!(table.is_empty());
+    evicted.borrow_mut().sort_unstable();
+    assert_eq!(*evicted.borrow(), [0, 2, 4, 6, 8]);
+}
+
+#[test]
+fn test_dyn_spec() {
+    let float_spec = Float64Key(EntryLayout::of::<f64, f64>().layout());
+    let int_spec = HasherSpec::<u64, f64, _>::new(FnvBuildHasher::default());
+    let mut tables: Vec<RawTable2Dyn> = vec![
+        RawTable2Dyn::new(0, Box::new(float_spec), Global).unwrap(),
+        RawTable2Dyn::new(0, Box::new(int_spec), Global).unwrap(),
+    ];
+    unsafe {
+        tables[0].as_map::<f64, f64>().insert(&-0.0, 1.0);
+        tables[1].as_map::<u64, f64>().insert(&7, 2.0);
+        assert_eq!(tables[0].as_map::<f64, f64>().get(&0.0), Some(&1.0));
+        assert_eq!(tables[1].as_map::<u64, f64>().get(&7), Some(&2.0));
+    }
+}
+
+/// entry为`(u64, u64)`, hash混入了种子
+#[derive(Clone, Copy)]
+struct Seeded(u64);
+
+impl EntrySpec for Seeded {
+    fn layout(&self) -> Layout {
+        Layout::new::<(u64, u64)>()
+    }
+    fn hash(&self, entry: *const u8) -> u64 {
+        (unsafe { *(entry as *const u64) } ^ self.0).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+    }
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { *(entry as *mut u64) = *(k as *const u64) }
+    }
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(8) }
+    }
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { *(value as *mut u64) = *(v as *const u64) }
+    }
+}
+
+#[test]
+fn test_rehash_with() {
+    let mut table = RawTable2::new(0, Seeded(1), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i + 1);
+        }
+    }
+    let mut table = table.rehash_with(Seeded(0xdead_beef));
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.size(), 1000);
+        for i in 0..1000 {
+            assert_eq!(map.get(&i), Some(&(i + 1)));
+        }
+    }
+
+    let empty = RawTable2::new(0, Seeded(1), Global).unwrap();
+    assert!(empty.rehash_with(Seeded(2)).is_empty());
+}
+
+#[test]
+fn test_try_extend() {
+    // entry与`Seeded`相同, 但带有版本标识
+    struct Versioned(u64);
+    impl EntrySpec for Versioned {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn spec_id(&self) -> Option<u64> {
+            Some(self.0)
+        }
+    }
+
+    // value位于偏移量4处, 内存结构与`Seeded`不同
+    let shifted = ClosureSpec::new(
+        Layout::new::<(u64, u64)>(),
+        4,
+        |k| unsafe { *(k as *const u32) as u64 },
+        |a, b| unsafe { *(a as *const u32) == *(b as *const u32) },
+        |e, k| unsafe { *(e as *mut u32) = *(k as *const u32) },
+        |v, n| unsafe { std::ptr::write_unaligned(v as *mut u64, *(n as *const u64)) },
+    );
+    let mut tables: Vec<RawTable2Dyn> = vec![
+        RawTable2Dyn::new(0, Box::new(Seeded(1)), Global).unwrap(),
+        RawTable2Dyn::new(0, Box::new(Seeded(2)), Global).unwrap(),
+        RawTable2Dyn::new(0, Box::new(shifted), Global).unwrap(),
+        RawTable2Dyn::new(0, Box::new(Versioned(1)), Global).unwrap(),
+        RawTable2Dyn::new(0, Box::new(Versioned(2)), Global).unwrap(),
+    ];
+    for table in tables.iter_mut() {
+        unsafe { table.as_map::<u64, u64>().insert(&1, 10) };
+    }
+
+    let (head, tail) = tables.split_at_mut(1);
+    unsafe {
+        assert!(head[0].try_extend(&tail[0]).is_ok());
+        assert_eq!(head[0].try_extend(&tail[1]), Err(ExtendError::Incompatible));
+        assert_eq!(head[0].try_extend(&tail[2]), Err(ExtendError::Incompatible));
+    }
+    assert!(!tail[2].is_compatible(&tail[3]));
+    assert!(tail[2].is_compatible(&tail[2]));
+    assert_eq!(head[0].len(), 1);
+}
+
+#[test]
+fn test_closure_spec() {
+    // 闭包捕获Key的宽度
+    let width = 4;
+    let spec = ClosureSpec::new(
+        Layout::new::<(u32, u32)>(),
+        4,
+        |k| unsafe { *(k as *const u32) as u64 },
+        move |a, b| unsafe {
+            std::slice::from_raw_parts(a, width) == std::slice::from_raw_parts(b, width)
+        },
+        move |e, k| unsafe { std::ptr::copy_nonoverlapping(k, e as *mut u8, width) },
+        |v, n| unsafe { *(v as *mut u32) = *(n as *const u32) },
+    );
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u32, u32>();
+        for i in 0..100 {
+            map.insert(&i, i * 3);
+        }
+        assert_eq!(map.get(&33), Some(&99));
+        assert!(map.get(&100).is_none());
+    }
+}
+
+#[test]
+fn test_normalize_key() {
+    // Key为8字节ASCII字符串, 大小写不敏感
+    struct CaseInsensitive;
+    impl EntrySpec for CaseInsensitive {
+        fn layout(&self) -> Layout {
+            Layout::new::<([u8; 8], u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const [u8; 8]) == *(entry2 as *const [u8; 8]) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut [u8; 8]) = *(k as *const [u8; 8]) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn normalize_key(&self, k: *const u8, scratch: *mut u8) -> *const u8 {
+            unsafe {
+                let key = &mut *(scratch as *mut [u8; 8]);
+                *key = *(k as *const [u8; 8]);
+                key.make_ascii_lowercase();
+            }
+            scratch
+        }
+    }
+
+    let mut table = RawTable2::new(0, CaseInsensitive, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<[u8; 8], u64>();
+        map.insert(b"HashMap\0", 1);
+        map.insert(b"hashmap\0", 2);
+        assert_eq!(map.size(), 1);
+        assert_eq!(map.get(b"HASHMAP\0"), Some(&2));
+        map.delete(b"hAsHmAp\0");
+        assert_eq!(map.size(), 0);
+    }
+}
+
+#[test]
+fn test_entry_layout_of() {
+    const LAYOUT: EntryLayout = EntryLayout::of::<u8, u64>();
+    assert_eq!((LAYOUT.size(), LAYOUT.voff(), LAYOUT.align()), (16, 8, 8));
+    assert_eq!(LAYOUT.layout(), Layout::new::<(u64, u8)>());
+
+    let layout = EntryLayout::of::<u64, u8>();
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (16, 8, 8));
+    assert_eq!(layout.value_size(), 8);
+
+    let layout = EntryLayout::of::<[u8; 3], u16>();
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (6, 4, 2));
+
+    let layout = EntryLayout::of::<u32, ()>();
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (4, 4, 4));
+}
+
+#[test]
+fn test_entry_layout_try_new() {
+    assert_eq!(
+        EntryLayout::try_new(16, 8, 8),
+        Ok(EntryLayout::of::<f64, f64>())
+    );
+    assert_eq!(
+        EntryLayout::try_new(4, 4, 4),
+        Ok(EntryLayout::of::<u32, ()>())
+    );
+    assert_eq!(
+        EntryLayout::try_new(16, 8, 6),
+        Err(LayoutError::AlignNotPowerOfTwo)
+    );
+    assert_eq!(EntryLayout::try_new(0, 0, 8), Err(LayoutError::ZeroSize));
+    assert_eq!(
+        EntryLayout::try_new(12, 8, 8),
+        Err(LayoutError::InvalidSize)
+    );
+    assert_eq!(
+        EntryLayout::try_new(16, 24, 8),
+        Err(LayoutError::OffsetOutOfBounds)
+    );
+}
+
+#[test]
+fn test_out_of_line_value() {
+    // entry为`(u64, *mut u8)`, value为单独分配的`[u64; 512]`
+    type Block = [u64; 512];
+    struct LargeValue;
+    impl EntrySpec for LargeValue {
+        fn layout(&self) -> Layout {
+            EntryLayout::of::<u64, *mut u8>().layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut Block) = *(v as *const Block) }
+        }
+        fn out_of_line_value(&self) -> Option<Layout> {
+            Some(Layout::new::<Block>())
+        }
+    }
+
+    let mut table = RawTable2::new(0, LargeValue, Global).unwrap();
+    let mut other = RawTable2::new(0, LargeValue, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, Block>();
+        for i in 0..100 {
+            map.insert(&i, [i; 512]);
+        }
+        map.insert(&7, [70; 512]);
+        map.delete(&8);
+        assert_eq!(map.size(), 99);
+        assert_eq!(map.get(&7).map(|v| v[511]), Some(70));
+        assert_eq!(map.get(&99).map(|v| v[0]), Some(99));
+        assert_eq!(map.get(&8), None);
+
+        let mut map = other.as_map::<u64, Block>();
+        map.insert(&7, [0; 512]);
+        map.insert(&1000, [1000; 512]);
+        other.extend(&table);
+        let map = other.as_map::<u64, Block>();
+        assert_eq!(map.size(), 100);
+        assert_eq!(map.get(&7).map(|v| v[511]), Some(70));
+        assert_eq!(map.get(&42).map(|v| v[0]), Some(42));
+    }
+
+    // 两个table的value内存相互独立
+    drop(table);
+    table = RawTable2::new(0, LargeValue, Global).unwrap();
+    unsafe {
+        let mut map = other.as_map::<u64, Block>();
+        assert_eq!(map.get(&42).map(|v| v[511]), Some(42));
+        map.clear();
+        assert_eq!(map.size(), 0);
+    }
+    other.retain(|_, _| false);
+    assert!(table.is_empty() && other.is_empty());
+}
+
+#[test]
+fn test_zero_sized_value() {
+    // entry只包含u64 Key, value的hook不应被调用
+    struct KeyOnly;
+    impl EntrySpec for KeyOnly {
+        fn layout(&self) -> Layout {
+            EntryLayout::of::<u64, ()>().layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, _entry: *const u8) -> *const u8 {
+            unreachable!()
+        }
+        fn assign_value(&self, _value: *const u8, _v: *const u8) {
+            unreachable!()
+        }
+        fn zero_sized_value(&self) -> bool {
+            true
+        }
+    }
+
+    assert_eq!(KeyOnly.layout().size(), 8);
+    let mut table = RawTable2::new(0, KeyOnly, Global).unwrap();
+    let mut other = RawTable2::new(0, KeyOnly, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, ()>();
+        for i in 0..100 {
+            map.insert(&i, ());
+        }
+        map.insert(&7, ());
+        assert_eq!(map.try_insert(&100, ()), Ok(()));
+        assert_eq!(map.size(), 101);
+        assert_eq!(map.get(&7), Some(&()));
+        assert_eq!(map.get(&101), None);
+
+        other.insert(&3u64 as *const u64 as *const u8, core::ptr::null());
+        other.extend(&table);
+        assert_eq!(other.len(), 101);
+        let value = other.access(&42u64 as *const u64 as *const u8).unwrap();
+        assert_eq!(value as usize % 8, 0);
+    }
+
+    let spec = HasherSpec::<u64, (), _>::new(FnvBuildHasher::default());
+    assert!(spec.zero_sized_value());
+    assert_eq!(spec.layout().size(), 8);
+    table.retain(|k, _| unsafe { *(k as *const u64) } < 50);
+    assert_eq!(table.len(), 50);
+}
+
+#[test]
+fn test_over_aligned_entry() {
+    // entry按`align`对齐, `size`可以不是`align`的整数倍
+    struct Aligned(Layout);
+    impl EntrySpec for Aligned {
+        fn layout(&self) -> Layout {
+            self.0
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+    }
+
+    for &(size, align) in &[(32, 32), (40, 32), (64, 64), (72, 64), (16, 128)] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let mut table = RawTable2::new(0, Aligned(layout), Global).unwrap();
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..200 {
+                map.insert(&i, i * 2);
+            }
+            map.delete(&100);
+            assert_eq!(map.size(), 199);
+            for i in 0..200 {
+                let value = map.get(&i).map(|v| v as *const u64 as usize);
+                assert_eq!(value.map(|v| (v - 8) % align), (i != 100).then_some(0));
+                assert_eq!(map.get(&i).copied(), (i != 100).then_some(i * 2));
+            }
+        }
+        let table = table.rehash_with(Aligned(layout));
+        assert_eq!(table.len(), 199);
+    }
+}
+
+#[test]
+fn test_split_value() {
+    // Key数组只存放u64, value为独立数组中的`[u64; 8]`
+    type Line = [u64; 8];
+    struct Split;
+    impl EntrySpec for Split {
+        fn layout(&self) -> Layout {
+            Layout::new::<u64>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, _entry: *const u8) -> *const u8 {
+            unreachable!()
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            assert_eq!(value as usize % 8, 0);
+            unsafe { *(value as *mut Line) = *(v as *const Line) }
+        }
+        fn split_value(&self) -> Option<Layout> {
+            Some(Layout::new::<Line>())
+        }
+    }
+
+    let mut table = RawTable2::new(0, Split, Global).unwrap();
+    let mut other = RawTable2::new(4, Split, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, Line>();
+        for i in 0..1000 {
+            map.insert(&i, [i; 8]);
+        }
+        // 反复删除与插入, 触发按原容量重建
+        for i in 0..1000 {
+            map.delete(&i);
+            map.insert(&(i + 1000), [i + 1000; 8]);
+        }
+        assert_eq!(map.size(), 1000);
+        for i in 1000..2000 {
+            assert_eq!(map.get(&i), Some(&[i; 8]));
+        }
+
+        let mut map = other.as_map::<u64, Line>();
+        map.insert(&1500, [0; 8]);
+        map.insert(&7, [7; 8]);
+        other.extend(&table);
+        let map = other.as_map::<u64, Line>();
+        assert_eq!(map.size(), 1001);
+        assert_eq!(map.get(&1500), Some(&[1500; 8]));
+        assert_eq!(map.get(&7), Some(&[7; 8]));
+    }
+
+    let mut table = table.rehash_with(Split);
+    table.retain(|k, v| unsafe {
+        *(k as *const u64) == (*(v as *const Line))[7] && *(k as *const u64) % 2 == 0
+    });
+    assert_eq!(table.len(), 500);
+    table.clear();
+    unsafe {
+        let mut map = table.as_map::<u64, Line>();
+        map.insert(&1, [1; 8]);
+        assert_eq!(map.get(&1), Some(&[1; 8]));
+    }
+
+    // Key数组与ctrl字节同样可以分别分配
+    let mut table = RawTable2::with_separate_buckets(0, Split, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, Line>();
+        for i in 0..1000 {
+            map.insert(&i, [i; 8]);
+        }
+        for i in 0..1000 {
+            assert_eq!(map.get(&i), Some(&[i; 8]));
+        }
+    }
+}
+
+#[test]
+fn test_value_columns() {
+    // 聚合状态: count(u32) + sum(f64) + min(u16) + max(u16)
+    const AGG: ColumnLayout<4> = ColumnLayout::new(
+        Layout::new::<u32>(),
+        [
+            Layout::new::<u32>(),
+            Layout::new::<f64>(),
+            Layout::new::<u16>(),
+            Layout::new::<u16>(),
+        ],
+    );
+    let entry = AGG.entry();
+    assert_eq!((entry.size(), entry.voff(), entry.align()), (32, 8, 8));
+    assert_eq!(AGG.column(0), Column::new(0, 4));
+    assert_eq!(AGG.column(1), Column::new(8, 8));
+    assert_eq!(AGG.columns()[2..], [Column::new(16, 2), Column::new(18, 2)]);
+
+    struct Aggregate;
+    impl EntrySpec for Aggregate {
+        fn layout(&self) -> Layout {
+            AGG.entry().layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u32) as u64 }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u32) == *(entry2 as *const u32) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u32) = *(k as *const u32) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(AGG.entry().voff()) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            let size = AGG.entry().value_size();
+            unsafe { std::ptr::copy_nonoverlapping(v, value as *mut u8, size) }
+        }
+        fn value_columns(&self) -> &[Column] {
+            AGG.columns()
+        }
+    }
+
+    let mut table = RawTable2::new(0, Aggregate, Global).unwrap();
+    unsafe {
+        for (key, x) in [(1u32, 3u16), (2, 5), (1, 7), (1, 1)] {
+            let key = &key as *const u32 as *const u8;
+            let is_new = table.access(key).is_none();
+            let value = table.assign(key) as *mut u8;
+            if is_new {
+                *(value as *mut u32) = 0;
+                *(value.add(AGG.column(1).offset()) as *mut f64) = 0.0;
+                *(value.add(AGG.column(2).offset()) as *mut u16) = u16::MAX;
+                *(value.add(AGG.column(3).offset()) as *mut u16) = 0;
+            }
+            *(value as *mut u32) += 1;
+            *(value.add(AGG.column(1).offset()) as *mut f64) += x as f64;
+            let min = value.add(AGG.column(2).offset()) as *mut u16;
+            *min = (*min).min(x);
+            let max = value.add(AGG.column(3).offset()) as *mut u16;
+            *max = (*max).max(x);
+        }
+
+        let key = &1u32 as *const u32 as *const u8;
+        let column = |col| table.access_column(key, col).unwrap();
+        assert_eq!(*(column(0) as *const u32), 3);
+        assert_eq!(*(column(1) as *const f64), 11.0);
+        assert_eq!(*(column(2) as *const u16), 1);
+        assert_eq!(*(column(3) as *const u16), 7);
+        assert_eq!(
+            table.access_column(&9u32 as *const u32 as *const u8, 0),
+            None
+        );
+    }
+}
+
+#[test]
+fn test_entry_layout_with_metadata() {
+    let layout = EntryLayout::of::<u64, u32>();
+    assert_eq!(layout.metadata(), Column::new(16, 0));
+
+    let layout = layout.with_metadata(4, 4);
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (24, 8, 8));
+    assert_eq!(layout.value_size(), 8);
+    assert_eq!(layout.metadata(), Column::new(16, 4));
+
+    let layout = EntryLayout::of::<u16, u8>().with_metadata(8, 8);
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (16, 2, 8));
+    assert_eq!(layout.metadata(), Column::new(8, 8));
+}
+
+#[test]
+fn test_entry_layout_with_cached_hash() {
+    let layout = EntryLayout::of::<u64, u32>();
+    assert_eq!(layout.cached_hash(), None);
+
+    let layout = layout.with_cached_hash();
+    assert_eq!((layout.size(), layout.voff(), layout.align()), (24, 8, 8));
+    assert_eq!(layout.value_size(), 8);
+    assert_eq!(layout.cached_hash(), Some(16));
+
+    // 追加元数据后hash缓存移动到元数据之后
+    let layout = layout.with_metadata(4, 4);
+    assert_eq!(layout.metadata(), Column::new(16, 4));
+    assert_eq!(layout.cached_hash(), Some(24));
+    assert_eq!(layout.size(), 32);
+
+    // 紧凑entry不提高对齐要求
+    let layout = EntryLayout::packed::<u64, u8>().with_cached_hash();
+    assert_eq!((layout.size(), layout.align()), (17, 1));
+    assert_eq!(layout.cached_hash(), Some(9));
+}
+
+#[test]
+fn test_metadata() {
+    // entry为`(u64, u64)`, 之后是4字节的元数据(记录被访问的次数)
+    const LAYOUT: EntryLayout = EntryLayout::of::<u64, u64>().with_metadata(4, 4);
+    struct Marked;
+    impl EntrySpec for Marked {
+        fn layout(&self) -> Layout {
+            LAYOUT.layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(LAYOUT.voff()) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn metadata(&self) -> Option<Column> {
+            Some(LAYOUT.metadata())
+        }
+    }
+
+    impl OrderedEntrySpec for Marked {
+        fn compare(&self, entry1: *const u8, entry2: *const u8) -> std::cmp::Ordering {
+            unsafe { (*(entry1 as *const u64)).cmp(&*(entry2 as *const u64)) }
+        }
+    }
+
+    // 按Key升序读取每个entry的(key, 元数据)
+    fn marks(table: &RawTable2<Marked, Global>) -> Vec<(u64, u32)> {
+        table
+            .sorted_indices()
+            .into_iter()
+            .zip(table.iter_sorted())
+            .map(|(index, (entry, _))| unsafe {
+                let mark = table.metadata_at(index).unwrap();
+                (*(entry as *const u64), *(mark as *const u32))
+            })
+            .collect()
+    }
+
+    let mut table = RawTable2::new(0, Marked, Global).unwrap();
+    let mut other = RawTable2::new(0, Marked, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..4 {
+            map.insert(&i, i);
+        }
+        assert!(marks(&table).iter().all(|&(_, mark)| mark == 0));
+        for (index, (entry, _)) in table.sorted_indices().into_iter().zip(table.iter_sorted()) {
+            *(table.metadata_at(index).unwrap() as *mut u32) = *(entry as *const u64) as u32 + 10;
+        }
+
+        // value的写入与扩容均保留元数据
+        let mut map = table.as_map::<u64, u64>();
+        map.insert(&1, 100);
+        for i in 4..64 {
+            map.insert(&i, i);
+        }
+        assert_eq!(map.get(&1), Some(&100));
+        assert_eq!(
+            marks(&table)[..5],
+            [(0, 10), (1, 11), (2, 12), (3, 13), (4, 0)]
+        );
+
+        // extend时已存在的Key保留自己的元数据, 新Key带入other的元数据
+        let mut map = other.as_map::<u64, u64>();
+        map.insert(&2, 0);
+        map.insert(&1000, 0);
+        other.extend(&table);
+        assert_eq!(other.len(), 65);
+        assert_eq!(other.as_map::<u64, u64>().get(&2), Some(&2));
+        assert_eq!(marks(&other)[..4], [(0, 10), (1, 11), (2, 0), (3, 13)]);
+    }
+
+    assert_eq!(table.metadata_at(usize::MAX), None);
+    let empty = RawTable2::new(0, Marked, Global).unwrap();
+    assert_eq!(empty.metadata_at(0), None);
+}
+
+#[test]
+fn test_iter() {
+    let spec = HasherSpec::<u64, u32, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.iter().next(), None);
+
+    // 小table的末尾控制字节组会读到首组的镜像
+    for n in [3u64, 1000] {
+        unsafe {
+            let mut map = table.as_map::<u64, u32>();
+            for i in 0..n {
+                map.insert(&i, i as u32 * 2);
+            }
+        }
+        let mut iter = table.iter();
+        assert_eq!(iter.size_hint(), (n as usize, Some(n as usize)));
+        iter.next();
+        assert_eq!(iter.len(), n as usize - 1);
+
+        let mut entries: Vec<(u64, u32)> = table
+            .iter()
+            .map(|(key, value)| unsafe { (*(key as *const u64), *(value as *const u32)) })
+            .collect();
+        entries.sort_unstable();
+        assert_eq!(entries.len(), n as usize);
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(i, &e)| e == (i as u64, i as u32 * 2)));
+
+        let (mut index, mut count) = (0, 0);
+        while let Some((i, _)) = table.next_entry(index) {
+            index = i + 1;
+            count += 1;
+        }
+        assert_eq!(count, n);
+    }
+}
+
+#[test]
+fn test_into_iter() {
+    let spec = HasherSpec::<u64, Rc<u64>, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let value = Rc::new(7);
+    unsafe {
+        let mut map = table.as_map::<u64, Rc<u64>>();
+        for i in 0..100 {
+            map.insert(&i, value.clone());
+        }
+    }
+    let mut entries: Vec<(u64, Rc<u64>)> = unsafe { table.as_map::<u64, Rc<u64>>() }
+        .into_iter()
+        .collect();
+    entries.sort_unstable();
+    assert_eq!(entries.len(), 100);
+    assert!(entries.iter().enumerate().all(|(i, e)| e.0 == i as u64));
+    assert_eq!(Rc::strong_count(&value), 101);
+    assert!(table.is_empty());
+    drop(entries);
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    // 提前drop迭代器时剩余的value被drop, table同样被清空, 之后仍可复用
+    unsafe {
+        let mut map = table.as_map::<u64, Rc<u64>>();
+        for i in 0..10 {
+            map.insert(&i, value.clone());
+        }
+        let mut iter = map.into_iter();
+        assert_eq!(iter.len(), 10);
+        drop(iter.next());
+        assert_eq!(iter.len(), 9);
+        drop(iter);
+        assert_eq!(Rc::strong_count(&value), 1);
+        assert!(table.is_empty());
+        table.as_map::<u64, Rc<u64>>().insert(&1, value.clone());
+        assert_eq!(table.as_map::<u64, Rc<u64>>().get(&1), Some(&value));
+    }
+}
+
+#[test]
+fn test_next_entry_in() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.next_entry_in(0, usize::MAX), None);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+
+    // 每次扫描固定数量的槽位, 不越过上界
+    const STEP: usize = 37;
+    let (mut start, mut count) = (0, 0);
+    while start < table.buckets() {
+        let end = start + STEP;
+        let mut index = start;
+        while let Some((i, _)) = table.next_entry_in(index, end) {
+            assert!(i >= start && i < end);
+            index = i + 1;
+            count += 1;
+        }
+        start = end;
+    }
+    assert_eq!(count, 1000);
+
+    let (first, _) = table.next_entry(0).unwrap();
+    assert_eq!(table.next_entry_in(0, first), None);
+    assert_eq!(table.next_entry_in(0, first + 1).map(|e| e.0), Some(first));
+}
+
+#[test]
+fn test_prev_entry() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.prev_entry(usize::MAX), None);
+
+    for n in [3, 1000] {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..n {
+                map.insert(&i, i);
+            }
+        }
+        // 从尾部扫描的结果与正向扫描相反
+        let (mut index, mut forward_indices) = (0, Vec::new());
+        while let Some((i, _)) = table.next_entry(index) {
+            forward_indices.push(i);
+            index = i + 1;
+        }
+        let (mut index, mut backward_indices) = (usize::MAX, Vec::new());
+        while let Some((i, _)) = table.prev_entry(index) {
+            backward_indices.push(i);
+            index = i;
+        }
+        backward_indices.reverse();
+        assert_eq!(forward_indices.len(), n as usize);
+        assert_eq!(backward_indices, forward_indices);
+
+        // 双向迭代在中间相遇
+        let entries: Vec<*const u8> = table.iter().map(|e| e.0).collect();
+        let mut iter = table.iter();
+        let last = iter.next_back().map(|e| e.0);
+        assert_eq!(last, entries.last().copied());
+        assert_eq!(iter.len(), n as usize - 1);
+        let mut rest: Vec<*const u8> = iter.rev().map(|e| e.0).collect();
+        rest.reverse();
+        assert_eq!(rest, entries[..n as usize - 1]);
+    }
+}
+
+#[test]
+fn test_chunks() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(
+        table.chunks(4).collect::<Vec<_>>(),
+        [BucketRange::new(0, 1)]
+    );
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..10000 {
+            map.insert(&i, i);
+        }
+    }
+    for n in [0, 1, 3, 8, usize::MAX] {
+        let chunks: Vec<BucketRange> = table.chunks(n).collect();
+        assert!(chunks.len() <= n.max(1));
+        // 各范围按组对齐、互不相交且覆盖全部槽位
+        assert_eq!(chunks[0].start(), 0);
+        assert_eq!(chunks.last().unwrap().end(), table.buckets());
+        assert!(chunks.windows(2).all(|w| w[0].end() == w[1].start()));
+        assert!(chunks.iter().all(|c| c.start() % Group::WIDTH == 0));
+
+        let mut sum = 0;
+        for chunk in chunks {
+            for (key, value) in table.iter_range(chunk) {
+                unsafe { assert_eq!(*(key as *const u64), *(value as *const u64)) };
+                sum += unsafe { *(key as *const u64) };
+            }
+        }
+        assert_eq!(sum, (0..10000).sum::<u64>());
+    }
+}
+
+#[test]
+fn test_cursor() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let mut cursor = table.cursor();
+    assert!(!cursor.advance());
+    assert!(!cursor.remove_current());
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i * 2);
+        }
+    }
+    // 迭代过程中清除所有偶数Key
+    let mut cursor = table.cursor();
+    let mut visited = 0;
+    while cursor.advance() {
+        visited += 1;
+        let (key, value) = cursor.current().unwrap();
+        let key = unsafe { *(key as *const u64) };
+        assert_eq!(unsafe { *(value as *const u64) }, key * 2);
+        if key % 2 == 0 {
+            assert!(cursor.remove_current());
+            assert_eq!(cursor.current(), None);
+            assert!(!cursor.remove_current());
+        }
+    }
+    assert!(!cursor.advance());
+    assert_eq!(visited, 1000);
+    assert_eq!(table.len(), 500);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&3), Some(&6));
+        assert!(map.iter().all(|(k, _)| k % 2 == 1));
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_generation() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(64, spec, Global).unwrap();
+    assert_eq!(table.generation(), 0);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..64 {
+            map.insert(&i, i);
+        }
+        map.delete(&0);
+    }
+    // 容量足够时不会扩容
+    assert_eq!(table.generation(), 0);
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 64..1000 {
+            map.insert(&i, i);
+        }
+    }
+    let generation = table.generation();
+    assert!(generation > 0);
+
+    let table = table.rehash_with(HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default()));
+    assert_eq!(table.generation(), generation + 1);
+    assert_eq!(table.iter().count(), 999);
+}
+
+#[test]
+fn test_full_buckets_indices() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.full_buckets_indices().next(), None);
+    assert_eq!(table.full_buckets_indices().next_back(), None);
+
+    for n in [5, 1000] {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..n {
+                map.insert(&i, i);
+            }
+        }
+        let forward: Vec<usize> = table.full_buckets_indices().collect();
+        let mut backward: Vec<usize> = table.full_buckets_indices().rev().collect();
+        backward.reverse();
+        assert_eq!(forward.len(), n as usize);
+        assert_eq!(forward, backward);
+        assert!(forward.windows(2).all(|w| w[0] < w[1]));
+
+        // 两端交替迭代, 在中间相遇且不重叠
+        let mut indices = table.full_buckets_indices();
+        let mut mixed = Vec::new();
+        while let Some(front) = indices.next() {
+            mixed.push(front);
+            assert_eq!(indices.len(), n as usize - mixed.len());
+            mixed.extend(indices.next_back());
+        }
+        mixed.sort_unstable();
+        assert_eq!(mixed, forward);
+    }
+}
+
+#[test]
+fn test_iter_by_hash() {
+    let spec = || HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec(), Global).unwrap();
+    let mut other = RawTable2::new(2000, spec(), Global).unwrap();
+    unsafe {
+        // 写入顺序与容量不同, 内容相同
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+        let mut map = other.as_map::<u64, u64>();
+        for i in (0..1500).rev() {
+            map.insert(&i, i);
+        }
+        for i in 1000..1500 {
+            map.delete(&i);
+        }
+    }
+    let keys = |table: &RawTable2<_, Global>| -> Vec<u64> {
+        table
+            .iter_by_hash()
+            .map(|(key, _)| unsafe { *(key as *const u64) })
+            .collect()
+    };
+    let order = keys(&table);
+    assert_eq!(order.len(), 1000);
+    assert_eq!(order, keys(&other));
+
+    let hasher = FnvBuildHasher::default();
+    let hashes: Vec<u64> = order
+        .iter()
+        .map(|k| crate::map::make_hash::<u64, _>(&hasher, k))
+        .collect();
+    assert!(hashes.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn test_scan() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let mut token = ScanToken::START;
+    assert!(table
+        .scan(token.clone(), 10, |_, _| unreachable!())
+        .is_done());
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    // 扫描之间继续写入(触发扩容)并删除奇数Key
+    let mut seen = Vec::new();
+    let mut batch = 0;
+    while !token.is_done() {
+        token = table.scan(token, 7, |key, _| {
+            seen.push(unsafe { *(key as *const u64) })
+        });
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..10 {
+                map.insert(&(1000 + batch * 10 + i), 0);
+            }
+            map.delete(&(batch * 2 + 1));
+        }
+        batch += 1;
+    }
+    assert_eq!(table.scan(token.clone(), 7, |_, _| unreachable!()), token);
+
+    let total = seen.len();
+    seen.sort_unstable();
+    seen.dedup();
+    assert_eq!(seen.len(), total);
+    // 一直存在的Key恰好被访问一次, 扫描期间写入的Key不被访问
+    assert!((0..1000).step_by(2).all(|k| seen.binary_search(&k).is_ok()));
+    assert!(seen.iter().all(|&k| k < 1000));
+}
+
+#[test]
+fn test_for_each_key() {
+    // 记录access_value的调用次数
+    struct KeyOnlyAccess(Rc<Cell<usize>>);
+    impl EntrySpec for KeyOnlyAccess {
+        fn layout(&self) -> Layout {
+            EntryLayout::of::<u64, u64>().layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            self.0.set(self.0.get() + 1);
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+    }
+
+    let accessed = Rc::new(Cell::new(0));
+    let mut table = RawTable2::new(0, KeyOnlyAccess(accessed.clone()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    let before = accessed.get();
+    let mut digest = 0;
+    let mut count = 0;
+    table.for_each_key(|key| {
+        digest ^= unsafe { *(key as *const u64) };
+        count += 1;
+    });
+    assert_eq!(count, 1000);
+    assert_eq!(accessed.get(), before);
+    assert_eq!(digest, (0..1000).fold(0, |acc, k| acc ^ k));
+}
+
+#[test]
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+fn test_arena_alloc() {
+    use super::arena::ArenaAlloc;
+
+    let mut arena = ArenaAlloc::new();
+    assert_eq!(arena.capacity(), 0);
+    for round in 0..3u64 {
+        let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+        let mut table = RawTable2::new(0, spec, &arena).unwrap();
+        let mut map = crate::HashMap::with_hasher_in(FnvBuildHasher::default(), &arena);
+        unsafe {
+            let mut raw = table.as_map::<u64, u64>();
+            for i in 0..10000 {
+                raw.insert(&i, i + round);
+                map.insert(i, i + round);
+            }
+            raw.delete(&7);
+            assert_eq!(raw.get(&9999), Some(&(9999 + round)));
+            assert_eq!(raw.get(&7), None);
+        }
+        assert_eq!(map.get(&42), Some(&(42 + round)));
+        assert!(core::ptr::eq(*table.allocator(), &arena));
+        let capacity = arena.capacity();
+        drop((table, map));
+        // 容器销毁后才能重置arena, 之后复用最大的chunk
+        arena.reset();
+        assert!(arena.capacity() > 0 && arena.capacity() <= capacity);
+    }
+}
+
+#[test]
+#[cfg(all(not(feature = "nightly"), feature = "allocator-api2"))]
+fn test_stable_custom_allocator() {
+    use allocator_api2::alloc::{AllocError, Allocator, Global as Api2Global};
+    use core::ptr::NonNull;
+
+    // 在stable上由caller直接实现`allocator_api2::alloc::Allocator`的分配器
+    #[derive(Clone, Default)]
+    struct Tracking(Rc<Cell<usize>>);
+    unsafe impl Allocator for Tracking {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + layout.size());
+            Api2Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.set(self.0.get() - layout.size());
+            Api2Global.deallocate(ptr, layout);
+        }
+    }
+
+    let alloc = Tracking::default();
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, alloc.clone()).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    assert_eq!(alloc.0.get(), table.alloc_stats().bytes_held());
+    assert!(alloc.0.get() > 0);
+    let clone = table.try_clone().unwrap();
+    drop(table);
+    assert_eq!(alloc.0.get(), clone.alloc_stats().bytes_held());
+    drop(clone);
+    assert_eq!(alloc.0.get(), 0);
+}
+
+#[test]
+fn test_alloc_stats() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.alloc_stats(), AllocStats::default());
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    // 每次扩容都会重新分配bucket数组并释放旧数组, 最终只持有当前的bucket数组
+    let stats = table.alloc_stats();
+    assert!(stats.allocations() > 1);
+    assert_eq!(stats.largest_allocation(), stats.bytes_held());
+    assert!(stats.bytes_held() >= table.buckets() * 16);
+    assert!(stats.bytes_requested() > stats.bytes_held());
+
+    // 清空不会释放bucket数组
+    table.clear();
+    assert_eq!(table.alloc_stats(), stats);
+}
+
+#[test]
+fn test_growth_policy() {
+    fn grow(policy: GrowthPolicy) -> (usize, usize) {
+        let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+        let mut table = RawTable2::new(0, spec, Global).unwrap();
+        table.set_growth_policy(policy);
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..1000 {
+                map.insert(&i, i);
+            }
+            for i in 0..1000 {
+                assert_eq!(map.get(&i), Some(&i));
+            }
+        }
+        (table.buckets(), table.alloc_stats().allocations())
+    }
+
+    let (buckets, allocations) = grow(GrowthPolicy::Double);
+    assert_eq!(buckets, 2048);
+    // 步长足够大时一次扩容即可容纳所有entries
+    assert_eq!(grow(GrowthPolicy::Step(1000)), (2048, 1));
+    assert_eq!(grow(GrowthPolicy::Custom(|_, _| 1000)), (2048, 1));
+    let (ratio_buckets, ratio_allocations) = grow(GrowthPolicy::Ratio(16, 1));
+    assert!(ratio_buckets >= 2048);
+    assert!(ratio_allocations < allocations);
+    // 策略给出的容量不足时至少扩容到下一档
+    assert_eq!(grow(GrowthPolicy::Custom(|_, _| 0)), (buckets, allocations));
+}
+
+#[test]
+fn test_try_new_in() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let layout = Layout::new::<u64>();
+    assert_eq!(
+        RawTable2::try_new_in(16, layout, spec.clone(), Global).err(),
+        Some(NewError::LayoutMismatch)
+    );
+    assert!(matches!(
+        RawTable2::try_new_in(usize::MAX, spec.layout(), spec.clone(), Global),
+        Err(NewError::Reserve(_))
+    ));
+
+    let mut table = RawTable2::try_new_in(16, spec.layout(), spec, Global).unwrap();
+    assert!(table.buckets() >= 16);
+    unsafe {
+        for i in 0u64..100 {
+            let value = table.try_assign(&i as *const u64 as *const u8).unwrap();
+            *(value as *mut u64) = i * 2;
+        }
+        let value = table.try_assign(&7u64 as *const u64 as *const u8).unwrap();
+        assert_eq!(*(value as *const u64), 14);
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.size(), 100);
+        assert_eq!(map.get(&99), Some(&198));
+    }
+}
+
+#[test]
+fn test_shrink_policy() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.shrink_policy(), None);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..10000 {
+            map.insert(&i, i);
+        }
+        for i in 5000..10000 {
+            map.delete(&i);
+        }
+    }
+    // 默认不收缩
+    assert_eq!(table.buckets(), 16384);
+
+    table.set_shrink_policy(Some(ShrinkPolicy::default()));
+    table.retain(|key, _| unsafe { *(key as *const u64) < 1000 });
+    assert_eq!(table.len(), 1000);
+    assert_eq!(table.buckets(), 4096);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        // 达到容量下限后不再收缩
+        for i in 10..1000 {
+            map.delete(&i);
+        }
+        assert_eq!(map.size(), 10);
+        for i in 0..10 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+    }
+    assert_eq!(table.buckets(), 2048);
+    // bucket数量不变时不会重新分配
+    let allocations = table.alloc_stats().allocations();
+    unsafe { table.as_map::<u64, u64>().delete(&0) };
+    assert_eq!(table.alloc_stats().allocations(), allocations);
+}
+
+#[test]
+#[cfg(feature = "madvise")]
+fn test_memory_hints() {
+    use super::madvise::{page_range, MemoryHints};
+    // 只建议完全覆盖的整页
+    assert_eq!(page_range(4096, 8192, 4096), Some((4096, 8192)));
+    assert_eq!(page_range(100, 8192, 4096), Some((4096, 4096)));
+    assert_eq!(page_range(100, 4000, 4096), None);
+    assert_eq!(page_range(usize::MAX - 10, 100, 4096), None);
+
+    let hints = MemoryHints::new()
+        .with_huge_pages(true)
+        .with_release_on_shrink(true)
+        .with_sequential_scans(true);
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.memory_hints(), MemoryHints::default());
+    table.set_memory_hints(hints);
+    table.set_shrink_policy(Some(ShrinkPolicy::default()));
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..100_000 {
+            map.insert(&i, i);
+        }
+    }
+    let mut keys = 0;
+    table.for_each_key(|_| keys += 1);
+    assert_eq!(keys, 100_000);
+    // 收缩释放的内存被建议丢弃之后, 新table中的entries不受影响
+    table.retain(|key, _| unsafe { *(key as *const u64) < 1000 });
+    assert_eq!(table.buckets(), 4096);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        for i in 0..2000 {
+            assert_eq!(map.get(&i).copied(), Some(i).filter(|_| i < 1000));
+        }
+    }
+    let clone = table.try_clone().unwrap();
+    assert_eq!(clone.memory_hints(), hints);
+    table.reset();
+    assert_eq!(table.capacity(), 0);
+    unsafe { table.as_map::<u64, u64>().insert(&1, 2) };
+    assert_eq!(table.len(), 1);
+    assert_eq!(clone.len(), 1000);
+}
+
+#[test]
+fn test_tombstone_policy() {
+    // 所有Key的hash相同, 连成一片的FULL槽位中删除的Key总是留下tombstone
+    #[derive(Default)]
+    struct Collide;
+    impl std::hash::Hasher for Collide {
+        fn finish(&self) -> u64 {
+            0
+        }
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    let spec = HasherSpec::<u64, u64, _>::new(std::hash::BuildHasherDefault::<Collide>::default());
+    let mut table = RawTable2::new(200, spec, Global).unwrap();
+    assert_eq!(table.tombstone_policy(), None);
+    let capacity = table.capacity();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..200 {
+            map.insert(&i, i);
+        }
+        // 默认只在扩容时回收tombstone
+        for i in 0..100 {
+            map.delete(&i);
+        }
+    }
+    assert!(table.tombstones() > capacity / 4);
+
+    // tombstone超过容量的1/4时原地整理, 不分配内存
+    table.set_tombstone_policy(Some(TombstonePolicy::default()));
+    let allocations = table.alloc_stats().allocations();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        map.delete(&100);
+        assert_eq!(map.size(), 99);
+        for i in 0..200 {
+            assert_eq!(map.get(&i), Some(&i).filter(|_| i > 100));
+        }
+    }
+    assert_eq!(table.tombstones(), 0);
+    assert_eq!(table.capacity(), capacity);
+
+    // 删除与插入交替时tombstone数量保持在上限以内
+    for i in 101..2000 {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            map.delete(&i);
+            map.insert(&(i + 99), i);
+        }
+        assert!(table.tombstones() <= capacity / 4);
+    }
+    assert_eq!(table.len(), 99);
+    assert_eq!(table.alloc_stats().allocations(), allocations);
+}
+
+#[test]
+fn test_freeze() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    for reorder in [false, true] {
+        let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..1000 {
+                map.insert(&i, i * 2);
+            }
+            for i in 100..1000 {
+                map.delete(&i);
+            }
+        }
+        let capacity = table.capacity();
+        let frozen = table.freeze(reorder);
+        assert_eq!(frozen.len(), 100);
+        assert_eq!(frozen.tombstones(), 0);
+        assert!(frozen.capacity() >= 100 && frozen.capacity() < capacity);
+        for i in 0..1000u64 {
+            let value = unsafe { frozen.access(&i as *const u64 as *const u8) };
+            assert_eq!(
+                value.map(|value| unsafe { *(value as *const u64) }),
+                Some(i * 2).filter(|_| i < 100)
+            );
+        }
+        assert_eq!(frozen.iter().count(), 100);
+    }
+
+    // 空table冻结后仍可查找
+    let frozen = RawTable2::new(16, spec, Global).unwrap().freeze(true);
+    assert!(frozen.is_empty());
+    assert_eq!(
+        unsafe { frozen.access(&1u64 as *const u64 as *const u8) },
+        None
+    );
+}
+
+#[test]
+fn test_memory_usage() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.memory_usage(), MemoryUsage::default());
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..100 {
+            map.insert(&i, i);
+        }
+    }
+    let usage = table.memory_usage();
+    assert_eq!(table.buckets(), 128);
+    assert_eq!(usage.ctrl_bytes(), 128 + Group::WIDTH);
+    assert_eq!(usage.bucket_bytes(), 128 * 16);
+    assert_eq!(usage.value_bytes(), 0);
+    assert_eq!(usage.payload_bytes(), 0);
+    // 与实际持有的分配一致
+    assert_eq!(usage.total(), table.alloc_stats().bytes_held());
+    assert_eq!(usage.per_entry_overhead(), (usage.total() - 100 * 16) / 100);
+}
+
+#[test]
+fn test_bucket_align() {
+    fn ctrl_addr<E: EntrySpec>(table: &RawTable2<E>) -> usize {
+        let (index, key) = table.next_entry_in(0, table.buckets()).unwrap();
+        key as usize + (index + 1) * 16
+    }
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    assert_eq!(
+        RawTable2::try_with_bucket_align(0, 3000, spec.clone(), Global).err(),
+        Some(NewError::InvalidAlign)
+    );
+    let table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    assert_eq!(table.bucket_align(), Group::WIDTH);
+
+    let mut table = RawTable2::try_with_bucket_align(3, HUGE_PAGE_ALIGN, spec, Global).unwrap();
+    assert_eq!(table.bucket_align(), HUGE_PAGE_ALIGN);
+    unsafe { table.as_map::<u64, u64>().insert(&1, 1) };
+    assert_eq!(table.buckets(), 4);
+    // bucket数组按对齐要求填充
+    assert_eq!(table.memory_usage().bucket_bytes(), HUGE_PAGE_ALIGN);
+    assert_eq!(ctrl_addr(&table) % HUGE_PAGE_ALIGN, 0);
+
+    // 扩容后仍保持对齐
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..200_000 {
+            map.insert(&i, i);
+        }
+        assert_eq!(map.get(&199_999), Some(&199_999));
+    }
+    assert_eq!(table.buckets(), 262_144);
+    assert_eq!(ctrl_addr(&table) % HUGE_PAGE_ALIGN, 0);
+    assert_eq!(table.memory_usage().bucket_bytes(), 262_144 * 16);
+    assert_eq!(
+        table.memory_usage().total(),
+        table.alloc_stats().bytes_held()
+    );
+}
+
+#[test]
+fn test_separate_buckets() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::with_separate_buckets(0, spec.clone(), Global).unwrap();
+    let mut other = RawTable2::new(0, spec.clone(), Global).unwrap();
+    assert!(table.is_separate() && !other.is_separate());
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+        // 反复删除与插入, 触发按原容量重建
+        for i in 0..1000 {
+            map.delete(&i);
+            map.insert(&(i + 1000), i + 1000);
+        }
+        assert_eq!(map.size(), 1000);
+        for i in 1000..2000 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        other.as_map::<u64, u64>().insert(&7, 7);
+        other.extend(&table);
+        assert_eq!(other.len(), 1001);
+    }
+
+    // bucket数组按槽位下标升序排列, 与ctrl字节分别分配
+    let (first, key) = table.next_entry_in(0, table.buckets()).unwrap();
+    let (second, next) = table.next_entry_in(first + 1, table.buckets()).unwrap();
+    assert_eq!(next as usize - key as usize, (second - first) * 16);
+    let usage = table.memory_usage();
+    assert_eq!(usage.ctrl_bytes(), table.buckets() + Group::WIDTH);
+    assert_eq!(usage.bucket_bytes(), table.buckets() * 16);
+    assert_eq!(usage.total(), table.alloc_stats().bytes_held());
+
+    let mut table = table.rehash_with(spec);
+    assert!(table.is_separate());
+    table.retain(|k, _| unsafe { *(k as *const u64) % 2 == 0 });
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.size(), 500);
+        assert_eq!(map.get(&1998), Some(&1998));
+        assert_eq!(map.get(&1999), None);
+    }
+}
+
+#[test]
+fn test_assign_unchecked_capacity() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    table.try_reserve(1000).unwrap();
+    let capacity = table.capacity();
+    let allocations = table.alloc_stats().allocations();
+    for i in 0..1000u64 {
+        unsafe {
+            let value = table
+                .assign_unchecked_capacity(&i as *const u64 as *const u8)
+                .unwrap();
+            *(value as *mut u64) = i * 3;
+        }
+    }
+    // 已存在的Key只返回value地址
+    unsafe {
+        let value = table
+            .assign_unchecked_capacity(&7u64 as *const u64 as *const u8)
+            .unwrap();
+        assert_eq!(*(value as *const u64), 21);
+    }
+    assert_eq!(table.len(), 1000);
+    assert_eq!(table.capacity(), capacity);
+    assert_eq!(table.alloc_stats().allocations(), allocations);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            assert_eq!(map.get(&i).copied(), Some(i * 3));
+        }
+    }
+
+    // 固定容量模式下容量不足时预留失败
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::with_fixed_capacity(8, spec, Global).unwrap();
+    assert!(table.try_reserve(table.capacity()).is_ok());
+    assert!(table.try_reserve(table.capacity() + 1).is_err());
+}
+
+#[test]
+fn test_reserve_populated() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    table.reserve_populated(0).unwrap();
+    assert_eq!(table.memory_usage().total(), 0);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..100 {
+            map.insert(&i, i);
+        }
+    }
+    table.reserve_populated(10_000).unwrap();
+    assert_eq!(table.buckets(), 16384);
+    // 预先写入空槽位不影响已有entries, 之后的写入无需扩容
+    let allocations = table.alloc_stats().allocations();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..100 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+        for i in 100..10_100 {
+            map.insert(&i, i);
+        }
+        assert_eq!(map.get(&10_099), Some(&10_099));
+    }
+    assert_eq!(table.alloc_stats().allocations(), allocations);
+}
+
+#[test]
+fn test_reset() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::with_separate_buckets(0, spec, Global).unwrap();
+    table.set_shrink_policy(Some(ShrinkPolicy::default()));
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    table.reset();
+    assert!(table.is_empty());
+    assert_eq!(table.alloc_stats().bytes_held(), 0);
+    assert_eq!(table.memory_usage(), MemoryUsage::default());
+    // 配置被保留, table可以继续使用
+    assert!(table.is_separate());
+    assert_eq!(table.shrink_policy(), Some(ShrinkPolicy::default()));
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        map.insert(&1, 1);
+        assert_eq!(map.get(&1), Some(&1));
+        assert_eq!(map.get(&2), None);
+    }
+    table.reset();
+    table.reset();
+    assert_eq!(table.alloc_stats().bytes_held(), 0);
+}
+
+#[test]
+fn test_rebuild_in_place() {
+    use std::collections::hash_map::RandomState;
+
+    for separate in [false, true] {
+        let spec = HasherSpec::<u64, u64, _>::new(RandomState::new());
+        let mut table = if separate {
+            RawTable2::with_separate_buckets(0, spec, Global).unwrap()
+        } else {
+            RawTable2::new(0, spec, Global).unwrap()
+        };
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..10_000 {
+                map.insert(&i, i);
+            }
+        }
+        // 轮换hash种子不分配内存
+        let stats = table.alloc_stats();
+        let spec = HasherSpec::<u64, u64, _>::new(RandomState::new());
+        assert!(table.rebuild_in_place(spec).is_ok());
+        assert_eq!(table.alloc_stats(), stats);
+        unsafe {
+            let map = table.as_map::<u64, u64>();
+            assert_eq!(map.size(), 10_000);
+            for i in 0..10_000 {
+                assert_eq!(map.get(&i), Some(&i));
+            }
+        }
+    }
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2Dyn::new(0, Box::new(spec), Global).unwrap();
+    unsafe {
+        table.insert(
+            &1u64 as *const u64 as *const u8,
+            &1u64 as *const u64 as *const u8,
+        )
+    };
+    let spec = HasherSpec::<u32, u32, _>::new(FnvBuildHasher::default());
+    assert!(table.rebuild_in_place(Box::new(spec)).is_err());
+    assert_eq!(table.len(), 1);
+}
+
+#[test]
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+fn test_bucket_pool() {
+    use super::pool::BucketPool;
+
+    let pool = BucketPool::new(Global, 1 << 20);
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec.clone(), &pool).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    // 扩容释放的旧bucket数组进入空闲列表
+    assert_eq!(pool.reused(), 0);
+    assert!(pool.pooled_bytes() > 0);
+    let pooled = pool.pooled_bytes();
+
+    // 相同spec的table扩容时复用这些内存块
+    let mut other = RawTable2::new(0, spec, &pool).unwrap();
+    unsafe {
+        let mut map = other.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+        assert_eq!(map.get(&999), Some(&999));
+    }
+    assert!(pool.reused() > 0);
+    assert_eq!(pool.pooled_bytes(), pooled);
+
+    drop(table);
+    assert_eq!(pool.pooled_bytes(), pooled + other.memory_usage().total());
+    pool.trim();
+    assert_eq!(pool.pooled_bytes(), 0);
+    // 超过上限的内存块直接归还给内部的Allocator
+    let small = BucketPool::new(Global, 0);
+    drop(other);
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, &small).unwrap();
+    unsafe { table.as_map::<u64, u64>().insert(&1, 1) };
+    drop(table);
+    assert_eq!(small.pooled_bytes(), 0);
+}
+
+#[test]
+fn test_fixed_capacity() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::with_fixed_capacity(100, spec, Global).unwrap();
+    assert!(table.is_fixed());
+    table.set_shrink_policy(Some(ShrinkPolicy::new(4, 0)));
+    let capacity = table.capacity();
+    assert!(capacity >= 100);
+    let buckets = table.buckets();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..capacity as u64 {
+            assert_eq!(map.try_insert(&i, i), Ok(()));
+        }
+        let first = map.get(&0).unwrap() as *const u64;
+        // 容量耗尽后新Key写入失败, 已存在的Key仍可覆盖
+        assert_eq!(
+            map.try_insert(&1000, 1000),
+            Err(InsertError::Reserve(
+                crate::TryReserveError::CapacityOverflow
+            ))
+        );
+        assert_eq!(map.try_insert(&0, 10), Ok(()));
+        assert_eq!(map.get(&0), Some(&10));
+        assert_eq!(map.get(&0).unwrap() as *const u64, first);
+        // 删除不会触发收缩
+        for i in 1..capacity as u64 {
+            map.delete(&i);
+        }
+        assert_eq!(map.size(), 1);
+        assert_eq!(map.get(&0).unwrap() as *const u64, first);
+    }
+    assert_eq!(table.buckets(), buckets);
+    assert!(table.reserve_populated(capacity).is_err());
+
+    // compact回收tombstone, 之后可以写满容量
+    table.compact();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 1..capacity as u64 {
+            assert_eq!(map.try_insert(&i, i), Ok(()));
+        }
+        assert_eq!(map.size(), capacity);
+        assert_eq!(map.get(&0), Some(&10));
+    }
+    assert_eq!(table.buckets(), buckets);
+}
+
+#[test]
+#[cfg(feature = "epoch")]
+fn test_epoch_raw_map() {
+    use super::epoch::EpochRawMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i);
+        }
+    }
+    let map = EpochRawMap::new(table);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    // 每个快照内部一致: 已写入的Key的value总是其自身或其两倍
+                    map.read(|table| unsafe {
+                        let value = table.access(&7u64 as *const u64 as *const u8).unwrap();
+                        let value = *(value as *const u64);
+                        assert!(value == 7 || value == 14);
+                        assert!(table.len() >= 1000);
+                    });
+                }
+            });
+        }
+        for i in 0..100u64 {
+            map.update(|table| unsafe {
+                let mut map = table.as_map::<u64, u64>();
+                map.insert(&(1000 + i), i);
+                map.insert(&7, if i % 2 == 0 { 14 } else { 7 });
+            })
+            .unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+    assert_eq!(map.len(), 1100);
+    let value = unsafe {
+        map.get(&1099u64 as *const u64 as *const u8, |value| {
+            value.map(|value| *(value as *const u64))
+        })
+    };
+    assert_eq!(value, Some(99));
+}
+
+#[test]
+fn test_send_sync() {
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RawTable2<HasherSpec<u64, u64, FnvBuildHasher>>>();
+    assert_send_sync::<RawMap<'_, u64, u64, HasherSpec<u64, u64, FnvBuildHasher>, Global>>();
+
+    // hash闭包捕获宿主内存中的seed指针, spec因此不满足`Send`/`Sync`
+    let seed = Box::new(0x9e37_79b9_7f4a_7c15u64);
+    let seed_ptr = &*seed as *const u64;
+    let spec = ClosureSpec::new(
+        Layout::new::<(u64, u64)>(),
+        8,
+        move |k| unsafe { *(k as *const u64) ^ *seed_ptr },
+        |a, b| unsafe { *(a as *const u64) == *(b as *const u64) },
+        |e, k| unsafe { *(e as *mut u64) = *(k as *const u64) },
+        |v, n| unsafe { *(v as *mut u64) = *(n as *const u64) },
+    );
+    let spec = unsafe { AssertThreadSafe::new(spec) };
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+
+    // 转移到其他线程写入
+    table = thread::spawn(move || {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..100 {
+                map.insert(&i, i * 2);
+            }
+        }
+        table
+    })
+    .join()
+    .unwrap();
+
+    // 多个线程同时读取
+    thread::scope(|s| {
+        for t in 0..4u64 {
+            let table = &table;
+            s.spawn(move || unsafe {
+                let value = table.access(&(t * 10) as *const u64 as *const u8).unwrap();
+                assert_eq!(*(value as *const u64), t * 20);
+            });
+        }
+    });
+    assert_eq!(table.len(), 100);
+    drop(table);
+    drop(seed);
+}
+
+#[test]
+fn test_striped_raw_map() {
+    use super::striped::StripedRawMap;
+    use std::thread;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let map = StripedRawMap::new(6, 0, spec, Global).unwrap();
+    assert_eq!(map.stripes(), 8);
+    map.reserve(1000).unwrap();
+    let counter = &u64::MAX as *const u64 as *const u8;
+    unsafe {
+        map.try_insert(counter, &0u64 as *const u64 as *const u8)
+            .unwrap()
+    };
+
+    // 多个线程同时写入各自的Key, 并共同累加同一个计数器
+    thread::scope(|s| {
+        for t in 0..4u64 {
+            let map = &map;
+            s.spawn(move || unsafe {
+                for i in t * 1000..(t + 1) * 1000 {
+                    let value = i * 2;
+                    map.try_insert(
+                        &i as *const u64 as *const u8,
+                        &value as *const u64 as *const u8,
+                    )
+                    .unwrap();
+                    let counter = &u64::MAX as *const u64 as *const u8;
+                    map.try_assign(counter, |value| *(value as *mut u64) += 1)
+                        .unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 4001);
+    unsafe {
+        assert_eq!(
+            map.get(counter, |value| *(value.unwrap() as *const u64)),
+            4000
+        );
+        map.get(&1234u64 as *const u64 as *const u8, |value| {
+            assert_eq!(*(value.unwrap() as *const u64), 2468)
+        });
+        map.delete(&1234u64 as *const u64 as *const u8);
+        assert!(map.get(&1234u64 as *const u64 as *const u8, |value| value.is_none()));
+    }
+
+    // 每个Key只存在于一个stripe中
+    let tables = map.into_stripes();
+    assert_eq!(tables.len(), 8);
+    assert_eq!(tables.iter().map(|table| table.len()).sum::<usize>(), 4000);
+    assert!(tables.iter().all(|table| !table.is_empty()));
+}
+
+#[test]
+fn test_striped_raw_map_hash32() {
+    use super::striped::StripedRawMap;
+
+    // 32位hash直接取Key的低32位, 启用hash32时高32位是低32位的副本
+    #[derive(Clone)]
+    struct Identity32;
+    impl EntrySpec for Identity32 {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            u64::from(unsafe { *(entry as *const u64) } as u32)
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn hash32(&self) -> bool {
+            true
+        }
+    }
+
+    let map = StripedRawMap::new(8, 0, Identity32, Global).unwrap();
+    for i in 0..4096u64 {
+        unsafe {
+            map.try_insert(&i as *const u64 as *const u8, &i as *const u64 as *const u8)
+                .unwrap()
+        };
+    }
+
+    // stripe下标与H1的低位无关: 每个stripe内的Key覆盖H1低3位的所有取值
+    let mut tables = map.into_stripes();
+    assert_eq!(tables.iter().map(|table| table.len()).sum::<usize>(), 4096);
+    for table in &mut tables {
+        assert!(!table.is_empty());
+        let mut low_bits = [false; 8];
+        unsafe {
+            for (k, v) in table.as_map::<u64, u64>().iter() {
+                assert_eq!(k, v);
+                low_bits[(*k & 7) as usize] = true;
+            }
+        }
+        assert!(low_bits.iter().all(|&seen| seen));
+    }
+}
+
+#[test]
+fn test_incremental_resize() {
+    use super::incremental::IncrementalRawMap;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut map = IncrementalRawMap::new(0, 4, spec, Global).unwrap();
+    assert_eq!(map.step(), 4);
+    let mut resizes = 0;
+    unsafe {
+        for i in 0..10000u64 {
+            let (was_resizing, pending) = (map.is_resizing(), map.pending());
+            let value = i * 2;
+            map.try_insert(
+                &i as *const u64 as *const u8,
+                &value as *const u64 as *const u8,
+            )
+            .unwrap();
+            if !was_resizing && map.is_resizing() {
+                resizes += 1;
+            }
+            // 每次写入最多迁移`step`个entries
+            if was_resizing {
+                assert!(pending - map.pending() <= 4);
+            }
+            assert_eq!(map.len(), i as usize + 1);
+        }
+        assert!(resizes > 5);
+
+        // 迁移期间新旧两个table中的Key均可访问、覆盖与删除
+        while !map.is_resizing() {
+            let i = map.len() as u64;
+            map.try_insert(&i as *const u64 as *const u8, &i as *const u64 as *const u8)
+                .unwrap();
+        }
+        assert!(map.pending() > 100);
+        let len = map.len() as u64;
+        for i in 0..len {
+            let value = map.access(&i as *const u64 as *const u8).unwrap();
+            assert_eq!(*(value as *const u64), if i < 10000 { i * 2 } else { i });
+        }
+        let key = 1u64;
+        let value = map.try_assign(&key as *const u64 as *const u8).unwrap();
+        *(value as *mut u64) = 7;
+        map.delete(&2u64 as *const u64 as *const u8);
+        assert_eq!(map.len(), len as usize - 1);
+        assert_eq!(map.iter().len(), len as usize - 1);
+        assert_eq!(map.iter().count(), len as usize - 1);
+
+        map.finish_resize().unwrap();
+        assert!(!map.is_resizing());
+        assert_eq!(map.pending(), 0);
+        let mut table = map.into_table().unwrap();
+        assert_eq!(table.len(), len as usize - 1);
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&1), Some(&7));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&3), Some(&6));
+    }
+}
+
+#[test]
+fn test_cow_snapshot() {
+    use super::snapshot::{CowRawTable, FrozenTable};
+    use std::thread;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = CowRawTable::new(RawTable2::new(0, spec, Global).unwrap());
+    unsafe {
+        let mut map = table.get_mut().unwrap().as_map::<u64, u64>();
+        for i in 0..100 {
+            map.insert(&i, i);
+        }
+    }
+
+    // 快照在写入方继续修改时保持不变
+    let frozen = table.snapshot();
+    assert!(table.is_shared());
+    assert!(table.get_mut().is_none());
+    let reader = {
+        let frozen = frozen.clone();
+        thread::spawn(move || {
+            let mut sum = 0;
+            for (_, value) in frozen.iter() {
+                sum += unsafe { *(value as *const u64) };
+            }
+            (frozen.len(), sum)
+        })
+    };
+    unsafe {
+        let mut map = table.make_mut().unwrap().as_map::<u64, u64>();
+        for i in 100..200 {
+            map.insert(&i, i);
+        }
+        map.delete(&0);
+    }
+    assert_eq!(reader.join().unwrap(), (100, 4950));
+    assert_eq!(frozen.len(), 100);
+    assert_eq!(table.len(), 199);
+
+    // 写入方持有私有副本, 新快照可见修改, 旧快照不受影响
+    let latest = table.snapshot();
+    assert!(!FrozenTable::ptr_eq(&frozen, &latest));
+    assert!(unsafe { frozen.access(&0u64 as *const u64 as *const u8) }.is_some());
+    assert!(unsafe { latest.access(&0u64 as *const u64 as *const u8) }.is_none());
+
+    // 快照全部释放后原地修改
+    drop(frozen);
+    drop(latest);
+    assert!(!table.is_shared());
+    let current = &*table as *const RawTable2<_>;
+    let table_mut = table.make_mut().unwrap();
+    assert!(std::ptr::eq(table_mut, current));
+    unsafe { table_mut.as_map::<u64, u64>().insert(&0, 0) };
+    assert_eq!(table.len(), 200);
+}
+
+#[test]
+fn test_atomic_value() {
+    use std::sync::atomic::Ordering;
+    use std::thread;
+
+    // Key为u64, value位于偏移量8处, 宽度由spec指定
+    struct Counter(AtomicWidth);
+    impl EntrySpec for Counter {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe {
+                match self.0 {
+                    AtomicWidth::U32 => *(value as *mut u32) = *(v as *const u64) as u32,
+                    AtomicWidth::U64 => *(value as *mut u64) = *(v as *const u64),
+                }
+            }
+        }
+        fn atomic_value(&self) -> Option<AtomicWidth> {
+            Some(self.0)
+        }
+    }
+
+    for width in [AtomicWidth::U32, AtomicWidth::U64] {
+        let mut table = RawTable2::new(0, Counter(width), Global).unwrap();
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..16 {
+                map.insert(&i, 0);
+            }
+        }
+
+        // 多个线程通过`&RawTable2`同时计数
+        let table = &table;
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(move || unsafe {
+                    for i in 0..1600u64 {
+                        let key = i % 16;
+                        let key = &key as *const u64 as *const u8;
+                        table.fetch_add(key, 1, Ordering::Relaxed).unwrap();
+                    }
+                });
+            }
+        });
+        unsafe {
+            let key = |k: &u64| k as *const u64 as *const u8;
+            let total: u64 = (0..16)
+                .map(|i| table.load_value(key(&i), Ordering::Acquire).unwrap())
+                .sum();
+            assert_eq!(total, 6400);
+            assert_eq!(table.load_value(key(&3), Ordering::Acquire), Some(400));
+            assert_eq!(table.fetch_add(key(&99), 1, Ordering::Relaxed), None);
+            assert_eq!(
+                table.compare_exchange_value(key(&3), 400, 7, Ordering::AcqRel, Ordering::Acquire),
+                Some(Ok(400))
+            );
+            assert_eq!(
+                table.compare_exchange_value(key(&3), 400, 8, Ordering::AcqRel, Ordering::Acquire),
+                Some(Err(7))
+            );
+        }
+    }
+
+    // u32 value回绕
+    let mut table = RawTable2::new(0, Counter(AtomicWidth::U32), Global).unwrap();
+    unsafe {
+        table.as_map::<u64, u64>().insert(&1, u32::MAX as u64);
+        let key = &1u64 as *const u64 as *const u8;
+        assert_eq!(
+            table.fetch_add(key, 2, Ordering::Relaxed),
+            Some(u32::MAX as u64)
+        );
+        assert_eq!(table.load_value(key, Ordering::Relaxed), Some(1));
+    }
+}
+
+#[test]
+fn test_seq_raw_map() {
+    use super::seqlock::SeqRawMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let table = RawTable2::new(16, spec, Global).unwrap();
+    assert!(SeqRawMap::new(table).is_err());
+
+    // value的两个字段总是一同写入
+    let spec = HasherSpec::<u64, (u64, u64), _>::new(FnvBuildHasher::default());
+    let table = RawTable2::with_fixed_capacity(64, spec, Global).unwrap();
+    let map = SeqRawMap::new(table).ok().unwrap();
+    let key = |k: &u64| k as *const u64 as *const u8;
+    unsafe {
+        for i in 0..32u64 {
+            map.try_insert(key(&i), &(i, i) as *const (u64, u64) as *const u8)
+                .unwrap();
+        }
+    }
+    assert_eq!(map.generation(), 64);
+
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let pair = unsafe {
+                        map.read_optimistic(key(&7), |value| {
+                            value.map(|value| std::ptr::read_volatile(value as *const (u64, u64)))
+                        })
+                    };
+                    if let Some((a, b)) = pair {
+                        assert_eq!(a, b);
+                    }
+                    assert!(map.len() >= 31);
+                }
+            });
+        }
+        for i in 0..2000u64 {
+            unsafe {
+                map.try_insert(key(&7), &(i, i) as *const (u64, u64) as *const u8)
+                    .unwrap();
+                if i % 10 == 0 {
+                    map.delete(key(&7));
+                }
+            }
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+
+    let generation = map.generation();
+    assert_eq!(generation % 2, 0);
+    let value = unsafe { map.read_optimistic(key(&7), |value| value.is_some()) };
+    assert!(value);
+    assert_eq!(map.generation(), generation);
+    assert_eq!(map.into_inner().len(), 32);
+}
+
+/// 满足任意`Group::WIDTH`对齐要求的内存块
+#[derive(Clone, Copy)]
+#[repr(C, align(32))]
+struct Block([u8; 32]);
+
+#[test]
+fn test_shared_table() {
+    use super::shared::{Mismatch, SharedError, SharedTable};
+
+    // 按32字节对齐的内存区域, 模拟共享内存段
+    fn region(len: usize) -> Vec<Block> {
+        vec![Block([0; 32]); (len + 31) / 32]
+    }
+    fn bytes(region: &mut [Block]) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(region.as_mut_ptr() as *mut u8, region.len() * 32) }
+    }
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i * 3);
+        }
+        map.delete(&10);
+    }
+    let (size, align) = table.shared_size().unwrap();
+    assert!(align >= Group::WIDTH);
+    let mut exported = region(size + 16);
+    assert_eq!(
+        table.export_shared(&mut bytes(&mut exported)[..size - 1]),
+        Err(SharedError::TooSmall { required: size })
+    );
+    assert_eq!(
+        table.export_shared(&mut bytes(&mut exported)[1..]),
+        Err(SharedError::Misaligned { align })
+    );
+    assert_eq!(table.export_shared(bytes(&mut exported)), Ok(size));
+    drop(table);
+
+    // 在另一个地址attach, 区域内只有相对偏移量
+    let mut attached = exported.clone();
+    drop(exported);
+    let shared = unsafe { SharedTable::attach(bytes(&mut attached), spec.clone()).unwrap() };
+    assert_eq!(shared.len(), 999);
+    unsafe {
+        let value = shared.access(&7u64 as *const u64 as *const u8).unwrap();
+        assert_eq!(*(value as *const u64), 21);
+        assert!(shared.access(&10u64 as *const u64 as *const u8).is_none());
+        assert!(shared.access(&1000u64 as *const u64 as *const u8).is_none());
+    }
+    let mut sum = 0;
+    shared.for_each(|_, value| sum += unsafe { *(value as *const u64) });
+    assert_eq!(sum, (0..1000).map(|i| i * 3).sum::<u64>() - 30);
+
+    // 内存结构不同的spec无法attach
+    let other = HasherSpec::<u32, u32, _>::new(FnvBuildHasher::default());
+    assert_eq!(
+        unsafe { SharedTable::attach(bytes(&mut attached), other) }.err(),
+        Some(SharedError::Incompatible(Mismatch::EntryLayout {
+            found: Layout::new::<(u64, u64)>(),
+            expected: Layout::new::<(u32, u32)>(),
+        }))
+    );
+
+    // 空table
+    let table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    let (size, _) = table.shared_size().unwrap();
+    let mut empty = region(size);
+    table.export_shared(bytes(&mut empty)).unwrap();
+    let shared = unsafe { SharedTable::attach(bytes(&mut empty), spec.clone()).unwrap() };
+    assert!(shared.is_empty());
+    assert!(unsafe { shared.access(&1u64 as *const u64 as *const u8) }.is_none());
+
+    // entries引用table之外内存的模式无法导出
+    let table = RawTable2::with_separate_buckets(16, spec, Global).unwrap();
+    assert_eq!(table.shared_size(), Err(SharedError::Unsupported));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_snapshot() {
+    use super::shared::{
+        Checksum, Mismatch, SnapshotError, SnapshotSection, SNAPSHOT_MAJOR, SNAPSHOT_MINOR,
+    };
+    use core::alloc::Layout;
+    use std::io;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let layout = Layout::new::<(u64, u64)>();
+    let mut table = RawTable2::try_with_bucket_align(0, 64, spec.clone(), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i * 3);
+        }
+        // 留下tombstone
+        for i in 0..100 {
+            map.delete(&(i * 5));
+        }
+    }
+    let mut snapshot = Vec::new();
+    table.write_snapshot(&mut snapshot).unwrap();
+    let (size, _) = table.shared_size().unwrap();
+    assert_eq!(snapshot.len(), size);
+
+    let mut restored =
+        unsafe { RawTable2::read_snapshot(&snapshot[..], layout, spec.clone(), Global) }.unwrap();
+    assert_eq!(restored.len(), 900);
+    assert_eq!(restored.buckets(), table.buckets());
+    assert_eq!(restored.capacity(), table.capacity());
+    drop(table);
+    unsafe {
+        let mut map = restored.as_map::<u64, u64>();
+        assert_eq!(map.get(&7), Some(&21));
+        assert_eq!(map.get(&10), None);
+        // 恢复的table可以继续写入和扩容
+        for i in 1000..3000 {
+            map.insert(&i, i * 3);
+        }
+        for i in 0..3000 {
+            assert_eq!(
+                map.get(&i).copied(),
+                Some(i * 3).filter(|_| i >= 500 || i % 5 != 0)
+            );
+        }
+    }
+
+    // 截断的快照
+    let err = unsafe {
+        RawTable2::read_snapshot(
+            &snapshot[..snapshot.len() - 1],
+            layout,
+            spec.clone(),
+            Global,
+        )
+    }
+    .err()
+    .unwrap();
+    assert!(matches!(err, SnapshotError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof));
+
+    // 损坏的快照
+    let ctrl_len = restored.buckets() + Group::WIDTH;
+    for (pos, section) in [
+        (24, SnapshotSection::Header),
+        (snapshot.len() - ctrl_len - 1, SnapshotSection::Buckets),
+        (snapshot.len() - 1, SnapshotSection::Ctrl),
+    ] {
+        let mut corrupted = snapshot.clone();
+        corrupted[pos] ^= 0x10;
+        let err = unsafe { RawTable2::read_snapshot(&corrupted[..], layout, spec.clone(), Global) }
+            .err()
+            .unwrap();
+        assert!(matches!(err, SnapshotError::Corrupted(s) if s == section));
+    }
+
+    // 内存结构不同的spec无法恢复
+    let other = HasherSpec::<u32, u32, _>::new(FnvBuildHasher::default());
+    let err = unsafe {
+        RawTable2::read_snapshot(&snapshot[..], Layout::new::<(u32, u32)>(), other, Global)
+    }
+    .err()
+    .unwrap();
+    assert!(matches!(
+        err,
+        SnapshotError::Incompatible(Mismatch::EntryLayout { .. })
+    ));
+
+    // 不同平台或格式版本写入的快照
+    let mut other_version = snapshot.clone();
+    other_version[12..14].copy_from_slice(&(SNAPSHOT_MAJOR + 1).to_le_bytes());
+    // 之前的版本在大端平台上以本机字节序写入的元数据
+    let mut other_endian = snapshot.clone();
+    other_endian[8..12].reverse();
+    other_endian[12..14].reverse();
+    let mut not_snapshot = snapshot.clone();
+    not_snapshot[0] = 0;
+    for (bytes, mismatch) in [
+        (
+            other_version,
+            Mismatch::Version {
+                found: (SNAPSHOT_MAJOR + 1, SNAPSHOT_MINOR),
+            },
+        ),
+        (other_endian, Mismatch::Endianness),
+        (not_snapshot, Mismatch::Magic),
+    ] {
+        let err = unsafe { RawTable2::read_snapshot(&bytes[..], layout, spec.clone(), Global) }
+            .err()
+            .unwrap();
+        assert!(matches!(err, SnapshotError::Incompatible(m) if m == mismatch));
+    }
+
+    // 之后的次版本在元数据末尾追加的字段被跳过, bucket数组随之后移
+    let field = |bytes: &mut [u8], offset: usize, delta: u64| {
+        let value = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        bytes[offset..offset + 8].copy_from_slice(&(value + delta).to_le_bytes());
+    };
+    let (header_size, align) = (128, Group::WIDTH.max(8));
+    let block = (header_size + 8 + align - 1) / align * align;
+    let mut newer = snapshot[..header_size - 8].to_vec();
+    newer[14..16].copy_from_slice(&(SNAPSHOT_MINOR + 1).to_le_bytes());
+    newer[16..20].copy_from_slice(&(header_size as u32 + 8).to_le_bytes());
+    field(&mut newer, 80, (block - header_size) as u64);
+    field(&mut newer, 88, (block - header_size) as u64);
+    newer.extend_from_slice(&[0xab; 8]);
+    let checksum = Checksum::of(&newer);
+    newer.extend_from_slice(&checksum.to_le_bytes());
+    newer.resize(block, 0);
+    newer.extend_from_slice(&snapshot[header_size..]);
+    let newer =
+        unsafe { RawTable2::read_snapshot(&newer[..], layout, spec.clone(), Global) }.unwrap();
+    assert_eq!(newer.len(), 900);
+    let err = unsafe { RawTable2::read_snapshot(&snapshot[..], layout, StrKey, Global) }
+        .err()
+        .unwrap();
+    assert!(matches!(err, SnapshotError::LayoutMismatch));
+
+    // 空table
+    let table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    let mut snapshot = Vec::new();
+    table.write_snapshot(&mut snapshot).unwrap();
+    let restored =
+        unsafe { RawTable2::read_snapshot(&snapshot[..], layout, spec.clone(), Global) }.unwrap();
+    assert!(restored.is_empty());
+
+    // entries引用table之外内存的模式无法写入快照
+    let table = RawTable2::with_separate_buckets(16, spec, Global).unwrap();
+    let err = table.write_snapshot(io::sink()).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_snapshot_chunks() {
+    use super::shared::{SnapshotError, SnapshotImport, SnapshotSection};
+    use core::alloc::Layout;
+    use std::io::{self, Read};
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let layout = Layout::new::<(u64, u64)>();
+    let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..500 {
+            map.insert(&i, i + 1);
+        }
+        for i in 0..50 {
+            map.delete(&(i * 7));
+        }
+    }
+    let mut snapshot = Vec::new();
+    table.write_snapshot(&mut snapshot).unwrap();
+
+    // 分块导出的字节流与write_snapshot一致
+    let chunks = table.snapshot_chunks(1000).unwrap();
+    assert_eq!(chunks.total(), snapshot.len());
+    assert_eq!(chunks.len(), (snapshot.len() + 999) / 1000);
+    let chunks = chunks.collect::<Vec<_>>();
+    assert!(chunks[..chunks.len() - 1].iter().all(|c| c.len() == 1000));
+    assert_eq!(chunks.concat(), snapshot);
+    let mut read = Vec::new();
+    table
+        .snapshot_chunks(1)
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+    assert_eq!(read, snapshot);
+
+    // 按任意大小分批导入, 快照之后的字节不会被消耗
+    let mut stream = snapshot.clone();
+    stream.extend_from_slice(b"trailing");
+    let mut import = unsafe { SnapshotImport::new(layout, spec.clone(), Global) }.unwrap();
+    let mut pos = 0;
+    for size in [3, 17, 1, 100, 4096].iter().cycle() {
+        let end = (pos + size).min(stream.len());
+        pos += import.feed(&stream[pos..end]).unwrap();
+        if import.needed() == 0 {
+            break;
+        }
+    }
+    assert_eq!(pos, snapshot.len());
+    assert_eq!(import.feed(b"more").unwrap(), 0);
+    let mut restored = import.finish().unwrap();
+    assert_eq!(restored.len(), 450);
+    assert_eq!(restored.capacity(), table.capacity());
+    unsafe {
+        let map = restored.as_map::<u64, u64>();
+        for i in 0..500 {
+            assert_eq!(
+                map.get(&i).copied(),
+                Some(i + 1).filter(|_| i % 7 != 0 || i >= 350)
+            );
+        }
+    }
+
+    // 不完整的快照
+    let mut import = unsafe { SnapshotImport::new(layout, spec.clone(), Global) }.unwrap();
+    import.feed(&snapshot[..snapshot.len() / 2]).unwrap();
+    let err = import.finish().err().unwrap();
+    assert!(matches!(err, SnapshotError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof));
+    // 中途丢弃导入器
+    let mut import = unsafe { SnapshotImport::new(layout, spec.clone(), Global) }.unwrap();
+    import.feed(&snapshot[..snapshot.len() - 1]).unwrap();
+    drop(import);
+
+    // 损坏的bucket数组在最后一个字节到达时报告
+    let mut corrupted = snapshot.clone();
+    corrupted[snapshot.len() / 2] ^= 0x01;
+    let mut import = unsafe { SnapshotImport::new(layout, spec.clone(), Global) }.unwrap();
+    import.feed(&corrupted[..snapshot.len() - 1]).unwrap();
+    let err = import.feed(&corrupted[snapshot.len() - 1..]).err().unwrap();
+    assert!(matches!(
+        err,
+        SnapshotError::Corrupted(SnapshotSection::Buckets)
+    ));
+
+    // 空table
+    let empty = RawTable2::new(0, spec.clone(), Global).unwrap();
+    let bytes = empty
+        .snapshot_chunks(7)
+        .unwrap()
+        .collect::<Vec<_>>()
+        .concat();
+    let mut import = unsafe { SnapshotImport::new(layout, spec, Global) }.unwrap();
+    assert_eq!(import.feed(&bytes).unwrap(), bytes.len());
+    assert!(import.finish().unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_snapshot_byte_order() {
+    use super::shared::{
+        Mismatch, SharedError, SharedTable, SnapshotError, SnapshotImport, SNAPSHOT_MAJOR,
+    };
+    use core::alloc::Layout;
+
+    fn ptr(v: &u64) -> *const u8 {
+        v as *const u64 as *const u8
+    }
+    let layout = Layout::new::<(u64, u64)>();
+    let mut table = RawTable2::new(0, LeCodec, Global).unwrap();
+    unsafe {
+        for i in 0..300 {
+            table.try_insert(ptr(&i), ptr(&(i << 40 | 7))).unwrap();
+        }
+        table.delete(ptr(&3));
+    }
+
+    // 任意平台上写入的快照中, 元数据与entries均为小端字节序
+    let mut little = Vec::new();
+    table.write_snapshot(&mut little).unwrap();
+    assert_eq!(little[8..12], [4, 3, 2, 1]);
+    assert_eq!(little[12..14], SNAPSHOT_MAJOR.to_le_bytes());
+
+    // 模拟大端平台上不转换字节序的写入: 只有bucket数组不同
+    let big = table
+        .snapshot_chunks_in(100, cfg!(target_endian = "little"))
+        .unwrap()
+        .collect::<Vec<_>>()
+        .concat();
+    assert_eq!(big.len(), little.len());
+    assert_eq!(big[..8], little[..8]);
+    assert_eq!(big[8..12], [1, 2, 3, 4]);
+    let ctrl_len = table.buckets() + Group::WIDTH;
+    assert_eq!(
+        big[big.len() - ctrl_len..],
+        little[little.len() - ctrl_len..]
+    );
+    assert_ne!(big, little);
+
+    // 读取时比对校验和之后转换为本平台的字节序
+    for bytes in [&little, &big] {
+        let restored =
+            unsafe { RawTable2::read_snapshot(&bytes[..], layout, LeCodec, Global) }.unwrap();
+        assert_eq!(restored.len(), 299);
+        for i in 0..300 {
+            let value = unsafe { restored.access(ptr(&i)).map(|v| *(v as *const u64)) };
+            assert_eq!(value, Some(i << 40 | 7).filter(|_| i != 3));
+        }
+        let mut import = unsafe { SnapshotImport::new(layout, LeCodec, Global) }.unwrap();
+        for chunk in bytes.chunks(33) {
+            import.feed(chunk).unwrap();
+        }
+        assert_eq!(import.finish().unwrap().len(), 299);
+    }
+
+    // spec不支持转换字节序时无法读取, 写入时保持本平台的字节序
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let other = if cfg!(target_endian = "little") {
+        &big
+    } else {
+        &little
+    };
+    let err = unsafe { RawTable2::read_snapshot(&other[..], layout, spec.clone(), Global) }
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err,
+        SnapshotError::Incompatible(Mismatch::Endianness)
+    ));
+    let mut plain = RawTable2::new(0, spec.clone(), Global).unwrap();
+    unsafe { plain.as_map::<u64, u64>().insert(&1, 2) };
+    let mut native = Vec::new();
+    plain.write_snapshot(&mut native).unwrap();
+    let chunks = plain
+        .snapshot_chunks_in(100, true)
+        .unwrap()
+        .collect::<Vec<_>>()
+        .concat();
+    assert_eq!(chunks, native);
+
+    // attach原样访问区域, 不转换字节序
+    let mut mapping = vec![Block([0; 32]); other.len() / 32 + 1];
+    let region = unsafe {
+        std::slice::from_raw_parts_mut(mapping.as_mut_ptr() as *mut u8, mapping.len() * 32)
+    };
+    region[..other.len()].copy_from_slice(other);
+    assert_eq!(
+        unsafe { SharedTable::attach(&region[..], LeCodec) }.err(),
+        Some(SharedError::Incompatible(Mismatch::Endianness))
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_frozen_raw_table() {
+    use super::shared::{FrozenRawTable, SharedError, SnapshotSection};
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i * 3);
+        }
+        map.delete(&10);
+    }
+    let mut snapshot = Vec::new();
+    table.write_snapshot(&mut snapshot).unwrap();
+    drop(table);
+
+    // 模拟按页对齐映射的快照文件
+    let mut mapping = vec![Block([0; 32]); snapshot.len() / 32 + 1];
+    let region = unsafe {
+        std::slice::from_raw_parts_mut(mapping.as_mut_ptr() as *mut u8, mapping.len() * 32)
+    };
+    region[..snapshot.len()].copy_from_slice(&snapshot);
+    let region = &region[..];
+
+    let frozen = unsafe { FrozenRawTable::attach(region, spec.clone()).unwrap() };
+    assert_eq!(frozen.verify(), Ok(()));
+    assert_eq!(frozen.len(), 999);
+    unsafe {
+        let value = frozen.access(&7u64 as *const u64 as *const u8).unwrap();
+        assert_eq!(*(value as *const u64), 21);
+        assert!(frozen.access(&10u64 as *const u64 as *const u8).is_none());
+    }
+    let iter = frozen.iter();
+    assert_eq!(iter.len(), 999);
+    let mut keys: Vec<u64> = iter
+        .map(|(key, value)| unsafe {
+            assert_eq!(*(value as *const u64), *(key as *const u64) * 3);
+            *(key as *const u64)
+        })
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(keys, (0..1000).filter(|&i| i != 10).collect::<Vec<_>>());
+
+    // ctrl字节按Group对齐读取, 只满足元数据对齐要求的区域同样被拒绝
+    if Group::WIDTH > 8 {
+        let mut shifted = vec![Block([0; 32]); mapping.len() + 1];
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(shifted.as_mut_ptr() as *mut u8, shifted.len() * 32)
+        };
+        bytes[8..8 + snapshot.len()].copy_from_slice(&snapshot);
+        assert_eq!(
+            unsafe { FrozenRawTable::attach(&bytes[8..], spec.clone()) }.err(),
+            Some(SharedError::Misaligned {
+                align: Group::WIDTH
+            })
+        );
+    }
+
+    // 元数据损坏时无法attach, 数据损坏由verify检出
+    let mut corrupted = mapping.clone();
+    let bytes = unsafe {
+        std::slice::from_raw_parts_mut(corrupted.as_mut_ptr() as *mut u8, corrupted.len() * 32)
+    };
+    bytes[snapshot.len() - 1] ^= 1;
+    let frozen = unsafe { FrozenRawTable::attach(bytes, spec.clone()).unwrap() };
+    assert_eq!(
+        frozen.verify(),
+        Err(SharedError::Corrupted(SnapshotSection::Ctrl))
+    );
+    bytes[16] ^= 1;
+    assert_eq!(
+        unsafe { FrozenRawTable::attach(bytes, spec.clone()) }.err(),
+        Some(SharedError::Corrupted(SnapshotSection::Header))
+    );
+
+    // 截断的快照
+    assert_eq!(
+        unsafe { FrozenRawTable::attach(&region[..snapshot.len() - 1], spec) }.err(),
+        Some(SharedError::TooSmall {
+            required: snapshot.len()
+        })
+    );
+}
+
+#[test]
+fn test_local_aggregator() {
+    use super::aggregate::LocalAggregator;
+    use std::thread;
+
+    let add =
+        |value: *const u8, v: *const u8| unsafe { *(value as *mut u64) += *(v as *const u64) };
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let template = LocalAggregator::new(spec.clone(), Global).unwrap();
+
+    // 每个线程统计各自输入中每个"单词"(取值0..100)出现的次数
+    let locals: Vec<_> = (0..4u64)
+        .map(|t| {
+            let mut local = template.fork().unwrap();
+            thread::spawn(move || {
+                for i in 0..10_000u64 {
+                    let word = (i * 7 + t) % 100;
+                    unsafe {
+                        local.upsert(
+                            &word as *const u64 as *const u8,
+                            &1u64 as *const u64 as *const u8,
+                            add,
+                        )
+                    }
+                    .unwrap();
+                }
+                local
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+    assert!(locals.iter().all(|local| local.len() == 100));
+
+    let mut global = RawTable2::new(0, spec, Global).unwrap();
+    for mut local in locals {
+        let buckets = local.table().buckets();
+        unsafe { local.merge_into(&mut global, add) }.unwrap();
+        assert!(local.is_empty());
+        assert_eq!(local.table().buckets(), buckets);
+    }
+    assert_eq!(global.len(), 100);
+    let mut total = 0;
+    for (_, value) in global.iter() {
+        let count = unsafe { *(value as *const u64) };
+        assert_eq!(count, 400);
+        total += count;
+    }
+    assert_eq!(total, 40_000);
+
+    // 不兼容的table无法合并
+    let other = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut seeded = RawTable2::new(0, Box::new(other) as Box<dyn EntrySpec>, Global).unwrap();
+    let mut local = LocalAggregator::new(
+        Box::new(HasherSpec::<u32, u32, FnvBuildHasher>::new(
+            FnvBuildHasher::default(),
+        )) as Box<dyn EntrySpec>,
+        Global,
+    )
+    .unwrap();
+    unsafe {
+        local
+            .upsert(
+                &1u32 as *const u32 as *const u8,
+                &1u32 as *const u32 as *const u8,
+                |_, _| {},
+            )
+            .unwrap();
+        assert_eq!(
+            local.merge_into(&mut seeded, |_, _| {}),
+            Err(ExtendError::Incompatible)
+        );
+    }
+    assert_eq!(local.len(), 1);
+}
+
+#[test]
+#[cfg(feature = "rkyv")]
+fn test_rkyv_archive() {
+    use super::shared::SharedError;
+    use crate::external_trait_impls::rkyv::ArchiveError;
+    use rkyv::rancor::Error;
+    use rkyv::util::AlignedVec;
+    use std::string::ToString;
+
+    type Spec = HasherSpec<u64, u64, FnvBuildHasher>;
+
+    #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+    struct Asset {
+        id: u32,
+        table: RawTable2<Spec>,
+    }
+
+    let mut asset = Asset {
+        id: 7,
+        table: RawTable2::new(0, Spec::default(), Global).unwrap(),
+    };
+    unsafe {
+        let mut map = asset.table.as_map::<u64, u64>();
+        for i in 0..1000 {
+            map.insert(&i, i * 3);
+        }
+    }
+
+    // 区域按Group宽度对齐, 归档缓冲区需满足同样的对齐要求
+    let bytes = rkyv::api::high::to_bytes_in::<_, Error>(&asset, AlignedVec::<32>::new()).unwrap();
+    let archived = rkyv::access::<ArchivedAsset, Error>(&bytes).unwrap();
+    assert_eq!(archived.id, 7);
+    unsafe {
+        let shared = archived.table.attach(Spec::default()).unwrap();
+        assert_eq!(shared.len(), 1000);
+        shared.verify().unwrap();
+        let value = shared.access(&5u64 as *const u64 as *const u8).unwrap();
+        assert_eq!(*(value as *const u64), 15);
+        assert!(shared.access(&1000u64 as *const u64 as *const u8).is_none());
+    }
+
+    let mut restored = rkyv::deserialize::<Asset, Error>(archived).unwrap();
+    assert_eq!(restored.id, 7);
+    assert_eq!(restored.table.len(), 1000);
+    unsafe {
+        let view = restored.table.as_map::<u64, u64>();
+        assert!((0..1000).all(|i| view.get(&i) == Some(&(i * 3))));
+    }
+
+    // entries引用table之外内存的模式无法归档
+    let table = RawTable2::with_separate_buckets(16, Spec::default(), Global).unwrap();
+    let err = rkyv::to_bytes::<Error>(&table).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ArchiveError::Shared(SharedError::Unsupported).to_string()
+    );
+}
+
+/// entry为`(u64, u64)`, Key与value均编码为小端字节
+struct LeCodec;
+
+impl EntrySpec for LeCodec {
+    fn layout(&self) -> Layout {
+        Layout::new::<(u64, u64)>()
+    }
+    fn hash(&self, entry: *const u8) -> u64 {
+        unsafe { *(entry as *const u64) }.wrapping_mul(0x9e3779b97f4a7c15)
+    }
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+    }
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { *(entry as *mut u64) = *(k as *const u64) }
+    }
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(8) }
+    }
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { *(value as *mut u64) = *(v as *const u64) }
+    }
+    fn encode_key(&self, entry: *const u8, out: &mut Vec<u8>) -> bool {
+        out.extend_from_slice(&unsafe { *(entry as *const u64) }.to_le_bytes());
+        true
+    }
+    fn encode_value(&self, value: *const u8, out: &mut Vec<u8>) -> bool {
+        out.extend_from_slice(&unsafe { *(value as *const u64) }.to_le_bytes());
+        true
+    }
+    fn decode_key(&self, bytes: &[u8], k: *mut u8) -> bool {
+        match <[u8; 8]>::try_from(bytes) {
+            Ok(bytes) => {
+                unsafe { *(k as *mut u64) = u64::from_le_bytes(bytes) };
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    fn decode_value(&self, bytes: &[u8], value: *const u8) -> bool {
+        self.decode_key(bytes, value as *mut u8)
+    }
+    fn swap_key_bytes(&self, entry: *mut u8) -> bool {
+        unsafe { *(entry as *mut u64) = (*(entry as *const u64)).swap_bytes() };
+        true
+    }
+    fn swap_value_bytes(&self, value: *mut u8) -> bool {
+        self.swap_key_bytes(value)
+    }
+}
+
+#[test]
+fn test_insert_encoded_decode_failure() {
+    let mut table = RawTable2::new(0, LeCodec, Global).unwrap();
+    let key = 1u64.to_le_bytes();
+    let get = |table: &RawTable2<LeCodec>| unsafe {
+        table
+            .access(&1u64 as *const u64 as *const u8)
+            .map(|value| *(value as *const u64))
+    };
+    unsafe {
+        assert_eq!(
+            table.try_insert_encoded(&key, &10u64.to_le_bytes()),
+            Ok(true)
+        );
+        assert_eq!(
+            table.try_insert_encoded(&key, &11u64.to_le_bytes()),
+            Ok(true)
+        );
+        assert_eq!(get(&table), Some(11));
+        // value解码失败时已存在的value保持不变
+        assert_eq!(table.try_insert_encoded(&key, &[1, 2, 3]), Ok(false));
+        assert_eq!(get(&table), Some(11));
+        assert_eq!(table.len(), 1);
+        // 新Key解码失败时不留下entry
+        assert_eq!(
+            table.try_insert_encoded(&2u64.to_le_bytes(), &[1, 2, 3]),
+            Ok(false)
+        );
+        assert_eq!(table.len(), 1);
+    }
+}
+
+#[test]
+fn test_journal() {
+    use super::journal::*;
+
+    fn ptr(v: &u64) -> *const u8 {
+        v as *const u64 as *const u8
+    }
+    fn get(table: &RawTable2<LeCodec>, key: u64) -> Option<u64> {
+        unsafe { table.access(ptr(&key)).map(|value| *(value as *const u64)) }
+    }
+
+    let mut log = Vec::new();
+    let mut ops = Vec::new();
+    let table = RawTable2::new(0, LeCodec, Global).unwrap();
+    let mut journaled = JournaledTable::new(table, |record: JournalRecord<'_>| {
+        ops.push(record.op);
+        record.encode(&mut log);
+    });
+    unsafe {
+        for i in 0..100 {
+            journaled.try_insert(ptr(&i), ptr(&(i * 2))).unwrap();
+        }
+        journaled.try_insert(ptr(&7), ptr(&700)).unwrap();
+        assert_eq!(journaled.delete(ptr(&8)), Ok(true));
+        // 不存在的Key不产生记录
+        assert_eq!(journaled.delete(ptr(&1000)), Ok(false));
+    }
+    assert_eq!(journaled.len(), 99);
+    let (table, _) = journaled.into_parts();
+    assert_eq!(ops.len(), 102);
+    assert_eq!(ops[0], JournalOp::Insert);
+    assert_eq!(ops[100], JournalOp::Overwrite);
+    assert_eq!(ops[101], JournalOp::Delete);
+
+    let (record, len) = JournalRecord::decode(&log).unwrap();
+    assert_eq!(record.op, JournalOp::Insert);
+    assert_eq!(record.key, &0u64.to_le_bytes());
+    assert_eq!(len, 1 + 1 + 8 + 1 + 8);
+
+    // 日志尾部写入中途中断
+    log.extend_from_slice(&[0, 8, 1, 2]);
+    let mut records = JournalRecords::new(&log);
+    let empty = RawTable2::new(0, LeCodec, Global).unwrap();
+    let replayed =
+        unsafe { JournaledTable::replay(empty, &mut records, |_: JournalRecord<'_>| {}) }
+            .unwrap()
+            .into_parts()
+            .0;
+    assert_eq!(records.consumed(), log.len() - 4);
+    assert_eq!(replayed.len(), table.len());
+    for i in 0..100 {
+        assert_eq!(get(&replayed, i), get(&table, i));
+    }
+    assert_eq!(get(&replayed, 7), Some(700));
+    assert_eq!(get(&replayed, 8), None);
+
+    // 无法解码的记录
+    let bad = [JournalRecord {
+        op: JournalOp::Delete,
+        key: &[1, 2, 3],
+        value: &[],
+    }];
+    let empty = RawTable2::new(0, LeCodec, Global).unwrap();
+    assert_eq!(
+        unsafe { JournaledTable::replay(empty, bad, |_: JournalRecord<'_>| {}) }.err(),
+        Some(JournalError::Codec)
+    );
+
+    // spec不支持编码时删除不会生效
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe { table.try_insert(ptr(&1), ptr(&1)) }.unwrap();
+    let mut journaled = JournaledTable::new(table, |_: JournalRecord<'_>| {});
+    assert_eq!(
+        unsafe { journaled.delete(ptr(&1)) },
+        Err(JournalError::Codec)
+    );
+    assert_eq!(journaled.len(), 1);
+}
+
+#[test]
+fn test_hash_map_conversion() {
+    let mut map = crate::HashMap::with_hasher(FnvBuildHasher::default());
+    for i in 0..100u64 {
+        map.insert(i, i * 2);
+    }
+    let mut table = RawTable2::from(map);
+    assert_eq!(table.len(), 100);
+    unsafe {
+        let view = table.as_map::<u64, u64>();
+        assert_eq!(view.get(&7).copied(), Some(14));
+        assert!(view.get(&100).is_none());
+    }
+
+    let map = table.into_hash_map();
+    assert_eq!(map.len(), 100);
+    assert!((0..100).all(|i| map[&i] == i * 2));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_std_hash_map_conversion() {
+    let map: std::collections::HashMap<u32, u64, FnvBuildHasher> =
+        (0..50).map(|i| (i, i as u64)).collect();
+    let table = RawTable2::from(map.clone());
+    assert_eq!(table.len(), 50);
+    assert_eq!(table.into_std_hash_map(), map);
+}
+
+#[test]
+fn test_debug_visit() {
+    use std::format;
+    use std::string::String;
+
+    #[derive(Default)]
+    struct Dump {
+        desc: Option<TableDescriptor>,
+        entries: Vec<String>,
+        limit: usize,
+    }
+
+    impl EntryVisitor for Dump {
+        fn begin(&mut self, desc: &TableDescriptor) {
+            self.desc = Some(*desc);
+        }
+
+        fn visit(&mut self, entry: &DebugEntry<'_>) -> bool {
+            self.entries.push(format!("{entry:?}"));
+            self.entries.len() < self.limit
+        }
+    }
+
+    // 为Float64Key补充Key与Value的格式化
+    struct Formatted(Float64Key);
+    impl EntrySpec for Formatted {
+        fn layout(&self) -> Layout {
+            self.0.layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            self.0.hash(entry)
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            self.0.equals(entry1, entry2)
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            self.0.assign_key(entry, k)
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            self.0.access_value(entry)
+        }
+        fn fmt_key(&self, entry: *const u8, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{}", unsafe { *(entry as *const f64) })
+        }
+        fn fmt_value(
+            &self,
+            value: *const u8,
+            f: &mut core::fmt::Formatter<'_>,
+        ) -> core::fmt::Result {
+            write!(f, "{}", unsafe { *(value as *const f64) })
+        }
+    }
+
+    let entry = Formatted(Float64Key(EntryLayout::of::<f64, f64>().layout()));
+    let mut table = RawTable2::new(0, entry, Global).unwrap();
+    let mut dump = Dump {
+        limit: usize::MAX,
+        ..Dump::default()
+    };
+    table.debug_visit(&mut dump);
+    let desc = dump.desc.unwrap();
+    assert_eq!((desc.buckets, desc.items), (0, 0));
+    assert!(desc.ctrl.is_null() && desc.bucket0.is_null());
+    assert!(dump.entries.is_empty());
+
+    unsafe {
+        let mut map = table.as_map::<f64, f64>();
+        for i in 0..3 {
+            map.insert(&(i as f64), i as f64 * 1.5);
+        }
+        map.delete(&0.0);
+    }
+    let mut dump = Dump {
+        limit: usize::MAX,
+        ..Dump::default()
+    };
+    table.debug_visit(&mut dump);
+    let desc = dump.desc.unwrap();
+    assert_eq!(desc.version, TABLE_DESCRIPTOR_VERSION);
+    assert_eq!(desc.flags, 0);
+    assert_eq!(desc.buckets, table.buckets());
+    assert_eq!(desc.items, 2);
+    assert_eq!(
+        desc.tombstones + desc.growth_left + desc.items,
+        table.capacity()
+    );
+    assert_eq!((desc.entry_size, desc.entry_align), (16, 8));
+    dump.entries.sort();
+    assert_eq!(dump.entries, ["1 => 1.5", "2 => 3"]);
+    assert!(format!("{desc}").starts_with("version=1 flags=0x0 buckets=4 items=2 "));
+
+    // 按描述中的地址直接读取每个有效槽位的Key
+    let mut keys = Vec::new();
+    for i in 0..desc.buckets {
+        unsafe {
+            if *desc.ctrl.add(i) & 0x80 == 0 {
+                let bucket = desc.bucket0.offset(i as isize * desc.bucket_stride);
+                keys.push(*(bucket as *const f64));
+            }
+        }
+    }
+    keys.sort_by(f64::total_cmp);
+    assert_eq!(keys, [1.0, 2.0]);
+
+    // 返回`false`时停止访问
+    let mut dump = Dump {
+        limit: 1,
+        ..Dump::default()
+    };
+    table.debug_visit(&mut dump);
+    assert_eq!(dump.entries.len(), 1);
+
+    let spec = HasherSpec::<u64, (), _>::new(FnvBuildHasher::default());
+    let mut set = RawTable2::with_separate_buckets(0, spec, Global).unwrap();
+    unsafe { set.insert(&7u64 as *const u64 as *const u8, core::ptr::null()) };
+    let desc = set.describe();
+    assert_eq!(desc.flags, TableDescriptor::SEPARATE | TableDescriptor::SET);
+    assert_eq!(desc.bucket_stride, 8);
+}
+
+#[test]
+fn test_nonnull_api() {
+    use core::ptr::NonNull;
+
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let key = |k: &u64| NonNull::from(k).cast::<u8>();
+    unsafe {
+        assert!(table.access_nonnull(key(&1)).is_none());
+        *table.assign_nonnull(key(&1)).cast::<u64>().as_ptr() = 10;
+        *table
+            .try_assign_nonnull(key(&2))
+            .unwrap()
+            .cast::<u64>()
+            .as_ptr() = 20;
+        let value = table.access_nonnull(key(&2)).unwrap();
+        assert_eq!(*value.cast::<u64>().as_ptr(), 20);
+        assert_eq!(
+            value.as_ptr() as *const u8,
+            table.access(&2u64 as *const u64 as *const u8).unwrap()
+        );
+    }
+
+    let mut keys = Vec::new();
+    let mut index = 0;
+    while let Some((i, entry)) = table.next_entry_nonnull(index) {
+        assert_eq!(
+            table.next_entry(index),
+            Some((i, entry.as_ptr() as *const u8))
+        );
+        keys.push(unsafe { *entry.cast::<u64>().as_ptr() });
+        index = i + 1;
+    }
+    keys.sort_unstable();
+    assert_eq!(keys, [1, 2]);
+}
+
+#[test]
+fn test_extend_from_iter() {
+    // 按`size_hint`一次性预留, 只分配一次
+    let mut table: RawTable2<HasherSpec<u64, u64, FnvBuildHasher>> =
+        (0..1000).map(|i| (i, i * 2)).collect();
+    assert_eq!(table.len(), 1000);
+    assert_eq!(table.alloc_stats().allocations(), 1);
+
+    Extend::extend(&mut table, (500..1500).map(|i| (i, i)));
+    assert_eq!(table.len(), 1500);
+    let other = crate::HashMap::<u64, u64>::from_iter([(0, 9), (2000, 1)]);
+    Extend::extend(&mut table, &other);
+    assert_eq!(table.len(), 1501);
+    unsafe {
+        let view = table.as_map::<u64, u64>();
+        assert_eq!(view.get(&0), Some(&9));
+        assert_eq!(view.get(&499), Some(&998));
+        assert_eq!(view.get(&500), Some(&500));
+        assert_eq!(view.get(&2000), Some(&1));
+    }
+}
+
+#[test]
+fn test_into_hashbrown() {
+    let spec = HasherSpec::<u64, u32, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut view = table.as_map::<u64, u32>();
+        for i in 0..1000 {
+            view.insert(&i, i as u32);
+        }
+        for i in (0..1000).step_by(3) {
+            view.delete(&i);
+        }
+    }
+    let value = unsafe { table.access(&1u64 as *const u64 as *const u8) }.unwrap();
+    let capacity = table.capacity();
+
+    // bucket数组直接移交, value地址与容量均不变
+    let mut map = table.into_hashbrown();
+    assert_eq!(map.len(), 666);
+    assert_eq!(map.capacity(), capacity);
+    assert!(core::ptr::eq(&map[&1], value as *const u32));
+    assert!((0..1000).all(|i| map.get(&i) == (i % 3 != 0).then_some(&(i as u32))));
+    map.insert(3, 7);
+    for i in 1000..2000 {
+        map.insert(i, 0);
+    }
+    assert_eq!(map[&3], 7);
+    assert_eq!(map.len(), 1667);
+
+    // 按`bucket_align`对齐分配的table退化为逐个移动entries
+    let spec = HasherSpec::<u64, u32, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::try_with_bucket_align(0, 4096, spec, Global).unwrap();
+    unsafe { table.as_map::<u64, u32>().insert(&5, 50) };
+    let map = table.into_hashbrown();
+    assert_eq!(map.len(), 1);
+    assert_eq!(map[&5], 50);
+}
+
+#[test]
+fn test_export_columns() {
+    let spec = HasherSpec::<u32, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u32, u64>();
+        for i in 0..1000u32 {
+            map.insert(&i, i as u64 * 10);
+        }
+    }
+
+    // 整个table导出为两个稠密列
+    let mut keys = vec![0u32; 1000];
+    let mut values = vec![0u64; 1000];
+    let rows = unsafe {
+        table
+            .as_map::<u32, u64>()
+            .export_columns(&mut keys, &mut values)
+    };
+    assert_eq!(rows, Ok(1000));
+    assert!(keys.iter().zip(&values).all(|(&k, &v)| v == k as u64 * 10));
+    keys.sort_unstable();
+    assert!(keys.iter().copied().eq(0..1000));
+    let err = unsafe {
+        table
+            .as_map::<u32, u64>()
+            .export_columns(&mut keys[..999], &mut values)
+    };
+    assert_eq!(err, Err(ColumnsTooSmall { rows: 1000 }));
+
+    // 按槽位范围分批导出原始字节, 只导出value的低4字节
+    let key = Column::new(0, 4);
+    let value = Column::new(0, 4);
+    let mut key_bytes = vec![0u8; 4000];
+    let mut value_bytes = vec![0u8; 4000];
+    let mut rows = 0;
+    for range in table.chunks(7) {
+        let n = unsafe {
+            table.export_columns_in(
+                range,
+                key,
+                value,
+                &mut key_bytes[rows * 4..],
+                &mut value_bytes[rows * 4..],
+            )
+        }
+        .unwrap();
+        rows += n;
+    }
+    assert_eq!(rows, 1000);
+    let mut all = (vec![0u8; 4000], vec![0u8; 4000]);
+    assert_eq!(
+        unsafe { table.export_columns(key, value, &mut all.0, &mut all.1) },
+        Ok(1000)
+    );
+    assert_eq!((&all.0, &all.1), (&key_bytes, &value_bytes));
+    for (k, v) in key_bytes.chunks(4).zip(value_bytes.chunks(4)) {
+        let k = u32::from_ne_bytes(k.try_into().unwrap());
+        assert_eq!(u32::from_ne_bytes(v.try_into().unwrap()), k * 10);
+    }
+    assert_eq!(
+        unsafe { table.export_columns(key, value, &mut all.0[..3996], &mut all.1) },
+        Err(ColumnsTooSmall { rows: 1000 })
+    );
+}
+
+#[test]
+fn test_prefetch() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    // 空table与小table同样可以预取
+    unsafe { table.prefetch(&1u64 as *const u64 as *const u8) };
+    for n in [4u64, 1000] {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..n {
+                map.insert(&i, i + 1);
+            }
+        }
+        let allocations = table.alloc_stats().allocations();
+        for i in 0..n * 2 {
+            let key = &i as *const u64 as *const u8;
+            unsafe {
+                table.prefetch(key);
+                let value = table.access(key).map(|value| *(value as *const u64));
+                assert_eq!(value, Some(i + 1).filter(|_| i < n));
+            }
+        }
+        assert_eq!(table.len(), n as usize);
+        assert_eq!(table.alloc_stats().allocations(), allocations);
+    }
+}
+
+#[test]
+fn test_lookup_pipeline() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let keys: [u64; 8] = [0, 3, 5000, 17, 999, 4242, 1, 123_456];
+    let lookup = |table: &RawTable2<_>| unsafe {
+        let ptrs: [_; 8] = core::array::from_fn(|i| &keys[i] as *const u64 as *const u8);
+        table
+            .lookup_pipeline(ptrs)
+            .map(|value| value.map(|value| *(value as *const u64)))
+    };
+    // 空table
+    assert_eq!(lookup(&table), [None; 8]);
+
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..5000 {
+            map.insert(&i, i + 1);
+        }
+    }
+    let expected = keys.map(|key| Some(key + 1).filter(|_| key < 5000));
+    assert_eq!(lookup(&table), expected);
+    for (key, value) in keys.iter().zip(lookup(&table)) {
+        let access = unsafe { table.access(key as *const u64 as *const u8) };
+        assert_eq!(access.map(|value| unsafe { *(value as *const u64) }), value);
+    }
+
+    // 同一批中的重复Key与单个Key
+    let key = 42u64;
+    let key = &key as *const u64 as *const u8;
+    let values = unsafe { table.lookup_pipeline([key, key]) };
+    assert_eq!(values[0], values[1]);
+    assert_eq!(unsafe { table.lookup_pipeline([key]) }, [values[0]]);
+}
+
+#[test]
+fn test_int_spec() {
+    let spec = IntSpec::<u64, u32>::new(7);
+    let keys: Vec<u64> = (0..29).map(|i| i * 0x1_0000_0001).collect();
+    let ptrs: Vec<_> = keys.iter().map(|k| k as *const u64 as *const u8).collect();
+    let mut hashes = vec![0; keys.len()];
+    spec.hash_many(&ptrs, &mut hashes);
+    for (key, hash) in ptrs.iter().zip(&hashes) {
+        assert_eq!(*hash, spec.hash(*key));
+    }
+    // 不同种子得到不同的hash
+    assert_ne!(IntSpec::<u64, u32>::new(8).hash(ptrs[1]), hashes[1]);
+
+    let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u32>();
+        for i in 0..20_000u64 {
+            map.insert(&(i << 32), i as u32);
+        }
+        assert_eq!(map.get(&(7 << 32)), Some(&7));
+        assert_eq!(map.get(&7), None);
+    }
+    // 批量路径使用hash_many
+    let mut other = RawTable2::new(0, spec, Global).unwrap();
+    unsafe { other.extend(&table) };
+    assert_eq!(other.len(), 20_000);
+
+    let spec = IntSpec::<u128, ()>::new(0);
+    let mut set = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        for i in 0..1000u128 {
+            set.insert(
+                &(i << 64 | i) as *const u128 as *const u8,
+                core::ptr::null(),
+            );
+        }
+        assert_eq!(set.len(), 1000);
+        assert!(set
+            .access(&(3u128 << 64 | 3) as *const u128 as *const u8)
+            .is_some());
+        assert!(set
+            .access(&(3u128 << 64) as *const u128 as *const u8)
+            .is_none());
+    }
+}
+
+#[test]
+fn test_int_hash_modes() {
+    let spec = IntSpec::<u64, u64>::new(0).with_hash(IntHash::Identity);
+    assert_eq!(spec.hash_mode(), IntHash::Identity);
+    assert_eq!(spec.hash_key(12345), 12345);
+    let spec = IntSpec::<u64, u64>::new(0).with_hash(IntHash::Fibonacci);
+    assert_eq!(spec.hash_key(3), 3u64.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+
+    for mode in [
+        IntHash::Mixed,
+        IntHash::Identity,
+        IntHash::Fibonacci,
+        IntHash::Fibonacci32,
+    ] {
+        let spec = IntSpec::<u64, u64>::new(0x5a5a).with_hash(mode);
+        let mut table = RawTable2::new(0, spec.clone(), Global).unwrap();
+        assert_eq!(table.access_int(1), None);
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..10_000u64 {
+                map.insert(&(i * 3), i);
+            }
+        }
+        for i in 0..30_000u64 {
+            let value = table.access_int(i);
+            assert_eq!(value, unsafe {
+                table.access(&i as *const u64 as *const u8)
+            });
+            assert_eq!(
+                value.map(|value| unsafe { *(value as *const u64) }),
+                Some(i / 3).filter(|_| i % 3 == 0)
+            );
+        }
+        let mut scratch = vec![0; 4];
+        let keys = [0u64, 3, 6, 9];
+        let ptrs: Vec<_> = keys.iter().map(|k| k as *const u64 as *const u8).collect();
+        spec.hash_many(&ptrs, &mut scratch);
+        for (key, hash) in keys.iter().zip(&scratch) {
+            assert_eq!(spec.hash_key(*key), *hash);
+        }
+    }
+
+    // 有符号与较窄的整数Key
+    let spec = IntSpec::<i32, u8>::new(1).with_hash(IntHash::Fibonacci);
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<i32, u8>();
+        for i in -100..100i32 {
+            map.insert(&i, i as u8);
+        }
+    }
+    assert_eq!(
+        table.access_int(-7).map(|value| unsafe { *value }),
+        Some(-7i32 as u8)
+    );
+    assert_eq!(table.access_int(100), None);
+}
+
+#[test]
+fn test_int_spec_hash_many_modes() {
+    // 批量路径(启用AVX2时逐lane计算)与逐个计算的hash逐位一致
+    let keys: Vec<u64> = (0..37u64)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (i << 61))
+        .collect();
+    let ptrs: Vec<_> = keys.iter().map(|k| k as *const u64 as *const u8).collect();
+    for mode in [
+        IntHash::Mixed,
+        IntHash::Identity,
+        IntHash::Fibonacci,
+        IntHash::Fibonacci32,
+    ] {
+        let spec = IntSpec::<u64, u64>::new(0xdead_beef_0123_4567).with_hash(mode);
+        let mut hashes = vec![0; keys.len()];
+        spec.hash_many(&ptrs, &mut hashes);
+        for (key, hash) in keys.iter().zip(&hashes) {
+            assert_eq!(*hash, spec.hash_key(*key));
+        }
+    }
+}
+
+#[test]
+fn test_hash32() {
+    // 只产生32位hash的spec: 高32位为0, 不启用hash32时所有Key的H2相同
+    struct Narrow(bool);
+    impl EntrySpec for Narrow {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            let key = unsafe { *(entry as *const u64) };
+            u64::from((key as u32).wrapping_mul(0x9e37_79b9))
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn hash32(&self) -> bool {
+            self.0
+        }
+    }
+
+    for hash32 in [false, true] {
+        let mut table = RawTable2::new(0, Narrow(hash32), Global).unwrap();
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..5000 {
+                map.insert(&i, i + 1);
+            }
+            for i in 0..2500 {
+                map.delete(&(i * 2));
+            }
+            for i in 0..10_000 {
+                assert_eq!(
+                    map.get(&i).copied(),
+                    Some(i + 1).filter(|_| i < 5000 && i % 2 == 1)
+                );
+            }
+        }
+    }
+
+    // 启用hash32时H2取自32位hash的最高7位
+    assert_eq!(table_hash(&Narrow(false), 0x1234_5678), 0x1234_5678);
+    assert_eq!(
+        table_hash(&Narrow(true), 0xffff_0000_1234_5678),
+        0x1234_5678_1234_5678
+    );
+}
+
+#[test]
+fn test_seeded_spec() {
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let key = &42u64 as *const u64 as *const u8;
+    let a = SeededSpec::with_seed(spec.clone(), 1);
+    let b = SeededSpec::with_seed(spec.clone(), 2);
+    // 相同的种子得到相同的hash, 不同的种子得到不同的hash与spec标识
+    assert_eq!(
+        a.hash(key),
+        SeededSpec::with_seed(spec.clone(), 1).hash(key)
+    );
+    assert_ne!(a.hash(key), b.hash(key));
+    assert_ne!(a.hash(key), spec.hash(key));
+    assert_ne!(a.spec_id(), b.spec_id());
+    assert_eq!(a.seed(), 1);
+
+    let mut hashes = [0; 3];
+    let keys = [1u64, 2, 3];
+    let ptrs = keys.map(|k| &keys[k as usize - 1] as *const u64 as *const u8);
+    a.hash_many(&ptrs, &mut hashes);
+    for (ptr, hash) in ptrs.iter().zip(hashes) {
+        assert_eq!(a.hash(*ptr), hash);
+    }
+
+    let mut tables = [a, b].map(|spec| RawTable2::new(0, spec, Global).unwrap());
+    for table in &mut tables {
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..1000 {
+                map.insert(&i, i + 1);
+            }
+            for i in 0..2000 {
+                assert_eq!(map.get(&i).copied(), Some(i + 1).filter(|_| i < 1000));
+            }
+        }
+    }
+    assert!(!tables[0].is_compatible(&tables[1]));
+    let order = |table: &RawTable2<SeededSpec<_>>| {
+        table
+            .iter()
+            .map(|(key, _)| unsafe { *(key as *const u64) })
+            .collect::<Vec<_>>()
+    };
+    assert_ne!(order(&tables[0]), order(&tables[1]));
+
+    // 随机种子
+    #[cfg(feature = "default-hasher")]
+    let seeds: std::collections::HashSet<u64> = (0..8)
+        .map(|_| SeededSpec::new(spec.clone()).seed())
+        .collect();
+    #[cfg(feature = "default-hasher")]
+    assert!(seeds.len() > 1);
+}
+
+#[test]
+fn test_small_table_scan() {
+    // 记录hash的调用次数
+    struct Counting(Rc<Cell<usize>>);
+    impl EntrySpec for Counting {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            self.0.set(self.0.get() + 1);
+            unsafe { *(entry as *const u64) }.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+    }
+
+    let hashes = Rc::new(Cell::new(0));
+    let mut table = RawTable2::new(0, Counting(hashes.clone()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..8 {
+            map.insert(&i, i * 2);
+        }
+        // 小table的查找不计算hash
+        hashes.set(0);
+        for i in 0..16 {
+            assert_eq!(map.get(&i).copied(), Some(i * 2).filter(|_| i < 8));
+        }
+        map.delete(&3);
+        assert_eq!(map.get(&3), None);
+        // spec-validation会额外调用hash校验找到的Key
+        #[cfg(not(feature = "spec-validation"))]
+        assert_eq!(hashes.get(), 0);
+
+        // entries较多时恢复hash查找
+        for i in 8..100 {
+            map.insert(&i, i * 2);
+        }
+        hashes.set(0);
+        assert_eq!(map.get(&50), Some(&100));
+        #[cfg(not(feature = "spec-validation"))]
+        assert_eq!(hashes.get(), 1);
+    }
+}
+
+#[test]
+#[cfg(feature = "ffi")]
+fn test_ffi() {
+    use super::ffi::*;
+    use core::ffi::c_void;
+    use core::ptr;
+
+    unsafe extern "C" fn hash(ctx: *mut c_void, key: *const u8) -> u64 {
+        *(ctx as *mut usize) += 1;
+        (*(key as *const u64)).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+    unsafe extern "C" fn equals(_ctx: *mut c_void, key1: *const u8, key2: *const u8) -> bool {
+        *(key1 as *const u64) == *(key2 as *const u64)
+    }
+    unsafe extern "C" fn assign_value(_ctx: *mut c_void, value: *mut u8, v: *const u8) {
+        *(value as *mut u32) = *(v as *const u32) + 1;
+    }
+
+    let mut hashes = 0usize;
+    let fns = RawSpecFns {
+        ctx: &mut hashes as *mut usize as *mut c_void,
+        hash,
+        equals,
+        assign_key: None,
+        assign_value: None,
+    };
+    let layout = rawtable_entry_layout(16, 8, 8);
+    assert!(rawtable_new(rawtable_entry_layout(16, 8, 3), fns, 0).is_null());
+    unsafe {
+        let table = rawtable_new(layout, fns, 0);
+        assert!(!table.is_null());
+        for i in 0..100u64 {
+            assert!(rawtable_set(
+                table,
+                &i as *const u64 as *const u8,
+                &(i * 2) as *const u64 as *const u8
+            ));
+        }
+        assert!(hashes > 0);
+        assert_eq!(rawtable_len(table), 100);
+        for i in 0..200u64 {
+            let value = rawtable_get(table, &i as *const u64 as *const u8);
+            if i < 100 {
+                assert_eq!(*(value as *const u64), i * 2);
+            } else {
+                assert!(value.is_null());
+            }
+        }
+        assert!(rawtable_delete(table, &5u64 as *const u64 as *const u8));
+        assert!(!rawtable_delete(table, &5u64 as *const u64 as *const u8));
+
+        let (mut cursor, mut key, mut value) = (0, ptr::null(), ptr::null());
+        let mut sum = 0;
+        while rawtable_iter_next(table, &mut cursor, &mut key, &mut value) {
+            assert_eq!(*(value as *const u64), *(key as *const u64) * 2);
+            sum += *(key as *const u64);
+        }
+        assert_eq!(sum, 99 * 100 / 2 - 5);
+        assert!(!rawtable_iter_next(
+            table,
+            &mut cursor,
+            &mut key,
+            &mut value
+        ));
+        rawtable_free(table);
+        rawtable_free(ptr::null_mut());
+
+        // 通过回调写入value
+        let fns = RawSpecFns {
+            assign_value: Some(assign_value),
+            ..fns
+        };
+        let table = rawtable_new(rawtable_entry_layout(16, 8, 8), fns, 4);
+        rawtable_set(
+            table,
+            &1u64 as *const u64 as *const u8,
+            &7u32 as *const u32 as *const u8,
+        );
+        let value = rawtable_get(table, &1u64 as *const u64 as *const u8);
+        assert_eq!(*(value as *const u32), 8);
+        rawtable_free(table);
+    }
+}
+
+#[test]
+#[cfg(feature = "ffi")]
+fn test_ffi_vtable() {
+    use super::ffi::*;
+    use core::ffi::c_void;
+
+    unsafe extern "C" fn hash(_ctx: *mut c_void, key: *const u8) -> u64 {
+        u64::from(*key)
+    }
+    unsafe extern "C" fn equals(_ctx: *mut c_void, key1: *const u8, key2: *const u8) -> bool {
+        *key1 == *key2
+    }
+
+    let fns = RawSpecFns {
+        ctx: core::ptr::null_mut(),
+        hash,
+        equals,
+        assign_key: None,
+        assign_value: None,
+    };
+    let object = rawtable_new_object(rawtable_entry_layout(2, 1, 1), fns, 0);
+    assert!(!object.table.is_null());
+    assert_eq!(object.vtable, rawtable_vtable());
+    unsafe {
+        let vtable = &*object.vtable;
+        assert_eq!(vtable.abi_version, RAWTABLE_ABI_VERSION);
+        assert_eq!(vtable.size as usize, core::mem::size_of::<RawTableVTable>());
+        for i in 0..=255u8 {
+            assert!((vtable.set)(object.table, &i, &i.wrapping_add(1)));
+        }
+        assert_eq!((vtable.len)(object.table), 256);
+        assert_eq!(*(vtable.get)(object.table, &9), 10);
+        assert!((vtable.delete)(object.table, &9));
+        assert!((vtable.get)(object.table, &9).is_null());
+        let (mut cursor, mut key, mut value) = (0, core::ptr::null(), core::ptr::null());
+        let mut count = 0;
+        while (vtable.iter_next)(object.table, &mut cursor, &mut key, &mut value) {
+            assert_eq!(*value, (*key).wrapping_add(1));
+            count += 1;
+        }
+        assert_eq!(count, 255);
+        (vtable.destroy)(object.table);
+
+        let table = (vtable.create)(rawtable_entry_layout(2, 3, 1), fns, 0);
+        assert!(table.is_null());
+    }
+}
+
+#[test]
+fn test_lookup_cache() {
+    // 所有Key的hash相同, 记录equals的调用次数
+    struct Colliding(Rc<Cell<usize>>);
+    impl EntrySpec for Colliding {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u64, u64)>()
+        }
+        fn hash(&self, _entry: *const u8) -> u64 {
+            0x1234_5678_9abc_def0
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            self.0.set(self.0.get() + 1);
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+    }
+
+    let equals = Rc::new(Cell::new(0));
+    let mut table = RawTable2::new(0, Colliding(equals.clone()), Global).unwrap();
+    assert!(!table.has_lookup_cache());
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..64 {
+            map.insert(&i, i * 2);
+        }
+        // 未开启时每次查找都沿探测序列比较Key
+        equals.set(0);
+        assert_eq!(map.get(&63), Some(&126));
+        let probe = equals.get();
+        assert!(probe > 1);
+        equals.set(0);
+        assert_eq!(map.get(&63), Some(&126));
+        assert_eq!(equals.get(), probe);
+    }
+
+    table.set_lookup_cache(true);
+    assert!(table.has_lookup_cache());
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&63), Some(&126));
+        // 命中缓存时只比较一次Key
+        #[cfg(not(feature = "spec-validation"))]
+        {
+            equals.set(0);
+            assert_eq!(map.get(&63), Some(&126));
+            assert_eq!(equals.get(), 1);
+        }
+        // hash相同的其他Key与缓存的槽位不相等, 回退到探测
+        assert_eq!(map.get(&10), Some(&20));
+        assert_eq!(map.get(&100), None);
+        // 缓存的槽位被删除后不再命中
+        map.delete(&10);
+        assert_eq!(map.get(&10), None);
+        map.insert(&10, 30);
+        assert_eq!(map.get(&10), Some(&30));
+        assert_eq!(map.get(&10), Some(&30));
+    }
+    // 扩容与原地整理移动entries之后缓存失效
+    table.compact();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 64..1000 {
+            map.insert(&i, i * 2);
+        }
+        for i in 11..1000 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+    }
+    table.reset();
+    assert_eq!(unsafe { table.as_map::<u64, u64>().get(&20) }, None);
+    table.set_lookup_cache(false);
+    assert!(!table.has_lookup_cache());
+}
+
+#[test]
+fn test_cached_hash() {
+    // entry为`(u64, u64)`, 之后缓存8字节的hash
+    const LAYOUT: EntryLayout = EntryLayout::of::<u64, u64>().with_cached_hash();
+    struct Cached(u64, Rc<Cell<usize>>);
+    impl EntrySpec for Cached {
+        fn layout(&self) -> Layout {
+            LAYOUT.layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            self.1.set(self.1.get() + 1);
+            (unsafe { *(entry as *const u64) } ^ self.0).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn cached_hash(&self) -> Option<usize> {
+            LAYOUT.cached_hash()
+        }
+    }
+
+    let hashes = Rc::new(Cell::new(0));
+    let mut table = RawTable2::new(0, Cached(0, hashes.clone()), Global).unwrap();
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        // 每个Key只在写入时计算一次hash, 扩容直接使用缓存
+        for i in 0..1000 {
+            map.insert(&i, i * 2);
+        }
+        #[cfg(not(feature = "spec-validation"))]
+        assert_eq!(hashes.get(), 1000);
+        for i in 0..1000 {
+            if i % 3 == 0 {
+                map.delete(&i);
+            }
+        }
+    }
+
+    // 回收tombstone同样使用缓存
+    hashes.set(0);
+    table.compact();
+    #[cfg(not(feature = "spec-validation"))]
+    assert_eq!(hashes.get(), 0);
+
+    // 更换spec时重新计算并缓存hash
+    table
+        .rebuild_in_place(Cached(0x5555, hashes.clone()))
+        .ok()
+        .unwrap();
+    assert_eq!(hashes.get(), 666);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 1000..2000 {
+            map.insert(&i, i * 2);
+        }
+        for i in 0..2000 {
+            assert_eq!(
+                map.get(&i).copied(),
+                Some(i * 2).filter(|_| i >= 1000 || i % 3 != 0)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cached_hash_digest() {
+    // hash的低40位全为0, 所有Key从同一个槽位开始探测且H2相同, 只能依靠缓存的hash区分
+    const LAYOUT: EntryLayout = EntryLayout::of::<u64, u64>().with_cached_hash();
+    struct Digest(Rc<Cell<usize>>);
+    impl EntrySpec for Digest {
+        fn layout(&self) -> Layout {
+            LAYOUT.layout()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u64) << 40 }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            self.0.set(self.0.get() + 1);
+            unsafe { *(entry1 as *const u64) == *(entry2 as *const u64) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u64) = *(k as *const u64) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(8) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u64) = *(v as *const u64) }
+        }
+        fn cached_hash(&self) -> Option<usize> {
+            LAYOUT.cached_hash()
+        }
+    }
+
+    let equals = Rc::new(Cell::new(0));
+    for n in [4u64, 64] {
+        let mut table = RawTable2::new(0, Digest(equals.clone()), Global).unwrap();
+        unsafe {
+            let mut map = table.as_map::<u64, u64>();
+            for i in 0..n {
+                map.insert(&i, i + 1);
+            }
+            // 写入新Key时不需要与已有Key比较
+            #[cfg(not(feature = "spec-validation"))]
+            assert_eq!(equals.get(), 0);
+
+            equals.set(0);
+            for i in n..n * 2 {
+                assert_eq!(map.get(&i), None);
+            }
+            assert_eq!(equals.get(), 0);
+            for i in 0..n {
+                assert_eq!(map.get(&i).copied(), Some(i + 1));
+            }
+            #[cfg(not(feature = "spec-validation"))]
+            assert_eq!(equals.get(), n as usize);
+        }
+        equals.set(0);
+    }
+}
+
+#[test]
+fn test_group_backend() {
+    use crate::control::GroupBackend;
+
+    assert_eq!(GroupBackend::group_width(), Group::WIDTH);
+    let expected = if cfg!(miri) {
+        GroupBackend::Generic
+    } else if cfg!(all(
+        feature = "avx2-group",
+        target_arch = "x86_64",
+        target_feature = "avx2"
+    )) {
+        GroupBackend::Avx2
+    } else if cfg!(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    )) {
+        GroupBackend::Sse2
+    } else if cfg!(all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        target_endian = "little"
+    )) {
+        GroupBackend::Neon
+    } else if cfg!(all(target_arch = "wasm32", target_feature = "simd128")) {
+        GroupBackend::Simd128
+    } else {
+        GroupBackend::current()
+    };
+    assert_eq!(GroupBackend::current(), expected);
+}
+
+#[test]
+fn test_inline_raw_map() {
+    use super::inline::InlineRawMap;
+
+    // 64字节内联存放4个`(u64, u64)`
+    let spec = HasherSpec::<u64, u64, _>::new(FnvBuildHasher::default());
+    let mut map = InlineRawMap::<_, 64>::new(spec, Global).unwrap();
+    assert_eq!(map.inline_capacity(), 4);
+    unsafe {
+        for i in 0..4u64 {
+            let value = i * 2;
+            map.try_insert(
+                &i as *const u64 as *const u8,
+                &value as *const u64 as *const u8,
+            )
+            .unwrap();
+        }
+        let value = 100u64;
+        map.try_insert(
+            &3u64 as *const u64 as *const u8,
+            &value as *const u64 as *const u8,
+        )
+        .unwrap();
+        assert!(map.is_inline());
+        assert!(map.as_table().is_none());
+        assert_eq!(map.len(), 4);
+        let get = |map: &InlineRawMap<_, 64>, key: u64| {
+            map.access(&key as *const u64 as *const u8)
+                .map(|value| *(value as *const u64))
+        };
+        assert_eq!(get(&map, 1), Some(2));
+        assert_eq!(get(&map, 3), Some(100));
+        assert_eq!(get(&map, 4), None);
+
+        // 删除后空出的位置可以继续内联写入
+        map.delete(&0u64 as *const u64 as *const u8);
+        assert_eq!(get(&map, 0), None);
+        let value = map.try_assign(&10u64 as *const u64 as *const u8).unwrap();
+        *(value as *mut u64) = 20;
+        assert!(map.is_inline());
+        let mut sum = 0;
+        map.for_each(|_, value| sum += *(value as *const u64));
+        assert_eq!(sum, 2 + 4 + 100 + 20);
+
+        // 超出内联容量后迁移到swiss table
+        let value = 0u64;
+        map.try_insert(
+            &11u64 as *const u64 as *const u8,
+            &value as *const u64 as *const u8,
+        )
+        .unwrap();
+        assert!(!map.is_inline());
+        assert_eq!(map.len(), 5);
+        assert!(map.as_table().unwrap().alloc_stats().allocations() > 0);
+        for (key, value) in [(1, 2), (2, 4), (3, 100), (10, 20), (11, 0)] {
+            assert_eq!(get(&map, key), Some(value));
+        }
+        map.delete(&1u64 as *const u64 as *const u8);
+        assert_eq!(get(&map, 1), None);
+        let table = map.into_table().unwrap();
+        assert_eq!(table.len(), 4);
+    }
+
+    // 需要payload的Key直接迁移
+    let mut map = InlineRawMap::<_, 128>::new(StrKey, Global).unwrap();
+    assert_eq!(map.inline_capacity(), 5);
+    unsafe {
+        let key: &[u8] = b"key";
+        let value = 1u64;
+        map.try_insert(
+            &key as *const &[u8] as *const u8,
+            &value as *const u64 as *const u8,
+        )
+        .unwrap();
+        assert!(!map.is_inline());
+        assert!(map.access(&key as *const &[u8] as *const u8).is_some());
+    }
+}
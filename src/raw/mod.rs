@@ -11,12 +11,39 @@ use core::ptr::NonNull;
 use core::slice;
 use core::{hint, ptr};
 
+pub mod aggregate;
 mod alloc;
+pub mod alloc_stats;
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+pub mod arena;
+pub mod columnar;
+pub mod debug;
+pub mod entry_layout;
+#[cfg(feature = "epoch")]
+pub mod epoch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod incremental;
+pub mod inline;
+#[cfg(all(target_feature = "avx2", target_arch = "x86_64", not(miri)))]
+mod int_hash_avx2;
+pub mod journal;
+mod lookup_cache;
+#[cfg(feature = "madvise")]
+pub mod madvise;
 pub mod map;
-#[cfg(test)]
+#[cfg(all(test, feature = "raw-map-infallible"))]
 mod map_test;
-
-#[cfg(test)]
+mod payload;
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
+pub mod pool;
+pub mod seqlock;
+pub mod shared;
+pub mod snapshot;
+pub mod spec;
+pub mod striped;
+
+#[cfg(any(feature = "nightly", feature = "allocator-api2"))]
 pub(crate) use self::alloc::AllocError;
 pub(crate) use self::alloc::{do_alloc, Allocator, Global};
 
@@ -130,9 +157,14 @@ fn capacity_to_buckets(cap: usize, table_layout: TableLayout) -> Option<usize> {
         // so anything with alignment over the Group::WIDTH won't hit this edge
         // case.
 
-        // This is brittle, e.g. if we ever add 32 byte groups, it will select
-        // 3 regardless of the table_layout.size.
+        // With 32 byte groups a table of 1 byte items needs 32 buckets, which
+        // is past the small table sizes below.
+        if Group::WIDTH == 32 && table_layout.size <= 1 {
+            return Some(32);
+        }
         let min_cap = match (Group::WIDTH, table_layout.size) {
+            (32, 2..=3) => 14,
+            (32, 4..=7) => 7,
             (16, 0..=1) => 14,
             (16, 2..=3) => 7,
             (8, 0..=1) => 7,
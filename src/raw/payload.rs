@@ -0,0 +1,85 @@
+use super::{do_alloc, Allocator, Fallibility, Layout, NonNull, TryReserveError};
+use crate::alloc::vec::Vec;
+use core::cmp;
+
+/// 单个chunk的最小字节数
+const PAYLOAD_CHUNK_SIZE: usize = 4096;
+
+///
+/// table私有的变长payload内存池, 按chunk分配且从不移动已分配的内存,
+/// 因此entry中记录的payload地址在`clear`或table销毁之前始终有效。
+///
+/// 被删除entry的payload不会单独回收, 其内存在`clear`时统一释放。
+///
+pub(crate) struct PayloadArena {
+    /// 已分配的chunks, 最后一个为当前正在使用的chunk
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    /// 当前chunk中已使用的字节数
+    used: usize,
+}
+
+impl PayloadArena {
+    pub(crate) const fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            used: 0,
+        }
+    }
+
+    ///
+    /// 从内存池中分配`size`字节的内存(按字节对齐), 当前chunk不足时向`alloc`申请新的chunk
+    ///
+    pub(crate) fn alloc<A: Allocator>(
+        &mut self,
+        alloc: &A,
+        size: usize,
+    ) -> Result<NonNull<u8>, TryReserveError> {
+        if let Some(&(chunk, layout)) = self.chunks.last() {
+            if layout.size() - self.used >= size {
+                let ptr = unsafe { NonNull::new_unchecked(chunk.as_ptr().add(self.used)) };
+                self.used += size;
+                return Ok(ptr);
+            }
+        }
+
+        let chunk_size = cmp::max(size, PAYLOAD_CHUNK_SIZE);
+        let layout = match Layout::array::<u8>(chunk_size) {
+            Ok(layout) => layout,
+            Err(_) => return Err(Fallibility::Fallible.capacity_overflow()),
+        };
+        let chunk = match do_alloc(alloc, layout) {
+            Ok(chunk) => chunk,
+            Err(_) => return Err(Fallibility::Fallible.alloc_err(layout)),
+        };
+        self.chunks.push((chunk, layout));
+        self.used = size;
+        Ok(chunk)
+    }
+
+    ///
+    /// 归还最近一次`alloc`分配的`ptr`(共`size`字节), 用于写入Key失败时回滚; 期间不能有其他分配
+    ///
+    pub(crate) fn release_last(&mut self, ptr: NonNull<u8>, size: usize) {
+        debug_assert!(self.chunks.last().map_or(false, |&(chunk, _)| {
+            chunk.as_ptr().wrapping_add(self.used - size) == ptr.as_ptr()
+        }));
+        self.used -= size;
+    }
+
+    /// 获取内存池当前持有的chunks的总字节数
+    pub(crate) fn capacity(&self) -> usize {
+        self.chunks.iter().map(|(_, layout)| layout.size()).sum()
+    }
+
+    ///
+    /// 释放内存池中的所有chunks, 之前分配的payload地址全部失效
+    ///
+    /// `alloc`必须与分配这些chunks时使用的Allocator相同
+    ///
+    pub(crate) unsafe fn free<A: Allocator>(&mut self, alloc: &A) {
+        for (chunk, layout) in self.chunks.drain(..) {
+            alloc.deallocate(chunk, layout);
+        }
+        self.used = 0;
+    }
+}
@@ -0,0 +1,119 @@
+use super::{AllocError, Allocator, Layout, NonNull};
+use crate::alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ptr;
+
+///
+/// 回收内存块的Allocator包装, `&BucketPool<A>`实现了`Allocator`, 可在多个`RawTable2`之间共享。
+///
+/// table扩容、收缩或rehash时释放的旧bucket数组(以及大entry模式的value、payload chunks等内存块)
+/// 不会立即归还给内部的Allocator, 而是按原`Layout`放入空闲列表, 之后请求相同`Layout`的分配直接复用;
+/// 空闲列表持有的字节数超过上限时, 多出的内存块直接归还给内部的Allocator。
+///
+/// bucket数量总是2的幂, 因此相同spec的table反复扩缩容时很容易命中相同的`Layout`
+///
+pub struct BucketPool<A: Allocator> {
+    inner: A,
+    /// 空闲的内存块及其`Layout`
+    free: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// 空闲列表当前持有的字节数
+    pooled: Cell<usize>,
+    /// 空闲列表最多持有的字节数
+    max_pooled: usize,
+    /// 由空闲列表满足的分配次数
+    reused: Cell<usize>,
+}
+
+// 空闲的内存块只归此pool所有, 可以随pool一同转移到其他线程
+unsafe impl<A: Allocator + Send> Send for BucketPool<A> {}
+
+impl<A: Allocator> BucketPool<A> {
+    /// 构造空的pool, 空闲列表最多持有`max_pooled`字节
+    pub const fn new(inner: A, max_pooled: usize) -> Self {
+        Self {
+            inner,
+            free: RefCell::new(Vec::new()),
+            pooled: Cell::new(0),
+            max_pooled,
+            reused: Cell::new(0),
+        }
+    }
+
+    /// 获取内部的Allocator
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    /// 获取空闲列表当前持有的字节数
+    pub fn pooled_bytes(&self) -> usize {
+        self.pooled.get()
+    }
+
+    /// 获取由空闲列表满足的分配次数
+    pub fn reused(&self) -> usize {
+        self.reused.get()
+    }
+
+    /// 将空闲列表中的所有内存块归还给内部的Allocator
+    pub fn trim(&self) {
+        for (block, layout) in self.free.borrow_mut().drain(..) {
+            unsafe { self.inner.deallocate(block, layout) };
+        }
+        self.pooled.set(0);
+    }
+
+    /// 从空闲列表中取出`Layout`完全相同的内存块, 优先复用最近放入的内存块
+    fn take(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let mut free = self.free.borrow_mut();
+        let pos = free.iter().rposition(|&(_, l)| l == layout)?;
+        let (block, _) = free.swap_remove(pos);
+        self.pooled.set(self.pooled.get() - layout.size());
+        self.reused.set(self.reused.get() + 1);
+        Some(block)
+    }
+
+    /// 将内存块放入空闲列表, 超过上限或列表无法扩容时返回`false`
+    fn give(&self, block: NonNull<u8>, layout: Layout) -> bool {
+        let pooled = match self.pooled.get().checked_add(layout.size()) {
+            Some(pooled) if pooled <= self.max_pooled => pooled,
+            _ => return false,
+        };
+        let mut free = self.free.borrow_mut();
+        if free.try_reserve(1).is_err() {
+            return false;
+        }
+        free.push((block, layout));
+        self.pooled.set(pooled);
+        true
+    }
+}
+
+impl<A: Allocator> Drop for BucketPool<A> {
+    fn drop(&mut self) {
+        self.trim();
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for &BucketPool<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() != 0 {
+            if let Some(block) = self.take(layout) {
+                return Ok(unsafe {
+                    NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(
+                        block.as_ptr(),
+                        layout.size(),
+                    ))
+                });
+            }
+        }
+        self.inner.allocate(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 || !self.give(ptr, layout) {
+            self.inner.deallocate(ptr, layout);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::map::{EntrySpec, InsertError, RawTable2};
+use super::{Allocator, Global};
+use crate::scopeguard::guard;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::sync::atomic::{self, AtomicUsize, Ordering};
+
+///
+/// 以序列号(seqlock)校验的乐观读取: 写入之间通过序列号串行执行, 写入期间序列号为奇数;
+/// 读取不加锁, 读取前后序列号不一致时说明期间发生了写入, 丢弃结果并重试。
+///
+/// 只接受从不重新分配bucket数组且value内嵌在entry中的固定容量table, 保证读取期间访问的内存不会被释放。
+/// 适用于读多写少、由宿主自行管理并发的场景
+///
+pub struct SeqRawMap<E: EntrySpec, A: Allocator = Global> {
+    table: UnsafeCell<RawTable2<E, A>>,
+    /// 每次写入前后各加1, 为奇数时写入正在进行
+    seq: AtomicUsize,
+    /// 每次写入结束前发布的entries数量, 读取时不必访问table
+    len: AtomicUsize,
+}
+
+// 写入之间互斥, 读取只在`read_optimistic`中访问table, spec的hook会被多个线程同时调用
+unsafe impl<E: EntrySpec + Send + Sync, A: Allocator + Send + Sync> Sync for SeqRawMap<E, A> {}
+
+impl<E: EntrySpec, A: Allocator> SeqRawMap<E, A> {
+    ///
+    /// 包装`table`, table不是固定容量模式(见`RawTable2::with_fixed_capacity`)或使用大entry模式时原样返回
+    ///
+    #[allow(clippy::result_large_err)] // 原样返回table, 以便caller继续使用
+    pub fn new(table: RawTable2<E, A>) -> Result<Self, RawTable2<E, A>> {
+        if !table.is_fixed() || table.out_of_line().is_some() {
+            return Err(table);
+        }
+        Ok(Self {
+            len: AtomicUsize::new(table.len()),
+            table: UnsafeCell::new(table),
+            seq: AtomicUsize::new(0),
+        })
+    }
+
+    /// 获取当前序列号, 为奇数时写入正在进行
+    pub fn generation(&self) -> usize {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    ///
+    /// 不加锁地查找`key: &K`并以value指针(不存在时为`None`)调用`f`, 读取期间发生写入时重试,
+    /// 因此`f`可能被调用多次, 只有最后一次的结果被返回。
+    ///
+    /// # Safety
+    ///
+    /// 与写入并发的尝试中`f`可能读到不完整的value, `f`只能拷贝value而不能依赖其内容(例如解引用其中的指针);
+    /// spec的hook同样需要容忍不完整的entry, 且不得访问`clear`会释放的payload
+    ///
+    pub unsafe fn read_optimistic<R>(
+        &self,
+        key: *const u8,
+        mut f: impl FnMut(Option<*const u8>) -> R,
+    ) -> R {
+        self.optimistic(|table| f((*table).access(key)))
+    }
+
+    ///
+    /// 不加锁地以table指针调用`f`, 期间发生写入时重试。
+    ///
+    /// 写入方可能同时持有`&mut RawTable2`, 因此这里只传递裸指针, 由`f`按`read_optimistic`的约定访问table
+    ///
+    unsafe fn optimistic<R>(&self, mut f: impl FnMut(*const RawTable2<E, A>) -> R) -> R {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq & 1 == 1 {
+                hint::spin_loop();
+                continue;
+            }
+            let result = f(self.table.get());
+            // 之前的读取不能被重排到再次读取序列号之后
+            atomic::fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == seq {
+                return result;
+            }
+        }
+    }
+
+    ///
+    /// 持有写入权并以table调用`f`, 期间乐观读取都会重试; 写入之间串行执行
+    ///
+    /// # Safety
+    ///
+    /// `f`不得释放或重新分配读取可能访问的内存, 例如调用`reset`、`clear`或更换allocator
+    ///
+    pub unsafe fn write<R>(&self, f: impl FnOnce(&mut RawTable2<E, A>) -> R) -> R {
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq & 1 == 1 {
+                hint::spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+            match self
+                .seq
+                .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        // 之后的写入不能被重排到序列号变为奇数之前
+        atomic::fence(Ordering::Release);
+        let _unlock = guard(self, |this| {
+            // `f`结束(包括panic)后发布entries数量, 此时已不存在对table的`&mut`
+            this.len
+                .store(unsafe { (*this.table.get()).len() }, Ordering::Release);
+            this.seq.fetch_add(1, Ordering::Release);
+        });
+        f(&mut *self.table.get())
+    }
+
+    /// 写入`key: &K`与`value: &V`, 固定容量已满且Key不存在时返回错误
+    pub unsafe fn try_insert(&self, key: *const u8, value: *const u8) -> Result<(), InsertError> {
+        self.write(|table| table.try_insert(key, value))
+    }
+
+    /// 删除`key: &K`
+    pub unsafe fn delete(&self, key: *const u8) {
+        self.write(|table| table.delete(key))
+    }
+
+    /// 获取最近一次写入完成时的entries数量
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 取出内部的table
+    pub fn into_inner(self) -> RawTable2<E, A> {
+        self.table.into_inner()
+    }
+}
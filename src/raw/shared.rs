@@ -0,0 +1,1412 @@
+use super::entry_layout::Column;
+use super::map::{spec_hash, with_normalized_key, EntrySpec, RawTable2};
+use super::{
+    bucket_mask_to_capacity, invalid_mut, mem, ptr, slice, Allocator, FullBucketsIndices, Group,
+    Layout, NonNull, RawTableInner, TableLayout, Tag, TagSliceExt,
+};
+use crate::alloc::vec::Vec;
+use crate::util::addr;
+#[cfg(feature = "std")]
+use crate::TryReserveError;
+use core::cmp;
+use core::iter::FusedIterator;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+/// 共享内存区域与快照起始处的标识
+const SHARED_MAGIC: [u8; 8] = *b"RAWTABLE";
+
+///
+/// 字节序标记: 元数据总是以小端字节序写入, 此标记记录bucket数组中entries的字节序,
+/// 小端entries为`ENDIAN_MARK`, 大端entries为`ENDIAN_MARK.swap_bytes()`
+///
+const ENDIAN_MARK: u32 = 0x0102_0304;
+
+/// 本平台字节序的entries对应的字节序标记
+const NATIVE_ENDIAN_MARK: u32 = if cfg!(target_endian = "big") {
+    ENDIAN_MARK.swap_bytes()
+} else {
+    ENDIAN_MARK
+};
+
+///
+/// 共享区域与快照格式的主版本号: 已有字段的含义或区域内存结构发生不兼容的变化时递增, 读取方只接受相同的主版本号
+///
+pub const SNAPSHOT_MAJOR: u16 = 1;
+
+///
+/// 共享区域与快照格式的次版本号: 只在元数据末尾(校验和之前)追加字段时递增, 读取方跳过不认识的字段,
+/// 因此主版本号相同时, 任意次版本号的写入方与读取方之间都可以互相读取
+///
+pub const SNAPSHOT_MINOR: u16 = 0;
+
+/// 元数据的固定前缀(标识、字节序标记、版本号与元数据字节数)的字节数, 其结构在所有版本中保持不变
+const HEADER_PREFIX: usize = 20;
+
+/// 当前版本写入的元数据字节数, 包括末尾的校验和
+const HEADER_SIZE: usize = mem::size_of::<SharedHeader>() + mem::size_of::<u64>();
+
+/// 可接受的元数据字节数上限, 防止损坏的元数据导致过量读取
+const MAX_HEADER_SIZE: usize = 4096;
+
+///
+/// 共享内存区域起始处的元数据, 之后是之后的版本追加的字段与元数据的校验和,
+/// 再之后(按bucket数组的对齐要求)紧跟bucket数组与ctrl字节。
+///
+/// 所有位置均以相对区域起始地址的偏移量记录, 因此区域可以被映射到不同进程中的任意地址;
+/// 各字段(包括末尾的校验和)以小端字节序编码, 内存中的值为本平台字节序
+///
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SharedHeader {
+    magic: [u8; 8],
+    endian: u32,
+    major: u16,
+    minor: u16,
+    /// 元数据的字节数, 包括之后的版本追加的字段与末尾的校验和
+    header_size: u32,
+    group_width: u32,
+    /// entry的字节数(即bucket步长)与对齐要求
+    entry_size: u64,
+    entry_align: u64,
+    /// 元数据区域的偏移量与字节数, 没有元数据区域时偏移量为`u64::MAX`
+    metadata_offset: u64,
+    metadata_size: u64,
+    bucket_mask: u64,
+    items: u64,
+    /// 导出时的剩余容量, 包含tombstone的影响
+    growth_left: u64,
+    /// ctrl字节相对区域起始地址的偏移量
+    ctrl_offset: u64,
+    /// 区域的有效字节数
+    total: u64,
+    /// spec的身份标识, 没有标识时为`u64::MAX`
+    spec_id: u64,
+    /// bucket数组区域(含之前的对齐填充)的校验和
+    bucket_checksum: u64,
+    /// ctrl字节的校验和
+    ctrl_checksum: u64,
+}
+
+/// 元数据校验失败的原因
+enum Invalid {
+    Mismatch(Mismatch),
+    /// 元数据与其校验和不一致
+    Corrupted,
+}
+
+impl From<Mismatch> for Invalid {
+    fn from(value: Mismatch) -> Self {
+        Self::Mismatch(value)
+    }
+}
+
+impl From<Invalid> for SharedError {
+    fn from(value: Invalid) -> Self {
+        match value {
+            Invalid::Mismatch(mismatch) => Self::Incompatible(mismatch),
+            Invalid::Corrupted => Self::Corrupted(SnapshotSection::Header),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Invalid> for SnapshotError {
+    fn from(value: Invalid) -> Self {
+        match value {
+            Invalid::Mismatch(mismatch) => Self::Incompatible(mismatch),
+            Invalid::Corrupted => Self::Corrupted(SnapshotSection::Header),
+        }
+    }
+}
+
+impl SharedHeader {
+    ///
+    /// 将各整数字段在本平台字节序与小端字节序之间转换, 小端平台上不变; 转换是对合的, 编码与解码共用
+    ///
+    fn swap_le(self) -> Self {
+        Self {
+            magic: self.magic,
+            endian: self.endian.to_le(),
+            major: self.major.to_le(),
+            minor: self.minor.to_le(),
+            header_size: self.header_size.to_le(),
+            group_width: self.group_width.to_le(),
+            entry_size: self.entry_size.to_le(),
+            entry_align: self.entry_align.to_le(),
+            metadata_offset: self.metadata_offset.to_le(),
+            metadata_size: self.metadata_size.to_le(),
+            bucket_mask: self.bucket_mask.to_le(),
+            items: self.items.to_le(),
+            growth_left: self.growth_left.to_le(),
+            ctrl_offset: self.ctrl_offset.to_le(),
+            total: self.total.to_le(),
+            spec_id: self.spec_id.to_le(),
+            bucket_checksum: self.bucket_checksum.to_le(),
+            ctrl_checksum: self.ctrl_checksum.to_le(),
+        }
+    }
+
+    /// 编码为当前版本的元数据, 末尾为之前所有字节的校验和
+    fn encode(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        let body = mem::size_of::<Self>();
+        let le = self.swap_le();
+        unsafe {
+            ptr::copy_nonoverlapping(&le as *const Self as *const u8, bytes.as_mut_ptr(), body)
+        };
+        let checksum = Checksum::of(&bytes[..body]);
+        bytes[body..].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    ///
+    /// 校验元数据的固定前缀(`prefix`至少为`HEADER_PREFIX`字节), 返回元数据的字节数
+    ///
+    fn header_size(prefix: &[u8]) -> Result<usize, Invalid> {
+        let field = |range: core::ops::Range<usize>| &prefix[range];
+        if field(0..8) != SHARED_MAGIC {
+            return Err(Mismatch::Magic.into());
+        }
+        let endian = u32::from_le_bytes(field(8..12).try_into().unwrap());
+        if endian != ENDIAN_MARK && endian != ENDIAN_MARK.swap_bytes() {
+            return Err(Invalid::Corrupted);
+        }
+        let major = u16::from_le_bytes(field(12..14).try_into().unwrap());
+        let minor = u16::from_le_bytes(field(14..16).try_into().unwrap());
+        if major != SNAPSHOT_MAJOR {
+            // 之前的版本在大端平台上以本机字节序写入整个元数据
+            if endian == ENDIAN_MARK.swap_bytes() && major.swap_bytes() == SNAPSHOT_MAJOR {
+                return Err(Mismatch::Endianness.into());
+            }
+            return Err(Mismatch::Version {
+                found: (major, minor),
+            }
+            .into());
+        }
+        let size = u32::from_le_bytes(field(16..20).try_into().unwrap()) as usize;
+        if !(HEADER_SIZE..=MAX_HEADER_SIZE).contains(&size) || size % 8 != 0 {
+            return Err(Invalid::Corrupted);
+        }
+        Ok(size)
+    }
+
+    ///
+    /// 解析全部`header_size`字节的元数据并比对末尾的校验和, 跳过之后的版本追加的字段
+    ///
+    fn decode(bytes: &[u8]) -> Result<Self, Invalid> {
+        let body = bytes.len() - mem::size_of::<u64>();
+        let checksum = u64::from_le_bytes(bytes[body..].try_into().unwrap());
+        if Checksum::of(&bytes[..body]) != checksum {
+            return Err(Invalid::Corrupted);
+        }
+        Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const Self) }.swap_le())
+    }
+
+    ///
+    /// 校验元数据由Group宽度相同的平台上, entry内存结构与spec标识均与`spec`一致的table写入,
+    /// 返回区域的内存结构
+    ///
+    fn region_for<E: EntrySpec>(&self, spec: &E) -> Result<(usize, usize, usize, usize), Mismatch> {
+        if self.group_width as usize != Group::WIDTH {
+            return Err(Mismatch::GroupWidth {
+                found: self.group_width as usize,
+                expected: Group::WIDTH,
+            });
+        }
+        let expected = spec.layout().pad_to_align();
+        let found = Layout::from_size_align(self.entry_size as usize, self.entry_align as usize)
+            .map_err(|_| Mismatch::Geometry)?;
+        if found != expected {
+            return Err(Mismatch::EntryLayout { found, expected });
+        }
+        let found = match self.metadata_offset {
+            u64::MAX => None,
+            offset => Some(Column::new(offset as usize, self.metadata_size as usize)),
+        };
+        if found != spec.metadata() {
+            return Err(Mismatch::Metadata {
+                found,
+                expected: spec.metadata(),
+            });
+        }
+        if self.spec_id != spec_id_of(spec) {
+            return Err(Mismatch::SpecId {
+                found: Some(self.spec_id).filter(|&id| id != u64::MAX),
+                expected: spec.spec_id(),
+            });
+        }
+        if spec.split_value().is_some()
+            || (spec.out_of_line_value().is_some() && !spec.zero_sized_value())
+        {
+            return Err(Mismatch::ValueMode);
+        }
+
+        let buckets = (self.bucket_mask as usize).wrapping_add(1);
+        if !buckets.is_power_of_two()
+            || self
+                .items
+                .checked_add(self.growth_left)
+                .map_or(true, |n| n > bucket_mask_to_capacity(buckets - 1) as u64)
+        {
+            return Err(Mismatch::Geometry);
+        }
+        let region = shared_layout(
+            TableLayout::from(expected),
+            buckets,
+            self.header_size as usize,
+        )
+        .ok_or(Mismatch::Geometry)?;
+        if self.total != region.0 as u64 || self.ctrl_offset != region.3 as u64 {
+            return Err(Mismatch::Geometry);
+        }
+        Ok(region)
+    }
+}
+
+///
+/// 快照或共享区域与读取方的运行配置(平台、格式版本与spec)不一致之处
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mismatch {
+    /// 不是快照或共享区域
+    Magic,
+    /// 格式的主版本号与`SNAPSHOT_MAJOR`不同
+    Version {
+        /// 写入方的主版本号与次版本号
+        found: (u16, u16),
+    },
+    ///
+    /// entries的字节序与本平台不同且无法转换: attach的共享区域只能原样访问, 或spec不支持`EntrySpec::swap_key_bytes`
+    /// 与`swap_value_bytes`; 之前的版本在大端平台上写入的快照同样如此
+    ///
+    Endianness,
+    /// 由Group宽度不同的平台(或不同的SIMD实现)写入
+    GroupWidth {
+        /// 写入方的Group宽度
+        found: usize,
+        /// 读取方的Group宽度
+        expected: usize,
+    },
+    /// entry的内存结构(按对齐要求填充后)不一致
+    EntryLayout {
+        /// 写入方的entry内存结构
+        found: Layout,
+        /// 读取方spec的entry内存结构
+        expected: Layout,
+    },
+    /// entry中的元数据区域不一致
+    Metadata {
+        /// 写入方的元数据区域
+        found: Option<Column>,
+        /// 读取方spec的元数据区域
+        expected: Option<Column>,
+    },
+    /// spec标识不一致
+    SpecId {
+        /// 写入方的spec标识
+        found: Option<u64>,
+        /// 读取方的spec标识
+        expected: Option<u64>,
+    },
+    /// 读取方的spec使用SoA value或大entry模式, 无法读取value内嵌在entry中的区域
+    ValueMode,
+    /// 元数据中的bucket数量、entries数量或偏移量无效
+    Geometry,
+}
+
+/// 导出或attach共享table时的错误
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SharedError {
+    /// table使用了分离模式、SoA value、大entry模式或payload, entries引用了进程内的其他内存
+    Unsupported,
+    /// 内存区域小于所需的字节数
+    TooSmall {
+        /// 所需的字节数
+        required: usize,
+    },
+    /// 内存区域的起始地址没有按所需的对齐要求对齐
+    Misaligned {
+        /// 所需的对齐要求
+        align: usize,
+    },
+    /// 内存区域由不兼容的平台、格式版本或spec写入
+    Incompatible(Mismatch),
+    /// 内存区域的某一部分与其校验和不一致
+    Corrupted(SnapshotSection),
+}
+
+/// 共享区域与快照中由各自的校验和覆盖的部分
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SnapshotSection {
+    /// 元数据
+    Header,
+    /// bucket数组
+    Buckets,
+    /// ctrl字节
+    Ctrl,
+}
+
+/// `RawTable2::read_snapshot`返回的错误
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// 读取失败, 快照被截断时为`io::ErrorKind::UnexpectedEof`
+    Io(io::Error),
+    /// caller给出的entry内存结构与`EntrySpec::layout`不一致
+    LayoutMismatch,
+    /// 快照由不兼容的平台、格式版本或spec写入
+    Incompatible(Mismatch),
+    /// 快照的某一部分与其校验和不一致, 已读入的内存均已释放
+    Corrupted(SnapshotSection),
+    /// table内存分配失败
+    Reserve(TryReserveError),
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for SnapshotError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<TryReserveError> for SnapshotError {
+    fn from(value: TryReserveError) -> Self {
+        Self::Reserve(value)
+    }
+}
+
+///
+/// 共享区域与快照各部分的校验和: 按8字节小端字依次混合, 最后混入总字节数。
+///
+/// 只用于检测截断、位翻转等意外损坏, 不能抵御有意的篡改
+///
+pub(super) struct Checksum {
+    state: u64,
+    /// 尚未凑满8字节的尾部
+    tail: [u8; 8],
+    tail_len: usize,
+    len: u64,
+}
+
+impl Checksum {
+    const fn new() -> Self {
+        Self {
+            state: 0x243f_6a88_85a3_08d3,
+            tail: [0; 8],
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    pub(super) fn of(bytes: &[u8]) -> u64 {
+        let mut checksum = Self::new();
+        checksum.update(bytes);
+        checksum.finish()
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+        if self.tail_len > 0 {
+            let n = cmp::min(8 - self.tail_len, bytes.len());
+            self.tail[self.tail_len..self.tail_len + n].copy_from_slice(&bytes[..n]);
+            self.tail_len += n;
+            bytes = &bytes[n..];
+            if self.tail_len < 8 {
+                return;
+            }
+            self.mix(u64::from_le_bytes(self.tail));
+            self.tail_len = 0;
+        }
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.mix(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let rest = words.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.tail_len = rest.len();
+    }
+
+    #[inline]
+    fn mix(&mut self, word: u64) {
+        self.state = (self.state ^ word)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .rotate_left(29);
+    }
+
+    fn finish(mut self) -> u64 {
+        if self.tail_len > 0 {
+            self.tail[self.tail_len..].fill(0);
+            self.mix(u64::from_le_bytes(self.tail));
+        }
+        self.mix(self.len);
+        self.state ^ (self.state >> 32)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for Checksum {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 以`inner`的ctrl字节调用`f`, 空table给出全部为EMPTY的ctrl字节
+unsafe fn with_ctrl_bytes<R>(inner: &RawTableInner, f: impl FnOnce(&[u8]) -> R) -> R {
+    if inner.is_empty_singleton() {
+        let mut ctrl = [0u8; Group::WIDTH + 1];
+        slice::from_raw_parts_mut(ctrl.as_mut_ptr().cast::<Tag>(), ctrl.len()).fill_empty();
+        f(&ctrl)
+    } else {
+        f(slice::from_raw_parts(
+            inner.ctrl(0).cast::<u8>(),
+            inner.num_ctrl_bytes(),
+        ))
+    }
+}
+
+///
+/// 计算bucket数量为`buckets`、元数据为`header_size`字节时共享区域的内存结构:
+/// (区域字节数, 对齐要求, bucket数组偏移量, ctrl字节偏移量)
+///
+fn shared_layout(
+    table_layout: TableLayout,
+    buckets: usize,
+    header_size: usize,
+) -> Option<(usize, usize, usize, usize)> {
+    let (layout, ctrl_offset) = table_layout.calculate_layout_for(buckets)?;
+    let align = layout.align().max(mem::align_of::<SharedHeader>());
+    let block = (header_size + layout.align() - 1) & !(layout.align() - 1);
+    let total = block.checked_add(layout.size())?;
+    Some((total, align, block, block + ctrl_offset))
+}
+
+fn spec_id_of<E: EntrySpec>(spec: &E) -> u64 {
+    spec.spec_id().unwrap_or(u64::MAX)
+}
+
+///
+/// 原地反转`entry`的字节序: Key与Value由spec转换, 缓存的hash由table转换, 元数据区域保持不变;
+/// spec不支持时返回`false`
+///
+unsafe fn swap_entry_bytes<E: EntrySpec + ?Sized>(spec: &E, entry: *mut u8) -> bool {
+    if !spec.swap_key_bytes(entry) {
+        return false;
+    }
+    if !spec.zero_sized_value() && !spec.swap_value_bytes(spec.access_value(entry) as *mut u8) {
+        return false;
+    }
+    if let Some(offset) = spec.cached_hash() {
+        let hash = entry.add(offset) as *mut u64;
+        hash.write_unaligned(hash.read_unaligned().swap_bytes());
+    }
+    true
+}
+
+/// 反转字节序时存放单个bucket的临时内存, 按entry的对齐要求对齐
+struct SwapBuffer<'t> {
+    spec: &'t dyn EntrySpec,
+    bytes: Vec<u8>,
+    /// bucket在`bytes`中的偏移量
+    offset: usize,
+    stride: usize,
+    /// 当前存放的bucket下标
+    index: Option<usize>,
+}
+
+impl<'t> SwapBuffer<'t> {
+    ///
+    /// 构造步长为`stride`的临时内存, 并以全为0的bucket试探spec能否反转字节序(只取决于spec), 不能时返回`None`
+    ///
+    fn new(spec: &'t dyn EntrySpec, stride: usize) -> Option<Self> {
+        let align = spec.layout().align();
+        let bytes = alloc::vec![0; stride + align - 1];
+        let offset = bytes.as_ptr().align_offset(align);
+        let mut buffer = Self {
+            spec,
+            bytes,
+            offset,
+            stride,
+            index: None,
+        };
+        unsafe { swap_entry_bytes(spec, buffer.bucket_mut().as_mut_ptr()) }.then_some(buffer)
+    }
+
+    fn bucket_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[self.offset..self.offset + self.stride]
+    }
+
+    /// 获取`inner`中槽位`index`(须为有效的bucket)反转字节序后的拷贝
+    unsafe fn swapped(&mut self, inner: &RawTableInner, index: usize) -> &[u8] {
+        if self.index != Some(index) {
+            let (spec, stride) = (self.spec, self.stride);
+            let bucket = self.bucket_mut().as_mut_ptr();
+            ptr::copy_nonoverlapping(inner.bucket_ptr(index, stride), bucket, stride);
+            swap_entry_bytes(spec, bucket);
+            self.index = Some(index);
+        }
+        &self.bytes[self.offset..self.offset + self.stride]
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 获取`export_shared`所需的内存区域字节数与对齐要求
+    ///
+    pub fn shared_size(&self) -> Result<(usize, usize), SharedError> {
+        let (inner, table_layout) = self.contiguous_block().ok_or(SharedError::Unsupported)?;
+        let (total, align, ..) = shared_layout(table_layout, inner.buckets(), HEADER_SIZE)
+            .ok_or(SharedError::Unsupported)?;
+        Ok((total, align))
+    }
+
+    ///
+    /// 将bucket数组与ctrl字节连同元数据拷贝到`dst`(例如共享内存段)中, 返回写入的字节数。
+    ///
+    /// 其他进程可以将同一区域映射到任意地址并通过`SharedTable::attach`只读查找, entries保持本平台的字节序;
+    /// 分离模式、SoA value、大entry模式以及使用过payload的table包含进程内的地址, 无法导出
+    ///
+    pub fn export_shared(&self, dst: &mut [u8]) -> Result<usize, SharedError> {
+        let (inner, table_layout) = self.contiguous_block().ok_or(SharedError::Unsupported)?;
+        let buckets = inner.buckets();
+        let (total, align, block, ctrl_offset) =
+            shared_layout(table_layout, buckets, HEADER_SIZE).ok_or(SharedError::Unsupported)?;
+        if dst.len() < total {
+            return Err(SharedError::TooSmall { required: total });
+        }
+        if addr(dst.as_ptr()) % align != 0 {
+            return Err(SharedError::Misaligned { align });
+        }
+
+        unsafe {
+            // 空bucket与对齐填充写入0, 不拷贝未初始化的内存
+            dst[block..ctrl_offset].fill(0);
+            if !inner.is_empty_singleton() {
+                let base = dst.as_mut_ptr();
+                let stride = table_layout.size;
+                for index in inner.full_buckets_indices() {
+                    ptr::copy_nonoverlapping(
+                        inner.bucket_ptr(index, stride),
+                        base.add(ctrl_offset - (index + 1) * stride),
+                        stride,
+                    );
+                }
+            }
+            with_ctrl_bytes(inner, |ctrl| dst[ctrl_offset..total].copy_from_slice(ctrl));
+            let header = self.shared_header(
+                inner,
+                table_layout,
+                NATIVE_ENDIAN_MARK,
+                Checksum::of(&dst[block..ctrl_offset]),
+                Checksum::of(&dst[ctrl_offset..total]),
+            );
+            dst[..HEADER_SIZE].copy_from_slice(&header.encode());
+        }
+        Ok(total)
+    }
+
+    ///
+    /// 构造描述`inner`的元数据, 区域的内存结构按`table_layout`计算, `endian`为entries字节序对应的标记
+    ///
+    fn shared_header(
+        &self,
+        inner: &RawTableInner,
+        table_layout: TableLayout,
+        endian: u32,
+        bucket_checksum: u64,
+        ctrl_checksum: u64,
+    ) -> SharedHeader {
+        let (total, _, _, ctrl_offset) =
+            shared_layout(table_layout, inner.buckets(), HEADER_SIZE).unwrap();
+        let spec = self.spec();
+        let metadata = spec.metadata();
+        SharedHeader {
+            magic: SHARED_MAGIC,
+            endian,
+            major: SNAPSHOT_MAJOR,
+            minor: SNAPSHOT_MINOR,
+            header_size: HEADER_SIZE as u32,
+            group_width: Group::WIDTH as u32,
+            entry_size: table_layout.size as u64,
+            entry_align: spec.layout().align() as u64,
+            metadata_offset: metadata.map_or(u64::MAX, |column| column.offset() as u64),
+            metadata_size: metadata.map_or(0, |column| column.size() as u64),
+            bucket_mask: inner.bucket_mask as u64,
+            items: inner.items as u64,
+            growth_left: inner.growth_left as u64,
+            ctrl_offset: ctrl_offset as u64,
+            total: total as u64,
+            spec_id: spec_id_of(spec),
+            bucket_checksum,
+            ctrl_checksum,
+        }
+    }
+}
+
+/// 快照写入与读取时每次批量处理的字节数
+#[cfg(feature = "std")]
+const SNAPSHOT_CHUNK: usize = 64 * 1024;
+
+/// 快照字节流中从某一位置开始的连续片段
+enum Run<'s> {
+    /// 直接来自table内存的字节
+    Bytes(&'s [u8]),
+    /// 空bucket或对齐填充, 共给定数量的0
+    Zeros(usize),
+}
+
+///
+/// `RawTable2::snapshot_chunks`返回的导出器, 按顺序给出与`write_snapshot`完全一致的快照字节流,
+/// 直接从table内存读取而不在内存中生成整个快照, 每次只需要caller提供的缓冲区。
+///
+/// 作为迭代器时依次给出`chunk_size`字节的块(最后一块可能更短); 也可以通过`fill`(或`Read`)写入caller的缓冲区。
+/// 导出期间table被共享借用, 因此不会被修改
+///
+pub struct SnapshotChunks<'t> {
+    inner: &'t RawTableInner,
+    /// bucket步长
+    stride: usize,
+    /// 需要反转entries的字节序时, 逐个存放反转后的bucket
+    swap: Option<SwapBuffer<'t>>,
+    header: [u8; HEADER_SIZE],
+    /// 空table的ctrl字节
+    empty_ctrl: [u8; Group::WIDTH + 1],
+    block: usize,
+    ctrl_offset: usize,
+    total: usize,
+    /// 下一个待给出的字节在快照中的位置
+    pos: usize,
+    chunk_size: usize,
+}
+
+impl<'t> SnapshotChunks<'t> {
+    /// 给出快照中从`pos`(小于`total`)开始的片段
+    fn run(&mut self, pos: usize) -> Run<'_> {
+        if pos < HEADER_SIZE {
+            return Run::Bytes(&self.header[pos..]);
+        }
+        if pos < self.block {
+            // 元数据之后的对齐填充
+            return Run::Zeros(self.block - pos);
+        }
+        let inner = self.inner;
+        if pos >= self.ctrl_offset {
+            let offset = pos - self.ctrl_offset;
+            if inner.is_empty_singleton() {
+                return Run::Bytes(&self.empty_ctrl[offset..]);
+            }
+            let ctrl = unsafe {
+                slice::from_raw_parts(inner.ctrl(0).cast::<u8>(), inner.num_ctrl_bytes())
+            };
+            return Run::Bytes(&ctrl[offset..]);
+        }
+        if inner.is_empty_singleton() {
+            // 空table没有bucket数组
+            return Run::Zeros(self.ctrl_offset - pos);
+        }
+        // 内存中bucket按下标降序排列, 之前是分配起始处的对齐填充
+        let buckets = inner.buckets();
+        let padding = self.ctrl_offset - self.block - buckets * self.stride;
+        let offset = pos - self.block;
+        if offset < padding {
+            return Run::Zeros(padding - offset);
+        }
+        let index = buckets - 1 - (offset - padding) / self.stride;
+        let within = (offset - padding) % self.stride;
+        unsafe {
+            if !inner.is_bucket_full(index) {
+                Run::Zeros(self.stride - within)
+            } else if let Some(swap) = &mut self.swap {
+                Run::Bytes(&swap.swapped(inner, index)[within..])
+            } else {
+                let bucket = inner.bucket_ptr(index, self.stride);
+                Run::Bytes(slice::from_raw_parts(
+                    bucket.add(within),
+                    self.stride - within,
+                ))
+            }
+        }
+    }
+
+    /// 计算快照中`start..end`字节的校验和
+    fn checksum(&mut self, start: usize, end: usize) -> u64 {
+        const ZEROS: [u8; 64] = [0; 64];
+        let mut checksum = Checksum::new();
+        let mut pos = start;
+        while pos < end {
+            let len = match self.run(pos) {
+                Run::Bytes(bytes) => {
+                    let len = cmp::min(bytes.len(), end - pos);
+                    checksum.update(&bytes[..len]);
+                    len
+                }
+                Run::Zeros(len) => {
+                    let len = cmp::min(len, cmp::min(end - pos, ZEROS.len()));
+                    checksum.update(&ZEROS[..len]);
+                    len
+                }
+            };
+            pos += len;
+        }
+        checksum.finish()
+    }
+
+    ///
+    /// 将之后的快照字节写入`out`, 返回写入的字节数; 只有快照已全部给出时才返回0
+    ///
+    pub fn fill(&mut self, out: &mut [u8]) -> usize {
+        let mut written = 0;
+        while written < out.len() && self.pos < self.total {
+            let out = &mut out[written..];
+            let len = match self.run(self.pos) {
+                Run::Bytes(bytes) => {
+                    let len = cmp::min(bytes.len(), out.len());
+                    out[..len].copy_from_slice(&bytes[..len]);
+                    len
+                }
+                Run::Zeros(len) => {
+                    let len = cmp::min(len, out.len());
+                    out[..len].fill(0);
+                    len
+                }
+            };
+            self.pos += len;
+            written += len;
+        }
+        written
+    }
+
+    /// 获取快照的总字节数, 与`shared_size`一致
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 获取尚未给出的字节数
+    pub fn remaining(&self) -> usize {
+        self.total - self.pos
+    }
+}
+
+impl Iterator for SnapshotChunks<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        if self.pos == self.total {
+            return None;
+        }
+        let mut chunk = alloc::vec![0; cmp::min(self.chunk_size, self.remaining())];
+        self.fill(&mut chunk);
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.remaining() + self.chunk_size - 1) / self.chunk_size;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SnapshotChunks<'_> {}
+
+impl FusedIterator for SnapshotChunks<'_> {}
+
+#[cfg(feature = "std")]
+impl Read for SnapshotChunks<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.fill(buf))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 获取按`chunk_size`字节分块给出快照的导出器, 用于通过网络流式发送快照或以有限的内存写出。
+    ///
+    /// 创建时会读取一遍bucket数组以计算校验和; 与`export_shared`相同, 不引用其他内存的table才能导出。
+    ///
+    /// 快照中的entries为小端字节序: 大端平台上逐个拷贝bucket并通过`EntrySpec::swap_key_bytes`与`swap_value_bytes`转换,
+    /// spec不支持时保持本平台的字节序, 此时只有大端平台能够读取
+    ///
+    /// # Panics
+    ///
+    /// `chunk_size`为0时panic
+    ///
+    pub fn snapshot_chunks(&self, chunk_size: usize) -> Result<SnapshotChunks<'_>, SharedError> {
+        self.snapshot_chunks_in(chunk_size, cfg!(target_endian = "big"))
+    }
+
+    ///
+    /// 同`snapshot_chunks`, `swap_bytes`为true且spec支持时反转entries的字节序
+    ///
+    pub(crate) fn snapshot_chunks_in(
+        &self,
+        chunk_size: usize,
+        swap_bytes: bool,
+    ) -> Result<SnapshotChunks<'_>, SharedError> {
+        assert!(chunk_size > 0, "chunk_size must be non-zero");
+        let (inner, table_layout) = self.contiguous_block().ok_or(SharedError::Unsupported)?;
+        let (total, _, block, ctrl_offset) =
+            shared_layout(table_layout, inner.buckets(), HEADER_SIZE)
+                .ok_or(SharedError::Unsupported)?;
+        let mut empty_ctrl = [0u8; Group::WIDTH + 1];
+        unsafe {
+            slice::from_raw_parts_mut(empty_ctrl.as_mut_ptr().cast::<Tag>(), empty_ctrl.len())
+                .fill_empty();
+        }
+        let swap = if swap_bytes {
+            SwapBuffer::new(self.spec(), table_layout.size)
+        } else {
+            None
+        };
+        let endian = match swap {
+            Some(_) => NATIVE_ENDIAN_MARK.swap_bytes(),
+            None => NATIVE_ENDIAN_MARK,
+        };
+        let mut chunks = SnapshotChunks {
+            inner,
+            stride: table_layout.size,
+            swap,
+            header: [0; HEADER_SIZE],
+            empty_ctrl,
+            block,
+            ctrl_offset,
+            total,
+            pos: 0,
+            chunk_size,
+        };
+        let header = self.shared_header(
+            inner,
+            table_layout,
+            endian,
+            chunks.checksum(block, ctrl_offset),
+            chunks.checksum(ctrl_offset, total),
+        );
+        chunks.header = header.encode();
+        Ok(chunks)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 将整个table(bucket数量、entries数量、ctrl字节与bucket数组的原始字节)写入`w`, 之后可通过`read_snapshot`
+    /// 原样恢复而无需重新计算hash。
+    ///
+    /// 写入的字节与`export_shared`导出的区域完全一致, 空bucket写入0; 元数据、bucket数组与ctrl字节各自带有校验和,
+    /// 因此bucket数组会被读取两次。元数据记录了格式版本、entries的字节序、Group宽度与entry内存结构,
+    /// 主版本号(`SNAPSHOT_MAJOR`)相同的版本之间保证可以互相读取; 元数据以小端字节序写入,
+    /// entries的字节序见`snapshot_chunks`。只适用于不引用其他内存的POD entries,
+    /// 分离模式、SoA value、大entry模式以及使用过payload的table返回`io::ErrorKind::Unsupported`。
+    /// 每次向`w`写入至多64 KiB, 无需再包装`BufWriter`; 需要自行控制分块时使用`snapshot_chunks`
+    ///
+    pub fn write_snapshot<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut chunks = self
+            .snapshot_chunks(SNAPSHOT_CHUNK)
+            .map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?;
+        let mut chunk = alloc::vec![0; cmp::min(SNAPSHOT_CHUNK, chunks.total())];
+        loop {
+            match chunks.fill(&mut chunk) {
+                0 => return Ok(()),
+                len => w.write_all(&chunk[..len])?,
+            }
+        }
+    }
+
+    ///
+    /// 从`r`中恢复`write_snapshot`写入的table, 不重新计算hash; 恰好读取快照的字节数, 不会读取之后的数据。
+    ///
+    /// `layout`为caller预期的entry内存结构, 与`spec.layout()`不一致时返回`SnapshotError::LayoutMismatch`;
+    /// 快照的格式主版本号、Group宽度、entry内存结构或`spec_id`与读取方不一致时,
+    /// 返回指明不一致之处的`SnapshotError::Incompatible`。entries的字节序与本平台不同时,
+    /// 校验和比对通过之后通过`EntrySpec::swap_key_bytes`与`swap_value_bytes`转换, spec不支持时返回`Mismatch::Endianness`。
+    /// 读入的每一部分都会与其校验和比对, 不一致时返回`SnapshotError::Corrupted`, 截断的快照返回`io::ErrorKind::UnexpectedEof`。
+    /// 快照不是来自`Read`时(例如分块通过网络到达)使用`SnapshotImport`
+    ///
+    /// # Safety
+    ///
+    /// 快照须由内存结构相同且hash稳定(不使用随机种子)的spec写入, entries不能引用其他内存;
+    /// 字节序不同时, 同一Key在两个平台上的hash须相同
+    ///
+    pub unsafe fn read_snapshot<R: Read>(
+        mut r: R,
+        layout: Layout,
+        spec: E,
+        alloc: A,
+    ) -> Result<Self, SnapshotError> {
+        let mut import = SnapshotImport::new(layout, spec, alloc)?;
+        let mut chunk = Vec::new();
+        loop {
+            let len = cmp::min(import.needed(), SNAPSHOT_CHUNK);
+            if len == 0 {
+                return import.finish();
+            }
+            chunk.resize(len, 0);
+            r.read_exact(&mut chunk)?;
+            import.feed(&chunk)?;
+        }
+    }
+}
+
+///
+/// 增量恢复快照的导入器: 快照字节可以按任意大小分批通过`feed`给出(例如`SnapshotChunks`经网络发送的块),
+/// 元数据之后的字节直接写入table的内存, 不在内存中缓存整个快照; 全部字节到达后通过`finish`得到table。
+///
+/// 未完成或出错的导入器被丢弃时, 已分配的内存均会被释放
+///
+#[cfg(feature = "std")]
+pub struct SnapshotImport<E: EntrySpec, A: Allocator> {
+    state: ImportState<E, A>,
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::large_enum_variant)] // 每个导入器只有一个状态, 不值得为此装箱
+enum ImportState<E: EntrySpec, A: Allocator> {
+    /// 正在读取元数据, 读取到固定前缀之后`header_size`才是元数据的字节数
+    Header {
+        spec: E,
+        alloc: A,
+        bytes: Vec<u8>,
+        header_size: usize,
+    },
+    /// 正在读取对齐填充、bucket数组与ctrl字节
+    Body {
+        table: RawTable2<E, A>,
+        header: SharedHeader,
+        block: usize,
+        ctrl_offset: usize,
+        total: usize,
+        /// 下一个字节在快照中的位置
+        pos: usize,
+        bucket_checksum: Checksum,
+        ctrl_checksum: Checksum,
+    },
+    /// 已出错或已完成
+    Failed,
+}
+
+#[cfg(feature = "std")]
+impl<E: EntrySpec, A: Allocator> SnapshotImport<E, A> {
+    ///
+    /// 构造导入器, `layout`与`spec`的要求同`RawTable2::read_snapshot`
+    ///
+    /// # Safety
+    ///
+    /// 同`RawTable2::read_snapshot`
+    ///
+    pub unsafe fn new(layout: Layout, spec: E, alloc: A) -> Result<Self, SnapshotError> {
+        if layout.pad_to_align() != spec.layout().pad_to_align() {
+            return Err(SnapshotError::LayoutMismatch);
+        }
+        Ok(Self {
+            state: ImportState::Header {
+                spec,
+                alloc,
+                bytes: Vec::with_capacity(HEADER_SIZE),
+                header_size: HEADER_PREFIX,
+            },
+        })
+    }
+
+    ///
+    /// 获取至少还需要的字节数, 快照已全部到达时为0; 元数据解析之前只是下一阶段所需的字节数
+    ///
+    pub fn needed(&self) -> usize {
+        match &self.state {
+            ImportState::Header {
+                bytes, header_size, ..
+            } => header_size - bytes.len(),
+            ImportState::Body { total, pos, .. } => total - pos,
+            ImportState::Failed => 0,
+        }
+    }
+
+    ///
+    /// 给出之后的快照字节, 返回被消耗的字节数, 只有快照已全部到达时才会小于`bytes.len()`。
+    ///
+    /// 元数据不兼容、分配失败或校验和不一致时返回错误, 之后导入器不能再使用;
+    /// bucket数组与ctrl字节的校验和在最后一个字节到达时比对
+    ///
+    /// # Panics
+    ///
+    /// 此前已返回错误时panic
+    ///
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<usize, SnapshotError> {
+        assert!(
+            !matches!(self.state, ImportState::Failed),
+            "snapshot import has already failed"
+        );
+        let mut consumed = 0;
+        while consumed < bytes.len() && self.needed() > 0 {
+            let len = cmp::min(self.needed(), bytes.len() - consumed);
+            if let Err(err) = unsafe { self.advance(&bytes[consumed..consumed + len]) } {
+                self.abort();
+                return Err(err);
+            }
+            consumed += len;
+        }
+        Ok(consumed)
+    }
+
+    /// 处理不超过`needed`字节的`bytes`
+    unsafe fn advance(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        match &mut self.state {
+            ImportState::Header {
+                bytes: header,
+                header_size,
+                ..
+            } => {
+                header.extend_from_slice(bytes);
+                if header.len() < *header_size {
+                    return Ok(());
+                }
+                if *header_size == HEADER_PREFIX {
+                    *header_size = SharedHeader::header_size(header)?;
+                    return Ok(());
+                }
+                self.start_body()
+            }
+            ImportState::Body {
+                table,
+                header,
+                block,
+                ctrl_offset,
+                total,
+                pos,
+                bucket_checksum,
+                ctrl_checksum,
+            } => {
+                let (block, ctrl_offset, total) = (*block, *ctrl_offset, *total);
+                let singleton = table.inner_mut().is_empty_singleton();
+                // 分配的内存起始处, 即bucket数组区域(含对齐填充)的起始位置
+                let data = table
+                    .inner_mut()
+                    .ctrl(0)
+                    .cast::<u8>()
+                    .wrapping_sub(ctrl_offset - block);
+                let mut bytes = bytes;
+                while !bytes.is_empty() {
+                    let end = if *pos < block {
+                        block
+                    } else if *pos < ctrl_offset {
+                        ctrl_offset
+                    } else {
+                        total
+                    };
+                    let (run, rest) = bytes.split_at(cmp::min(bytes.len(), end - *pos));
+                    if *pos >= block {
+                        if *pos < ctrl_offset {
+                            bucket_checksum.update(run);
+                        } else {
+                            ctrl_checksum.update(run);
+                        }
+                        // 空table只校验全为0的bucket数组区域与全部为EMPTY的ctrl字节
+                        if !singleton {
+                            ptr::copy_nonoverlapping(
+                                run.as_ptr(),
+                                data.add(*pos - block),
+                                run.len(),
+                            );
+                        }
+                    }
+                    *pos += run.len();
+                    bytes = rest;
+                }
+                if *pos < total {
+                    return Ok(());
+                }
+                let checksum = mem::replace(bucket_checksum, Checksum::new());
+                if checksum.finish() != header.bucket_checksum {
+                    return Err(SnapshotError::Corrupted(SnapshotSection::Buckets));
+                }
+                let checksum = mem::replace(ctrl_checksum, Checksum::new());
+                if checksum.finish() != header.ctrl_checksum {
+                    return Err(SnapshotError::Corrupted(SnapshotSection::Ctrl));
+                }
+                if singleton && header.items != 0 {
+                    return Err(SnapshotError::Incompatible(Mismatch::Geometry));
+                }
+                if header.endian != NATIVE_ENDIAN_MARK && !singleton {
+                    // 校验和覆盖的是快照中的字节, 比对通过之后才转换为本平台的字节序
+                    let (inner, table_layout) = table.contiguous_block().unwrap();
+                    for index in 0..inner.buckets() {
+                        if inner.is_bucket_full(index) {
+                            let bucket = inner.bucket_ptr(index, table_layout.size);
+                            swap_entry_bytes(table.spec(), bucket);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            ImportState::Failed => unreachable!(),
+        }
+    }
+
+    /// 解析完整的元数据并分配table
+    unsafe fn start_body(&mut self) -> Result<(), SnapshotError> {
+        let (spec, alloc, bytes) = match mem::replace(&mut self.state, ImportState::Failed) {
+            ImportState::Header {
+                spec, alloc, bytes, ..
+            } => (spec, alloc, bytes),
+            _ => unreachable!(),
+        };
+        let header = SharedHeader::decode(&bytes)?;
+        let (total, _, block, ctrl_offset) = header
+            .region_for(&spec)
+            .map_err(SnapshotError::Incompatible)?;
+        if header.endian != NATIVE_ENDIAN_MARK
+            && SwapBuffer::new(&spec, header.entry_size as usize).is_none()
+        {
+            return Err(SnapshotError::Incompatible(Mismatch::Endianness));
+        }
+        let buckets = header.bucket_mask as usize + 1;
+        let table = if buckets == 1 {
+            RawTable2::new(0, spec, alloc)?
+        } else {
+            RawTable2::with_exact_buckets(buckets, spec, alloc)?
+        };
+        self.state = ImportState::Body {
+            table,
+            header,
+            block,
+            ctrl_offset,
+            total,
+            pos: bytes.len(),
+            bucket_checksum: Checksum::new(),
+            ctrl_checksum: Checksum::new(),
+        };
+        Ok(())
+    }
+
+    /// 丢弃已读入的内容, 保证table被丢弃时不会访问未完整读入或已损坏的bucket
+    fn abort(&mut self) {
+        if let ImportState::Body { mut table, .. } =
+            mem::replace(&mut self.state, ImportState::Failed)
+        {
+            unsafe {
+                let inner = table.inner_mut();
+                if !inner.is_empty_singleton() {
+                    inner.ctrl_slice().fill_empty();
+                }
+            }
+        }
+    }
+
+    ///
+    /// 在快照全部到达后得到恢复的table, 快照尚未完整时返回`io::ErrorKind::UnexpectedEof`
+    ///
+    /// # Panics
+    ///
+    /// 此前`feed`已返回错误时panic
+    ///
+    pub fn finish(mut self) -> Result<RawTable2<E, A>, SnapshotError> {
+        assert!(
+            !matches!(self.state, ImportState::Failed),
+            "snapshot import has already failed"
+        );
+        if self.needed() > 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        match mem::replace(&mut self.state, ImportState::Failed) {
+            ImportState::Body {
+                mut table, header, ..
+            } => {
+                let inner = unsafe { table.inner_mut() };
+                inner.items = header.items as usize;
+                inner.growth_left = header.growth_left as usize;
+                Ok(table)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: EntrySpec, A: Allocator> Drop for SnapshotImport<E, A> {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+///
+/// 映射`RawTable2::write_snapshot`写入的快照文件(例如通过mmap)后得到的只读table, 通过`attach`校验元数据与内存结构,
+/// 查找与迭代直接在映射的内存上进行, 无需反序列化; 来源不可信时可先通过`verify`比对校验和
+///
+#[cfg(feature = "std")]
+pub type FrozenRawTable<'a, E> = SharedTable<'a, E>;
+
+///
+/// 只读attach到`RawTable2::export_shared`写入的内存区域上的table, 不拷贝也不分配任何内存
+///
+/// `RawTable2::write_snapshot`写入的快照与导出的区域字节完全一致, 因此同样可以attach到映射快照文件的内存上
+///
+pub struct SharedTable<'a, E: EntrySpec> {
+    spec: E,
+    /// ctrl指向区域内部, 不拥有其内存
+    inner: RawTableInner,
+    entry_size: usize,
+    /// attach时校验过的元数据
+    header: SharedHeader,
+    /// 区域的有效字节
+    region: &'a [u8],
+}
+
+impl<'a, E: EntrySpec> SharedTable<'a, E> {
+    ///
+    /// attach到`bytes`中的table, `spec`须与导出方的spec内存结构一致且hash在进程之间保持稳定(不能使用随机种子)。
+    ///
+    /// 区域被原样访问, 不转换字节序, 因此entries的字节序与本平台不同时返回`Mismatch::Endianness`
+    ///
+    /// # Safety
+    ///
+    /// `bytes`须由相同架构上的`export_shared`写入, 且在attach期间不被修改
+    ///
+    pub unsafe fn attach(bytes: &'a [u8], spec: E) -> Result<Self, SharedError> {
+        let align = mem::align_of::<SharedHeader>();
+        if addr(bytes.as_ptr()) % align != 0 {
+            return Err(SharedError::Misaligned { align });
+        }
+        if bytes.len() < HEADER_PREFIX {
+            return Err(SharedError::TooSmall {
+                required: HEADER_PREFIX,
+            });
+        }
+        let header_size = SharedHeader::header_size(bytes)?;
+        if bytes.len() < header_size {
+            return Err(SharedError::TooSmall {
+                required: header_size,
+            });
+        }
+        let header = SharedHeader::decode(&bytes[..header_size])?;
+        let (total, align, ..) = header
+            .region_for(&spec)
+            .map_err(SharedError::Incompatible)?;
+        if header.endian != NATIVE_ENDIAN_MARK {
+            return Err(SharedError::Incompatible(Mismatch::Endianness));
+        }
+        // ctrl字节按Group对齐读取, 区域须满足完整的对齐要求
+        if addr(bytes.as_ptr()) % align != 0 {
+            return Err(SharedError::Misaligned { align });
+        }
+        if bytes.len() < total {
+            return Err(SharedError::TooSmall { required: total });
+        }
+        let ctrl = bytes.as_ptr().add(header.ctrl_offset as usize) as *mut u8;
+        Ok(Self {
+            spec,
+            inner: RawTableInner {
+                bucket_mask: header.bucket_mask as usize,
+                ctrl: NonNull::new_unchecked(ctrl),
+                growth_left: 0,
+                items: header.items as usize,
+            },
+            entry_size: header.entry_size as usize,
+            header,
+            region: &bytes[..total],
+        })
+    }
+
+    ///
+    /// 比对bucket数组与ctrl字节的校验和, 需要读取整个区域; `attach`只校验元数据
+    ///
+    pub fn verify(&self) -> Result<(), SharedError> {
+        let header = &self.header;
+        let (total, _, block, ctrl_offset) = header.region_for(&self.spec).unwrap();
+        if Checksum::of(&self.region[block..ctrl_offset]) != header.bucket_checksum {
+            return Err(SharedError::Corrupted(SnapshotSection::Buckets));
+        }
+        if Checksum::of(&self.region[ctrl_offset..total]) != header.ctrl_checksum {
+            return Err(SharedError::Corrupted(SnapshotSection::Ctrl));
+        }
+        Ok(())
+    }
+
+    /// 获取entries数量
+    pub fn len(&self) -> usize {
+        self.inner.items
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// 获取`key: &K`的value指针, 指针指向共享区域内部, 在`bytes`的生命周期内有效
+    ///
+    pub unsafe fn access(&self, key: *const u8) -> Option<*const u8> {
+        with_normalized_key(&self.spec, key, |key| {
+            let hash = spec_hash(&self.spec, key);
+            let mut equals = |index| self.spec.equals(key, self.bucket(index));
+            let index = self.inner.find_inner(hash, &mut equals)?;
+            Some(self.value_ptr(self.bucket(index)))
+        })
+    }
+
+    /// 依次以每个entry的Key与value指针调用`f`
+    pub fn for_each(&self, mut f: impl FnMut(*const u8, *const u8)) {
+        for (key, value) in self.iter() {
+            f(key, value);
+        }
+    }
+
+    ///
+    /// 获取依次给出每个entry的Key与value指针的迭代器, 指针指向区域内部
+    ///
+    pub fn iter(&self) -> SharedIter<'_, 'a, E> {
+        SharedIter {
+            table: self,
+            inner: unsafe { self.inner.full_buckets_indices() },
+            items: self.inner.items,
+        }
+    }
+
+    unsafe fn bucket(&self, index: usize) -> *const u8 {
+        self.inner.bucket_ptr(index, self.entry_size)
+    }
+
+    unsafe fn value_ptr(&self, entry: *const u8) -> *const u8 {
+        if self.spec.zero_sized_value() {
+            invalid_mut(self.spec.layout().align())
+        } else {
+            self.spec.access_value(entry)
+        }
+    }
+}
+
+///
+/// `SharedTable::iter`返回的迭代器
+///
+pub struct SharedIter<'t, 'a, E: EntrySpec> {
+    table: &'t SharedTable<'a, E>,
+    inner: FullBucketsIndices,
+    /// 尚未返回的entries数量
+    items: usize,
+}
+
+impl<E: EntrySpec> Iterator for SharedIter<'_, '_, E> {
+    type Item = (*const u8, *const u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.items == 0 {
+            return None;
+        }
+        let index = self.inner.next()?;
+        self.items -= 1;
+        unsafe {
+            let bucket = self.table.bucket(index);
+            Some((bucket, self.table.value_ptr(bucket)))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.items, Some(self.items))
+    }
+}
+
+impl<E: EntrySpec> ExactSizeIterator for SharedIter<'_, '_, E> {}
+
+impl<E: EntrySpec> FusedIterator for SharedIter<'_, '_, E> {}
@@ -0,0 +1,107 @@
+use super::map::{EntrySpec, RawTable2};
+use super::{Allocator, Global};
+use crate::alloc::sync::Arc;
+use crate::TryReserveError;
+use core::ops::Deref;
+
+///
+/// 不可变的table快照, 克隆只增加引用计数, 可以在线程之间共享。
+///
+/// 快照在构造之后内容不再改变, 可以在写入方继续修改的同时完成一致的时间点迭代
+///
+pub struct FrozenTable<E: EntrySpec, A: Allocator = Global>(Arc<RawTable2<E, A>>);
+
+impl<E: EntrySpec, A: Allocator> FrozenTable<E, A> {
+    /// 判断两个快照是否为同一个table
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> Clone for FrozenTable<E, A> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> Deref for FrozenTable<E, A> {
+    type Target = RawTable2<E, A>;
+
+    fn deref(&self) -> &RawTable2<E, A> {
+        &self.0
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> RawTable2<E, A> {
+    ///
+    /// 将table冻结为只读的`FrozenTable`: 先以容纳当前entries的最小bucket数组重建, 回收所有tombstone;
+    /// `reorder`为true时按各entry的探测起点顺序重新排列, 查找时访问的控制字节与entries更集中。
+    ///
+    /// 重建只是尽力而为, 内存不足时改为在原有内存中整理entries。冻结之后只能查找与迭代,
+    /// 适用于加载完成后不再修改的只读服务
+    ///
+    pub fn freeze(mut self, reorder: bool) -> FrozenTable<E, A> {
+        if self.rebuild_fitted(reorder).is_err() {
+            self.compact();
+        }
+        FrozenTable(Arc::new(self))
+    }
+}
+
+///
+/// 写时复制的table: `snapshot`以引用计数共享当前table, 不复制任何entries;
+/// 存在未释放的快照时, 写入方首次修改前先复制出私有副本, 修改完成后副本即成为之后快照所见的table。
+///
+/// 没有快照时直接原地修改, 不产生复制开销。需要在多个线程中获取快照时, 可以将其置于锁中,
+/// `snapshot`只在锁内克隆一个`Arc`
+///
+pub struct CowRawTable<E: EntrySpec, A: Allocator = Global> {
+    current: Arc<RawTable2<E, A>>,
+}
+
+impl<E: EntrySpec, A: Allocator> CowRawTable<E, A> {
+    /// 以`table`作为当前table构造
+    pub fn new(table: RawTable2<E, A>) -> Self {
+        Self {
+            current: Arc::new(table),
+        }
+    }
+
+    /// 获取当前table的快照
+    pub fn snapshot(&self) -> FrozenTable<E, A> {
+        FrozenTable(Arc::clone(&self.current))
+    }
+
+    /// 获取当前table, 存在快照时返回`None`
+    pub fn get_mut(&mut self) -> Option<&mut RawTable2<E, A>> {
+        Arc::get_mut(&mut self.current)
+    }
+
+    /// 当前table是否被快照共享
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.current) > 1
+    }
+}
+
+impl<E: EntrySpec + Clone, A: Allocator + Clone> CowRawTable<E, A> {
+    ///
+    /// 获取可修改的当前table: 被快照共享时先复制出私有副本并替换当前table, 已有快照的内容不受影响。
+    ///
+    /// 复制失败时返回错误, 当前table保持不变
+    ///
+    pub fn make_mut(&mut self) -> Result<&mut RawTable2<E, A>, TryReserveError> {
+        if Arc::get_mut(&mut self.current).is_none() {
+            self.current = Arc::new(self.current.try_clone()?);
+        }
+        // 新副本只被此处引用
+        Ok(Arc::get_mut(&mut self.current).unwrap())
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> Deref for CowRawTable<E, A> {
+    type Target = RawTable2<E, A>;
+
+    fn deref(&self) -> &RawTable2<E, A> {
+        &self.current
+    }
+}
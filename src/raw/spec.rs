@@ -0,0 +1,871 @@
+// `EntrySpec`的hook均为接收entry指针的safe函数, 指针有效性由table保证
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use super::entry_layout::EntryLayout;
+#[cfg(all(target_feature = "avx2", target_arch = "x86_64", not(miri)))]
+use super::int_hash_avx2;
+use super::map::{table_hash, EntrySpec, OrderedEntrySpec, RawTable2};
+#[cfg(feature = "raw-map-infallible")]
+use super::Global;
+use super::{ptr, Allocator, Layout, PhantomData, RawTable};
+use crate::map::make_hash;
+use crate::HashMap;
+use core::cmp::Ordering;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::mem;
+use core::slice;
+
+///
+/// 基于`BuildHasher`的通用`EntrySpec`, entry按`(K, V)`的内存结构排布。
+///
+/// 适用于所有实现了`Hash + Eq`的`Copy`类型Key, 例如整数、`&[u8]`([`BytesSpec`])
+/// 以及派生了`Hash`/`Eq`的`#[repr(C)]`结构体。
+///
+/// 与持有`(K, V)`的容器相同, spec对K与V协变, 因此table只能缩短而不能延长Key的生命周期:
+///
+/// ```compile_fail
+/// use hashbrown::raw_map::{HasherSpec, RawTable2};
+/// use std::collections::hash_map::RandomState;
+///
+/// fn extend<'a>(
+///     table: RawTable2<HasherSpec<&'a [u8], u32, RandomState>>,
+/// ) -> RawTable2<HasherSpec<&'static [u8], u32, RandomState>> {
+///     table
+/// }
+/// ```
+///
+pub struct HasherSpec<K, V, S> {
+    hash_builder: S,
+    layout: Layout,
+    voff: usize,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+/// 以字节切片为Key的`EntrySpec`, hash与相等性均基于切片内容, caller需保证切片内存长于table存活
+pub type BytesSpec<'a, V, S> = HasherSpec<&'a [u8], V, S>;
+
+impl<K, V, S> HasherSpec<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher,
+{
+    /// 基于指定的`BuildHasher`构造spec
+    pub fn new(hash_builder: S) -> Self {
+        let layout = EntryLayout::of::<K, V>();
+        Self {
+            hash_builder,
+            layout: layout.layout(),
+            voff: layout.voff(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// 获取此spec使用的`BuildHasher`
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+}
+
+impl<K, V, S: Clone> Clone for HasherSpec<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            hash_builder: self.hash_builder.clone(),
+            layout: self.layout,
+            voff: self.voff,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// 以`S::default()`构造spec
+impl<K, V, S> Default for HasherSpec<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+impl<K, V, S> EntrySpec for HasherSpec<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher,
+{
+    #[inline]
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        let key = unsafe { &*(entry as *const K) };
+        make_hash::<K, S>(&self.hash_builder, key)
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        let k1 = unsafe { &*(entry1 as *const K) };
+        let k2 = unsafe { &*(entry2 as *const K) };
+        k1 == k2
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { ptr::write(entry as *mut K, *(k as *const K)) }
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(self.voff) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(v, value as *mut u8, mem::size_of::<V>()) }
+    }
+
+    #[inline]
+    fn zero_sized_value(&self) -> bool {
+        mem::size_of::<V>() == 0
+    }
+}
+
+impl<K, V, S> OrderedEntrySpec for HasherSpec<K, V, S>
+where
+    K: Hash + Ord + Copy,
+    S: BuildHasher,
+{
+    #[inline]
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering {
+        let k1 = unsafe { &*(entry1 as *const K) };
+        let k2 = unsafe { &*(entry2 as *const K) };
+        k1.cmp(k2)
+    }
+}
+
+///
+/// 将`HashMap`中的所有{key, value}移入以相同`BuildHasher`构造的table, 用于逐步迁移已有代码。
+/// table不会调用value的析构函数, 因此要求`V: Copy`
+///
+#[cfg(feature = "raw-map-infallible")]
+impl<K, V, S> From<HashMap<K, V, S>> for RawTable2<HasherSpec<K, V, S>>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher + Clone,
+{
+    fn from(map: HashMap<K, V, S>) -> Self {
+        let spec = HasherSpec::new(map.hasher().clone());
+        from_entries(map.len(), spec, map)
+    }
+}
+
+/// 将`std::collections::HashMap`中的所有{key, value}移入以相同`BuildHasher`构造的table
+#[cfg(all(feature = "raw-map-infallible", feature = "std"))]
+impl<K, V, S> From<std::collections::HashMap<K, V, S>> for RawTable2<HasherSpec<K, V, S>>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher + Clone,
+{
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let spec = HasherSpec::new(map.hasher().clone());
+        from_entries(map.len(), spec, map)
+    }
+}
+
+///
+/// 依次写入所有{key, value}, 重复的Key以靠后的value为准。
+///
+/// 写入之前按`size_hint`预留容量: 当前table为空时预留下界, 否则预留下界的一半(假设约一半的Key已存在)。
+/// table不会调用value的析构函数, 因此要求`V: Copy`。
+///
+/// 方法调用语法会解析到合并另一个table的inherent方法`RawTable2::extend`, 因此需写作`Extend::extend(&mut table, iter)`
+///
+#[cfg(feature = "raw-map-infallible")]
+impl<K, V, S, A> Extend<(K, V)> for RawTable2<HasherSpec<K, V, S>, A>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher,
+    A: Allocator,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        let reserve = if self.is_empty() {
+            iter.size_hint().0
+        } else {
+            (iter.size_hint().0 + 1) / 2
+        };
+        self.try_reserve(reserve).expect("map growth failure");
+        // entry按`(K, V)`排布, 与`as_map::<K, V>`一致
+        let mut map = unsafe { self.as_map::<K, V>() };
+        for (key, value) in iter {
+            unsafe { map.insert(&key, value) };
+        }
+    }
+}
+
+/// 同`Extend<(K, V)>`, 适用于从另一个map的迭代器中拷贝{key, value}
+#[cfg(feature = "raw-map-infallible")]
+impl<'a, K, V, S, A> Extend<(&'a K, &'a V)> for RawTable2<HasherSpec<K, V, S>, A>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher,
+    A: Allocator,
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        Extend::extend(self, iter.into_iter().map(|(&key, &value)| (key, value)));
+    }
+}
+
+/// 以`S::default()`构造spec, 其余同`Extend<(K, V)>`
+#[cfg(feature = "raw-map-infallible")]
+impl<K, V, S> FromIterator<(K, V)> for RawTable2<HasherSpec<K, V, S>>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut table =
+            RawTable2::new(0, HasherSpec::new(S::default()), Global).expect("map growth failure");
+        Extend::extend(&mut table, iter);
+        table
+    }
+}
+
+/// 构造容量为`cap`的table并依次写入`entries`
+#[cfg(feature = "raw-map-infallible")]
+fn from_entries<K, V, S>(
+    cap: usize,
+    spec: HasherSpec<K, V, S>,
+    entries: impl IntoIterator<Item = (K, V)>,
+) -> RawTable2<HasherSpec<K, V, S>>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher,
+{
+    let mut table = RawTable2::new(cap, spec, Global).expect("map growth failure");
+    // entry按`(K, V)`排布, 与`as_map::<K, V>`一致
+    let mut map = unsafe { table.as_map::<K, V>() };
+    for (key, value) in entries {
+        unsafe { map.insert(&key, value) };
+    }
+    table
+}
+
+impl<K, V, S, A> RawTable2<HasherSpec<K, V, S>, A>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher + Clone,
+    A: Allocator,
+{
+    ///
+    /// 将所有{key, value}移入以相同`BuildHasher`构造的`HashMap`, 与`From<HashMap>`互为逆操作
+    ///
+    pub fn into_hash_map(mut self) -> HashMap<K, V, S> {
+        let mut map = HashMap::with_capacity_and_hasher(self.len(), self.spec().hasher().clone());
+        map.extend(unsafe { self.as_map::<K, V>() });
+        map
+    }
+
+    /// 将所有{key, value}移入以相同`BuildHasher`构造的`std::collections::HashMap`
+    #[cfg(feature = "std")]
+    pub fn into_std_hash_map(mut self) -> std::collections::HashMap<K, V, S> {
+        let mut map = std::collections::HashMap::with_capacity_and_hasher(
+            self.len(),
+            self.spec().hasher().clone(),
+        );
+        map.extend(unsafe { self.as_map::<K, V>() });
+        map
+    }
+
+    ///
+    /// 将table零拷贝地转换为以相同`BuildHasher`与allocator构造的`HashMap`: bucket数组与ctrl字节直接移交, 不重新hash。
+    ///
+    /// 要求entry内存结构与Rust元组`(K, V)`一致(Rust可能重排元组字段), 且bucket数组与ctrl字节位于同一块内存
+    /// (未使用分离模式与`bucket_align`); 不满足时退化为逐个移动entries, 同`into_hash_map`
+    ///
+    pub fn into_hashbrown(mut self) -> HashMap<K, V, S, A>
+    where
+        A: Clone,
+    {
+        let hash_builder = self.spec().hasher().clone();
+        let alloc = self.allocator().clone();
+        if tuple_layout_matches(self.spec()) {
+            if let Some(table) = self.take_contiguous_block() {
+                // `HasherSpec`的hash即`make_hash`, ctrl字节与`HashMap`的计算结果一致;
+                // allocator的克隆可以释放原allocator分配的内存
+                let table = RawTable {
+                    table,
+                    alloc,
+                    marker: PhantomData,
+                };
+                return HashMap {
+                    hash_builder,
+                    table,
+                };
+            }
+        }
+        let mut map = HashMap::with_capacity_and_hasher_in(self.len(), hash_builder, alloc);
+        map.extend(unsafe { self.as_map::<K, V>() });
+        map
+    }
+}
+
+/// 判断`spec`的entry内存结构是否与Rust元组`(K, V)`一致
+fn tuple_layout_matches<K, V, S>(spec: &HasherSpec<K, V, S>) -> bool {
+    let tuple = mem::MaybeUninit::<(K, V)>::uninit();
+    let base = tuple.as_ptr();
+    let (koff, voff) = unsafe {
+        (
+            ptr::addr_of!((*base).0)
+                .cast::<u8>()
+                .offset_from(base.cast()) as usize,
+            ptr::addr_of!((*base).1)
+                .cast::<u8>()
+                .offset_from(base.cast()) as usize,
+        )
+    };
+    spec.layout.pad_to_align() == Layout::new::<(K, V)>()
+        && (koff == 0 || mem::size_of::<K>() == 0)
+        && (voff == spec.voff || mem::size_of::<V>() == 0)
+}
+
+///
+/// 按原始字节处理Key的`EntrySpec`, 适用于没有实现`Hash`/`Eq`的`#[repr(C)]` POD类型。
+///
+/// hash与相等性均基于Key的全部`size_of::<K>()`字节, 因此K不能包含padding字节,
+/// 否则内容相同的Key可能因padding不同而被视为不同的Key。
+///
+pub struct PodSpec<K, V, S> {
+    hash_builder: S,
+    layout: Layout,
+    voff: usize,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K: Copy, V, S: BuildHasher> PodSpec<K, V, S> {
+    /// 基于指定的`BuildHasher`构造spec
+    pub fn new(hash_builder: S) -> Self {
+        let layout = EntryLayout::of::<K, V>();
+        Self {
+            hash_builder,
+            layout: layout.layout(),
+            voff: layout.voff(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// 获取此spec使用的`BuildHasher`
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    #[inline(always)]
+    unsafe fn key_bytes<'a>(entry: *const u8) -> &'a [u8] {
+        slice::from_raw_parts(entry, mem::size_of::<K>())
+    }
+}
+
+impl<K, V, S: Clone> Clone for PodSpec<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            hash_builder: self.hash_builder.clone(),
+            layout: self.layout,
+            voff: self.voff,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K: Copy, V, S: BuildHasher> EntrySpec for PodSpec<K, V, S> {
+    #[inline]
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        let mut state = self.hash_builder.build_hasher();
+        state.write(unsafe { Self::key_bytes(entry) });
+        state.finish()
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { Self::key_bytes(entry1) == Self::key_bytes(entry2) }
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(k, entry as *mut u8, mem::size_of::<K>()) }
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(self.voff) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(v, value as *mut u8, mem::size_of::<V>()) }
+    }
+
+    #[inline]
+    fn zero_sized_value(&self) -> bool {
+        mem::size_of::<V>() == 0
+    }
+}
+
+///
+/// 基于`BuildHasher`的紧凑`EntrySpec`, entry按`EntryLayout::packed`排布, K与V之间没有padding。
+///
+/// K与V均可能位于未对齐的地址上, 因此Key通过`ptr::read_unaligned`读取,
+/// value只能通过`RawMap::read`或`RawTable2::access`返回的指针按未对齐方式访问
+///
+pub struct PackedSpec<K, V, S> {
+    hash_builder: S,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, S> PackedSpec<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher,
+{
+    /// 基于指定的`BuildHasher`构造spec
+    pub fn new(hash_builder: S) -> Self {
+        Self {
+            hash_builder,
+            phantom: PhantomData,
+        }
+    }
+
+    /// 获取此spec使用的`BuildHasher`
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    #[inline(always)]
+    unsafe fn read_key(entry: *const u8) -> K {
+        ptr::read_unaligned(entry as *const K)
+    }
+}
+
+impl<K, V, S: Clone> Clone for PackedSpec<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            hash_builder: self.hash_builder.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> EntrySpec for PackedSpec<K, V, S>
+where
+    K: Hash + Eq + Copy,
+    S: BuildHasher,
+{
+    #[inline]
+    fn layout(&self) -> Layout {
+        EntryLayout::packed::<K, V>().layout()
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        let key = unsafe { Self::read_key(entry) };
+        make_hash::<K, S>(&self.hash_builder, &key)
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { Self::read_key(entry1) == Self::read_key(entry2) }
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(k, entry as *mut u8, mem::size_of::<K>()) }
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(mem::size_of::<K>()) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(v, value as *mut u8, mem::size_of::<V>()) }
+    }
+
+    #[inline]
+    fn zero_sized_value(&self) -> bool {
+        mem::size_of::<V>() == 0
+    }
+}
+
+impl<K, V, S> OrderedEntrySpec for PackedSpec<K, V, S>
+where
+    K: Hash + Ord + Copy,
+    S: BuildHasher,
+{
+    #[inline]
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering {
+        unsafe { Self::read_key(entry1).cmp(&Self::read_key(entry2)) }
+    }
+}
+
+///
+/// `IntSpec`支持的定长整数Key, 折叠为一个64位字之后参与hash
+///
+pub trait IntKey: Copy + Eq {
+    /// 折叠为64位字, 不超过64位的整数须保持单射
+    fn fold(self) -> u64;
+}
+
+macro_rules! impl_int_key {
+    ($($t:ty => $u:ty),* $(,)?) => {$(
+        impl IntKey for $t {
+            #[inline(always)]
+            fn fold(self) -> u64 {
+                self as $u as u64
+            }
+        }
+    )*};
+}
+
+impl_int_key!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, usize => usize);
+impl_int_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+impl IntKey for u128 {
+    #[inline(always)]
+    fn fold(self) -> u64 {
+        (self as u64) ^ ((self >> 64) as u64).wrapping_mul(INT_MIX_PRIME)
+    }
+}
+
+impl IntKey for i128 {
+    #[inline(always)]
+    fn fold(self) -> u64 {
+        (self as u128).fold()
+    }
+}
+
+/// 折叠u128高位以及hash混合所用的奇数常量
+pub(super) const INT_MIX_PRIME: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// `IntHash::Fibonacci32`所用的32位黄金分割常量
+const INT_MIX_PRIME_32: u32 = 0x9e37_79b9;
+
+/// `IntSpec::hash_many`每次迭代处理的Key数量
+pub(super) const INT_HASH_LANES: usize = 8;
+
+/// `int_hash`中与高32位半字异或的常量
+pub(super) const INT_HASH_CROSS: u64 = 0x85eb_ca6b;
+
+/// murmur3 finalizer的两个乘数
+pub(super) const INT_FMIX_1: u64 = 0xff51_afd7_ed55_8ccd;
+pub(super) const INT_FMIX_2: u64 = 0xc4ce_b9fe_1a85_ec53;
+
+///
+/// xxh3风格的整数hash: 两个32位半字交叉相乘, 再经过murmur3的finalizer充分混合。
+///
+/// 启用AVX2时`int_hash_avx2`以完全相同的运算逐lane计算, 两者的结果必须保持逐位一致
+///
+#[inline(always)]
+fn int_hash(word: u64, seed: u64) -> u64 {
+    let x = word ^ seed;
+    let product = (x & 0xffff_ffff).wrapping_mul((x >> 32) ^ INT_HASH_CROSS);
+    let mut h = product ^ x.rotate_left(23);
+    h ^= h >> 33;
+    h = h.wrapping_mul(INT_FMIX_1);
+    h ^= h >> 33;
+    h = h.wrapping_mul(INT_FMIX_2);
+    h ^ (h >> 33)
+}
+
+///
+/// `IntSpec`的hash函数, 输入为与种子异或之后的64位字
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IntHash {
+    /// 充分混合所有位的整数hash, 适用于任意分布的Key
+    #[default]
+    Mixed,
+    /// 直接以Key作为hash, 适用于低位已经均匀分布的Key(例如连续分配的ID);
+    /// 高位相同的Key之间ctrl字节的H2相同, 探测时需要更多次Key比较
+    Identity,
+    /// 乘以黄金分割常量(multiply-shift), 起始槽位由Key的低位唯一确定, H2取自充分混合的高位
+    Fibonacci,
+    /// `Fibonacci`的32位版本: 先将64位字折叠为32位再做32位乘法, 并启用`EntrySpec::hash32`,
+    /// 适用于没有高效64位乘法的32位目标(例如Cortex-M与wasm32)
+    Fibonacci32,
+}
+
+///
+/// 以定长整数(`u32`、`u64`、`u128`等)为Key的`EntrySpec`, 使用内置的整数hash而非`BuildHasher`。
+///
+/// `hash_many`每次迭代同时计算8个Key的hash: 启用AVX2(`-C target-feature=+avx2`)时`Mixed`与`Fibonacci`
+/// 在两个256位寄存器上逐lane计算, 其余情况下逐个计算, 各lane之间没有依赖, 乘法的延迟可以相互重叠,
+/// 因此`extend`、`lookup_pipeline`等批量路径上hash不再是瓶颈。hash由`seed`决定, 不同进程间保持稳定;
+/// 需要抵御hash flooding时应使用随机`seed`或改用`HasherSpec`。
+///
+/// 单个Key的查找可以使用`RawTable2::access_int`, 探测循环中直接比较整数Key
+///
+pub struct IntSpec<K, V> {
+    seed: u64,
+    mode: IntHash,
+    layout: Layout,
+    voff: usize,
+    phantom: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K: IntKey, V> IntSpec<K, V> {
+    /// 基于hash种子构造spec, hash函数为`IntHash::Mixed`
+    pub fn new(seed: u64) -> Self {
+        let layout = EntryLayout::of::<K, V>();
+        Self {
+            seed,
+            mode: IntHash::Mixed,
+            layout: layout.layout(),
+            voff: layout.voff(),
+            phantom: PhantomData,
+        }
+    }
+
+    ///
+    /// 使用`mode`作为hash函数, 必须在写入任何Key之前设置
+    ///
+    pub fn with_hash(mut self, mode: IntHash) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 获取hash种子
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 获取hash函数
+    pub fn hash_mode(&self) -> IntHash {
+        self.mode
+    }
+
+    /// 计算Key`key`的hash, 与`EntrySpec::hash`一致
+    #[inline]
+    pub fn hash_key(&self, key: K) -> u64 {
+        self.hash_word(key.fold())
+    }
+
+    #[inline(always)]
+    fn hash_word(&self, word: u64) -> u64 {
+        match self.mode {
+            IntHash::Mixed => int_hash(word, self.seed),
+            IntHash::Identity => word ^ self.seed,
+            IntHash::Fibonacci => (word ^ self.seed).wrapping_mul(INT_MIX_PRIME),
+            IntHash::Fibonacci32 => {
+                let word = word ^ self.seed;
+                let folded = (word as u32) ^ ((word >> 32) as u32);
+                u64::from(folded.wrapping_mul(INT_MIX_PRIME_32))
+            }
+        }
+    }
+
+    /// 计算`INT_HASH_LANES`个64位字的hash
+    #[inline(always)]
+    fn hash_lanes(&self, words: &[u64; INT_HASH_LANES], out: &mut [u64; INT_HASH_LANES]) {
+        #[cfg(all(target_feature = "avx2", target_arch = "x86_64", not(miri)))]
+        match self.mode {
+            IntHash::Mixed => return unsafe { int_hash_avx2::mixed(words, self.seed, out) },
+            IntHash::Fibonacci => {
+                return unsafe { int_hash_avx2::fibonacci(words, self.seed, out) }
+            }
+            IntHash::Identity | IntHash::Fibonacci32 => {}
+        }
+        for (hash, word) in out.iter_mut().zip(words) {
+            *hash = self.hash_word(*word);
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn read_key(entry: *const u8) -> K {
+        *(entry as *const K)
+    }
+}
+
+impl<K, V> Clone for IntSpec<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            seed: self.seed,
+            mode: self.mode,
+            layout: self.layout,
+            voff: self.voff,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K: IntKey, V> EntrySpec for IntSpec<K, V> {
+    #[inline]
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        self.hash_key(unsafe { Self::read_key(entry) })
+    }
+
+    fn hash_many(&self, keys: &[*const u8], out: &mut [u64]) {
+        let mut key_chunks = keys.chunks_exact(INT_HASH_LANES);
+        let mut out_chunks = out.chunks_exact_mut(INT_HASH_LANES);
+        for (keys, out) in (&mut key_chunks).zip(&mut out_chunks) {
+            // 先收集到定长数组, 之后的混合在各lane上独立进行
+            let mut words = [0u64; INT_HASH_LANES];
+            for (word, key) in words.iter_mut().zip(keys) {
+                *word = unsafe { Self::read_key(*key) }.fold();
+            }
+            self.hash_lanes(&words, out.try_into().unwrap());
+        }
+        let rest = key_chunks.remainder();
+        for (hash, key) in out_chunks.into_remainder().iter_mut().zip(rest) {
+            *hash = self.hash(*key);
+        }
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        unsafe { Self::read_key(entry1) == Self::read_key(entry2) }
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        unsafe { ptr::write(entry as *mut K, *(k as *const K)) }
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(self.voff) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        unsafe { ptr::copy_nonoverlapping(v, value as *mut u8, mem::size_of::<V>()) }
+    }
+
+    #[inline]
+    fn zero_sized_value(&self) -> bool {
+        mem::size_of::<V>() == 0
+    }
+
+    #[inline]
+    fn hash32(&self) -> bool {
+        self.mode == IntHash::Fibonacci32
+    }
+}
+
+impl<K: IntKey + Ord, V> OrderedEntrySpec for IntSpec<K, V> {
+    #[inline]
+    fn compare(&self, entry1: *const u8, entry2: *const u8) -> Ordering {
+        unsafe { Self::read_key(entry1).cmp(&Self::read_key(entry2)) }
+    }
+}
+
+impl<K: IntKey, V, A: Allocator> RawTable2<IntSpec<K, V>, A> {
+    ///
+    /// 按整数Key查找value指针, 结果与`access(&key)`一致。
+    ///
+    /// 探测循环在此处展开, 直接按`K`比较Key, 不经过Key规范化、小table扫描以及`equals`的间接调用
+    ///
+    #[inline]
+    pub fn access_int(&self, key: K) -> Option<*const u8> {
+        let hash = table_hash(self.spec(), self.spec().hash_key(key));
+        unsafe { self.find_value_by(hash, |entry| IntSpec::<K, V>::read_key(entry) == key) }
+    }
+}
+
+///
+/// 基于闭包(或函数指针)构造的`EntrySpec`, 闭包可以捕获上下文(例如宿主runtime的类型信息)。
+///
+/// entry按`layout`排布, value位于entry中偏移量为`voff`处
+///
+pub struct ClosureSpec<H, Q, AK, AV> {
+    layout: Layout,
+    voff: usize,
+    hash_fn: H,
+    eq_fn: Q,
+    assign_k: AK,
+    assign_v: AV,
+}
+
+impl<H, Q, AK, AV> ClosureSpec<H, Q, AK, AV>
+where
+    H: Fn(*const u8) -> u64,
+    Q: Fn(*const u8, *const u8) -> bool,
+    AK: Fn(*const u8, *const u8),
+    AV: Fn(*const u8, *const u8),
+{
+    ///
+    /// 构造spec, 各闭包分别对应`EntrySpec`的`hash`、`equals`、`assign_key`与`assign_value`
+    ///
+    pub fn new(
+        layout: Layout,
+        voff: usize,
+        hash_fn: H,
+        eq_fn: Q,
+        assign_k: AK,
+        assign_v: AV,
+    ) -> Self {
+        Self {
+            layout,
+            voff,
+            hash_fn,
+            eq_fn,
+            assign_k,
+            assign_v,
+        }
+    }
+}
+
+impl<H, Q, AK, AV> EntrySpec for ClosureSpec<H, Q, AK, AV>
+where
+    H: Fn(*const u8) -> u64,
+    Q: Fn(*const u8, *const u8) -> bool,
+    AK: Fn(*const u8, *const u8),
+    AV: Fn(*const u8, *const u8),
+{
+    #[inline]
+    fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    #[inline]
+    fn hash(&self, entry: *const u8) -> u64 {
+        (self.hash_fn)(entry)
+    }
+
+    #[inline]
+    fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+        (self.eq_fn)(entry1, entry2)
+    }
+
+    #[inline]
+    fn assign_key(&self, entry: *const u8, k: *const u8) {
+        (self.assign_k)(entry, k)
+    }
+
+    #[inline]
+    fn access_value(&self, entry: *const u8) -> *const u8 {
+        unsafe { entry.add(self.voff) }
+    }
+
+    #[inline]
+    fn assign_value(&self, value: *const u8, v: *const u8) {
+        (self.assign_v)(value, v)
+    }
+}
@@ -0,0 +1,196 @@
+use super::map::{normalized_hash, EntrySpec, InsertError, RawTable2};
+use super::{Allocator, Global, Layout};
+use crate::alloc::boxed::Box;
+use crate::alloc::vec::Vec;
+use crate::scopeguard::guard;
+use crate::TryReserveError;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// stripe数量的上限
+const MAX_STRIPES: usize = 1 << 16;
+
+/// 选择stripe之前重新混合hash使用的乘数(2^64 / 黄金分割比, 奇数)
+const STRIPE_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+///
+/// 分段加锁的并发哈希表: Key按hash分配到固定数量的stripe, 每个stripe是由独立自旋锁保护的独立`RawTable2`,
+/// 各stripe之间不共享任何内存, 因此写入不同stripe的线程可以同时进行。
+///
+/// 单个stripe扩容或rehash时只持有自身的锁; `reserve`需要一致地修改所有stripe, 按顺序获取全部的锁。
+/// 介于整表一把锁与完全无锁之间, 适用于写入较多且Key分布均匀的场景
+///
+pub struct StripedRawMap<E: EntrySpec, A: Allocator = Global> {
+    stripes: Box<[Stripe<E, A>]>,
+    /// 选择stripe时计算hash使用的spec副本
+    spec: E,
+}
+
+/// 单个stripe, 按缓存行对齐以避免相邻stripe的锁之间的伪共享
+#[repr(align(64))]
+struct Stripe<E: EntrySpec, A: Allocator> {
+    locked: AtomicBool,
+    table: UnsafeCell<RawTable2<E, A>>,
+}
+
+// 每个stripe的table只在持有其锁时被访问; spec副本会被多个线程同时用于计算hash
+unsafe impl<E: EntrySpec + Send + Sync, A: Allocator + Send> Sync for StripedRawMap<E, A> {}
+
+impl<E: EntrySpec, A: Allocator> Stripe<E, A> {
+    fn lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+    }
+
+    fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    /// 持有此stripe的锁调用`f`
+    fn with<R>(&self, f: impl FnOnce(&mut RawTable2<E, A>) -> R) -> R {
+        self.lock();
+        let _unlock = guard(self, |stripe| stripe.unlock());
+        f(unsafe { &mut *self.table.get() })
+    }
+}
+
+impl<E: EntrySpec + Clone, A: Allocator + Clone> StripedRawMap<E, A> {
+    ///
+    /// 构造具有`stripes`个stripe、总容量至少为`cap`的哈希表。
+    ///
+    /// stripe数量向上取整为2的幂且不超过65536, 容量平均分配到各stripe
+    ///
+    pub fn new(stripes: usize, cap: usize, spec: E, alloc: A) -> Result<Self, TryReserveError> {
+        let n = stripes.clamp(1, MAX_STRIPES).next_power_of_two();
+        let mut vec = Vec::new();
+        if vec.try_reserve_exact(n).is_err() {
+            return Err(match Layout::array::<Stripe<E, A>>(n) {
+                Ok(layout) => TryReserveError::AllocError { layout },
+                Err(_) => TryReserveError::CapacityOverflow,
+            });
+        }
+        let per_stripe = cap / n + usize::from(cap % n != 0);
+        for _ in 0..n {
+            vec.push(Stripe {
+                locked: AtomicBool::new(false),
+                table: UnsafeCell::new(RawTable2::new(per_stripe, spec.clone(), alloc.clone())?),
+            });
+        }
+        Ok(Self {
+            stripes: vec.into_boxed_slice(),
+            spec,
+        })
+    }
+}
+
+impl<E: EntrySpec, A: Allocator> StripedRawMap<E, A> {
+    /// 获取stripe的数量
+    pub fn stripes(&self) -> usize {
+        self.stripes.len()
+    }
+
+    ///
+    /// 获取`key: &K`所属的stripe。
+    ///
+    /// hash直接截取的任何位段都可能与table内部使用的H1(低位)或H2(最高7位)重合, 例如`EntrySpec::hash32`时高32位是低32位的副本,
+    /// 因此先乘以奇数重新混合, 再取乘积的最高16位作为stripe下标, 使同一stripe内的Key在H1与H2上仍均匀分布
+    ///
+    unsafe fn stripe(&self, key: *const u8) -> &Stripe<E, A> {
+        let hash = normalized_hash(&self.spec, key).wrapping_mul(STRIPE_MIX);
+        &self.stripes[(hash >> 48) as usize & (self.stripes.len() - 1)]
+    }
+
+    ///
+    /// 查找`key: &K`, 并以value指针(不存在时为`None`)调用`f`; 调用期间持有所属stripe的锁, 指针只在`f`中有效
+    ///
+    pub unsafe fn get<R>(&self, key: *const u8, f: impl FnOnce(Option<*const u8>) -> R) -> R {
+        self.stripe(key).with(|table| f(table.access(key)))
+    }
+
+    ///
+    /// 写入`key: &K`与`value: &V`, 已存在的Key覆盖其value; 只持有所属stripe的锁
+    ///
+    pub unsafe fn try_insert(&self, key: *const u8, value: *const u8) -> Result<(), InsertError> {
+        self.stripe(key).with(|table| table.try_insert(key, value))
+    }
+
+    ///
+    /// 获取`key: &K`的可赋值value地址(不存在时先写入Key)并以之调用`f`, 适用于计数器等原地更新。
+    ///
+    /// 新写入Key的value未初始化, 由`f`负责写入; 调用期间持有所属stripe的锁, 指针只在`f`中有效
+    ///
+    pub unsafe fn try_assign<R>(
+        &self,
+        key: *const u8,
+        f: impl FnOnce(*mut u8) -> R,
+    ) -> Result<R, InsertError> {
+        self.stripe(key)
+            .with(|table| Ok(f(table.try_assign(key)? as *mut u8)))
+    }
+
+    /// 删除`key: &K`, 只持有所属stripe的锁
+    pub unsafe fn delete(&self, key: *const u8) {
+        self.stripe(key).with(|table| table.delete(key))
+    }
+
+    ///
+    /// 为所有stripe一共预留至少`additional`个entries的容量。
+    ///
+    /// 按顺序获取所有stripe的锁后再逐个扩容, 期间所有读写均被阻塞; 任一stripe扩容失败时返回错误,
+    /// 此前已扩容的stripe保持扩容后的状态
+    ///
+    pub fn reserve(&self, additional: usize) -> Result<(), TryReserveError> {
+        let n = self.stripes.len();
+        let per_stripe = additional / n + usize::from(additional % n != 0);
+        for stripe in self.stripes.iter() {
+            stripe.lock();
+        }
+        let _unlock = guard(&self.stripes, |stripes| {
+            for stripe in stripes.iter() {
+                stripe.unlock();
+            }
+        });
+        for stripe in self.stripes.iter() {
+            unsafe { &mut *stripe.table.get() }.try_reserve(per_stripe)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 依次持有每个stripe的锁并以其table调用`f`。
+    ///
+    /// 各stripe在不同时刻被访问, 所得结果不是整表在某一时刻的快照
+    ///
+    pub fn for_each_stripe(&self, mut f: impl FnMut(&RawTable2<E, A>)) {
+        for stripe in self.stripes.iter() {
+            stripe.with(|table| f(table));
+        }
+    }
+
+    /// 获取entries数量, 各stripe的数量在不同时刻获取
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        self.for_each_stripe(|table| len += table.len());
+        len
+    }
+
+    /// 判断是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 拆分为各stripe的table
+    pub fn into_stripes(self) -> Vec<RawTable2<E, A>> {
+        self.stripes
+            .into_vec()
+            .into_iter()
+            .map(|stripe| stripe.table.into_inner())
+            .collect()
+    }
+}
@@ -36,3 +36,45 @@ pub(crate) fn unlikely(b: bool) -> bool {
 pub(crate) fn invalid_mut<T>(addr: usize) -> *mut T {
     unsafe { core::mem::transmute(addr) }
 }
+
+// FIXME: use `<*const T>::addr` once it is stable.
+// Like `invalid_mut`, a transmute strips the provenance instead of exposing it.
+#[inline(always)]
+#[allow(clippy::transmutes_expressible_as_ptr_casts)]
+pub(crate) fn addr<T>(ptr: *const T) -> usize {
+    unsafe { core::mem::transmute(ptr.cast::<()>()) }
+}
+
+/// Hints the CPU to pull the cache line containing `ptr` into L1.
+///
+/// Prefetching never faults, so `ptr` does not need to be dereferenceable.
+/// This is a no-op on targets without a prefetch instruction.
+#[inline(always)]
+pub(crate) fn prefetch_read<T>(ptr: *const T) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse"))]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast());
+    }
+    #[cfg(all(target_arch = "x86", target_feature = "sse"))]
+    unsafe {
+        use core::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast());
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        core::arch::asm!(
+            "prfm pldl1keep, [{ptr}]",
+            ptr = in(reg) ptr,
+            options(nostack, preserves_flags, readonly)
+        );
+    }
+    #[cfg(not(any(
+        all(
+            any(target_arch = "x86_64", target_arch = "x86"),
+            target_feature = "sse"
+        ),
+        target_arch = "aarch64"
+    )))]
+    let _ = ptr;
+}
@@ -533,3 +533,108 @@ fn set_seq_par_equivalence_union() {
 
     assert_eq3!(union_ab_seq, union_ab_par, *SET_UNION_AB);
 }
+
+#[test]
+#[cfg(feature = "raw-map-infallible")]
+fn raw_map_par_iter() {
+    use allocator_api2::alloc::Global;
+    use hashbrown::raw_map::{HasherSpec, RawTable2};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let spec = HasherSpec::<u64, u64, _>::new(hashbrown::DefaultHashBuilder::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    assert_eq!(table.par_iter().count(), 0);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        for i in 0..100_000 {
+            map.insert(&i, i * 2);
+        }
+    }
+
+    let sum = table
+        .par_iter()
+        .map(|entry| unsafe { *(entry.value() as *const u64) })
+        .sum::<u64>();
+    assert_eq!(sum, (0..100_000).map(|i| i * 2).sum::<u64>());
+    let mut indices: Vec<usize> = table.par_iter().map(|entry| entry.index()).collect();
+    indices.sort_unstable();
+    assert_eq!(indices, table.full_buckets_indices().collect::<Vec<_>>());
+
+    let total = AtomicU64::new(0);
+    table.par_for_each(|key, _| {
+        total.fetch_add(unsafe { *(key as *const u64) }, Ordering::Relaxed);
+    });
+    assert_eq!(total.into_inner(), (0..100_000).sum::<u64>());
+
+    table.par_retain(|key, _| unsafe { *(key as *const u64) } % 3 == 0);
+    assert_eq!(table.len(), 33_334);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&3), Some(&6));
+        assert_eq!(map.get(&4), None);
+    }
+}
+
+#[test]
+fn raw_map_build_parallel() {
+    use allocator_api2::alloc::Global;
+    use hashbrown::raw_map::{HasherSpec, RawTable2};
+
+    // 每个Key出现两次, 靠后的value生效
+    let keys: Vec<u64> = (0..50_000).chain(0..50_000).collect();
+    let values: Vec<u64> = (0..100_000).collect();
+    let key_ptrs: Vec<*const u8> = keys.iter().map(|k| k as *const u64 as *const u8).collect();
+    let value_ptrs: Vec<*const u8> = values
+        .iter()
+        .map(|v| v as *const u64 as *const u8)
+        .collect();
+
+    for partitions in [0, 1, 3, 16] {
+        let spec = HasherSpec::<u64, u64, _>::new(hashbrown::DefaultHashBuilder::default());
+        let mut table = unsafe {
+            RawTable2::build_parallel(&key_ptrs, &value_ptrs, partitions, spec, Global).unwrap()
+        };
+        assert_eq!(table.len(), 50_000);
+        unsafe {
+            let map = table.as_map::<u64, u64>();
+            for i in (0..50_000).step_by(997) {
+                assert_eq!(map.get(&i), Some(&(i + 50_000)));
+            }
+            assert_eq!(map.get(&50_000), None);
+        }
+    }
+
+    let spec = HasherSpec::<u64, u64, _>::new(hashbrown::DefaultHashBuilder::default());
+    let table = unsafe { RawTable2::build_parallel(&[], &[], 4, spec, Global).unwrap() };
+    assert!(table.is_empty());
+}
+
+#[test]
+fn raw_map_par_extend() {
+    use hashbrown::raw_map::{HasherSpec, RawTable2};
+
+    // 每个Key出现两次, 靠后的value生效
+    let mut table: RawTable2<HasherSpec<u64, u64, hashbrown::DefaultHashBuilder>> = (0..100_000)
+        .into_par_iter()
+        .map(|i| (i % 50_000, i))
+        .collect();
+    assert_eq!(table.len(), 50_000);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        for i in (0..50_000).step_by(997) {
+            assert_eq!(map.get(&i), Some(&(i + 50_000)));
+        }
+    }
+
+    table.par_extend((25_000..75_000u64).into_par_iter().map(|i| (i, i)));
+    assert_eq!(table.len(), 75_000);
+    unsafe {
+        let map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&0), Some(&50_000));
+        assert_eq!(map.get(&30_000), Some(&30_000));
+        assert_eq!(map.get(&74_999), Some(&74_999));
+    }
+
+    table.par_extend(Vec::<(u64, u64)>::new());
+    assert_eq!(table.len(), 75_000);
+}
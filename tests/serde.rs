@@ -63,3 +63,140 @@ fn set_serde_tokens() {
         ],
     );
 }
+
+#[test]
+fn raw_map_serde() {
+    use allocator_api2::alloc::Global;
+    use hashbrown::raw_map::{HasherSpec, RawTable2};
+    use serde::de::value::{Error, MapDeserializer};
+    use serde::de::DeserializeSeed;
+    use serde_test::assert_ser_tokens;
+
+    let spec = HasherSpec::<u64, u64, _>::new(BuildHasherDefault::<FnvHasher>::default());
+    let mut table = RawTable2::new(0, spec, Global).unwrap();
+    let entries =
+        MapDeserializer::<_, Error>::new(vec![(1u64, 10u64), (2, 20), (1, 11)].into_iter());
+    unsafe { table.as_map::<u64, u64>() }
+        .deserialize(entries)
+        .unwrap();
+    assert_eq!(table.len(), 2);
+    unsafe {
+        let mut map = table.as_map::<u64, u64>();
+        assert_eq!(map.get(&1), Some(&11));
+        assert_eq!(map.get(&2), Some(&20));
+        map.delete(&2);
+    }
+
+    assert_ser_tokens(
+        &unsafe { table.as_map::<u64, u64>() },
+        &[
+            Token::Map { len: Some(1) },
+            Token::U64(1),
+            Token::U64(11),
+            Token::MapEnd,
+        ],
+    );
+}
+
+#[test]
+fn raw_table_serde_codec() {
+    use allocator_api2::alloc::Global;
+    use hashbrown::raw_map::{EntrySpec, RawTable2};
+    use serde::de::value::{Error, MapDeserializer};
+    use serde::de::DeserializeSeed;
+    use serde_test::{assert_ser_tokens, assert_ser_tokens_error};
+    use std::alloc::Layout;
+
+    // entry为(u32, u32), Key与value均编码为小端字节
+    struct LeSpec {
+        codec: bool,
+    }
+    impl EntrySpec for LeSpec {
+        fn layout(&self) -> Layout {
+            Layout::new::<(u32, u32)>()
+        }
+        fn hash(&self, entry: *const u8) -> u64 {
+            unsafe { *(entry as *const u32) as u64 }
+        }
+        fn equals(&self, entry1: *const u8, entry2: *const u8) -> bool {
+            unsafe { *(entry1 as *const u32) == *(entry2 as *const u32) }
+        }
+        fn assign_key(&self, entry: *const u8, k: *const u8) {
+            unsafe { *(entry as *mut u32) = *(k as *const u32) }
+        }
+        fn access_value(&self, entry: *const u8) -> *const u8 {
+            unsafe { entry.add(4) }
+        }
+        fn assign_value(&self, value: *const u8, v: *const u8) {
+            unsafe { *(value as *mut u32) = *(v as *const u32) }
+        }
+        fn encode_key(&self, entry: *const u8, out: &mut Vec<u8>) -> bool {
+            out.extend_from_slice(&unsafe { *(entry as *const u32) }.to_le_bytes());
+            self.codec
+        }
+        fn encode_value(&self, value: *const u8, out: &mut Vec<u8>) -> bool {
+            out.extend_from_slice(&unsafe { *(value as *const u32) }.to_le_bytes());
+            self.codec
+        }
+        fn decode_key(&self, bytes: &[u8], k: *mut u8) -> bool {
+            match <[u8; 4]>::try_from(bytes) {
+                Ok(bytes) if self.codec => {
+                    unsafe { *(k as *mut u32) = u32::from_le_bytes(bytes) };
+                    true
+                }
+                _ => false,
+            }
+        }
+        fn decode_value(&self, bytes: &[u8], value: *const u8) -> bool {
+            match <[u8; 4]>::try_from(bytes) {
+                Ok(bytes) if self.codec => {
+                    unsafe { *(value as *mut u32) = u32::from_le_bytes(bytes) };
+                    true
+                }
+                _ => false,
+            }
+        }
+    }
+
+    let mut table = RawTable2::new(0, LeSpec { codec: true }, Global).unwrap();
+    let entries = MapDeserializer::<_, Error>::new(
+        vec![
+            (&[1u8, 0, 0, 0][..], &[10u8, 0, 0, 0][..]),
+            (&[1, 0, 0, 0], &[11, 0, 0, 0]),
+        ]
+        .into_iter(),
+    );
+    (&mut table).deserialize(entries).unwrap();
+    assert_eq!(table.len(), 1);
+    assert_ser_tokens(
+        &table,
+        &[
+            Token::Map { len: Some(1) },
+            Token::Bytes(&[1, 0, 0, 0]),
+            Token::Bytes(&[11, 0, 0, 0]),
+            Token::MapEnd,
+        ],
+    );
+
+    // 无法解码的value不会留下新写入的Key
+    let entries =
+        MapDeserializer::<_, Error>::new(vec![(&[2u8, 0, 0, 0][..], &[20u8, 0][..])].into_iter());
+    assert!((&mut table).deserialize(entries).is_err());
+    assert_eq!(table.len(), 1);
+
+    let table = RawTable2::new(0, LeSpec { codec: false }, Global).unwrap();
+    assert_ser_tokens(&table, &[Token::Map { len: Some(0) }, Token::MapEnd]);
+    let mut table = table;
+    unsafe {
+        table.try_insert(
+            &1u32 as *const u32 as *const u8,
+            &1u32 as *const u32 as *const u8,
+        )
+    }
+    .unwrap();
+    assert_ser_tokens_error(
+        &table,
+        &[Token::Map { len: Some(1) }],
+        "entry spec does not support encoding",
+    );
+}